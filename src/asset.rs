@@ -1,10 +1,17 @@
+pub mod ai;
+pub mod elevator;
 pub mod font;
 pub mod frame;
+pub mod gcd;
+pub mod ini;
 pub mod map;
 pub mod message;
 pub mod palette;
+pub mod party;
 pub mod proto;
+pub mod save;
 pub mod script;
+pub mod worldmap;
 
 use enumflags2_derive::EnumFlags;
 use enum_map_derive::Enum;
@@ -425,6 +432,15 @@ pub enum Trait {
     Gifted = 15,
 }
 
+#[derive(Clone, Copy, Debug, Enum, Eq, PartialEq, Primitive)]
+pub enum PcStat {
+    UnspentSkillPoints = 0,
+    Level = 1,
+    Experience = 2,
+    Reputation = 3,
+    Karma = 4,
+}
+
 #[derive(Clone, Copy, Debug, Enum, Eq, PartialEq, Primitive)]
 pub enum WeaponKind {
     Unarmed     = 0,
@@ -0,0 +1,285 @@
+use enumflags2::BitFlags;
+use std::collections::HashMap;
+use std::io::{self, prelude::*};
+
+use crate::asset::ini::{Ini, Section};
+use crate::game::object::DamageFlag;
+
+macro_rules! name_enum {
+    ($(#[$attr:meta])* pub enum $name:ident { $($variant:ident => $s:expr,)* }) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+        pub enum $name {
+            $($variant,)*
+        }
+
+        impl $name {
+            pub fn from_name(s: &str) -> Option<Self> {
+                $(if s.eq_ignore_ascii_case($s) {
+                    return Some($name::$variant);
+                })*
+                None
+            }
+
+            pub fn name(self) -> &'static str {
+                match self {
+                    $($name::$variant => $s,)*
+                }
+            }
+
+            /// All values in the declaration order.
+            pub fn values() -> &'static [Self] {
+                &[$($name::$variant,)*]
+            }
+        }
+    };
+}
+
+name_enum! {
+    /// How careful the critter is with area attacks (bursts, explosives) near friends.
+    pub enum AreaAttackMode {
+        Always => "always",
+        Sometimes => "sometimes",
+        BeSure => "be_sure",
+        BeCareful => "be_careful",
+        BeAbsolutelySure => "be_absolutely_sure",
+    }
+}
+
+name_enum! {
+    /// Target selection preference.
+    pub enum AttackWho {
+        WhomeverAttackingMe => "whomever_attacking_me",
+        Strongest => "strongest",
+        Weakest => "weakest",
+        Whomever => "whomever",
+        Closest => "closest",
+    }
+}
+
+name_enum! {
+    pub enum BestWeapon {
+        NoPref => "no_pref",
+        Melee => "melee",
+        MeleeOverRanged => "melee_over_ranged",
+        RangedOverMelee => "ranged_over_melee",
+        Ranged => "ranged",
+        Unarmed => "unarmed",
+        UnarmedOverThrown => "unarmed_over_thrown",
+        Random => "random",
+    }
+}
+
+name_enum! {
+    /// Values are in the order of the original `chem_use` indices returned to scripts.
+    pub enum ChemUse {
+        Clean => "clean",
+        StimsWhenHurtLittle => "stims_when_hurt_little",
+        StimsWhenHurtLots => "stims_when_hurt_lots",
+        Sometimes => "sometimes",
+        Anytime => "anytime",
+        Always => "always",
+    }
+}
+
+name_enum! {
+    pub enum Disposition {
+        None => "none",
+        Custom => "custom",
+        Coward => "coward",
+        Defensive => "defensive",
+        Aggressive => "aggressive",
+        Berserk => "berserk",
+    }
+}
+
+name_enum! {
+    /// How the critter keeps distance from its target.
+    pub enum Distance {
+        StayClose => "stay_close",
+        Charge => "charge",
+        Snipe => "snipe",
+        OnYourOwn => "on_your_own",
+        Stay => "stay",
+    }
+}
+
+name_enum! {
+    pub enum RunAwayMode {
+        None => "none",
+        Coward => "coward",
+        FingerHurts => "finger_hurts",
+        Bleeding => "bleeding",
+        NotFeelingGood => "not_feeling_good",
+        Tourniquet => "tourniquet",
+        Never => "never",
+    }
+}
+
+impl RunAwayMode {
+    /// Percentage of max HP lost at which the critter runs away.
+    // hp_run_away_value
+    pub fn hp_lost_percent(self) -> Option<i32> {
+        Some(match self {
+            RunAwayMode::None => return None,
+            RunAwayMode::Coward => 0,
+            RunAwayMode::FingerHurts => 25,
+            RunAwayMode::Bleeding => 40,
+            RunAwayMode::NotFeelingGood => 60,
+            RunAwayMode::Tourniquet => 75,
+            RunAwayMode::Never => 100,
+        })
+    }
+}
+
+/// Combat behavior settings of a critter.
+#[derive(Clone, Debug)]
+pub struct AiPacket {
+    pub name: String,
+    pub packet_num: u32,
+    /// Chance in percents to keep attacking rather than doing nothing.
+    pub aggression: i32,
+    pub area_attack_mode: AreaAttackMode,
+    pub attack_who: AttackWho,
+    pub best_weapon: BestWeapon,
+    pub chem_use: ChemUse,
+    pub disposition: Disposition,
+    pub distance: Distance,
+    pub run_away_mode: RunAwayMode,
+    /// Run away if HP drops below this value. Used when `run_away_mode` is `None`.
+    pub min_hp: i32,
+    /// Don't attack if the chance to hit is below this value.
+    pub min_to_hit: i32,
+    /// Max distance from the dude the critter will wander off to.
+    pub max_dist: u32,
+    /// Chance in percents (divided by 1000) of using the secondary attack mode.
+    pub secondary_freq: i32,
+    /// Chance of making a called shot is `1 / called_freq`.
+    pub called_freq: i32,
+    /// Run away if any of these effects are present.
+    pub hurt_too_much: BitFlags<DamageFlag>,
+}
+
+impl AiPacket {
+    fn read(section: &Section) -> io::Result<Self> {
+        fn invalid(section: &Section, key: &str) -> io::Error {
+            io::Error::new(io::ErrorKind::InvalidData,
+                format!("invalid value of `{}` in section [{}]", key, section.name()))
+        }
+        macro_rules! named {
+            ($ty:ty, $key:expr, $default:expr) => {
+                match section.get($key) {
+                    Some(v) => <$ty>::from_name(v).ok_or_else(|| invalid(section, $key))?,
+                    None => $default,
+                }
+            };
+        }
+
+        let mut hurt_too_much = BitFlags::empty();
+        for v in section.get_list("hurt_too_much") {
+            hurt_too_much |= match &v.to_lowercase()[..] {
+                "blind" => DamageFlag::Blind.into(),
+                "crippled" => DamageFlag::CripLegLeft | DamageFlag::CripLegRight
+                    | DamageFlag::CripArmLeft | DamageFlag::CripArmRight,
+                "crippled_legs" => DamageFlag::CripLegLeft | DamageFlag::CripLegRight,
+                "crippled_arms" => DamageFlag::CripArmLeft | DamageFlag::CripArmRight,
+                _ => return Err(invalid(section, "hurt_too_much")),
+            };
+        }
+
+        Ok(Self {
+            name: section.name().into(),
+            packet_num: section.require_int("packet_num")? as u32,
+            aggression: section.get_int("aggression").unwrap_or(0),
+            area_attack_mode: named!(AreaAttackMode, "area_attack_mode", AreaAttackMode::BeCareful),
+            attack_who: named!(AttackWho, "attack_who", AttackWho::Whomever),
+            best_weapon: named!(BestWeapon, "best_weapon", BestWeapon::NoPref),
+            chem_use: named!(ChemUse, "chem_use", ChemUse::Clean),
+            disposition: named!(Disposition, "disposition", Disposition::None),
+            distance: named!(Distance, "distance", Distance::StayClose),
+            run_away_mode: named!(RunAwayMode, "run_away_mode", RunAwayMode::None),
+            min_hp: section.get_int("min_hp").unwrap_or(0),
+            min_to_hit: section.get_int("min_to_hit").unwrap_or(0),
+            max_dist: section.get_int("max_dist").unwrap_or(0) as u32,
+            secondary_freq: section.get_int("secondary_freq").unwrap_or(0),
+            called_freq: section.get_int("called_freq").unwrap_or(0),
+            hurt_too_much,
+        })
+    }
+}
+
+/// AI packets from `data/ai.txt` indexed by packet number.
+#[derive(Debug, Default)]
+pub struct AiPackets {
+    packets: HashMap<u32, AiPacket>,
+}
+
+impl AiPackets {
+    pub fn read(rd: &mut impl BufRead) -> io::Result<Self> {
+        let ini = Ini::read(rd)?;
+        let mut packets = HashMap::new();
+        for section in ini.sections() {
+            let packet = AiPacket::read(section)?;
+            packets.insert(packet.packet_num, packet);
+        }
+        Ok(Self {
+            packets,
+        })
+    }
+
+    pub fn get(&self, packet_num: u32) -> Option<&AiPacket> {
+        self.packets.get(&packet_num)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read() {
+        let packets = AiPackets::read(&mut &b"
+[Raider Aggressive]
+aggression=60
+area_attack_mode=be_careful
+attack_who=closest
+best_weapon=ranged_over_melee
+chem_use=stims_when_hurt_lots
+distance=charge
+hurt_too_much=blind,crippled_legs
+max_dist=8
+min_to_hit=20
+packet_num=12
+run_away_mode=bleeding
+
+[Coward]
+packet_num=3
+run_away_mode=coward
+"[..]).unwrap();
+
+        let p = packets.get(12).unwrap();
+        assert_eq!(p.name, "Raider Aggressive");
+        assert_eq!(p.aggression, 60);
+        assert_eq!(p.attack_who, AttackWho::Closest);
+        assert_eq!(p.best_weapon, BestWeapon::RangedOverMelee);
+        assert_eq!(p.chem_use, ChemUse::StimsWhenHurtLots);
+        assert_eq!(p.distance, Distance::Charge);
+        assert_eq!(p.run_away_mode, RunAwayMode::Bleeding);
+        assert_eq!(p.hurt_too_much,
+            DamageFlag::Blind | DamageFlag::CripLegLeft | DamageFlag::CripLegRight);
+        assert_eq!(p.max_dist, 8);
+        assert_eq!(p.min_to_hit, 20);
+
+        let p = packets.get(3).unwrap();
+        assert_eq!(p.run_away_mode, RunAwayMode::Coward);
+        assert_eq!(p.chem_use, ChemUse::Clean);
+
+        assert!(packets.get(0).is_none());
+    }
+
+    #[test]
+    fn read_invalid() {
+        assert!(AiPackets::read(&mut &b"[A]\npacket_num=1\nchem_use=lots\n"[..]).is_err());
+        assert!(AiPackets::read(&mut &b"[A]\naggression=1\n"[..]).is_err());
+    }
+}
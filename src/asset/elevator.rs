@@ -0,0 +1,240 @@
+use std::io::{self, prelude::*};
+
+use crate::asset::EntityKind;
+use crate::asset::frame::FrameId;
+use crate::asset::ini::{Ini, Section};
+use crate::fs::FileSystem;
+
+pub const MAX_LEVELS: usize = 4;
+
+/// Background and button panel art of the elevator kinds known to the original game and their
+/// default levels.
+// TODO Add the default levels of the kinds 11 and up. Until then they come from
+// `data/elevators.ini` only.
+// gElevatorBackgrounds, gElevatorDescriptions
+const KINDS: [(FrameId, Option<FrameId>, &[DefaultLevel]); 24] = [
+    (FrameId::EL_BOS, None, &[(14, 0, 18940), (14, 1, 18936), (15, 0, 21340), (15, 1, 21340)]),
+    (FrameId::EL_BOS, Some(FrameId::EL_BOS2), &[(13, 0, 20502), (14, 0, 14912)]),
+    (FrameId::EL_MAST1, None, &[(33, 0, 12498), (33, 1, 20094), (34, 0, 17312)]),
+    (FrameId::EL_MAST1, Some(FrameId::EL_MAST2), &[(34, 0, 16140), (34, 1, 16140)]),
+    (FrameId::EL_MIL1, None, &[(49, 0, 14920), (49, 1, 15120)]),
+    (FrameId::EL_MIL1, Some(FrameId::EL_MIL2), &[(42, 0, 12944), (42, 1, 12944)]),
+    (FrameId::EL_MIL1, None, &[(42, 2, 16302), (43, 0, 14104)]),
+    (FrameId::EL_MIL1, Some(FrameId::EL_MIL3), &[(40, 0, 14130), (40, 1, 14130)]),
+    (FrameId::EL_VAULT, None, &[(9, 0, 13728), (9, 1, 13728), (9, 2, 13728)]),
+    (FrameId::EL_MIL1, None, &[(28, 0, 19934), (28, 1, 19934)]),
+    (FrameId::EL_MIL1, None, &[(28, 0, 20140), (28, 1, 20140)]),
+    (FrameId::EL_MIL1, Some(FrameId::EL_MIL2), &[]),
+    (FrameId::EL_BASE1, None, &[]),
+    (FrameId::EL_BOS, Some(FrameId::EL_BOS2), &[]),
+    (FrameId::EL_VAULT, None, &[]),
+    (FrameId::EL_VAULT, None, &[]),
+    (FrameId::EL_VAULT, None, &[]),
+    (FrameId::EL_BOS, Some(FrameId::EL_BOS2), &[]),
+    (FrameId::EL_BOS, Some(FrameId::EL_BOS2), &[]),
+    (FrameId::EL_BOS, Some(FrameId::EL_BOS2), &[]),
+    (FrameId::EL_BOS, Some(FrameId::EL_BOS2), &[]),
+    (FrameId::EL_BOS, Some(FrameId::EL_BOS2), &[]),
+    (FrameId::EL_BOS, Some(FrameId::EL_BOS2), &[]),
+    (FrameId::EL_BOS, Some(FrameId::EL_BOS2), &[]),
+];
+
+/// `Level` as `(map_id, elevation, tile_num)`.
+type DefaultLevel = (i32, u32, u32);
+
+/// Destination of an elevator button.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Level {
+    pub map_id: i32,
+    pub elevation: u32,
+    /// Linear hex tile number.
+    pub tile_num: u32,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ElevatorDef {
+    /// Background art. Its frames show the level indicator at each position.
+    pub fid: FrameId,
+    /// Art with the buttons of the lower levels drawn at the bottom of the background.
+    pub panel_fid: Option<FrameId>,
+    pub levels: Vec<Level>,
+}
+
+/// Elevator kinds and their levels. The built-in levels of the original kinds can be overridden
+/// and new kinds added in `data/elevators.ini` where each `[<kind>]` section lists `ID<n>`,
+/// `Elevation<n>` and `Tile<n>` of the level `n` starting from 1. The art can be overridden with
+/// `MainFrm` and `ButtonsFrm` interface FRM indices.
+pub struct ElevatorDb {
+    defs: Vec<ElevatorDef>,
+}
+
+impl ElevatorDb {
+    pub fn new(fs: &FileSystem) -> io::Result<Self> {
+        const PATH: &str = "data/elevators.ini";
+        if fs.exists(PATH) {
+            Self::read(&mut fs.reader(PATH)?)
+        } else {
+            Self::read(&mut io::empty())
+        }
+    }
+
+    pub fn read(rd: &mut impl BufRead) -> io::Result<Self> {
+        let mut defs: Vec<_> = KINDS.iter()
+            .map(|&(fid, panel_fid, levels)| ElevatorDef {
+                fid,
+                panel_fid,
+                levels: levels.iter()
+                    .map(|&(map_id, elevation, tile_num)| Level {
+                        map_id,
+                        elevation,
+                        tile_num,
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let ini = Ini::read(rd)?;
+        for section in ini.sections() {
+            let kind: usize = if let Ok(v) = section.name().trim().parse() {
+                v
+            } else {
+                continue;
+            };
+            if defs.len() <= kind {
+                defs.resize_with(kind + 1, || ElevatorDef {
+                    fid: FrameId::EL_BOS,
+                    panel_fid: None,
+                    levels: Vec::new(),
+                });
+            }
+            let def = &mut defs[kind];
+
+            if let Some(id) = section.get_int("MainFrm") {
+                def.fid = interface_fid(id, section)?;
+            }
+            if let Some(id) = section.get_int("ButtonsFrm") {
+                def.panel_fid = if id >= 0 {
+                    Some(interface_fid(id, section)?)
+                } else {
+                    None
+                };
+            }
+
+            // Sections without levels keep the default ones.
+            if section.get_int("ID1").is_some() {
+                def.levels.clear();
+            }
+            for i in 1..=MAX_LEVELS {
+                let map_id = if let Some(v) = section.get_int(&format!("ID{}", i)) {
+                    v
+                } else {
+                    break;
+                };
+                let elevation = require_u32(section, &format!("Elevation{}", i))?;
+                let tile_num = require_u32(section, &format!("Tile{}", i))?;
+                def.levels.push(Level {
+                    map_id,
+                    elevation,
+                    tile_num,
+                });
+            }
+        }
+
+        Ok(Self {
+            defs,
+        })
+    }
+
+    pub fn get(&self, kind: u32) -> Option<&ElevatorDef> {
+        self.defs.get(kind as usize)
+    }
+}
+
+fn invalid_data(key: &str, section: &Section) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData,
+        format!("invalid value of `{}` in section [{}]", key, section.name()))
+}
+
+fn require_u32(section: &Section, key: &str) -> io::Result<u32> {
+    let v = section.require_int(key)?;
+    if v >= 0 {
+        Ok(v as u32)
+    } else {
+        Err(invalid_data(key, section))
+    }
+}
+
+fn interface_fid(id: i32, section: &Section) -> io::Result<FrameId> {
+    if id >= 0 && id <= u16::max_value() as i32 {
+        FrameId::new_generic(EntityKind::Interface, id as u16)
+    } else {
+        None
+    }.ok_or_else(|| invalid_data("MainFrm/ButtonsFrm", section))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read() {
+        let db = ElevatorDb::read(&mut &b"
+[1]
+ID1=13
+Elevation1=0
+Tile1=20502
+ID2=14
+Elevation2=1
+Tile2=14912
+
+[30]
+MainFrm=148
+ButtonsFrm=-1
+ID1=5
+Elevation1=2
+Tile1=100
+"[..]).unwrap();
+
+        assert_eq!(db.get(0).unwrap().levels.len(), 4);
+
+        let def = db.get(1).unwrap();
+        assert_eq!(def.fid, FrameId::EL_BOS);
+        assert_eq!(def.panel_fid, Some(FrameId::EL_BOS2));
+        assert_eq!(def.levels, vec![
+            Level { map_id: 13, elevation: 0, tile_num: 20502 },
+            Level { map_id: 14, elevation: 1, tile_num: 14912 },
+        ]);
+
+        assert_eq!(db.get(29).unwrap().levels, vec![]);
+        assert_eq!(db.get(30), Some(&ElevatorDef {
+            fid: FrameId::EL_VAULT,
+            panel_fid: None,
+            levels: vec![Level { map_id: 5, elevation: 2, tile_num: 100 }],
+        }));
+        assert_eq!(db.get(31), None);
+    }
+
+    #[test]
+    fn defaults() {
+        let db = ElevatorDb::read(&mut io::empty()).unwrap();
+        let def = db.get(0).unwrap();
+        assert_eq!(def.fid, FrameId::EL_BOS);
+        assert_eq!(def.levels, vec![
+            Level { map_id: 14, elevation: 0, tile_num: 18940 },
+            Level { map_id: 14, elevation: 1, tile_num: 18936 },
+            Level { map_id: 15, elevation: 0, tile_num: 21340 },
+            Level { map_id: 15, elevation: 1, tile_num: 21340 },
+        ]);
+
+        let db = ElevatorDb::read(&mut &b"[0]\nMainFrm=148"[..]).unwrap();
+        let def = db.get(0).unwrap();
+        assert_eq!(def.fid, FrameId::EL_VAULT);
+        assert_eq!(def.levels.len(), 4);
+    }
+
+    #[test]
+    fn read_invalid() {
+        assert!(ElevatorDb::read(&mut &b"[0]\nID1=1\nElevation1=-1\nTile1=0"[..]).is_err());
+        assert!(ElevatorDb::read(&mut &b"[0]\nID1=1\nElevation1=0"[..]).is_err());
+    }
+}
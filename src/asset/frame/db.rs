@@ -35,6 +35,10 @@ impl FrameDb {
         })
     }
 
+    pub fn texture_factory(&self) -> &TextureFactory {
+        &self.texture_factory
+    }
+
     // art_get_name()
     /// Returns .frm or .frN file name without path.
     pub fn name(&self, fid: FrameId) -> Option<String> {
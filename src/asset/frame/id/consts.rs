@@ -1,6 +1,8 @@
 use super::*;
 
 impl FrameId {
+    /// Misc art, not from intrface.lst.
+    pub const EXPLOSION: FrameId = FrameId::Generic(Generic(0x5000_00a));
     pub const BLANK: FrameId = FrameId::Generic(Generic(0x6000_000));
     pub const MOUSE_HEX: FrameId = FrameId::Generic(Generic(0x6000_001));
     pub const EGG: FrameId = FrameId::Generic(Generic(0x6000_002));
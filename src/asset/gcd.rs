@@ -0,0 +1,141 @@
+use bstring::BString;
+use byteorder::{BigEndian, ReadBytesExt};
+use enum_map::EnumMap;
+use num_traits::FromPrimitive;
+use std::io::{self, Error, ErrorKind, prelude::*};
+
+use crate::asset::{Skill, Stat, Trait};
+
+/// Character data of the dude as stored in `.gcd` files. Premade characters are kept in such
+/// files.
+#[derive(Debug)]
+pub struct Gcd {
+    pub base_stats: EnumMap<Stat, i32>,
+    pub bonus_stats: EnumMap<Stat, i32>,
+    pub skills: EnumMap<Skill, i32>,
+    pub name: BString,
+    pub tagged_skills: Vec<Skill>,
+    pub traits: Vec<Trait>,
+    /// Character points not yet spent on primary stats.
+    pub char_points: i32,
+}
+
+/// Critter stats as stored in critter protos. Character files and savegames have these for the
+/// dude.
+#[derive(Debug)]
+pub struct CritterData {
+    pub base_stats: EnumMap<Stat, i32>,
+    pub bonus_stats: EnumMap<Stat, i32>,
+    pub skills: EnumMap<Skill, i32>,
+}
+
+// proto_read_protoSubNode()
+pub fn read_critter_data(rd: &mut impl Read) -> io::Result<CritterData> {
+    // Critter flags.
+    rd.read_u32::<BigEndian>()?;
+
+    let mut base_stats = EnumMap::new();
+    for stat in 0..35 {
+        base_stats[Stat::from_usize(stat).unwrap()] = rd.read_i32::<BigEndian>()?;
+    }
+    let mut bonus_stats = EnumMap::new();
+    for stat in 0..35 {
+        bonus_stats[Stat::from_usize(stat).unwrap()] = rd.read_i32::<BigEndian>()?;
+    }
+    let mut skills = EnumMap::new();
+    for skill in 0..18 {
+        skills[Skill::from_usize(skill).unwrap()] = rd.read_i32::<BigEndian>()?;
+    }
+
+    // Body kind, experience, kill kind, damage kind.
+    rd.read_exact(&mut [0; 4 * 4][..])?;
+
+    Ok(CritterData {
+        base_stats,
+        bonus_stats,
+        skills,
+    })
+}
+
+// pc_load_data()
+pub fn read_gcd(rd: &mut impl Read) -> io::Result<Gcd> {
+    let CritterData { base_stats, bonus_stats, skills } = read_critter_data(rd)?;
+
+    let mut name = [0; 32];
+    rd.read_exact(&mut name[..])?;
+    let name = name.iter().cloned().take_while(|&c| c != 0).collect::<Vec<u8>>().into();
+
+    let mut tagged_skills = Vec::new();
+    for _ in 0..4 {
+        let v = rd.read_i32::<BigEndian>()?;
+        if v >= 0 {
+            tagged_skills.push(Skill::from_i32(v)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "invalid tagged skill"))?);
+        }
+    }
+
+    let mut traits = Vec::new();
+    for _ in 0..2 {
+        let v = rd.read_i32::<BigEndian>()?;
+        if v >= 0 {
+            traits.push(Trait::from_i32(v)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "invalid trait"))?);
+        }
+    }
+
+    let char_points = rd.read_i32::<BigEndian>()?;
+
+    Ok(Gcd {
+        base_stats,
+        bonus_stats,
+        skills,
+        name,
+        tagged_skills,
+        traits,
+        char_points,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use byteorder::WriteBytesExt;
+    use std::io::Cursor;
+
+    #[test]
+    fn read_gcd_() {
+        let mut data = Vec::new();
+        data.write_u32::<BigEndian>(0).unwrap();
+        for i in 0..35 {
+            data.write_i32::<BigEndian>(i).unwrap();
+        }
+        for i in 0..35 {
+            data.write_i32::<BigEndian>(-i).unwrap();
+        }
+        for i in 0..18 {
+            data.write_i32::<BigEndian>(i * 10).unwrap();
+        }
+        data.extend_from_slice(&[0; 16]);
+        let mut name = b"Chitsa".to_vec();
+        name.resize(32, 0);
+        data.extend_from_slice(&name);
+        for &v in &[3, 8, 17, -1] {
+            data.write_i32::<BigEndian>(v).unwrap();
+        }
+        for &v in &[15, -1] {
+            data.write_i32::<BigEndian>(v).unwrap();
+        }
+        data.write_i32::<BigEndian>(2).unwrap();
+        assert_eq!(data.len(), 432);
+
+        let gcd = read_gcd(&mut Cursor::new(data)).unwrap();
+        assert_eq!(gcd.base_stats[Stat::Luck], 6);
+        assert_eq!(gcd.bonus_stats[Stat::Gender], -34);
+        assert_eq!(gcd.skills[Skill::Outdoorsman], 170);
+        assert_eq!(gcd.name, "Chitsa");
+        assert_eq!(gcd.tagged_skills, vec![Skill::UnarmedCombat, Skill::Sneak,
+            Skill::Outdoorsman]);
+        assert_eq!(gcd.traits, vec![Trait::Gifted]);
+        assert_eq!(gcd.char_points, 2);
+    }
+}
@@ -0,0 +1,135 @@
+use std::io::{self, prelude::*};
+use std::str;
+
+/// Configuration file consisting of named sections with `key=value` entries.
+/// Comments start with `;` and run to the end of line.
+#[derive(Debug, Default)]
+pub struct Ini {
+    sections: Vec<Section>,
+}
+
+impl Ini {
+    pub fn read(rd: &mut impl BufRead) -> io::Result<Self> {
+        let mut sections: Vec<Section> = Vec::new();
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            if rd.read_until(b'\n', &mut buf)? == 0 {
+                break;
+            }
+            // Some files have non-UTF8 characters in comments.
+            let line = String::from_utf8_lossy(&buf);
+            let line = line.splitn(2, ';').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') {
+                let name = line[1..].trim_end_matches(']').trim();
+                sections.push(Section {
+                    name: name.into(),
+                    entries: Vec::new(),
+                });
+            } else if let Some(section) = sections.last_mut() {
+                let mut parts = line.splitn(2, '=');
+                let key = parts.next().unwrap().trim();
+                let value = parts.next().unwrap_or("").trim();
+                section.entries.push((key.to_lowercase(), value.into()));
+            } else {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    format!("entry outside of section: {}", line)));
+            }
+        }
+        Ok(Self {
+            sections,
+        })
+    }
+
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+
+    /// Returns the first section named `name`. Section names are case-insensitive.
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|s| s.name.eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Debug)]
+pub struct Section {
+    name: String,
+    entries: Vec<(String, String)>,
+}
+
+impl Section {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item=(&str, &str)> {
+        self.entries.iter().map(|(k, v)| (&k[..], &v[..]))
+    }
+
+    /// Returns value of the first entry with `key`. Keys are case-insensitive.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| &v[..])
+    }
+
+    pub fn get_int(&self, key: &str) -> Option<i32> {
+        self.get(key)?.parse().ok()
+    }
+
+    /// Returns value of `key` as a comma-separated list.
+    pub fn get_list(&self, key: &str) -> Vec<&str> {
+        self.get(key)
+            .map(|v| v.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn require(&self, key: &str) -> io::Result<&str> {
+        self.get(key).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData,
+            format!("missing key `{}` in section [{}]", key, self.name)))
+    }
+
+    pub fn require_int(&self, key: &str) -> io::Result<i32> {
+        self.require(key)?.parse().map_err(|_| io::Error::new(io::ErrorKind::InvalidData,
+            format!("invalid integer value of `{}` in section [{}]", key, self.name)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read() {
+        let ini = Ini::read(&mut &b"; header comment
+[First]
+a=1
+B = two words ; comment
+list=x, y,,z
+
+[second]
+empty=
+"[..]).unwrap();
+        assert_eq!(ini.sections().len(), 2);
+
+        let s = ini.section("first").unwrap();
+        assert_eq!(s.name(), "First");
+        assert_eq!(s.get_int("a"), Some(1));
+        assert_eq!(s.get("b"), Some("two words"));
+        assert_eq!(s.get_list("list"), vec!["x", "y", "z"]);
+        assert_eq!(s.get("missing"), None);
+        assert!(s.require_int("b").is_err());
+
+        let s = ini.section("SECOND").unwrap();
+        assert_eq!(s.get("empty"), Some(""));
+        assert!(s.get_list("empty").is_empty());
+    }
+
+    #[test]
+    fn read_entry_outside_section() {
+        assert!(Ini::read(&mut &b"a=1\n"[..]).is_err());
+    }
+}
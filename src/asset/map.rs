@@ -1,17 +1,21 @@
-use byteorder::{BigEndian, ReadBytesExt};
+pub mod db;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use enumflags2::BitFlags;
 use enumflags2_derive::EnumFlags;
 use log::*;
 use measure_time::*;
 use num_traits::FromPrimitive;
 use std::cmp;
+use std::collections::HashMap;
 use std::io::{self, Error, ErrorKind, prelude::*};
 
 use crate::asset::*;
 use crate::asset::frame::{FrameId, FrameDb};
 use crate::asset::proto::{SubItem, ProtoId, ProtoDb};
 use crate::asset::script::ProgramId;
-use crate::game::object::*;
+use crate::game::GameTime;
+use crate::game::object::{self, *};
 use crate::game::script::*;
 use crate::graphics::{EPoint, Point};
 use crate::graphics::geometry::hex::{Direction, TileGrid};
@@ -31,6 +35,8 @@ struct ScriptInfo {
     program_id: ProgramId,
     local_var_count: usize,
     local_var_offset: usize,
+    flags: u32,
+    spatial: Spatial,
 }
 
 #[derive(Clone, Copy, Debug, Enum, EnumFlags, Eq, PartialEq)]
@@ -53,6 +59,15 @@ pub struct Map {
     pub entrance_direction: Direction,
     pub sqr_tiles: Vec<Option<Array2d<(u16, u16)>>>,
     pub map_vars: Box<[i32]>,
+    /// Objects read from the map by their persistent IDs.
+    pub objects_by_id: HashMap<u32, object::Handle>,
+}
+
+#[derive(Default)]
+struct ObjIds {
+    by_id: HashMap<u32, object::Handle>,
+    /// Critters along with persistent IDs of their `who_hit_me` objects.
+    who_hit_me: Vec<(object::Handle, u32)>,
 }
 
 pub struct MapReader<'a, R: 'a> {
@@ -133,6 +148,7 @@ impl<'a, R: 'a + Read> MapReader<'a, R> {
 
         // scripts
 
+        let mut map_sid = None;
         for script_kind in ScriptKind::iter() {
             debug!("reading {:?} scripts", script_kind);
             let script_count = self.reader.read_i32::<BigEndian>()?;
@@ -158,6 +174,14 @@ impl<'a, R: 'a + Read> MapReader<'a, R> {
                     scripts.truncate(node_script_count as usize);
 
                     for script in &scripts {
+                        // Saved maps keep the map script along with its local vars in the
+                        // system scripts.
+                        if savegame && map_sid.is_none()
+                            && script.sid.kind() == ScriptKind::System
+                            && Some(script.program_id) == program_id
+                        {
+                            map_sid = Some(script.sid);
+                        }
                         let local_vars = if savegame && script.local_var_count > 0 {
                             let end = script.local_var_offset + script.local_var_count;
                             Some(local_vars[script.local_var_offset..end].into())
@@ -165,17 +189,19 @@ impl<'a, R: 'a + Read> MapReader<'a, R> {
                             None
                         };
                         self.scripts.instantiate(script.sid, script.program_id, local_vars)?;
+                        self.scripts.set_map_fields(script.sid, script.flags, script.spatial);
                     }
                 }
             }
         }
 
         if let Some(program_id) = program_id {
-            self.make_map_script(program_id)?;
+            self.make_map_script(program_id, map_sid)?;
         }
 
         // objects
 
+        let mut obj_ids = ObjIds::default();
         let total_obj_count = self.reader.read_i32::<BigEndian>()?;
         debug!("object count: {}", total_obj_count);
         for elev in 0..ELEVATION_COUNT {
@@ -183,12 +209,15 @@ impl<'a, R: 'a + Read> MapReader<'a, R> {
             debug!("object count at elevation {}: {}", elev, obj_count);
 
             for _ in 0..obj_count {
-                let obj = self.read_obj(version != 19)?;
-                let script = obj.script;
-                let objh = self.objects.insert(obj);
-                if let Some((sid, _)) = script {
-                    self.scripts.attach_to_object(sid, objh);
-                }
+                self.read_obj(version != 19, &mut obj_ids)?;
+            }
+        }
+
+        if savegame {
+            for &(critter, who_hit_me) in &obj_ids.who_hit_me {
+                let who_hit_me = obj_ids.by_id.get(&who_hit_me).cloned();
+                self.objects.get(critter).borrow_mut()
+                    .sub.critter_mut().unwrap().combat.who_hit_me = who_hit_me;
             }
         }
 
@@ -202,6 +231,7 @@ impl<'a, R: 'a + Read> MapReader<'a, R> {
             entrance_direction,
             sqr_tiles,
             map_vars: map_vars.into(),
+            objects_by_id: obj_ids.by_id,
         })
     }
 
@@ -222,18 +252,19 @@ impl<'a, R: 'a + Read> MapReader<'a, R> {
 
         let _ = self.reader.read_i32::<BigEndian>()?;
 
+        let mut spatial = Spatial::default();
         match sid.kind() {
             ScriptKind::Spatial => {
-                let _elevation_and_tile = self.reader.read_i32::<BigEndian>()?;
-                let _spatial_radius = self.reader.read_i32::<BigEndian>()?;
+                spatial.elevation_and_tile = self.reader.read_i32::<BigEndian>()?;
+                spatial.radius = self.reader.read_i32::<BigEndian>()?;
             }
             ScriptKind::Time => {
-                let _elevation_and_tile = self.reader.read_i32::<BigEndian>()?;
+                spatial.elevation_and_tile = self.reader.read_i32::<BigEndian>()?;
             }
             _ => {}
         }
 
-        let _flags = self.reader.read_i32::<BigEndian>()?;
+        let flags = self.reader.read_u32::<BigEndian>()?;
 
         let program_id = self.read_program_id(1)?;
         trace!("program_id: {:?}", program_id);
@@ -258,13 +289,22 @@ impl<'a, R: 'a + Read> MapReader<'a, R> {
                 program_id,
                 local_var_count,
                 local_var_offset,
+                flags,
+                spatial,
             }))
         } else {
             Ok(None)
         }
     }
 
-    fn read_obj(&mut self, f2: bool) -> io::Result<Object> {
+    /// Reads a single object record along with its inventory. Used for the dude object which
+    /// original savegames store apart from the map.
+    // obj_load_dude()
+    pub fn read_object(&mut self) -> io::Result<object::Handle> {
+        self.read_obj(true, &mut ObjIds::default())
+    }
+
+    fn read_obj(&mut self, f2: bool, obj_ids: &mut ObjIds) -> io::Result<object::Handle> {
         let id = self.reader.read_u32::<BigEndian>()?;
         trace!("object ID {}", id);
         let pos = self.reader.read_i32::<BigEndian>()?;
@@ -291,8 +331,16 @@ impl<'a, R: 'a + Read> MapReader<'a, R> {
                 format!("unknown object flags: {:x}", flags)))?;
 
         let elevation = self.reader.read_u32::<BigEndian>()?;
-        let pid = ProtoId::read(self.reader)?;
-        trace!("{:?} {:?}", pid, self.proto_db.name(pid));
+        let obj_pid = ObjectProtoId::read(self.reader)?;
+        trace!("{:?} {:?}", obj_pid, obj_pid.proto_id().map(|pid| self.proto_db.name(pid)));
+        let pid = match obj_pid {
+            ObjectProtoId::ProtoId(pid) => Some(pid),
+            // Saved maps have the dude.
+            ObjectProtoId::Dude => None,
+            ObjectProtoId::None => return Err(Error::new(ErrorKind::InvalidData,
+                "object without PID")),
+        };
+        let kind = pid.map(|pid| pid.kind()).unwrap_or(EntityKind::Critter);
         let _cid = self.reader.read_u32::<BigEndian>()?;
         let light_emitter = LightEmitter {
             radius: self.reader.read_i32::<BigEndian>()? as u32,
@@ -310,21 +358,33 @@ impl<'a, R: 'a + Read> MapReader<'a, R> {
         let _ = self.reader.read_u32::<BigEndian>()?;
 
         let updated_flags = self.reader.read_u32::<BigEndian>()?;
+        // Some maps have uninitialized memory here.
+        let updated_flags = if updated_flags == 0xcccccccc {
+            0
+        } else {
+            updated_flags
+        };
+        let mut lock_flags = if kind == EntityKind::Critter {
+            BitFlags::empty()
+        } else {
+            BitFlags::from_bits_truncate(updated_flags)
+        };
 
-        let sub = if pid.kind() == EntityKind::Critter {
+        let mut who_hit_me = None;
+        let sub = if kind == EntityKind::Critter {
             // combat data
             let _damage_last_turn = self.reader.read_u32::<BigEndian>()?;
-            let _combat_state = self.reader.read_u32::<BigEndian>()?;
-            let _action_points = self.reader.read_u32::<BigEndian>()?;
+            let maneuver = BitFlags::from_bits_truncate(self.reader.read_u32::<BigEndian>()?);
+            let ap = self.reader.read_i32::<BigEndian>()?;
 
             let damage_flags = self.reader.read_u32::<BigEndian>()?;
             let damage_flags = BitFlags::from_bits(damage_flags)
                 .ok_or_else(|| Error::new(ErrorKind::InvalidData,
                     format!("unknown damage flags: {:x}", damage_flags)))?;
 
-            let _ai_packet = self.reader.read_u32::<BigEndian>()?;
-            let _team_num = self.reader.read_u32::<BigEndian>()?;
-            let _who_hit_me = self.reader.read_u32::<BigEndian>()?;
+            let ai_packet = self.reader.read_u32::<BigEndian>()?;
+            let team_id = self.reader.read_u32::<BigEndian>()?;
+            who_hit_me = Some(self.reader.read_u32::<BigEndian>()?);
 
             let health = self.reader.read_i32::<BigEndian>()?;
             let radiation = self.reader.read_i32::<BigEndian>()?;
@@ -335,70 +395,85 @@ impl<'a, R: 'a + Read> MapReader<'a, R> {
                 poison,
                 combat: CritterCombat {
                     damage_flags,
+                    maneuver,
+                    ap,
+                    ai_packet,
+                    team_id,
+                    who_hit_me: None,
                 },
             })
         } else {
-            assert!(updated_flags != 0xcccccccc);
-    //            let update_flags = if updated_flags == 0xcccccccc {
-    //                0
-    //            } else {
-    //                updated_flags
-    //            };
-            match pid.kind() {
+            let pid = pid.unwrap();
+            let mut sub = SubObject::None;
+            match kind {
                 EntityKind::Item => {
                     let proto = self.proto_db.proto(pid).unwrap();
+                    let mut item = Item::default();
                     match proto.sub.item().unwrap().sub {
                         SubItem::Weapon(ref proto) => {
-                            let _charges = self.reader.read_i32::<BigEndian>()?;
+                            let charges = self.reader.read_i32::<BigEndian>()?;
                             let ammo_pid = ProtoId::from_packed(self.reader.read_u32::<BigEndian>()?);
 
                             // object_fix_weapon_ammo()
-                            let _charges = proto.max_ammo;
-                            let _ammo_pid = if ammo_pid.is_none() {
+                            item.charges = if charges < 0 {
+                                proto.max_ammo
+                            } else {
+                                charges
+                            };
+                            item.ammo_pid = if ammo_pid.is_none() {
                                 proto.ammo_pid
                             } else {
                                 ammo_pid
                             };
                         }
                         SubItem::Ammo(_) => {
-                            let _charges = self.reader.read_i32::<BigEndian>()?;
+                            item.charges = self.reader.read_i32::<BigEndian>()?;
                         }
                         SubItem::Misc(ref proto) => {
                             let charges = self.reader.read_i32::<BigEndian>()?;
 
                             // object_fix_weapon_ammo()
-                            let _charges = if charges < 0 {
+                            item.charges = if charges < 0 {
                                 proto.max_charges
                             } else {
                                 charges
                             };
                         }
                         SubItem::Key(_) => {
-                            let _key_code = self.reader.read_i32::<BigEndian>()?;
+                            item.key_code = self.reader.read_i32::<BigEndian>()?;
                         }
                         _ => {}
                     }
+                    sub = SubObject::Item(item);
                 }
                 EntityKind::Scenery => {
                     let k = self.proto_db.kind(pid);
                     let kind = k.scenery().unwrap();
                     match kind {
                         SceneryKind::Door => {
-                            let _walk_thru = self.reader.read_i32::<BigEndian>()?;
+                            // Doors keep the lock state here.
+                            let open_flags = self.reader.read_u32::<BigEndian>()?;
+                            lock_flags |= BitFlags::from_bits_truncate(open_flags);
                         }
                         SceneryKind::Stairs => {
-                            let _dest_map_id = self.reader.read_u32::<BigEndian>()?;
-                            let _dest_pos_and_elevation = self.reader.read_u32::<BigEndian>()?;
+                            let map_id = self.reader.read_i32::<BigEndian>()?;
+                            let (pos, direction) =
+                                unpack_built_tile(self.reader.read_u32::<BigEndian>()?);
+                            sub = SubObject::Exit(Exit { map_id, pos, direction });
                         }
                         SceneryKind::Elevator => {
-                            let _elevator_kind = self.reader.read_u32::<BigEndian>()?;
-                            let _level = self.reader.read_u32::<BigEndian>()?;
+                            let kind = self.reader.read_u32::<BigEndian>()?;
+                            let level = self.reader.read_u32::<BigEndian>()?;
+                            sub = SubObject::Elevator(Elevator { kind, level });
                         }
                         SceneryKind::LadderDown | SceneryKind::LadderUp => {
-                            if f2 {
-                                let _dest_pos_and_elevation = self.reader.read_u32::<BigEndian>()?;
-                            }
-                            let _dest_map_id = self.reader.read_u32::<BigEndian>()?;
+                            let (pos, direction) = if f2 {
+                                unpack_built_tile(self.reader.read_u32::<BigEndian>()?)
+                            } else {
+                                (None, Direction::NE)
+                            };
+                            let map_id = self.reader.read_i32::<BigEndian>()?;
+                            sub = SubObject::Exit(Exit { map_id, pos, direction });
                         }
                         _ => {}
                     }
@@ -415,15 +490,21 @@ impl<'a, R: 'a + Read> MapReader<'a, R> {
     //            if ( v7 < 33 )
     //              obj->art_fid = art_id_(OBJ_TYPE_MISC, v7 + 16, (obj->art_fid & 0xFF0000) >> 16, 0);
     //          }*/
-                        let _dude_pos = self.reader.read_u32::<BigEndian>()?;
-                        let _elevation = self.reader.read_u32::<BigEndian>()?;
-                        let _direction = Direction::from_u32(self.reader.read_u32::<BigEndian>()?)
-                            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "invalid exit direction"));
+                        let pos = self.reader.read_i32::<BigEndian>()?;
+                        let elevation = self.reader.read_u32::<BigEndian>()?;
+                        let direction = Direction::from_u32(self.reader.read_u32::<BigEndian>()?)
+                            .unwrap_or(Direction::NE);
+                        let pos = if pos >= 0 && elevation < ELEVATION_COUNT {
+                            Some(tile_grid().from_linear_inv(pos as u32).elevated(elevation))
+                        } else {
+                            None
+                        };
+                        sub = SubObject::Exit(Exit { map_id, pos, direction });
                     }
                 }
                 _ => {}
             }
-            SubObject::None
+            sub
         };
 
         // inventory
@@ -436,8 +517,7 @@ impl<'a, R: 'a + Read> MapReader<'a, R> {
             trace!("loading inventory item {}/{}", i, inventory_len);
             let count = self.reader.read_i32::<BigEndian>()? as usize;
             trace!("item count: {}", count);
-            let object = self.read_obj(f2)?;
-            let object = self.objects.insert(object);
+            let object = self.read_obj(f2, obj_ids)?;
             inventory.items.push(InventoryItem {
                 object,
                 count,
@@ -452,7 +532,7 @@ impl<'a, R: 'a + Read> MapReader<'a, R> {
         } else {
             None
         };
-        Ok(Object {
+        let objh = self.objects.insert(Object {
             flags,
             pos,
             screen_pos,
@@ -461,13 +541,22 @@ impl<'a, R: 'a + Read> MapReader<'a, R> {
             frame_idx,
             direction,
             light_emitter,
-            pid: pid.into(),
+            pid: obj_pid,
             inventory,
             outline,
             sequence: None,
             script,
+            lock_flags,
             sub,
-        })
+        });
+        if let Some((sid, _)) = script {
+            self.scripts.attach_to_object(sid, objh);
+        }
+        obj_ids.by_id.insert(id, objh);
+        if let Some(who_hit_me) = who_hit_me {
+            obj_ids.who_hit_me.push((objh, who_hit_me));
+        }
+        Ok(objh)
     }
 
     fn read_obj_script(&mut self) -> io::Result<Option<(Sid, ProgramId)>> {
@@ -502,7 +591,7 @@ impl<'a, R: 'a + Read> MapReader<'a, R> {
         }
 
         let translucent = take_bit(flags, OutlineFlag::Translucent);
-        let disabled = take_bit(flags, OutlineFlag::Translucent);
+        let disabled = take_bit(flags, OutlineFlag::Disabled);
 
         let style =
             if take_bit(flags, OutlineFlag::GlowingRed) { OutlineStyle::GlowingRed }
@@ -528,8 +617,13 @@ impl<'a, R: 'a + Read> MapReader<'a, R> {
         }))
     }
 
-    fn make_map_script(&mut self, program_id: ProgramId) -> io::Result<()> {
-        let sid = self.scripts.instantiate_map_script(program_id)?;
+    fn make_map_script(&mut self, program_id: ProgramId, sid: Option<Sid>) -> io::Result<()> {
+        let sid = if let Some(sid) = sid {
+            self.scripts.set_map_sid(sid);
+            sid
+        } else {
+            self.scripts.instantiate_map_script(program_id)?
+        };
         let mut obj = Object::new(FrameId::MAPMK, ObjectProtoId::None, Some(Default::default()));
         obj.flags = BitFlags::from(Flag::LightThru)
             | Flag::WalkThru
@@ -549,3 +643,479 @@ impl<'a, R: 'a + Read> MapReader<'a, R> {
 
 
 
+
+/// Writes the map state in the map file format with the savegame flag set. Such maps are read
+/// back with `MapReader` restoring the objects, the scripts along with their local vars and
+/// the map vars.
+// map_save_file()
+pub struct MapWriter<'a, W: 'a> {
+    pub writer: &'a mut W,
+    pub name: &'a str,
+    pub id: i32,
+    pub entrance: EPoint,
+    pub entrance_direction: Direction,
+    pub game_time: GameTime,
+    pub sqr_tiles: &'a [Option<Array2d<(u16, u16)>>],
+    pub map_vars: &'a [i32],
+    pub objects: &'a Objects,
+    pub proto_db: &'a ProtoDb,
+    pub scripts: &'a Scripts,
+    /// Persistent IDs of the objects to write. Objects without ID are not written. Inventory
+    /// items of the written objects must have IDs too.
+    pub obj_ids: &'a HashMap<object::Handle, u32>,
+}
+
+impl<'a, W: 'a + Write> MapWriter<'a, W> {
+    pub fn write(&mut self) -> io::Result<()> {
+        debug_time!("MapWriter::write()");
+
+        let map_sid = self.scripts.map_sid();
+        let map_program_id = map_sid.map(|sid| self.scripts.get(sid).unwrap().program_id);
+
+        let mut scripts: Vec<_> = self.scripts.iter()
+            .filter(|&(sid, script)| Some(sid) == map_sid
+                || script.object.map(|o| self.obj_ids.contains_key(&o)).unwrap_or(true))
+            .collect();
+        scripts.sort_by_key(|&(sid, _)| sid);
+        let local_var_count: usize = scripts.iter()
+            .map(|(_, script)| script.local_vars.len())
+            .sum();
+
+        // header
+
+        self.writer.write_u32::<BigEndian>(20)?;
+
+        let mut name = [0; 16];
+        let name_len = cmp::min(self.name.len(), name.len() - 1);
+        name[..name_len].copy_from_slice(&self.name.as_bytes()[..name_len]);
+        self.writer.write_all(&name[..])?;
+
+        self.writer.write_i32::<BigEndian>(
+            linear_pos(self.entrance.point).map(|v| v as i32).unwrap_or(-1))?;
+        self.writer.write_u32::<BigEndian>(self.entrance.elevation)?;
+        self.writer.write_u32::<BigEndian>(self.entrance_direction as u32)?;
+        self.writer.write_i32::<BigEndian>(local_var_count as i32)?;
+        self.writer.write_i32::<BigEndian>(map_program_id.map(|v| v.val() as i32).unwrap_or(-1))?;
+
+        let mut flags = 0x1;
+        for (i, tiles) in self.sqr_tiles.iter().enumerate() {
+            if tiles.is_none() {
+                flags |= 1 << (i as u32 + 1);
+            }
+        }
+        self.writer.write_u32::<BigEndian>(flags)?;
+
+        self.writer.write_i32::<BigEndian>(0)?;
+        self.writer.write_i32::<BigEndian>(self.map_vars.len() as i32)?;
+        self.writer.write_i32::<BigEndian>(self.id)?;
+        self.writer.write_u32::<BigEndian>(self.game_time.as_decis())?;
+
+        self.writer.write_all(&[0; 44 * 4][..])?;
+
+        // map global vars
+
+        for &v in self.map_vars {
+            self.writer.write_i32::<BigEndian>(v)?;
+        }
+
+        // map local vars
+
+        for (_, script) in &scripts {
+            for &v in script.local_vars.iter() {
+                self.writer.write_i32::<BigEndian>(v)?;
+            }
+        }
+
+        // tiles
+
+        for tiles in self.sqr_tiles.iter().filter_map(|t| t.as_ref()) {
+            for y in 0..tiles.height() {
+                for x in (0..tiles.width()).rev() {
+                    let &(floor_id, roof_id) = tiles.get(x, y).unwrap();
+                    self.writer.write_u16::<BigEndian>(roof_id)?;
+                    self.writer.write_u16::<BigEndian>(floor_id)?;
+                }
+            }
+        }
+
+        // scripts
+
+        let mut local_var_offset = 0;
+        for script_kind in ScriptKind::iter() {
+            let scripts: Vec<_> = scripts.iter()
+                .filter(|(sid, _)| sid.kind() == script_kind)
+                .collect();
+            self.writer.write_i32::<BigEndian>(scripts.len() as i32)?;
+
+            const NODE_LEN: usize = 16;
+            for node in scripts.chunks(NODE_LEN) {
+                for &&(sid, script) in node {
+                    self.write_script(sid, script, local_var_offset)?;
+                    local_var_offset += script.local_vars.len();
+                }
+                // Unused slots are read back as garbage.
+                for _ in node.len()..NODE_LEN {
+                    self.writer.write_i32::<BigEndian>(-1)?;
+                    self.writer.write_all(&[0; 15 * 4][..])?;
+                }
+                self.writer.write_i32::<BigEndian>(node.len() as i32)?;
+                self.writer.write_i32::<BigEndian>(0)?;
+            }
+        }
+
+        // objects
+
+        let mut objects = Vec::with_capacity(ELEVATION_COUNT as usize);
+        for elevation in 0..ELEVATION_COUNT {
+            let mut elev_objects = Vec::new();
+            let grid = tile_grid();
+            for y in 0..grid.height() {
+                for x in 0..grid.width() {
+                    let pos = EPoint::new(elevation, Point::new(x, y));
+                    elev_objects.extend(self.objects.at(pos).iter()
+                        .cloned()
+                        .filter(|h| self.obj_ids.contains_key(h)));
+                }
+            }
+            objects.push(elev_objects);
+        }
+
+        let total_obj_count: usize = objects.iter().map(|v| v.len()).sum();
+        self.writer.write_i32::<BigEndian>(total_obj_count as i32)?;
+        for elev_objects in &objects {
+            self.writer.write_u32::<BigEndian>(elev_objects.len() as u32)?;
+            for &objh in elev_objects {
+                self.write_obj(objh)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_script(&mut self, sid: Sid, script: &Script, local_var_offset: usize)
+        -> io::Result<()>
+    {
+        self.writer.write_u32::<BigEndian>(sid.pack())?;
+        self.writer.write_i32::<BigEndian>(0)?;
+
+        match sid.kind() {
+            ScriptKind::Spatial => {
+                self.writer.write_i32::<BigEndian>(script.spatial.elevation_and_tile)?;
+                self.writer.write_i32::<BigEndian>(script.spatial.radius)?;
+            }
+            ScriptKind::Time => {
+                self.writer.write_i32::<BigEndian>(script.spatial.elevation_and_tile)?;
+            }
+            _ => {}
+        }
+
+        self.writer.write_u32::<BigEndian>(script.flags)?;
+
+        self.writer.write_i32::<BigEndian>(script.program_id.val() as i32 - 1)?;
+
+        self.writer.write_i32::<BigEndian>(0)?;
+        let self_obj_id = script.object
+            .and_then(|o| self.obj_ids.get(&o))
+            .map(|&id| id as i32)
+            .unwrap_or(-1);
+        self.writer.write_i32::<BigEndian>(self_obj_id)?;
+        self.writer.write_i32::<BigEndian>(local_var_offset as i32)?;
+        self.writer.write_i32::<BigEndian>(script.local_vars.len() as i32)?;
+
+        self.writer.write_all(&[0; 8 * 4][..])?;
+
+        Ok(())
+    }
+
+    fn write_obj(&mut self, objh: object::Handle) -> io::Result<()> {
+        let obj = self.objects.get(objh).borrow();
+
+        self.writer.write_u32::<BigEndian>(self.obj_ids[&objh])?;
+        self.writer.write_i32::<BigEndian>(obj.pos
+            .and_then(|p| linear_pos(p.point))
+            .map(|v| v as i32)
+            .unwrap_or(-1))?;
+        self.writer.write_i32::<BigEndian>(obj.screen_shift.x)?;
+        self.writer.write_i32::<BigEndian>(obj.screen_shift.y)?;
+        self.writer.write_i32::<BigEndian>(obj.screen_pos.x)?;
+        self.writer.write_i32::<BigEndian>(obj.screen_pos.y)?;
+        self.writer.write_i32::<BigEndian>(obj.frame_idx as i32)?;
+        self.writer.write_u32::<BigEndian>(obj.direction as u32)?;
+        self.writer.write_u32::<BigEndian>(obj.fid.packed())?;
+        self.writer.write_u32::<BigEndian>(obj.flags.bits())?;
+        self.writer.write_u32::<BigEndian>(obj.pos.map(|p| p.elevation).unwrap_or(0))?;
+        self.writer.write_u32::<BigEndian>(obj.pid.pack())?;
+        self.writer.write_i32::<BigEndian>(-1)?;
+        self.writer.write_i32::<BigEndian>(obj.light_emitter.radius as i32)?;
+        self.writer.write_i32::<BigEndian>(obj.light_emitter.intensity as i32)?;
+        self.writer.write_u32::<BigEndian>(outline_flags(obj.outline).bits())?;
+
+        if let Some((sid, program_id)) = obj.script {
+            self.writer.write_u32::<BigEndian>(sid.pack())?;
+            self.writer.write_i32::<BigEndian>(program_id.val() as i32 - 1)?;
+        } else {
+            self.writer.write_i32::<BigEndian>(-1)?;
+            self.writer.write_i32::<BigEndian>(-1)?;
+        }
+
+        // proto update data
+
+        self.writer.write_i32::<BigEndian>(obj.inventory.items.len() as i32)?;
+        self.writer.write_i32::<BigEndian>(obj.inventory.capacity as i32)?;
+        self.writer.write_u32::<BigEndian>(0)?;
+
+        if let Some(critter) = obj.sub.critter() {
+            self.writer.write_u32::<BigEndian>(0)?;
+
+            // combat data
+            self.writer.write_u32::<BigEndian>(0)?;
+            self.writer.write_u32::<BigEndian>(critter.combat.maneuver.bits())?;
+            self.writer.write_i32::<BigEndian>(critter.combat.ap)?;
+            self.writer.write_u32::<BigEndian>(critter.combat.damage_flags.bits())?;
+            self.writer.write_u32::<BigEndian>(critter.combat.ai_packet)?;
+            self.writer.write_u32::<BigEndian>(critter.combat.team_id)?;
+            self.writer.write_u32::<BigEndian>(critter.combat.who_hit_me
+                .and_then(|o| self.obj_ids.get(&o).cloned())
+                .unwrap_or(0xffffffff))?;
+
+            self.writer.write_i32::<BigEndian>(critter.health)?;
+            self.writer.write_i32::<BigEndian>(critter.radiation)?;
+            self.writer.write_i32::<BigEndian>(critter.poison)?;
+        } else {
+            self.writer.write_u32::<BigEndian>(obj.lock_flags.bits())?;
+
+            let pid = obj.pid.proto_id().unwrap();
+            match pid.kind() {
+                EntityKind::Item => {
+                    let item = obj.sub.item().unwrap();
+                    match self.proto_db.proto(pid).unwrap().sub.item().unwrap().sub {
+                        SubItem::Weapon(_) => {
+                            self.writer.write_i32::<BigEndian>(item.charges)?;
+                            self.writer.write_u32::<BigEndian>(item.ammo_pid
+                                .map(|pid| pid.pack())
+                                .unwrap_or(0xffffffff))?;
+                        }
+                        SubItem::Ammo(_) | SubItem::Misc(_) => {
+                            self.writer.write_i32::<BigEndian>(item.charges)?;
+                        }
+                        SubItem::Key(_) => {
+                            self.writer.write_i32::<BigEndian>(item.key_code)?;
+                        }
+                        _ => {}
+                    }
+                }
+                EntityKind::Scenery => {
+                    let exit = obj.sub.exit().cloned().unwrap_or(Exit {
+                        map_id: -1,
+                        pos: None,
+                        direction: Direction::NE,
+                    });
+                    match self.proto_db.kind(pid).scenery().unwrap() {
+                        SceneryKind::Door => {
+                            self.writer.write_u32::<BigEndian>(obj.lock_flags.bits())?;
+                        }
+                        SceneryKind::Stairs => {
+                            self.writer.write_i32::<BigEndian>(exit.map_id)?;
+                            self.writer.write_u32::<BigEndian>(
+                                pack_built_tile(exit.pos, exit.direction))?;
+                        }
+                        SceneryKind::Elevator => {
+                            let elevator = obj.sub.elevator().cloned()
+                                .unwrap_or(Elevator { kind: 0, level: 0 });
+                            self.writer.write_u32::<BigEndian>(elevator.kind)?;
+                            self.writer.write_u32::<BigEndian>(elevator.level)?;
+                        }
+                        SceneryKind::LadderDown | SceneryKind::LadderUp => {
+                            self.writer.write_u32::<BigEndian>(
+                                pack_built_tile(exit.pos, exit.direction))?;
+                            self.writer.write_i32::<BigEndian>(exit.map_id)?;
+                        }
+                        _ => {}
+                    }
+                }
+                EntityKind::Misc => {
+                    if pid.is_exit_area() {
+                        let exit = obj.sub.exit().unwrap();
+                        self.writer.write_i32::<BigEndian>(exit.map_id)?;
+                        self.writer.write_i32::<BigEndian>(exit.pos
+                            .and_then(|p| linear_pos(p.point))
+                            .map(|v| v as i32)
+                            .unwrap_or(-1))?;
+                        self.writer.write_u32::<BigEndian>(exit.pos.map(|p| p.elevation)
+                            .unwrap_or(0))?;
+                        self.writer.write_u32::<BigEndian>(exit.direction as u32)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // inventory
+
+        for item in &obj.inventory.items {
+            self.writer.write_i32::<BigEndian>(item.count as i32)?;
+            self.write_obj(item.object)?;
+        }
+
+        Ok(())
+    }
+}
+
+const BUILT_TILE_POS_MASK: u32 = 0x3ff_ffff;
+const BUILT_TILE_DIRECTION_SHIFT: u32 = 26;
+const BUILT_TILE_ELEVATION_SHIFT: u32 = 29;
+
+/// Unpacks destination position and direction of stairs and ladders.
+// builtTileGetTile(), builtTileGetElevation(), builtTileGetRotation()
+fn unpack_built_tile(v: u32) -> (Option<EPoint>, Direction) {
+    if v as i32 == -1 {
+        return (None, Direction::NE);
+    }
+    let elevation = v >> BUILT_TILE_ELEVATION_SHIFT;
+    let direction = Direction::from_u32((v >> BUILT_TILE_DIRECTION_SHIFT) & 0x7)
+        .unwrap_or(Direction::NE);
+    let pos = Some(tile_grid().from_linear_inv(v & BUILT_TILE_POS_MASK))
+        .filter(|_| elevation < ELEVATION_COUNT)
+        .map(|p| p.elevated(elevation));
+    (pos, direction)
+}
+
+fn pack_built_tile(pos: Option<EPoint>, direction: Direction) -> u32 {
+    match pos.and_then(|p| linear_pos(p.point).map(|v| (v, p.elevation))) {
+        Some((tile, elevation)) => tile
+            | (direction as u32) << BUILT_TILE_DIRECTION_SHIFT
+            | elevation << BUILT_TILE_ELEVATION_SHIFT,
+        None => 0xffff_ffff,
+    }
+}
+
+fn linear_pos(p: Point) -> Option<u32> {
+    tile_grid().to_linear_inv(p)
+}
+
+fn outline_flags(outline: Option<Outline>) -> BitFlags<OutlineFlag> {
+    let mut r = BitFlags::empty();
+    if let Some(outline) = outline {
+        r |= match outline.style {
+            OutlineStyle::GlowingRed => OutlineFlag::GlowingRed,
+            OutlineStyle::Red => OutlineFlag::Red,
+            OutlineStyle::Gray => OutlineFlag::Gray,
+            OutlineStyle::GlowingGreen => OutlineFlag::GlowingGreen,
+            OutlineStyle::Yellow => OutlineFlag::Yellow,
+            OutlineStyle::Brown => OutlineFlag::Brown,
+            OutlineStyle::Purple => return r,
+        };
+        if outline.translucent {
+            r |= OutlineFlag::Translucent;
+        }
+        if outline.disabled {
+            r |= OutlineFlag::Disabled;
+        }
+    }
+    r
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+    use crate::util::test::TestAssets;
+
+    #[test]
+    fn write_read() {
+        let mut assets = TestAssets::new();
+        let door_fid = assets.add_frm(EntityKind::Scenery);
+        let door_pid = assets.add_door(door_fid, 0);
+        let key_fid = assets.add_frm(EntityKind::Item);
+        let key_pid = assets.add_key(key_fid, 1);
+        let program_id = assets.add_program(2);
+        let proto_db = assets.proto_db();
+        let frm_db = assets.frm_db();
+
+        let mut objects = Objects::new(tile_grid(), ELEVATION_COUNT, proto_db.clone(),
+            frm_db.clone());
+        let mut scripts = assets.scripts(proto_db.clone());
+
+        let mut door = Object::new(door_fid, ObjectProtoId::ProtoId(door_pid),
+            Some((1, (10, 20)).into()));
+        door.direction = Direction::SE;
+        door.frame_idx = 3;
+        door.lock_flags = LockFlag::Locked | LockFlag::Jammed;
+        let door = objects.insert(door);
+        let door_sid = scripts.instantiate_object_script(ScriptKind::Item, program_id,
+            Some(vec![5, 6].into()), door).unwrap();
+        objects.get(door).borrow_mut().script = Some((door_sid, program_id));
+
+        let mut key = Object::new(key_fid, ObjectProtoId::ProtoId(key_pid),
+            Some((0, (30, 40)).into()));
+        key.sub.item_mut().unwrap().key_code = 1234;
+        let key = objects.insert(key);
+
+        let spatial_sid = Sid::new(ScriptKind::Spatial, 0);
+        let spatial = Spatial {
+            elevation_and_tile: 0x2000_1234,
+            radius: 3,
+        };
+        scripts.instantiate(spatial_sid, program_id, Some(vec![7, 8].into())).unwrap();
+        scripts.set_map_fields(spatial_sid, 0x10, spatial);
+
+        let mut sqr_tiles = vec![Some(Array2d::with_default(100, 100)), None, None];
+        *sqr_tiles[0].as_mut().unwrap().get_mut(1, 2).unwrap() = (3, 4);
+
+        let mut obj_ids = HashMap::new();
+        obj_ids.insert(door, 1);
+        obj_ids.insert(key, 2);
+
+        let mut data = Vec::new();
+        MapWriter {
+            writer: &mut data,
+            name: "test",
+            id: 7,
+            entrance: (1, (50, 60)).into(),
+            entrance_direction: Direction::W,
+            game_time: GameTime::from_decis(1000),
+            sqr_tiles: &sqr_tiles,
+            map_vars: &[9, 10],
+            objects: &objects,
+            proto_db: &proto_db,
+            scripts: &scripts,
+            obj_ids: &obj_ids,
+        }.write().unwrap();
+
+        let mut objects = Objects::new(tile_grid(), ELEVATION_COUNT, proto_db.clone(),
+            frm_db.clone());
+        let mut scripts = assets.scripts(proto_db.clone());
+        let map = MapReader {
+            reader: &mut Cursor::new(data),
+            objects: &mut objects,
+            proto_db: &proto_db,
+            frm_db: &frm_db,
+            scripts: &mut scripts,
+        }.read().unwrap();
+
+        assert_eq!(map.id, 7);
+        assert!(map.savegame);
+        assert_eq!(map.entrance, (1, (50, 60)).into());
+        assert_eq!(map.entrance_direction, Direction::W);
+        assert_eq!(&map.map_vars[..], &[9, 10]);
+        assert_eq!(map.sqr_tiles[0].as_ref().unwrap().get(1, 2), Some(&(3, 4)));
+        assert!(map.sqr_tiles[1].is_none());
+
+        let door = objects.get(map.objects_by_id[&1]).borrow();
+        assert_eq!(door.pos, Some((1, (10, 20)).into()));
+        assert_eq!(door.direction, Direction::SE);
+        assert_eq!(door.frame_idx, 3);
+        assert_eq!(door.lock_flags, LockFlag::Locked | LockFlag::Jammed);
+        assert_eq!(door.script, Some((door_sid, program_id)));
+        assert_eq!(&scripts.get(door_sid).unwrap().local_vars[..], &[5, 6]);
+
+        let key = objects.get(map.objects_by_id[&2]).borrow();
+        assert_eq!(key.pos, Some((0, (30, 40)).into()));
+        assert_eq!(key.sub.item().unwrap().key_code, 1234);
+
+        let script = scripts.get(spatial_sid).unwrap();
+        assert_eq!(script.flags, 0x10);
+        assert_eq!(script.spatial, spatial);
+        assert_eq!(&script.local_vars[..], &[7, 8]);
+    }
+}
@@ -0,0 +1,143 @@
+use std::io::{self, prelude::*};
+
+use crate::asset::ini::Ini;
+use crate::fs::FileSystem;
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct MapDef {
+    /// Map file name without extension in lower case.
+    pub name: String,
+    pub lookup_name: String,
+    /// Whether the state of the map is kept after the dude leaves it.
+    pub saved: bool,
+    /// Elevation and tile number of the positions one of which is picked at random for the dude
+    /// entering the map. Used by the random encounter maps.
+    pub random_start_points: Vec<(u32, u32)>,
+}
+
+/// Maps listed in `data/maps.txt` by their IDs.
+pub struct MapDb {
+    defs: Vec<Option<MapDef>>,
+}
+
+impl MapDb {
+    pub fn new(fs: &FileSystem) -> io::Result<Self> {
+        Self::read(&mut fs.reader("data/maps.txt")?)
+    }
+
+    // map_init()
+    pub fn read(rd: &mut impl BufRead) -> io::Result<Self> {
+        let ini = Ini::read(rd)?;
+        let mut defs = Vec::new();
+        for section in ini.sections() {
+            let name = section.name();
+            let id: usize = if let Some(id) = name.get(..4)
+                .filter(|s| s.eq_ignore_ascii_case("map "))
+                .and_then(|_| name[4..].trim().parse().ok())
+            {
+                id
+            } else {
+                continue;
+            };
+            let mut random_start_points = Vec::new();
+            loop {
+                let key = format!("random_start_point_{}", random_start_points.len());
+                if section.get(&key).is_none() {
+                    break;
+                }
+                let mut elevation = None;
+                let mut tile_num = None;
+                for field in section.get_list(&key) {
+                    let mut parts = field.splitn(2, ':').map(|s| s.trim());
+                    let k = parts.next().unwrap();
+                    let v = parts.next().and_then(|v| v.parse().ok());
+                    if k.eq_ignore_ascii_case("elev") {
+                        elevation = v;
+                    } else if k.eq_ignore_ascii_case("tile_num") {
+                        tile_num = v;
+                    }
+                }
+                if let (Some(elevation), Some(tile_num)) = (elevation, tile_num) {
+                    random_start_points.push((elevation, tile_num));
+                } else {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData,
+                        format!("invalid value of `{}` in section [{}]", key, name)));
+                }
+            }
+            let def = MapDef {
+                name: section.require("map_name")?.to_lowercase(),
+                lookup_name: section.get("lookup_name").unwrap_or("").into(),
+                saved: !section.get("saved").unwrap_or("yes").eq_ignore_ascii_case("no"),
+                random_start_points,
+            };
+            if defs.len() <= id {
+                defs.resize_with(id + 1, || None);
+            }
+            defs[id] = Some(def);
+        }
+        Ok(Self {
+            defs,
+        })
+    }
+
+    pub fn get(&self, id: i32) -> Option<&MapDef> {
+        if id >= 0 {
+            self.defs.get(id as usize)?.as_ref()
+        } else {
+            None
+        }
+    }
+
+    /// Finds map ID by the `lookup_name` ignoring case.
+    pub fn find_by_lookup_name(&self, name: &str) -> Option<i32> {
+        self.defs.iter()
+            .position(|d| d.as_ref().map(|d| d.lookup_name.eq_ignore_ascii_case(name))
+                .unwrap_or(false))
+            .map(|i| i as i32)
+    }
+
+    /// Finds map ID by the map file name. The `.map` extension is optional.
+    pub fn find_by_name(&self, name: &str) -> Option<i32> {
+        let name = name.to_lowercase();
+        let name = name.trim_end_matches(".map");
+        self.defs.iter()
+            .position(|d| d.as_ref().map(|d| d.name == name).unwrap_or(false))
+            .map(|i| i as i32)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read() {
+        let db = MapDb::read(&mut &b"
+[Map 000]
+lookup_name=Desert Encounter 1
+map_name=desert1
+saved=No
+random_start_point_0=elev:0, tile_num:19102
+random_start_point_1=elev:1,tile_num:20711
+
+[Map 002]
+lookup_name=Arroyo Caves
+map_name=ARCAVES
+"[..]).unwrap();
+        assert_eq!(db.get(0), Some(&MapDef {
+            name: "desert1".into(),
+            lookup_name: "Desert Encounter 1".into(),
+            saved: false,
+            random_start_points: vec![(0, 19102), (1, 20711)],
+        }));
+        assert_eq!(db.get(1), None);
+        assert_eq!(db.get(2).unwrap().name, "arcaves");
+        assert!(db.get(2).unwrap().saved);
+        assert_eq!(db.get(-1), None);
+        assert_eq!(db.find_by_name("ARCAVES.MAP"), Some(2));
+        assert_eq!(db.find_by_name("desert1"), Some(0));
+        assert_eq!(db.find_by_name("artemple"), None);
+        assert_eq!(db.find_by_lookup_name("arroyo caves"), Some(2));
+        assert_eq!(db.find_by_lookup_name("Arroyo"), None);
+    }
+}
@@ -0,0 +1,106 @@
+use std::io::{self, Error, ErrorKind, prelude::*};
+
+use crate::asset::ai::{AreaAttackMode, BestWeapon, Distance};
+use crate::asset::ini::{Ini, Section};
+use crate::game::object::ObjectProtoId;
+
+/// Possible party member as defined in `data/party.txt`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MemberDef {
+    pub pid: ObjectProtoId,
+    /// Values the player can order the member to use via dialog. Empty list means the setting
+    /// can't be changed. `None` area attack mode (`no_pref`) means the one of the AI packet.
+    pub area_attack_modes: Vec<Option<AreaAttackMode>>,
+    pub best_weapons: Vec<BestWeapon>,
+    pub distances: Vec<Distance>,
+}
+
+impl MemberDef {
+    fn read(section: &Section) -> io::Result<Self> {
+        let pid = section.require_int("party_member_pid")?;
+        let pid = ObjectProtoId::from_packed(pid as u32)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData,
+                format!("invalid party member PID: {}", pid)))?;
+        Ok(Self {
+            pid,
+            area_attack_modes: section.get_list("area_attack_mode").into_iter()
+                .filter_map(|s| if s == "no_pref" {
+                    Some(None)
+                } else {
+                    AreaAttackMode::from_name(s).map(Some)
+                })
+                .collect(),
+            best_weapons: section.get_list("best_weapon").into_iter()
+                .filter_map(BestWeapon::from_name)
+                .collect(),
+            distances: section.get_list("distance").into_iter()
+                .filter_map(Distance::from_name)
+                .collect(),
+        })
+    }
+}
+
+/// Reads the possible party members from `data/party.txt`. The first member is the dude.
+// partyMember_init()
+pub fn read_members(rd: &mut impl BufRead) -> io::Result<Vec<MemberDef>> {
+    let ini = Ini::read(rd)?;
+    let mut r = Vec::new();
+    while let Some(section) = ini.section(&format!("Party Member {}", r.len())) {
+        r.push(MemberDef::read(section)?);
+    }
+    Ok(r)
+}
+
+/// Reads PIDs of the possible party members from `data/party.txt`. The first member is the dude.
+pub fn read_member_pids(rd: &mut impl BufRead) -> io::Result<Vec<ObjectProtoId>> {
+    Ok(read_members(rd)?.into_iter().map(|m| m.pid).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::asset::EntityKind;
+    use crate::asset::proto::ProtoId;
+
+    #[test]
+    fn read_member_pids_() {
+        let pids = read_member_pids(&mut &b"
+[Party Member 0]
+party_member_pid=16777216
+
+[Party Member 1]
+area_attack_mode=no_pref
+party_member_pid=16777313
+
+[Party Member 3]
+party_member_pid=16777314
+"[..]).unwrap();
+        assert_eq!(pids, vec![
+            ObjectProtoId::Dude,
+            ProtoId::new(EntityKind::Critter, 96).unwrap().into(),
+        ]);
+    }
+
+    #[test]
+    fn read_members_() {
+        let members = read_members(&mut &b"
+[Party Member 0]
+party_member_pid=16777216
+
+[Party Member 1]
+area_attack_mode=no_pref, always, be_careful
+best_weapon=no_pref, melee, ranged
+distance=stay_close, charge, snipe
+party_member_pid=16777313
+"[..]).unwrap();
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].pid, ObjectProtoId::Dude);
+        assert!(members[0].distances.is_empty());
+
+        let m = &members[1];
+        assert_eq!(m.area_attack_modes,
+            vec![None, Some(AreaAttackMode::Always), Some(AreaAttackMode::BeCareful)]);
+        assert_eq!(m.best_weapons, vec![BestWeapon::NoPref, BestWeapon::Melee, BestWeapon::Ranged]);
+        assert_eq!(m.distances, vec![Distance::StayClose, Distance::Charge, Distance::Snipe]);
+    }
+}
@@ -115,6 +115,18 @@ impl SubItem {
             Key(_)          => ItemKind::Key,
         }
     }
+
+    pub fn weapon(&self) -> Option<&Weapon> {
+        if let SubItem::Weapon(ref v) = self { Some(v) } else { None }
+    }
+
+    pub fn ammo(&self) -> Option<&Ammo> {
+        if let SubItem::Ammo(ref v) = self { Some(v) } else { None }
+    }
+
+    pub fn armor(&self) -> Option<&Armor> {
+        if let SubItem::Armor(ref v) = self { Some(v) } else { None }
+    }
 }
 
 #[derive(Debug)]
@@ -236,6 +248,13 @@ pub struct Critter {
     pub team_id: u32,
 }
 
+impl Critter {
+    /// Whether the critter opens the closed doors on its way. Only bipeds and robots do.
+    pub fn can_open_doors(&self) -> bool {
+        (self.body_kind == 0 || self.body_kind == 2) && self.kill_kind != CritterKillKind::Gecko
+    }
+}
+
 #[derive(Clone, Copy, Debug, EnumFlags, Eq, PartialEq)]
 #[repr(u32)]
 pub enum CritterFlag {
@@ -305,6 +324,10 @@ impl SubScenery {
             Misc => SceneryKind::Misc,
         }
     }
+
+    pub fn door(&self) -> Option<&Door> {
+        if let SubScenery::Door(ref v) = self { Some(v) } else { None }
+    }
 }
 
 #[derive(Debug)]
@@ -313,6 +336,13 @@ pub struct Door {
     pub key_id: u32,
 }
 
+impl Door {
+    /// Whether the dude opens the door by just walking into it.
+    pub fn is_walk_thru(&self) -> bool {
+        self.flags & 1 != 0
+    }
+}
+
 #[derive(Debug)]
 pub struct Stairs {
     pub elevation_and_tile: u32,
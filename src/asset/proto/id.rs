@@ -62,7 +62,9 @@ use crate::asset::EntityKind;
 pub struct ProtoId(u32);
 
 impl ProtoId {
+    pub const BOTTLE_CAPS: Self = unsafe { Self::from_packed_unchecked(0x29) };
     pub const SHIV: Self = unsafe { Self::from_packed_unchecked(0x17F) };
+    pub const MIRRORED_SHADES: Self = unsafe { Self::from_packed_unchecked(0x1B1) };
     pub const EXIT_AREA_FIRST: Self = unsafe { Self::from_packed_unchecked(0x5000010) };
     pub const EXIT_AREA_LAST: Self = unsafe { Self::from_packed_unchecked(0x5000017) };
     pub const RADIOACTIVE_GOO_FIRST: Self = unsafe { Self::from_packed_unchecked(0x20003D9) };
@@ -71,6 +73,10 @@ impl ProtoId {
     pub const ACTIVE_DYNAMITE: Self = unsafe { Self::from_packed_unchecked(0xCE) };
     pub const ACTIVE_PLASTIC_EXPLOSIVE: Self = unsafe { Self::from_packed_unchecked(0xD1) };
     pub const SCROLL_BLOCKER: Self = unsafe { Self::from_packed_unchecked(0x0500000c) };
+    pub const SMALL_ENERGY_CELL: Self = unsafe { Self::from_packed_unchecked(0x26) };
+    pub const MICRO_FUSION_CELL: Self = unsafe { Self::from_packed_unchecked(0x27) };
+    pub const CAR_TRUNK: Self = unsafe { Self::from_packed_unchecked(0x1C7) };
+    pub const DRIVABLE_CAR: Self = unsafe { Self::from_packed_unchecked(0x20003F1) };
 
     pub fn new(kind: EntityKind, id: u32) -> Option<Self> {
         if id < 0xffffff {
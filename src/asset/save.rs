@@ -0,0 +1,386 @@
+//! Savegames of the original game. A slot directory has `SAVE.DAT` and gzipped map states
+//! in `.SAV` files.
+
+use bstring::BString;
+use byteorder::{BigEndian, ReadBytesExt};
+use enum_map::EnumMap;
+use num_traits::FromPrimitive;
+use std::io::{self, Error, ErrorKind, prelude::*};
+
+use crate::asset::{PcStat, Perk, Skill, Trait};
+use crate::asset::gcd::{self, CritterData};
+use crate::game::GameTime;
+use crate::graphics::Point;
+use crate::graphics::geometry::hex::TileGrid;
+
+pub const SIGNATURE: &[u8] = b"FALLOUT SAVE FILE";
+
+pub const THUMBNAIL_WIDTH: i32 = 224;
+pub const THUMBNAIL_HEIGHT: i32 = 133;
+
+const KILL_KIND_COUNT: usize = 19;
+const COMBAT_STATE_IN_COMBAT: u32 = 0x1;
+
+pub fn is_save_dat(data: &[u8]) -> bool {
+    data.starts_with(SIGNATURE)
+}
+
+#[derive(Debug)]
+pub struct Header {
+    /// Major and minor version.
+    pub version: (u16, u16),
+    pub dude_name: BString,
+    pub description: BString,
+    /// Real date of saving as year, month and day.
+    pub saved_on: (u16, u16, u16),
+    pub game_time: GameTime,
+    pub elevation: u32,
+    pub map_id: i32,
+    /// File name of the current map state in the slot directory, for example `ARTEMPLE.SAV`.
+    pub map_file_name: String,
+    /// Screenshot of `THUMBNAIL_WIDTH` x `THUMBNAIL_HEIGHT` palette color indices.
+    pub thumbnail: Box<[u8]>,
+}
+
+// LoadHeader()
+pub fn read_header(rd: &mut impl Read) -> io::Result<Header> {
+    let mut signature = [0; 24];
+    rd.read_exact(&mut signature[..])?;
+    if !is_save_dat(&signature) {
+        return Err(Error::new(ErrorKind::InvalidData, "not a SAVE.DAT file"));
+    }
+
+    let version = (rd.read_u16::<BigEndian>()?, rd.read_u16::<BigEndian>()?);
+    let _release = rd.read_u8()?;
+
+    let dude_name = read_fixed_str(rd, 32)?;
+    let description = read_fixed_str(rd, 30)?;
+
+    let day = rd.read_u16::<BigEndian>()?;
+    let month = rd.read_u16::<BigEndian>()?;
+    let year = rd.read_u16::<BigEndian>()?;
+    let _real_time = rd.read_i32::<BigEndian>()?;
+
+    // Game date is derived from the game time.
+    rd.read_exact(&mut [0; 3 * 2][..])?;
+    let game_time = GameTime::from_decis(rd.read_u32::<BigEndian>()?);
+
+    let elevation = rd.read_u16::<BigEndian>()? as u32;
+    let map_id = rd.read_i16::<BigEndian>()? as i32;
+    let map_file_name = read_fixed_str(rd, 16)?.into_string()
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid map file name"))?;
+
+    let mut thumbnail = vec![0; (THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT) as usize];
+    rd.read_exact(&mut thumbnail)?;
+
+    rd.read_exact(&mut [0; 128][..])?;
+
+    Ok(Header {
+        version,
+        dude_name,
+        description,
+        saved_on: (year, month, day),
+        game_time,
+        elevation,
+        map_id,
+        map_file_name,
+        thumbnail: thumbnail.into(),
+    })
+}
+
+/// Part of `SAVE.DAT` preceding the dude object.
+#[derive(Debug)]
+pub struct Game {
+    pub global_vars: Vec<i32>,
+    /// File names of the map states in the slot directory. Includes the current map.
+    pub map_file_names: Vec<String>,
+}
+
+/// Reads the game state following the header. The `global_var_count` is the number of
+/// variables in `data/vault13.gam`.
+// SaveObjDudeCid(), scr_game_load(), SlotMap2Game(), scr_skip_game_global_vars()
+pub fn read_game(rd: &mut impl Read, global_var_count: usize) -> io::Result<Game> {
+    let _dude_cid = rd.read_i32::<BigEndian>()?;
+
+    let mut global_vars = Vec::with_capacity(global_var_count);
+    for _ in 0..global_var_count {
+        global_vars.push(rd.read_i32::<BigEndian>()?);
+    }
+
+    let map_count = rd.read_i32::<BigEndian>()?;
+    if map_count <= 0 {
+        return Err(Error::new(ErrorKind::InvalidData, "no maps in SAVE.DAT"));
+    }
+    let mut map_file_names = Vec::with_capacity(map_count as usize);
+    for _ in 0..map_count {
+        map_file_names.push(read_c_str(rd)?.into_string()
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid map file name"))?);
+    }
+    let _automap_db_len = rd.read_i32::<BigEndian>()?;
+
+    // The global vars are written twice.
+    for _ in 0..global_var_count {
+        rd.read_i32::<BigEndian>()?;
+    }
+
+    Ok(Game {
+        global_vars,
+        map_file_names,
+    })
+}
+
+/// Part of `SAVE.DAT` following the dude object.
+#[derive(Debug)]
+pub struct Dude {
+    /// Position the view was centered on.
+    pub view_center: Point,
+    pub sneaking: bool,
+    pub critter: CritterData,
+    pub tagged_skills: Vec<Skill>,
+    /// Perk ranks of each party member listed in `data/party.txt`.
+    pub perks: Vec<EnumMap<Perk, u32>>,
+    pub pc_stats: EnumMap<PcStat, i32>,
+    pub traits: Vec<Trait>,
+}
+
+/// Reads the dude state that follows the dude object. The object itself is read with
+/// `MapReader::read_object()`. The `party_member_count` is the number of members listed
+/// in `data/party.txt`.
+///
+/// The state written after the dude (world map, party members, timer events etc.) isn't read.
+// obj_load_dude(), critter_load(), critter_kill_count_load(), skill_load(), roll_load(),
+// perk_load(), combat_load(), combat_ai_load(), stat_load(), item_load(), trait_load()
+pub fn read_dude(rd: &mut impl Read, party_member_count: usize) -> io::Result<Dude> {
+    let view_center = TileGrid::default().from_linear_inv(rd.read_u32::<BigEndian>()?);
+
+    let sneaking = rd.read_i32::<BigEndian>()? != 0;
+    let critter = gcd::read_critter_data(rd)?;
+
+    for _ in 0..KILL_KIND_COUNT {
+        let _kill_count = rd.read_i32::<BigEndian>()?;
+    }
+
+    let mut tagged_skills = Vec::new();
+    for _ in 0..4 {
+        if let Some(skill) = read_opt_enum(rd, "tagged skill")? {
+            tagged_skills.push(skill);
+        }
+    }
+
+    let mut perks = Vec::with_capacity(party_member_count);
+    for _ in 0..party_member_count {
+        let mut ranks = EnumMap::new();
+        for rank in ranks.values_mut() {
+            *rank = rd.read_i32::<BigEndian>()?.max(0) as u32;
+        }
+        perks.push(ranks);
+    }
+
+    let combat_state = rd.read_u32::<BigEndian>()?;
+    if combat_state & COMBAT_STATE_IN_COMBAT != 0 {
+        return Err(Error::new(ErrorKind::Other, "games saved in combat are not supported"));
+    }
+
+    // AI packet and team of the party members except the dude.
+    for _ in 0..party_member_count.saturating_sub(1) {
+        rd.read_i32::<BigEndian>()?;
+        rd.read_i32::<BigEndian>()?;
+    }
+
+    let mut pc_stats = EnumMap::new();
+    for v in pc_stats.values_mut() {
+        *v = rd.read_i32::<BigEndian>()?;
+    }
+
+    let mut traits = Vec::new();
+    for _ in 0..2 {
+        if let Some(tr) = read_opt_enum(rd, "trait")? {
+            traits.push(tr);
+        }
+    }
+
+    Ok(Dude {
+        view_center,
+        sneaking,
+        critter,
+        tagged_skills,
+        perks,
+        pc_stats,
+        traits,
+    })
+}
+
+/// Unpacks the `.SAV` map file contents if gzipped.
+pub fn unpack_map(data: Vec<u8>) -> io::Result<Vec<u8>> {
+    if data.starts_with(&[0x1f, 0x8b]) {
+        let mut r = Vec::new();
+        flate2::read::GzDecoder::new(&data[..]).read_to_end(&mut r)?;
+        Ok(r)
+    } else {
+        Ok(data)
+    }
+}
+
+fn read_opt_enum<T: FromPrimitive>(rd: &mut impl Read, what: &str) -> io::Result<Option<T>> {
+    let v = rd.read_i32::<BigEndian>()?;
+    if v >= 0 {
+        Ok(Some(T::from_i32(v)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("invalid {}", what)))?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn read_fixed_str(rd: &mut impl Read, len: usize) -> io::Result<BString> {
+    let mut buf = vec![0; len];
+    rd.read_exact(&mut buf)?;
+    let s_len = buf.iter().position(|&c| c == 0).unwrap_or(len);
+    buf.truncate(s_len);
+    Ok(buf.into())
+}
+
+fn read_c_str(rd: &mut impl Read) -> io::Result<BString> {
+    let mut r = Vec::new();
+    loop {
+        match rd.read_u8()? {
+            0 => break,
+            c => r.push(c),
+        }
+    }
+    Ok(r.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use byteorder::WriteBytesExt;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Cursor;
+
+    fn fixed_str(data: &mut Vec<u8>, s: &str, len: usize) {
+        let mut buf = s.as_bytes().to_vec();
+        buf.resize(len, 0);
+        data.extend_from_slice(&buf);
+    }
+
+    #[test]
+    fn read_header_() {
+        let mut data = Vec::new();
+        fixed_str(&mut data, "FALLOUT SAVE FILE", 24);
+        data.write_u16::<BigEndian>(1).unwrap();
+        data.write_u16::<BigEndian>(2).unwrap();
+        data.write_u8(b'R').unwrap();
+        fixed_str(&mut data, "Chitsa", 32);
+        fixed_str(&mut data, "Temple of Trials", 30);
+        for &v in &[17, 3, 2002] {
+            data.write_u16::<BigEndian>(v).unwrap();
+        }
+        data.write_i32::<BigEndian>(42).unwrap();
+        for &v in &[7, 25, 2241] {
+            data.write_u16::<BigEndian>(v).unwrap();
+        }
+        data.write_u32::<BigEndian>(302_401).unwrap();
+        data.write_u16::<BigEndian>(1).unwrap();
+        data.write_i16::<BigEndian>(0).unwrap();
+        fixed_str(&mut data, "ARTEMPLE.SAV", 16);
+        data.extend(vec![7; (THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT) as usize]);
+        data.extend(vec![0; 128]);
+        assert_eq!(data.len(), 30051);
+
+        let h = read_header(&mut Cursor::new(data)).unwrap();
+        assert_eq!(h.version, (1, 2));
+        assert_eq!(h.dude_name, "Chitsa");
+        assert_eq!(h.description, "Temple of Trials");
+        assert_eq!(h.saved_on, (2002, 3, 17));
+        assert_eq!(h.game_time, GameTime::from_decis(302_401));
+        assert_eq!(h.elevation, 1);
+        assert_eq!(h.map_id, 0);
+        assert_eq!(h.map_file_name, "ARTEMPLE.SAV");
+        assert!(h.thumbnail.iter().all(|&c| c == 7));
+    }
+
+    #[test]
+    fn read_game_() {
+        let mut data = Vec::new();
+        data.write_i32::<BigEndian>(18000).unwrap();
+        for i in 0..3 {
+            data.write_i32::<BigEndian>(i).unwrap();
+        }
+        data.write_i32::<BigEndian>(2).unwrap();
+        data.extend_from_slice(b"ARTEMPLE.SAV\0ARVILLAG.SAV\0");
+        data.write_i32::<BigEndian>(1234).unwrap();
+        for _ in 0..3 {
+            data.write_i32::<BigEndian>(-1).unwrap();
+        }
+        data.push(0xaa);
+
+        let rd = &mut Cursor::new(data);
+        let game = read_game(rd, 3).unwrap();
+        assert_eq!(game.global_vars, vec![0, 1, 2]);
+        assert_eq!(game.map_file_names, vec!["ARTEMPLE.SAV", "ARVILLAG.SAV"]);
+        assert_eq!(rd.read_u8().unwrap(), 0xaa);
+    }
+
+    #[test]
+    fn read_dude_() {
+        let mut data = Vec::new();
+        data.write_u32::<BigEndian>(0x4e20).unwrap();
+        data.write_i32::<BigEndian>(1).unwrap();
+        data.write_u32::<BigEndian>(0).unwrap();
+        for i in 0..35 + 35 + 18 + 4 {
+            data.write_i32::<BigEndian>(i).unwrap();
+        }
+        for _ in 0..KILL_KIND_COUNT {
+            data.write_i32::<BigEndian>(0).unwrap();
+        }
+        for &v in &[0, 8, 17, -1] {
+            data.write_i32::<BigEndian>(v).unwrap();
+        }
+        for member in 0..2 {
+            for perk in 0..119 {
+                data.write_i32::<BigEndian>(if perk == member { 1 } else { 0 }).unwrap();
+            }
+        }
+        data.write_u32::<BigEndian>(0).unwrap();
+        // AI packet and team of the only party member besides the dude.
+        for _ in 0..2 {
+            data.write_i32::<BigEndian>(0).unwrap();
+        }
+        for &v in &[3, 2, 1500, 0, 10] {
+            data.write_i32::<BigEndian>(v).unwrap();
+        }
+        for &v in &[15, -1] {
+            data.write_i32::<BigEndian>(v).unwrap();
+        }
+
+        let dude = read_dude(&mut Cursor::new(data), 2).unwrap();
+        assert_eq!(dude.view_center, TileGrid::default().from_linear_inv(0x4e20));
+        assert!(dude.sneaking);
+        assert_eq!(dude.critter.skills[Skill::Outdoorsman], 35 + 35 + 17);
+        assert_eq!(dude.tagged_skills, vec![Skill::SmallGuns, Skill::Sneak, Skill::Outdoorsman]);
+        assert_eq!(dude.perks.len(), 2);
+        assert_eq!(dude.perks[0][Perk::from_usize(0).unwrap()], 1);
+        assert_eq!(dude.perks[1][Perk::from_usize(1).unwrap()], 1);
+        assert_eq!(dude.perks[1][Perk::from_usize(0).unwrap()], 0);
+        assert_eq!(dude.pc_stats[PcStat::Experience], 1500);
+        assert_eq!(dude.traits, vec![Trait::Gifted]);
+    }
+
+    #[test]
+    fn read_dude_in_combat() {
+        let mut data = vec![0; 4 + 4 + 372 + KILL_KIND_COUNT * 4 + 4 * 4];
+        data.write_u32::<BigEndian>(COMBAT_STATE_IN_COMBAT).unwrap();
+        assert_eq!(read_dude(&mut Cursor::new(data), 0).unwrap_err().kind(), ErrorKind::Other);
+    }
+
+    #[test]
+    fn unpack_map_() {
+        let map = vec![1, 2, 3, 4];
+        assert_eq!(unpack_map(map.clone()).unwrap(), map);
+
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(&map).unwrap();
+        assert_eq!(unpack_map(gz.finish().unwrap()).unwrap(), map);
+    }
+}
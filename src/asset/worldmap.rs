@@ -0,0 +1,902 @@
+use enum_map::{enum_map, EnumMap};
+use enum_map_derive::Enum;
+use log::*;
+use num_traits::FromPrimitive;
+use std::io::{self, prelude::*};
+
+use crate::asset::EntityKind;
+use crate::asset::frame::FrameId;
+use crate::asset::ini::{Ini, Section};
+use crate::asset::map::db::MapDb;
+use crate::asset::proto::ProtoId;
+use crate::asset::script::ProgramId;
+use crate::fs::FileSystem;
+use crate::graphics::Point;
+use crate::graphics::geometry::hex::Direction;
+
+pub const TILE_WIDTH: i32 = 350;
+pub const TILE_HEIGHT: i32 = 300;
+pub const SUBTILE_SIZE: i32 = 50;
+pub const SUBTILE_COLS: i32 = TILE_WIDTH / SUBTILE_SIZE;
+pub const SUBTILE_ROWS: i32 = TILE_HEIGHT / SUBTILE_SIZE;
+
+#[derive(Clone, Copy, Debug, Enum, Eq, PartialEq)]
+pub enum DayTime {
+    Morning,
+    Afternoon,
+    Night,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Terrain {
+    pub name: String,
+    /// Travel time multiplier.
+    pub difficulty: u32,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Subtile {
+    /// Index into `WorldMapDef::terrains`.
+    pub terrain: usize,
+    /// Chance of random encounter in percents.
+    pub encounter_chance: EnumMap<DayTime, u32>,
+    pub encounter_table: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Tile {
+    pub fid: FrameId,
+    pub encounter_difficulty: i32,
+    /// `SUBTILE_COLS` x `SUBTILE_ROWS` subtiles row by row.
+    pub subtiles: Vec<Subtile>,
+}
+
+/// Placement of the critter group relative to the dude.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Formation {
+    Surrounding,
+    StraightLine,
+    DoubleLine,
+    Wedge,
+    Cone,
+    Huddle,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EncounterItem {
+    pub pid: ProtoId,
+    /// Inclusive range of the item count.
+    pub count: (u32, u32),
+    /// Whether the item is equipped.
+    pub wielded: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EncounterCritter {
+    /// Chance of the critter to be picked for each group member in percents.
+    pub ratio: u32,
+    pub dead: bool,
+    pub pid: ProtoId,
+    pub items: Vec<EncounterItem>,
+    pub script: Option<ProgramId>,
+}
+
+/// Critters of the `[Encounter: <name>]` section.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CritterGroup {
+    pub name: String,
+    pub formation: Formation,
+    /// Distance in hexes between the group members.
+    pub spacing: u32,
+    pub critters: Vec<EncounterCritter>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GroupAttitude {
+    Neutral,
+    /// The group attacks the dude.
+    Ambush,
+    /// The group fights the next group of the encounter.
+    Fighting,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EncounterGroup {
+    /// Inclusive range of the group size.
+    pub count: (u32, u32),
+    /// Index into `WorldMapDef::critter_groups`.
+    pub critters: usize,
+    pub attitude: GroupAttitude,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Encounter {
+    /// Relative chance of the encounter to be picked from the table.
+    pub chance: u32,
+    /// How many times the encounter can happen. `None` if unlimited.
+    pub counter: Option<u32>,
+    pub special: bool,
+    /// Map of the encounter. If `None` a random map of the table is used.
+    pub map_id: Option<i32>,
+    pub groups: Vec<EncounterGroup>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EncounterTable {
+    pub name: String,
+    /// Random encounter maps.
+    pub maps: Vec<i32>,
+    pub encounters: Vec<Encounter>,
+}
+
+/// World map layout and random encounters read from `data/worldmap.txt`.
+#[derive(Clone, Debug)]
+pub struct WorldMapDef {
+    pub terrains: Vec<Terrain>,
+    /// Number of tiles in a row.
+    pub tile_cols: i32,
+    pub tiles: Vec<Tile>,
+    pub encounter_tables: Vec<EncounterTable>,
+    pub critter_groups: Vec<CritterGroup>,
+}
+
+impl WorldMapDef {
+    pub fn new(fs: &FileSystem, map_db: &MapDb) -> io::Result<Self> {
+        Self::read(&mut fs.reader("data/worldmap.txt")?, map_db)
+    }
+
+    // wmConfigInit()
+    pub fn read(rd: &mut impl BufRead, map_db: &MapDb) -> io::Result<Self> {
+        let ini = Ini::read(rd)?;
+
+        let data = ini.section("Data").ok_or_else(|| invalid_data("missing [Data] section"))?;
+        let terrains = data.get_list("terrain_types").into_iter()
+            .map(|s| {
+                let (name, difficulty) = split_pair(s)?;
+                Ok(Terrain {
+                    name: name.into(),
+                    difficulty: difficulty.parse()
+                        .map_err(|_| invalid_data(format!("invalid terrain type: {}", s)))?,
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let frequencies = data.get_list("encounter_frequency").into_iter()
+            .map(|s| {
+                let (name, chance) = split_pair(s)?;
+                let chance = chance.trim_end_matches('%').trim().parse()
+                    .map_err(|_| invalid_data(format!("invalid encounter frequency: {}", s)))?;
+                Ok((name, chance))
+            })
+            .collect::<io::Result<Vec<(&str, u32)>>>()?;
+
+        let tile_cols = ini.section("Tile Data")
+            .and_then(|s| s.get_int("num_horizontal_tiles"))
+            .filter(|&v| v > 0)
+            .ok_or_else(|| invalid_data("missing or invalid num_horizontal_tiles"))?;
+
+        let mut tiles = Vec::new();
+        while let Some(section) = ini.section(&format!("Tile {}", tiles.len())) {
+            tiles.push(read_tile(section, &terrains, &frequencies)?);
+        }
+
+        let critter_groups = ini.sections().iter()
+            .filter_map(|s| {
+                let name = s.name();
+                name.get(..10)
+                    .filter(|p| p.eq_ignore_ascii_case("encounter:"))
+                    .map(|_| read_critter_group(s, name[10..].trim()))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut encounter_tables = Vec::new();
+        while let Some(section) =
+            ini.section(&format!("Encounter Table {}", encounter_tables.len()))
+        {
+            encounter_tables.push(read_encounter_table(section, &critter_groups, map_db)?);
+        }
+
+        Ok(Self {
+            terrains,
+            tile_cols,
+            tiles,
+            encounter_tables,
+            critter_groups,
+        })
+    }
+
+    /// Size of the world map in pixels.
+    pub fn size(&self) -> Point {
+        let rows = (self.tiles.len() as i32 + self.tile_cols - 1) / self.tile_cols;
+        Point::new(self.tile_cols * TILE_WIDTH, rows * TILE_HEIGHT)
+    }
+
+    /// Returns subtile at the world map pixel `pos`.
+    pub fn subtile_at(&self, pos: Point) -> Option<&Subtile> {
+        if pos.x < 0 || pos.y < 0 || pos.x >= self.tile_cols * TILE_WIDTH {
+            return None;
+        }
+        let tile = self.tiles.get(
+            (pos.y / TILE_HEIGHT * self.tile_cols + pos.x / TILE_WIDTH) as usize)?;
+        let x = pos.x % TILE_WIDTH / SUBTILE_SIZE;
+        let y = pos.y % TILE_HEIGHT / SUBTILE_SIZE;
+        tile.subtiles.get((y * SUBTILE_COLS + x) as usize)
+    }
+
+    /// Finds the encounter table by its lookup name ignoring case.
+    pub fn find_encounter_table(&self, name: &str) -> Option<usize> {
+        self.encounter_tables.iter().position(|t| t.name.eq_ignore_ascii_case(name))
+    }
+}
+
+fn read_tile(section: &Section, terrains: &[Terrain], frequencies: &[(&str, u32)])
+    -> io::Result<Tile>
+{
+    let fid = interface_fid(section.require_int("art_idx")?)
+        .ok_or_else(|| invalid_data(format!("invalid art_idx in [{}]", section.name())))?;
+    let encounter_difficulty = section.get_int("encounter_difficulty").unwrap_or(0);
+
+    let chance = |name: &str| frequencies.iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|&(_, v)| v)
+        .ok_or_else(|| invalid_data(format!("unknown encounter frequency: {}", name)));
+
+    let mut subtiles = Vec::with_capacity((SUBTILE_COLS * SUBTILE_ROWS) as usize);
+    for y in 0..SUBTILE_ROWS {
+        for x in 0..SUBTILE_COLS {
+            let key = format!("{}_{}", x, y);
+            let fields = section.get_list(&key);
+            if fields.len() < 5 {
+                return Err(invalid_data(
+                    format!("missing or invalid {} in [{}]", key, section.name())));
+            }
+            let terrain = terrains.iter()
+                .position(|t| t.name.eq_ignore_ascii_case(fields[0]))
+                .ok_or_else(|| invalid_data(format!("unknown terrain: {}", fields[0])))?;
+            // fields[1] is the fill direction used by the world map editor.
+            let (morning, afternoon, night) =
+                (chance(fields[2])?, chance(fields[3])?, chance(fields[4])?);
+            subtiles.push(Subtile {
+                terrain,
+                encounter_chance: enum_map! {
+                    DayTime::Morning => morning,
+                    DayTime::Afternoon => afternoon,
+                    DayTime::Night => night,
+                },
+                encounter_table: fields.get(5).map(|&s| s.into()),
+            });
+        }
+    }
+
+    Ok(Tile {
+        fid,
+        encounter_difficulty,
+        subtiles,
+    })
+}
+
+// wmReadEncounterType()
+fn read_encounter_table(section: &Section, critter_groups: &[CritterGroup], map_db: &MapDb)
+    -> io::Result<EncounterTable>
+{
+    let maps = section.get_list("maps").into_iter()
+        .filter_map(|name| {
+            let id = map_db.find_by_lookup_name(name);
+            if id.is_none() {
+                warn!("unknown map `{}` in [{}]", name, section.name());
+            }
+            id
+        })
+        .collect();
+
+    let mut encounters = Vec::new();
+    loop {
+        let key = format!("enc_{:02}", encounters.len());
+        if section.get(&key).is_none() {
+            break;
+        }
+        let invalid = || invalid_data(
+            format!("invalid value of `{}` in section [{}]", key, section.name()));
+
+        let mut encounter = Encounter {
+            chance: 0,
+            counter: None,
+            special: false,
+            map_id: None,
+            groups: Vec::new(),
+        };
+        for field in section.get_list(&key) {
+            let (name, value) = split_field(field);
+            match name.to_ascii_lowercase().as_str() {
+                "chance" => encounter.chance = parse_percents(value).ok_or_else(invalid)?,
+                "counter" => {
+                    let v: i32 = value.parse().map_err(|_| invalid())?;
+                    encounter.counter = if v >= 0 { Some(v as u32) } else { None };
+                }
+                "special" => encounter.special = true,
+                "map" => {
+                    encounter.map_id = map_db.find_by_lookup_name(value);
+                    if encounter.map_id.is_none() {
+                        warn!("unknown map `{}` in {} of [{}]", value, key, section.name());
+                    }
+                }
+                "enc" => encounter.groups = read_encounter_groups(value, critter_groups)
+                    .ok_or_else(invalid)?,
+                // TODO support conditions
+                _ if name.get(..3).map(|s| s.eq_ignore_ascii_case("if(")).unwrap_or(false) => {}
+                _ => warn!("unknown field `{}` in {} of [{}]", field, key, section.name()),
+            }
+        }
+        encounters.push(encounter);
+    }
+
+    Ok(EncounterTable {
+        name: section.require("lookup_name")?.into(),
+        maps,
+        encounters,
+    })
+}
+
+/// Parses the encounter groups like `(2-4) RAIDERS AMBUSH Player` or
+/// `(1) GUARDS FIGHTING (3-5) BANDITS`.
+// wmParseEncounterSubEncStr()
+fn read_encounter_groups(s: &str, critter_groups: &[CritterGroup])
+    -> Option<Vec<EncounterGroup>>
+{
+    let mut r = Vec::new();
+    let mut words = s.split_whitespace().peekable();
+    while words.peek().is_some() {
+        let count = if words.peek().unwrap().starts_with('(') {
+            parse_range(words.next().unwrap())?
+        } else {
+            (1, 1)
+        };
+
+        let mut name = Vec::new();
+        let mut attitude = GroupAttitude::Neutral;
+        while let Some(word) = words.next() {
+            match word.to_ascii_lowercase().as_str() {
+                "and" => break,
+                "ambush" => {
+                    attitude = GroupAttitude::Ambush;
+                    // The ambush target which is always the player.
+                    words.next();
+                }
+                "fighting" => {
+                    attitude = GroupAttitude::Fighting;
+                    break;
+                }
+                _ if attitude == GroupAttitude::Neutral => name.push(word),
+                _ => return None,
+            }
+        }
+        if name.is_empty() {
+            return None;
+        }
+        let name = name.join(" ");
+
+        if let Some(critters) = critter_groups.iter()
+            .position(|g| g.name.eq_ignore_ascii_case(&name))
+        {
+            r.push(EncounterGroup {
+                count,
+                critters,
+                attitude,
+            });
+        } else {
+            warn!("unknown encounter critter group: {}", name);
+        }
+    }
+    Some(r)
+}
+
+// wmReadEncBaseType()
+fn read_critter_group(section: &Section, name: &str) -> io::Result<CritterGroup> {
+    let invalid = |key: &str| invalid_data(
+        format!("invalid value of `{}` in section [{}]", key, section.name()));
+
+    let mut formation = Formation::Surrounding;
+    let mut spacing = 1;
+    for (i, field) in section.get_list("position").into_iter().enumerate() {
+        let (name, value) = split_field(field);
+        if i == 0 {
+            formation = match name.to_ascii_lowercase().as_str() {
+                "surrounding" => Formation::Surrounding,
+                "straight_line" => Formation::StraightLine,
+                "double_line" => Formation::DoubleLine,
+                "wedge" => Formation::Wedge,
+                "cone" => Formation::Cone,
+                "huddle" => Formation::Huddle,
+                _ => return Err(invalid("position")),
+            };
+        } else if name.eq_ignore_ascii_case("spacing") {
+            spacing = value.parse().map_err(|_| invalid("position"))?;
+        }
+    }
+
+    let mut critters = Vec::new();
+    loop {
+        let key = format!("type_{:02}", critters.len());
+        if section.get(&key).is_none() {
+            break;
+        }
+        let mut ratio = 100;
+        let mut dead = false;
+        let mut pid = None;
+        let mut items = Vec::new();
+        let mut script = None;
+        for field in section.get_list(&key) {
+            let (name, value) = split_field(field);
+            match name.to_ascii_lowercase().as_str() {
+                "ratio" => ratio = parse_percents(value).ok_or_else(|| invalid(&key))?,
+                "dead" => dead = true,
+                "pid" => pid = Some(parse_pid(value).ok_or_else(|| invalid(&key))?),
+                "item" => items.push(parse_item(value).ok_or_else(|| invalid(&key))?),
+                "script" => script = Some(value.parse().ok()
+                    .and_then(ProgramId::new)
+                    .ok_or_else(|| invalid(&key))?),
+                // TODO support conditions
+                _ if name.get(..3).map(|s| s.eq_ignore_ascii_case("if(")).unwrap_or(false) => {}
+                _ => warn!("unknown field `{}` in {} of [{}]", field, key, section.name()),
+            }
+        }
+        critters.push(EncounterCritter {
+            ratio,
+            dead,
+            pid: pid.ok_or_else(|| invalid(&key))?,
+            items,
+            script,
+        });
+    }
+
+    Ok(CritterGroup {
+        name: name.into(),
+        formation,
+        spacing,
+        critters,
+    })
+}
+
+/// Parses item like `(1-10)41{wielded}`.
+fn parse_item(s: &str) -> Option<EncounterItem> {
+    let (count, s) = if s.starts_with('(') {
+        let i = s.find(')')?;
+        (parse_range(&s[..=i])?, s[i + 1..].trim())
+    } else {
+        ((1, 1), s)
+    };
+    let (pid, wielded) = if s.to_ascii_lowercase().ends_with("{wielded}") {
+        (s[..s.len() - 9].trim(), true)
+    } else {
+        (s, false)
+    };
+    Some(EncounterItem {
+        pid: parse_pid(pid)?,
+        count,
+        wielded,
+    })
+}
+
+/// Parses inclusive range like `(2-5)` or `(3)`.
+fn parse_range(s: &str) -> Option<(u32, u32)> {
+    let s = s.trim();
+    if !s.starts_with('(') || !s.ends_with(')') {
+        return None;
+    }
+    let s = &s[1..s.len() - 1];
+    let (from, to) = if let Some(i) = s.find('-') {
+        (s[..i].trim().parse().ok()?, s[i + 1..].trim().parse().ok()?)
+    } else {
+        let v = s.trim().parse().ok()?;
+        (v, v)
+    };
+    if from <= to {
+        Some((from, to))
+    } else {
+        None
+    }
+}
+
+fn parse_pid(s: &str) -> Option<ProtoId> {
+    ProtoId::from_packed(s.trim().parse().ok()?)
+}
+
+fn parse_percents(s: &str) -> Option<u32> {
+    s.trim_end_matches('%').trim().parse().ok()
+}
+
+/// Splits `name:value` field. The value is empty if there's no colon.
+fn split_field(s: &str) -> (&str, &str) {
+    if let Some(i) = s.find(':') {
+        (s[..i].trim(), s[i + 1..].trim())
+    } else {
+        (s.trim(), "")
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AreaSize {
+    Small,
+    Medium,
+    Large,
+}
+
+impl AreaSize {
+    /// Radius of the area circle on the world map.
+    pub fn radius(self) -> i32 {
+        match self {
+            AreaSize::Small => 10,
+            AreaSize::Medium => 20,
+            AreaSize::Large => 30,
+        }
+    }
+
+    pub fn fid(self) -> FrameId {
+        match self {
+            AreaSize::Small => FrameId::WRLDSPR0,
+            AreaSize::Medium => FrameId::WRLDSPR1,
+            AreaSize::Large => FrameId::WRLDSPR2,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Entrance {
+    /// Whether the entrance is shown on the town map from the start.
+    pub known: bool,
+    /// Position of the entrance hotspot on the town map.
+    pub pos: Point,
+    /// `None` if the map is not in `MapDb`.
+    pub map_id: Option<i32>,
+    /// Elevation and tile number of the dude position. `None` for the map entrance.
+    pub pos_on_map: Option<(u32, u32)>,
+    pub direction: Direction,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AreaDef {
+    pub name: String,
+    pub pos: Point,
+    /// Whether the area is known from the start.
+    pub known: bool,
+    /// Whether the area can't be entered from the world map.
+    pub locked: bool,
+    pub size: AreaSize,
+    pub townmap_fid: Option<FrameId>,
+    pub townmap_label_fid: Option<FrameId>,
+    pub entrances: Vec<Entrance>,
+}
+
+/// Reads town areas from `data/city.txt`.
+pub fn read_areas_file(fs: &FileSystem, map_db: &MapDb) -> io::Result<Vec<AreaDef>> {
+    read_areas(&mut fs.reader("data/city.txt")?, map_db)
+}
+
+// wmAreaInit()
+pub fn read_areas(rd: &mut impl BufRead, map_db: &MapDb) -> io::Result<Vec<AreaDef>> {
+    let ini = Ini::read(rd)?;
+    let mut r = Vec::new();
+    while let Some(section) = ini.section(&format!("Area {:02}", r.len())) {
+        r.push(read_area(section, map_db)?);
+    }
+    Ok(r)
+}
+
+fn read_area(section: &Section, map_db: &MapDb) -> io::Result<AreaDef> {
+    let invalid = |key: &str| invalid_data(
+        format!("invalid value of `{}` in section [{}]", key, section.name()));
+
+    let pos = section.get_list("world_pos");
+    let pos = if let [x, y] = pos[..] {
+        Point::new(x.parse().map_err(|_| invalid("world_pos"))?,
+            y.parse().map_err(|_| invalid("world_pos"))?)
+    } else {
+        return Err(invalid("world_pos"));
+    };
+    let size = match section.require("size")?.to_ascii_lowercase().as_str() {
+        "small" => AreaSize::Small,
+        "medium" => AreaSize::Medium,
+        "large" => AreaSize::Large,
+        _ => return Err(invalid("size")),
+    };
+
+    let mut entrances = Vec::new();
+    loop {
+        let key = format!("entrance_{}", entrances.len());
+        if section.get(&key).is_none() {
+            break;
+        }
+        let fields = section.get_list(&key);
+        if fields.len() < 7 {
+            return Err(invalid(&key));
+        }
+        let int = |s: &str| s.parse::<i32>().map_err(|_| invalid(&key));
+        let map_id = map_db.find_by_lookup_name(fields[3]);
+        if map_id.is_none() {
+            warn!("unknown map `{}` in {} of [{}]", fields[3], key, section.name());
+        }
+        let elevation = int(fields[4])?;
+        let tile = int(fields[5])?;
+        entrances.push(Entrance {
+            known: is_on(fields[0]),
+            pos: Point::new(int(fields[1])?, int(fields[2])?),
+            map_id,
+            pos_on_map: if elevation >= 0 && tile >= 0 {
+                Some((elevation as u32, tile as u32))
+            } else {
+                None
+            },
+            direction: Direction::from_i32(int(fields[6])?).unwrap_or(Direction::NE),
+        });
+    }
+
+    Ok(AreaDef {
+        name: section.require("area_name")?.into(),
+        pos,
+        known: is_on(section.get("start_state").unwrap_or("off")),
+        locked: is_on(section.get("lock_state").unwrap_or("off")),
+        size,
+        townmap_fid: section.get_int("townmap_art_idx").and_then(interface_fid),
+        townmap_label_fid: section.get_int("townmap_label_art_idx").and_then(interface_fid),
+        entrances,
+    })
+}
+
+fn is_on(s: &str) -> bool {
+    s.eq_ignore_ascii_case("on")
+}
+
+fn interface_fid(idx: i32) -> Option<FrameId> {
+    if idx >= 0 && idx <= u16::max_value() as i32 {
+        FrameId::new_generic(EntityKind::Interface, idx as u16)
+    } else {
+        None
+    }
+}
+
+fn split_pair(s: &str) -> io::Result<(&str, &str)> {
+    let i = s.find(':').ok_or_else(|| invalid_data(format!("expected `name:value`: {}", s)))?;
+    Ok((s[..i].trim(), s[i + 1..].trim()))
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_world_map() {
+        let mut s = String::from("
+[Data]
+terrain_types=Desert:1, Mountain:2
+encounter_frequency=None:0%, Rare:5%, Common:20%
+
+[Tile Data]
+num_horizontal_tiles=2
+
+[Tile 0]
+art_idx=339
+encounter_difficulty=-1
+");
+        for y in 0..SUBTILE_ROWS {
+            for x in 0..SUBTILE_COLS {
+                s += &if x == 1 && y == 2 {
+                    format!("{}_{}=Mountain,Fill_W,Rare,Common,None,Arroyo_M,\n", x, y)
+                } else {
+                    format!("{}_{}=Desert,No_Fill,None,None,None,\n", x, y)
+                };
+            }
+        }
+        let def = WorldMapDef::read(&mut s.as_bytes(), &MapDb::read(&mut &b""[..]).unwrap())
+            .unwrap();
+
+        assert_eq!(def.terrains, vec![
+            Terrain { name: "Desert".into(), difficulty: 1 },
+            Terrain { name: "Mountain".into(), difficulty: 2 },
+        ]);
+        assert_eq!(def.tile_cols, 2);
+        assert_eq!(def.tiles.len(), 1);
+        assert_eq!(def.tiles[0].fid, FrameId::WRLDMP00);
+        assert_eq!(def.tiles[0].encounter_difficulty, -1);
+        assert_eq!(def.size(), Point::new(700, 300));
+
+        assert_eq!(def.subtile_at(Point::new(50 + 49, 100)), Some(&Subtile {
+            terrain: 1,
+            encounter_chance: enum_map! {
+                DayTime::Morning => 5,
+                DayTime::Afternoon => 20,
+                DayTime::Night => 0,
+            },
+            encounter_table: Some("Arroyo_M".into()),
+        }));
+        assert_eq!(def.subtile_at(Point::new(0, 0)).unwrap().terrain, 0);
+        assert_eq!(def.subtile_at(Point::new(0, 0)).unwrap().encounter_table, None);
+        assert_eq!(def.subtile_at(Point::new(350, 0)), None);
+        assert_eq!(def.subtile_at(Point::new(-1, 0)), None);
+        assert!(def.encounter_tables.is_empty());
+        assert!(def.critter_groups.is_empty());
+    }
+
+    #[test]
+    fn read_encounters() {
+        let map_db = MapDb::read(&mut &b"
+[Map 000]
+lookup_name=Desert Encounter 1
+map_name=desert1
+[Map 001]
+lookup_name=Special Bridge
+map_name=bridge
+"[..]).unwrap();
+        let def = WorldMapDef::read(&mut &b"
+[Data]
+terrain_types=Desert:1
+encounter_frequency=None:0%
+
+[Tile Data]
+num_horizontal_tiles=1
+
+[Encounter Table 0]
+lookup_name=Arroyo_D
+maps=Desert Encounter 1,Unknown
+enc_00=chance:12%,counter:-1,enc:(4-6) SILVER_GECKOS AMBUSH Player
+enc_01=Chance:1%,Counter:1,Special,Map:Special Bridge,Enc:(1) Raiders AND Silver_Geckos,If(Global(152) == 0)
+enc_02=chance:5%,enc:(1-2) Raiders FIGHTING (2) Unknown
+
+[Encounter: SILVER_GECKOS]
+position=huddle, spacing:2
+type_00=ratio:60%,pid:16777231,Script:57
+type_01=ratio:40%,dead,pid:16777232
+
+[Encounter: Raiders]
+type_00=pid:16777246,Item:(1-6)40,Item:8{wielded},item:(0-10) 41
+"[..], &map_db).unwrap();
+
+        let pid = |v| ProtoId::from_packed(v).unwrap();
+        assert_eq!(def.critter_groups, vec![
+            CritterGroup {
+                name: "SILVER_GECKOS".into(),
+                formation: Formation::Huddle,
+                spacing: 2,
+                critters: vec![
+                    EncounterCritter {
+                        ratio: 60,
+                        dead: false,
+                        pid: pid(16777231),
+                        items: vec![],
+                        script: ProgramId::new(57),
+                    },
+                    EncounterCritter {
+                        ratio: 40,
+                        dead: true,
+                        pid: pid(16777232),
+                        items: vec![],
+                        script: None,
+                    },
+                ],
+            },
+            CritterGroup {
+                name: "Raiders".into(),
+                formation: Formation::Surrounding,
+                spacing: 1,
+                critters: vec![
+                    EncounterCritter {
+                        ratio: 100,
+                        dead: false,
+                        pid: pid(16777246),
+                        items: vec![
+                            EncounterItem { pid: pid(40), count: (1, 6), wielded: false },
+                            EncounterItem { pid: pid(8), count: (1, 1), wielded: true },
+                            EncounterItem { pid: pid(41), count: (0, 10), wielded: false },
+                        ],
+                        script: None,
+                    },
+                ],
+            },
+        ]);
+
+        assert_eq!(def.find_encounter_table("arroyo_d"), Some(0));
+        assert_eq!(def.find_encounter_table("Arroyo_M"), None);
+        assert_eq!(def.encounter_tables, vec![EncounterTable {
+            name: "Arroyo_D".into(),
+            maps: vec![0],
+            encounters: vec![
+                Encounter {
+                    chance: 12,
+                    counter: None,
+                    special: false,
+                    map_id: None,
+                    groups: vec![
+                        EncounterGroup {
+                            count: (4, 6),
+                            critters: 0,
+                            attitude: GroupAttitude::Ambush,
+                        },
+                    ],
+                },
+                Encounter {
+                    chance: 1,
+                    counter: Some(1),
+                    special: true,
+                    map_id: Some(1),
+                    groups: vec![
+                        EncounterGroup {
+                            count: (1, 1),
+                            critters: 1,
+                            attitude: GroupAttitude::Neutral,
+                        },
+                        EncounterGroup {
+                            count: (1, 1),
+                            critters: 0,
+                            attitude: GroupAttitude::Neutral,
+                        },
+                    ],
+                },
+                Encounter {
+                    chance: 5,
+                    counter: None,
+                    special: false,
+                    map_id: None,
+                    groups: vec![
+                        EncounterGroup {
+                            count: (1, 2),
+                            critters: 1,
+                            attitude: GroupAttitude::Fighting,
+                        },
+                    ],
+                },
+            ],
+        }]);
+    }
+
+    #[test]
+    fn read_areas_() {
+        let map_db = MapDb::read(&mut &b"
+[Map 000]
+lookup_name=Arroyo Bridge
+map_name=arbridge
+"[..]).unwrap();
+        let areas = read_areas(&mut &b"
+[Area 00]
+area_name=Arroyo
+world_pos=173,122
+start_state=On
+lock_state=Off
+size=Medium
+townmap_art_idx=156
+townmap_label_art_idx=-1
+entrance_0=On,633,148,Arroyo Bridge,-1,-1,0
+entrance_1=Off,10,20,Unknown,1,12345,3
+
+[Area 02]
+area_name=Skipped
+"[..], &map_db).unwrap();
+
+        assert_eq!(areas, vec![AreaDef {
+            name: "Arroyo".into(),
+            pos: Point::new(173, 122),
+            known: true,
+            locked: false,
+            size: AreaSize::Medium,
+            townmap_fid: FrameId::new_generic(EntityKind::Interface, 156),
+            townmap_label_fid: None,
+            entrances: vec![
+                Entrance {
+                    known: true,
+                    pos: Point::new(633, 148),
+                    map_id: Some(0),
+                    pos_on_map: None,
+                    direction: Direction::NE,
+                },
+                Entrance {
+                    known: false,
+                    pos: Point::new(10, 20),
+                    map_id: None,
+                    pos_on_map: Some((1, 12345)),
+                    direction: Direction::SW,
+                },
+            ],
+        }]);
+    }
+}
@@ -1,4 +1,6 @@
 pub mod dat;
+#[cfg(test)]
+pub mod mem;
 pub mod std;
 
 use ::std::io::prelude::*;
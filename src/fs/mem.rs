@@ -0,0 +1,35 @@
+use std::collections::HashMap;
+use std::io::{BufRead, Cursor, Error, ErrorKind, Result};
+
+use super::{Metadata, Provider};
+
+/// Creates provider serving `files` from memory. The paths are case-insensitive.
+pub fn new_provider(files: HashMap<String, Vec<u8>>) -> Box<Provider> {
+    Box::new(MemFileSystem {
+        files: files.into_iter()
+            .map(|(path, data)| (path.to_ascii_lowercase(), data))
+            .collect(),
+    })
+}
+
+struct MemFileSystem {
+    files: HashMap<String, Vec<u8>>,
+}
+
+impl MemFileSystem {
+    fn get(&self, path: &str) -> Result<&Vec<u8>> {
+        self.files.get(&path.to_ascii_lowercase())
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("file not found: {}", path)))
+    }
+}
+
+impl Provider for MemFileSystem {
+    fn reader(&self, path: &str) -> Result<Box<BufRead + Send>> {
+        Ok(Box::new(Cursor::new(self.get(path)?.clone())))
+    }
+
+    fn metadata(&self, path: &str) -> Result<Metadata> {
+        let len = self.get(path)?.len() as u64;
+        Ok(Metadata { len })
+    }
+}
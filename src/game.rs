@@ -1,14 +1,22 @@
+pub mod barter;
+pub mod combat;
 pub mod dialog;
 pub mod fidget;
 pub mod object;
+pub mod party;
+pub mod roll;
+pub mod save;
 pub mod stats;
 pub mod script;
+pub mod skill;
 pub mod sequence;
 pub mod state;
+pub mod timer;
 pub mod ui;
 pub mod world;
+pub mod worldmap;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub struct GameTime(u32);
 
 impl GameTime {
@@ -20,6 +28,11 @@ impl GameTime {
         self.0
     }
 
+    #[must_use]
+    pub fn add_decis(self, decis: u32) -> Self {
+        Self(self.0.saturating_add(decis))
+    }
+
     pub fn as_seconds(self) -> u32 {
         self.0 / 10
     }
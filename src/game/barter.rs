@@ -0,0 +1,92 @@
+use std::cmp;
+
+/// Price reduction in percents given by the Master Trader perk.
+const MASTER_TRADER_DISCOUNT: i32 = 25;
+
+/// Parameters affecting barter prices.
+#[derive(Clone, Copy, Debug)]
+pub struct Pricing {
+    /// Best Barter skill in the player's party.
+    pub player_skill: i32,
+    /// Barter skill of the trader.
+    pub trader_skill: i32,
+    /// Price modifier in percents set by the dialog script.
+    pub barter_mod: i32,
+    /// Whether the player has the Master Trader perk.
+    pub master_trader: bool,
+}
+
+impl Pricing {
+    /// Returns the price the player has to pay for the trader's offer of total `cost`, of which
+    /// `caps` are bottle caps. Goods are valued at twice their cost while caps are always valued
+    /// at their face value.
+    // barter_compute_value()
+    pub fn price(&self, cost: u32, caps: u32) -> u32 {
+        let goods = cost.saturating_sub(caps) as f64 * 2.0;
+        let skill_ratio = (160 + cmp::max(self.trader_skill, 0)) as f64
+            / (160 + cmp::max(self.player_skill, 0)) as f64;
+        let discount = if self.master_trader { MASTER_TRADER_DISCOUNT } else { 0 };
+        let perc = cmp::max(100 + self.barter_mod - discount, 1) as f64 / 100.0;
+        (goods * skill_ratio * perc) as u32 + cmp::min(caps, cost)
+    }
+
+    /// Whether the trader accepts the player's offer worth `offer_value` in exchange for
+    /// the trader's offer of total `cost`, of which `caps` are bottle caps.
+    // barter_attempt_transaction()
+    pub fn accepts(&self, offer_value: u32, cost: u32, caps: u32) -> bool {
+        offer_value >= self.price(cost, caps)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pricing(player_skill: i32, trader_skill: i32) -> Pricing {
+        Pricing {
+            player_skill,
+            trader_skill,
+            barter_mod: 0,
+            master_trader: false,
+        }
+    }
+
+    #[test]
+    fn price() {
+        assert_eq!(pricing(40, 40).price(100, 0), 200);
+        assert!(pricing(100, 40).price(100, 0) < 200);
+        assert!(pricing(10, 80).price(100, 0) > 200);
+
+        let mut p = pricing(40, 40);
+        p.barter_mod = 50;
+        assert_eq!(p.price(100, 0), 300);
+        p.barter_mod = -200;
+        assert_eq!(p.price(100, 0), 2);
+
+        let mut p = pricing(40, 40);
+        p.master_trader = true;
+        assert_eq!(p.price(100, 0), 150);
+    }
+
+    #[test]
+    fn known_price() {
+        // 80 * 2 * (160 + 40) / (160 + 60) = 145.45
+        assert_eq!(pricing(60, 40).price(100, 20), 145 + 20);
+    }
+
+    #[test]
+    fn caps_face_value() {
+        let mut p = pricing(0, 100);
+        p.barter_mod = 30;
+        assert_eq!(p.price(50, 50), 50);
+        assert_eq!(p.price(150, 50), 50 + p.price(100, 0));
+    }
+
+    #[test]
+    fn accepts() {
+        let p = pricing(40, 40);
+        assert!(p.accepts(200, 100, 0));
+        assert!(!p.accepts(199, 100, 0));
+        assert!(p.accepts(0, 0, 0));
+    }
+}
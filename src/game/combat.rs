@@ -0,0 +1,310 @@
+pub mod ai;
+pub mod attack;
+
+use crate::asset::{AttackKind, CritterAnim, DamageKind};
+use crate::asset::proto;
+use crate::game::object;
+use crate::graphics::{EPoint, Point};
+use crate::graphics::geometry::hex::{self, Direction};
+
+/// AP cost of unarmed attack.
+pub const UNARMED_AP_COST: i32 = 3;
+
+/// Extra AP cost of aiming at a specific hit location.
+pub const CALLED_SHOT_AP_COST: i32 = 1;
+
+/// Damage in excess of the remaining hit points that results in a violent death.
+pub const OVERKILL_DAMAGE: i32 = 15;
+
+/// AP cost of moving by one hex.
+pub const MOVE_AP_COST: i32 = 1;
+
+/// Value of `fixed_param` passed to `combat_p_proc` at the start of critter's turn.
+// COMBAT_SUBTYPE_TURN
+pub const COMBAT_PROC_TURN: i32 = 4;
+
+/// Combat request issued by scripts. Handled by the game state after the script
+/// procedure returns.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Request {
+    Attack {
+        attacker: object::Handle,
+        target: object::Handle,
+    },
+    /// Explosion at `pos` dealing up to `damage` to objects around.
+    Explode {
+        pos: EPoint,
+        damage: i32,
+    },
+    /// Damage dealt by a script.
+    Damage {
+        target: object::Handle,
+        damage: i32,
+        kind: DamageKind,
+        /// Whether the armor is mostly ignored.
+        bypass: bool,
+        /// Whether the hit reaction is played.
+        animate: bool,
+    },
+    /// Kills `target` playing the death `anim`. `None` picks the default animation.
+    Kill {
+        target: object::Handle,
+        anim: Option<CritterAnim>,
+    },
+    Terminate,
+}
+
+/// How the damage was delivered. Used to choose the damage animation.
+#[derive(Clone, Copy, Debug)]
+pub struct DamageSource {
+    /// Position the damage came from.
+    pub pos: Option<Point>,
+    /// Critter that dealt the damage.
+    pub attacker: Option<object::Handle>,
+    pub damage_kind: DamageKind,
+    pub attack_kind: AttackKind,
+}
+
+/// Turn-based combat state.
+// combat_list, combat_turn_obj
+#[derive(Clone, Debug)]
+pub struct Combat {
+    combatants: Vec<object::Handle>,
+    current: usize,
+    round: u32,
+}
+
+impl Combat {
+    /// `combatants` must be in the turn order.
+    pub fn new(combatants: Vec<object::Handle>) -> Self {
+        assert!(!combatants.is_empty());
+        Self {
+            combatants,
+            current: 0,
+            round: 1,
+        }
+    }
+
+    pub fn combatants(&self) -> &[object::Handle] {
+        &self.combatants
+    }
+
+    pub fn contains(&self, obj: object::Handle) -> bool {
+        self.combatants.contains(&obj)
+    }
+
+    /// The combatant whose turn it is.
+    pub fn current(&self) -> object::Handle {
+        self.combatants[self.current]
+    }
+
+    pub fn round(&self) -> u32 {
+        self.round
+    }
+
+    /// Passes the turn to the next combatant and returns it. Starts new round after the last
+    /// combatant.
+    pub fn next_turn(&mut self) -> object::Handle {
+        self.current += 1;
+        if self.current >= self.combatants.len() {
+            self.current = 0;
+            self.round += 1;
+        }
+        self.current()
+    }
+
+    /// Adds `obj` to the end of the turn order.
+    pub fn add(&mut self, obj: object::Handle) {
+        if !self.contains(obj) {
+            self.combatants.push(obj);
+        }
+    }
+
+    /// Removes `obj` from the turn order. If it's the current combatant the turn passes to
+    /// the preceding one so that `next_turn()` picks the right combatant.
+    /// Returns `false` if there are no combatants left.
+    pub fn remove(&mut self, obj: object::Handle) -> bool {
+        if let Some(i) = self.combatants.iter().position(|&o| o == obj) {
+            self.combatants.remove(i);
+            if i < self.current || i == self.current && self.current > 0 {
+                self.current -= 1;
+            } else if i == self.current && !self.combatants.is_empty() {
+                self.current = self.combatants.len() - 1;
+                self.round -= 1;
+            }
+        }
+        !self.combatants.is_empty()
+    }
+}
+
+/// Orders combatants by the descending Sequence stat. `first` goes first regardless of its
+/// Sequence. Combatants with equal Sequence keep their relative order.
+// combat_sequence()
+pub fn turn_order(mut combatants: Vec<(object::Handle, i32)>, first: Option<object::Handle>)
+    -> Vec<object::Handle>
+{
+    combatants.sort_by_key(|&(obj, sequence)| (Some(obj) != first, -sequence));
+    combatants.into_iter().map(|(obj, _)| obj).collect()
+}
+
+/// AP cost of attacking with `weapon`. `None` means unarmed attack.
+// item_w_primary_mp_cost()
+pub fn attack_ap_cost(weapon: Option<&proto::Weapon>) -> i32 {
+    weapon.map(|w| w.ap_cost.primary).unwrap_or(UNARMED_AP_COST)
+}
+
+/// Maximum distance in hexes of attacking with `weapon`. `None` means unarmed attack.
+// item_w_range()
+pub fn attack_range(weapon: Option<&proto::Weapon>) -> u32 {
+    weapon.map(|w| w.max_range.primary.max(1) as u32).unwrap_or(1)
+}
+
+/// Critter animation used for the attack of `kind`.
+// item_w_anim()
+pub fn attack_anim(kind: AttackKind) -> CritterAnim {
+    match kind {
+        AttackKind::Stand | AttackKind::Punch => CritterAnim::ThrowPunch,
+        AttackKind::Kick => CritterAnim::KickLeg,
+        AttackKind::Swing => CritterAnim::SwingAnim,
+        AttackKind::Thrust => CritterAnim::ThrustAnim,
+        AttackKind::Throw => CritterAnim::ThrowAnim,
+        AttackKind::FireSingle => CritterAnim::FireSingle,
+        AttackKind::FireBurst => CritterAnim::FireBurst,
+        AttackKind::FireContinuous => CritterAnim::FireContinuous,
+    }
+}
+
+/// Returns `true` if `from` is in front of the critter at `pos` facing `direction`.
+pub fn is_in_front(pos: Point, direction: Direction, from: Point) -> bool {
+    if pos == from {
+        return true;
+    }
+    let d = hex::direction(pos, from);
+    d == direction || d == direction.rotate_cw() || d == direction.rotate_ccw()
+}
+
+/// Knockdown animation of a critter hit from the front if `from_front` is `true` or from
+/// the back otherwise.
+pub fn fall_anim(from_front: bool) -> CritterAnim {
+    if from_front {
+        CritterAnim::FallBack
+    } else {
+        CritterAnim::FallFront
+    }
+}
+
+/// Animation of getting up after the knockdown animation `fall_anim`.
+pub fn stand_up_anim(fall_anim: CritterAnim) -> CritterAnim {
+    if fall_anim == CritterAnim::FallBack || fall_anim == CritterAnim::FallBackSf {
+        CritterAnim::BackToStanding
+    } else {
+        CritterAnim::ProneToStanding
+    }
+}
+
+/// Chooses death animation of a critter killed by damage of `damage_kind` delivered by
+/// the attack of `attack_kind`. `overkill` is the damage in excess of the remaining hit points.
+// pick_death()
+pub fn death_anim(damage_kind: DamageKind, attack_kind: AttackKind, overkill: i32,
+    from_front: bool) -> CritterAnim
+{
+    let fall = fall_anim(from_front);
+    if overkill < OVERKILL_DAMAGE {
+        return match damage_kind {
+            DamageKind::Fire => CritterAnim::CharredBody,
+            DamageKind::Electric => CritterAnim::Electrify,
+            _ => fall,
+        };
+    }
+    match damage_kind {
+        DamageKind::Melee => match attack_kind {
+            AttackKind::FireSingle => CritterAnim::BigHole,
+            AttackKind::FireBurst | AttackKind::FireContinuous => CritterAnim::DancingAutofire,
+            AttackKind::Swing | AttackKind::Thrust => CritterAnim::SlicedInHalf,
+            AttackKind::Stand | AttackKind::Punch | AttackKind::Kick | AttackKind::Throw => fall,
+        },
+        DamageKind::Laser => CritterAnim::SlicedInHalf,
+        DamageKind::Fire => CritterAnim::BurnedToNothing,
+        DamageKind::Plasma => CritterAnim::MeltedToNothing,
+        DamageKind::Electric | DamageKind::Emp => CritterAnim::ElectrifiedToNothing,
+        DamageKind::Explosion => CritterAnim::ChunksOfFlesh,
+        DamageKind::Radiation | DamageKind::Poison => fall,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn turn_order_() {
+        let h = object::Handle::distinct(4);
+        let order = turn_order(vec![(h[0], 5), (h[1], 10), (h[2], 5), (h[3], 1)], None);
+        assert_eq!(order, vec![h[1], h[0], h[2], h[3]]);
+
+        let order = turn_order(vec![(h[0], 5), (h[1], 10), (h[2], 5), (h[3], 1)], Some(h[3]));
+        assert_eq!(order, vec![h[3], h[1], h[0], h[2]]);
+    }
+
+    #[test]
+    fn next_turn() {
+        let h = object::Handle::distinct(3);
+        let mut c = Combat::new(h.clone());
+        assert_eq!(c.current(), h[0]);
+        assert_eq!(c.next_turn(), h[1]);
+        assert_eq!(c.next_turn(), h[2]);
+        assert_eq!(c.round(), 1);
+        assert_eq!(c.next_turn(), h[0]);
+        assert_eq!(c.round(), 2);
+    }
+
+    #[test]
+    fn remove() {
+        let h = object::Handle::distinct(4);
+        let mut c = Combat::new(h.clone());
+        c.next_turn();
+        c.next_turn();
+        assert_eq!(c.current(), h[2]);
+
+        // Before current.
+        assert!(c.remove(h[0]));
+        assert_eq!(c.current(), h[2]);
+
+        // Current.
+        assert!(c.remove(h[2]));
+        assert_eq!(c.next_turn(), h[3]);
+
+        // Current at the start.
+        assert_eq!(c.next_turn(), h[1]);
+        let round = c.round();
+        assert!(c.remove(h[1]));
+        assert_eq!(c.next_turn(), h[3]);
+        assert_eq!(c.round(), round);
+
+        assert!(!c.remove(h[3]));
+    }
+
+    #[test]
+    fn death_anim_() {
+        use CritterAnim::*;
+        assert_eq!(death_anim(DamageKind::Melee, AttackKind::Punch, 100, true), FallBack);
+        assert_eq!(death_anim(DamageKind::Melee, AttackKind::FireSingle, 14, false), FallFront);
+        assert_eq!(death_anim(DamageKind::Melee, AttackKind::FireSingle, 15, false), BigHole);
+        assert_eq!(death_anim(DamageKind::Melee, AttackKind::FireBurst, 20, true), DancingAutofire);
+        assert_eq!(death_anim(DamageKind::Fire, AttackKind::FireContinuous, 0, true), CharredBody);
+        assert_eq!(death_anim(DamageKind::Fire, AttackKind::FireContinuous, 50, true),
+            BurnedToNothing);
+        assert_eq!(death_anim(DamageKind::Plasma, AttackKind::FireSingle, 50, true), MeltedToNothing);
+        assert_eq!(death_anim(DamageKind::Explosion, AttackKind::Throw, 50, true), ChunksOfFlesh);
+    }
+
+    #[test]
+    fn is_in_front_() {
+        let pos = Point::new(10, 10);
+        let front = hex::go(pos, Direction::E, 1);
+        let back = hex::go(pos, Direction::W, 1);
+        assert!(is_in_front(pos, Direction::E, front));
+        assert!(is_in_front(pos, Direction::NE, front));
+        assert!(!is_in_front(pos, Direction::E, back));
+    }
+}
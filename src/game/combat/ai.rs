@@ -0,0 +1,268 @@
+use enumflags2::BitFlags;
+
+use crate::asset::AttackKind;
+use crate::asset::ai::{AiPacket, AreaAttackMode, AttackWho, BestWeapon, ChemUse, RunAwayMode};
+use crate::game::object::{self, DamageFlag};
+
+use super::attack::{self, HitLocation};
+
+/// AP cost of using an item (e.g. a drug) in combat.
+pub const USE_ITEM_AP_COST: i32 = 2;
+
+/// Possible target as seen by the AI.
+#[derive(Clone, Copy, Debug)]
+pub struct Candidate {
+    pub obj: object::Handle,
+    pub distance: u32,
+    pub health: i32,
+    /// Whether this candidate attacks the critter doing the choice.
+    pub attacking_me: bool,
+}
+
+/// Returns `true` if the critter with the `packet` should run away given its `health`,
+/// `max_health` and `damage_flags`.
+// ai_check_hp(), combatai_check_retaliation()
+pub fn should_flee(packet: &AiPacket, health: i32, max_health: i32,
+    damage_flags: BitFlags<DamageFlag>) -> bool
+{
+    if damage_flags.intersects(packet.hurt_too_much) {
+        return true;
+    }
+    let min_hp = match packet.run_away_mode.hp_lost_percent() {
+        Some(_) if packet.run_away_mode == RunAwayMode::Never => return false,
+        Some(lost) => max_health - max_health * lost / 100,
+        None => packet.min_hp,
+    };
+    health < min_hp
+}
+
+/// Chooses the target among `candidates` according to the packet's preference.
+// ai_find_attackers(), ai_danger_source()
+pub fn pick_target(packet: &AiPacket, candidates: &[Candidate]) -> Option<object::Handle> {
+    let closest = |c: &Candidate| c.distance;
+    let r = match packet.attack_who {
+        AttackWho::WhomeverAttackingMe => candidates.iter()
+            .filter(|c| c.attacking_me)
+            .min_by_key(|c| closest(c))
+            .or_else(|| candidates.iter().min_by_key(|c| closest(c))),
+        AttackWho::Strongest => candidates.iter().max_by_key(|c| (c.health, -(c.distance as i64))),
+        AttackWho::Weakest => candidates.iter().min_by_key(|c| (c.health, c.distance)),
+        AttackWho::Whomever => candidates.first(),
+        AttackWho::Closest => candidates.iter().min_by_key(|c| closest(c)),
+    };
+    r.map(|c| c.obj)
+}
+
+/// Returns `true` if the critter wants to use healing drugs. `roll` must be in range `1..=100`.
+// ai_check_drugs()
+pub fn wants_chem(chem_use: ChemUse, health: i32, max_health: i32, roll: i32) -> bool {
+    if health >= max_health {
+        return false;
+    }
+    let health_percent = health * 100 / max_health.max(1);
+    match chem_use {
+        ChemUse::Clean => false,
+        ChemUse::StimsWhenHurtLittle => health_percent < 60,
+        ChemUse::StimsWhenHurtLots => health_percent < 30,
+        ChemUse::Sometimes => health_percent < 50 && roll <= 25,
+        ChemUse::Anytime => health_percent < 50 && roll <= 75,
+        ChemUse::Always => true,
+    }
+}
+
+/// Chooses the location to aim the attack of `kind` at. The chance of a called shot is
+/// `1 / called_freq`. `rng(from_inclusive, to_inclusive)` is the source of random numbers.
+// ai_called_shot()
+pub fn called_shot_location(packet: &AiPacket, kind: AttackKind,
+    rng: &mut impl FnMut(i32, i32) -> i32) -> HitLocation
+{
+    if packet.called_freq > 0 && attack::can_call_shot(kind) && rng(1, packet.called_freq) == 1 {
+        let len = HitLocation::CALLED.len() as i32;
+        HitLocation::CALLED[rng(0, len - 1) as usize]
+    } else {
+        HitLocation::Uncalled
+    }
+}
+
+/// Returns `true` if the critter with the area attack `mode` may fire a burst at the target.
+/// `friend_distance` is the distance from the target to the closest friend of the attacker.
+/// `roll` must be in range `1..=100`.
+// ai_attack_area_check()
+pub fn may_use_area_attack(mode: AreaAttackMode, friend_distance: Option<u32>, roll: i32)
+    -> bool
+{
+    let d = if let Some(v) = friend_distance {
+        v
+    } else {
+        return true;
+    };
+    match mode {
+        AreaAttackMode::Always => true,
+        AreaAttackMode::Sometimes => d > 1 || roll <= 50,
+        AreaAttackMode::BeSure => d > 2 || roll <= 15,
+        AreaAttackMode::BeCareful => d > 3,
+        AreaAttackMode::BeAbsolutelySure => d > 5,
+    }
+}
+
+/// Weapon category as seen by the weapon preference.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WeaponClass {
+    Unarmed,
+    Melee,
+    Thrown,
+    Ranged,
+}
+
+impl WeaponClass {
+    pub fn of(kind: AttackKind) -> Self {
+        match kind {
+            AttackKind::Stand | AttackKind::Punch | AttackKind::Kick => WeaponClass::Unarmed,
+            AttackKind::Swing | AttackKind::Thrust => WeaponClass::Melee,
+            AttackKind::Throw => WeaponClass::Thrown,
+            AttackKind::FireSingle | AttackKind::FireBurst | AttackKind::FireContinuous
+                => WeaponClass::Ranged,
+        }
+    }
+
+    fn is_ranged(self) -> bool {
+        attack::is_ranged(match self {
+            WeaponClass::Unarmed => AttackKind::Punch,
+            WeaponClass::Melee => AttackKind::Swing,
+            WeaponClass::Thrown => AttackKind::Throw,
+            WeaponClass::Ranged => AttackKind::FireSingle,
+        })
+    }
+}
+
+/// Returns rank of the weapon `class` under the `pref`. Lower rank is preferred.
+/// `None` means the class should not be used unless nothing else is available.
+// ai_best_weapon()
+pub fn weapon_rank(pref: BestWeapon, class: WeaponClass) -> Option<u32> {
+    use WeaponClass::*;
+    match pref {
+        BestWeapon::NoPref | BestWeapon::Random => Some(if class == Unarmed { 1 } else { 0 }),
+        BestWeapon::Melee => if class == Melee { Some(0) } else { None },
+        BestWeapon::MeleeOverRanged => Some(match class {
+            Melee => 0,
+            Thrown | Ranged => 1,
+            Unarmed => 2,
+        }),
+        BestWeapon::RangedOverMelee => Some(if class.is_ranged() {
+            0
+        } else if class == Melee {
+            1
+        } else {
+            2
+        }),
+        BestWeapon::Ranged => if class.is_ranged() { Some(0) } else { None },
+        BestWeapon::Unarmed => if class == Unarmed { Some(0) } else { None },
+        BestWeapon::UnarmedOverThrown => Some(match class {
+            Unarmed => 0,
+            Thrown => 1,
+            Melee | Ranged => 2,
+        }),
+    }
+}
+
+/// Chooses the best weapon among `weapons` for the `pref`. `None` in `weapons` stands for
+/// unarmed attack.
+pub fn pick_weapon(pref: BestWeapon, weapons: &[(Option<object::Handle>, WeaponClass)])
+    -> Option<object::Handle>
+{
+    weapons.iter()
+        .min_by_key(|&&(_, class)| weapon_rank(pref, class).unwrap_or(u32::max_value()))
+        .and_then(|&(w, _)| w)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::asset::ai::AiPackets;
+
+    fn packet(s: &str) -> AiPacket {
+        let s = format!("[Test]\npacket_num=0\n{}", s);
+        AiPackets::read(&mut s.as_bytes()).unwrap().get(0).unwrap().clone()
+    }
+
+    #[test]
+    fn should_flee_() {
+        let p = packet("run_away_mode=bleeding");
+        assert!(!should_flee(&p, 60, 100, BitFlags::empty()));
+        assert!(should_flee(&p, 59, 100, BitFlags::empty()));
+
+        let p = packet("run_away_mode=never\nhurt_too_much=blind");
+        assert!(!should_flee(&p, 1, 100, BitFlags::empty()));
+        assert!(should_flee(&p, 100, 100, DamageFlag::Blind.into()));
+
+        let p = packet("min_hp=10");
+        assert!(!should_flee(&p, 10, 100, BitFlags::empty()));
+        assert!(should_flee(&p, 9, 100, BitFlags::empty()));
+    }
+
+    #[test]
+    fn pick_target_() {
+        let h = object::Handle::distinct(3);
+        let c = [
+            Candidate { obj: h[0], distance: 5, health: 30, attacking_me: false },
+            Candidate { obj: h[1], distance: 2, health: 50, attacking_me: false },
+            Candidate { obj: h[2], distance: 8, health: 10, attacking_me: true },
+        ];
+        assert_eq!(pick_target(&packet("attack_who=closest"), &c), Some(h[1]));
+        assert_eq!(pick_target(&packet("attack_who=strongest"), &c), Some(h[1]));
+        assert_eq!(pick_target(&packet("attack_who=weakest"), &c), Some(h[2]));
+        assert_eq!(pick_target(&packet("attack_who=whomever_attacking_me"), &c), Some(h[2]));
+        assert_eq!(pick_target(&packet("attack_who=whomever_attacking_me"), &c[..2]), Some(h[1]));
+        assert_eq!(pick_target(&packet(""), &[]), None);
+    }
+
+    #[test]
+    fn wants_chem_() {
+        assert!(!wants_chem(ChemUse::Clean, 1, 100, 1));
+        assert!(wants_chem(ChemUse::StimsWhenHurtLittle, 59, 100, 100));
+        assert!(!wants_chem(ChemUse::StimsWhenHurtLots, 59, 100, 1));
+        assert!(wants_chem(ChemUse::StimsWhenHurtLots, 29, 100, 100));
+        assert!(wants_chem(ChemUse::Sometimes, 40, 100, 25));
+        assert!(!wants_chem(ChemUse::Sometimes, 40, 100, 26));
+        assert!(!wants_chem(ChemUse::Always, 100, 100, 1));
+    }
+
+    #[test]
+    fn called_shot_location_() {
+        let p = packet("called_freq=4");
+        let mut rolls = vec![1, 6].into_iter();
+        let mut rng = |_, _| rolls.next().unwrap();
+        assert_eq!(called_shot_location(&p, AttackKind::FireSingle, &mut rng), HitLocation::Eyes);
+        assert_eq!(called_shot_location(&p, AttackKind::Punch, &mut |_, _| 2),
+            HitLocation::Uncalled);
+        assert_eq!(called_shot_location(&p, AttackKind::FireBurst, &mut |_, _| 1),
+            HitLocation::Uncalled);
+        assert_eq!(called_shot_location(&packet(""), AttackKind::Punch, &mut |_, _| 1),
+            HitLocation::Uncalled);
+    }
+
+    #[test]
+    fn may_use_area_attack_() {
+        assert!(may_use_area_attack(AreaAttackMode::BeAbsolutelySure, None, 100));
+        assert!(may_use_area_attack(AreaAttackMode::Always, Some(0), 100));
+        assert!(may_use_area_attack(AreaAttackMode::Sometimes, Some(1), 50));
+        assert!(!may_use_area_attack(AreaAttackMode::Sometimes, Some(1), 51));
+        assert!(!may_use_area_attack(AreaAttackMode::BeCareful, Some(3), 1));
+        assert!(may_use_area_attack(AreaAttackMode::BeCareful, Some(4), 100));
+        assert!(!may_use_area_attack(AreaAttackMode::BeAbsolutelySure, Some(5), 1));
+    }
+
+    #[test]
+    fn pick_weapon_() {
+        let h = object::Handle::distinct(2);
+        let w = [
+            (None, WeaponClass::Unarmed),
+            (Some(h[0]), WeaponClass::Melee),
+            (Some(h[1]), WeaponClass::Ranged),
+        ];
+        assert_eq!(pick_weapon(BestWeapon::RangedOverMelee, &w), Some(h[1]));
+        assert_eq!(pick_weapon(BestWeapon::MeleeOverRanged, &w), Some(h[0]));
+        assert_eq!(pick_weapon(BestWeapon::Unarmed, &w), None);
+        assert_eq!(pick_weapon(BestWeapon::Ranged, &w[..2]), None);
+    }
+}
@@ -0,0 +1,642 @@
+use enum_map::{Enum, EnumMap};
+use enumflags2::BitFlags;
+use std::cmp;
+
+use crate::asset::{AttackKind, DamageKind, FlagExt, Skill, Stat};
+use crate::asset::proto;
+use crate::game::object::DamageFlag;
+use crate::game::roll::{self, Roll};
+
+/// Maximum chance to hit in percents.
+const MAX_HIT_CHANCE: i32 = 95;
+
+/// Hit chance penalty per hex of distance for ranged attacks.
+const DISTANCE_PENALTY: i32 = 4;
+
+/// Hit chance penalty per hex of distance for ranged attacks when the attacker is blind.
+const BLIND_DISTANCE_PENALTY: i32 = 12;
+
+#[derive(Clone, Copy, Debug, Enum, Eq, PartialEq)]
+pub enum HitLocation {
+    Head,
+    LeftArm,
+    RightArm,
+    Torso,
+    RightLeg,
+    LeftLeg,
+    Eyes,
+    Groin,
+    Uncalled,
+}
+
+impl HitLocation {
+    /// Locations that can be aimed at with a called shot.
+    pub const CALLED: [HitLocation; 8] = [
+        HitLocation::Head,
+        HitLocation::LeftArm,
+        HitLocation::RightArm,
+        HitLocation::Torso,
+        HitLocation::RightLeg,
+        HitLocation::LeftLeg,
+        HitLocation::Eyes,
+        HitLocation::Groin,
+    ];
+}
+
+/// Attacker side of the attack.
+#[derive(Clone, Copy, Debug)]
+pub struct Attacker<'a> {
+    /// `None` means unarmed attack.
+    pub weapon: Option<&'a proto::Proto>,
+    /// Ammo loaded into the weapon.
+    pub ammo: Option<&'a proto::Ammo>,
+    pub kind: AttackKind,
+    /// Level of the weapon skill, see `weapon_skill()`.
+    pub skill: i32,
+    pub perception: i32,
+    pub luck: i32,
+    pub crit_chance: i32,
+    pub better_crit: i32,
+    pub melee_damage: i32,
+    pub is_blind: bool,
+}
+
+/// Defender side of the attack.
+#[derive(Clone, Debug)]
+pub struct Defender {
+    pub armor_class: i32,
+    pub damage_threshold: EnumMap<DamageKind, i32>,
+    pub damage_resistance: EnumMap<DamageKind, i32>,
+    pub endurance: i32,
+    pub agility: i32,
+    pub luck: i32,
+}
+
+/// Circumstances of the attack.
+#[derive(Clone, Copy, Debug)]
+pub struct Situation {
+    /// Distance to the defender in hexes.
+    pub distance: u32,
+    /// Light level at the defender's position in range `0..=0x10000`.
+    pub light: u32,
+    pub location: HitLocation,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outcome {
+    pub roll: Roll,
+    pub location: HitLocation,
+    /// Damage dealt to the defender.
+    pub damage: i32,
+    /// Effects on the defender.
+    pub defender_flags: BitFlags<DamageFlag>,
+    /// Effects on the attacker (critical failures).
+    pub attacker_flags: BitFlags<DamageFlag>,
+}
+
+impl Outcome {
+    /// Combines outcome of another round of the same burst against the same defender.
+    pub fn merge(&mut self, other: &Outcome) {
+        self.roll = cmp::max(self.roll, other.roll);
+        self.damage += other.damage;
+        self.defender_flags |= other.defender_flags;
+        self.attacker_flags |= other.attacker_flags;
+    }
+}
+
+/// Skill used for attacking with `weapon`. `None` means unarmed attack.
+// item_w_skill()
+pub fn weapon_skill(weapon: Option<&proto::Proto>, kind: AttackKind) -> Skill {
+    match kind {
+        AttackKind::Stand | AttackKind::Punch | AttackKind::Kick => Skill::UnarmedCombat,
+        AttackKind::Swing | AttackKind::Thrust => Skill::Melee,
+        AttackKind::Throw => Skill::Throwing,
+        AttackKind::FireSingle | AttackKind::FireBurst | AttackKind::FireContinuous => {
+            if weapon.map(|w| w.flags_ext.contains(FlagExt::BigGun)).unwrap_or(false) {
+                Skill::BigGuns
+            } else {
+                match weapon.and_then(|w| w.sub.item()).and_then(|i| i.sub.weapon()).map(|w| w.damage_kind) {
+                    Some(DamageKind::Laser) | Some(DamageKind::Plasma) | Some(DamageKind::Electric)
+                        => Skill::EnergyWeapons,
+                    _ => Skill::SmallGuns,
+                }
+            }
+        }
+    }
+}
+
+pub fn is_ranged(kind: AttackKind) -> bool {
+    match kind {
+        AttackKind::Throw | AttackKind::FireSingle | AttackKind::FireBurst
+            | AttackKind::FireContinuous => true,
+        AttackKind::Stand | AttackKind::Punch | AttackKind::Kick | AttackKind::Swing
+            | AttackKind::Thrust => false,
+    }
+}
+
+/// Returns `true` if attacks of `kind` can be aimed at a specific hit location.
+/// Bursts spread over the whole body and can't be aimed.
+// item_w_called_shot()
+pub fn can_call_shot(kind: AttackKind) -> bool {
+    !is_burst(kind)
+}
+
+pub fn is_burst(kind: AttackKind) -> bool {
+    kind == AttackKind::FireBurst || kind == AttackKind::FireContinuous
+}
+
+/// Splits `rounds` of a burst into the rounds going straight at the target and the rounds
+/// going to the left and to the right of it. Returns `(center, left, right)`.
+// compute_spray()
+pub fn spread_rounds(rounds: u32) -> (u32, u32, u32) {
+    let center = cmp::max(rounds / 3, cmp::min(rounds, 1));
+    let left = rounds / 3;
+    let right = rounds - center - left;
+    (center, left, right)
+}
+
+/// Radius in hexes of the area affected by an explosion.
+pub const EXPLOSION_RADIUS: u32 = 2;
+
+/// Returns raw `damage` of an explosion at `distance` hexes from its center.
+/// The damage falls off linearly towards the edge of the explosion area.
+pub fn explosion_damage(damage: i32, distance: u32) -> i32 {
+    if distance > EXPLOSION_RADIUS {
+        0
+    } else {
+        damage * (EXPLOSION_RADIUS + 1 - distance) as i32 / (EXPLOSION_RADIUS + 1) as i32
+    }
+}
+
+/// Stat holding the damage threshold for `kind`.
+pub fn damage_threshold_stat(kind: DamageKind) -> Option<Stat> {
+    Some(match kind {
+        DamageKind::Melee => Stat::DmgThresh,
+        DamageKind::Laser => Stat::DmgThreshLaser,
+        DamageKind::Fire => Stat::DmgThreshFire,
+        DamageKind::Plasma => Stat::DmgThreshPlasma,
+        DamageKind::Electric => Stat::DmgThreshElectrical,
+        DamageKind::Emp => Stat::DmgThreshEmp,
+        DamageKind::Explosion => Stat::DmgThreshExplosion,
+        DamageKind::Radiation | DamageKind::Poison => return None,
+    })
+}
+
+/// Stat holding the damage resistance for `kind`.
+pub fn damage_resistance_stat(kind: DamageKind) -> Option<Stat> {
+    Some(match kind {
+        DamageKind::Melee => Stat::DmgResist,
+        DamageKind::Laser => Stat::DmgResistLaser,
+        DamageKind::Fire => Stat::DmgResistFire,
+        DamageKind::Plasma => Stat::DmgResistPlasma,
+        DamageKind::Electric => Stat::DmgResistElectrical,
+        DamageKind::Emp => Stat::DmgResistEmp,
+        DamageKind::Explosion => Stat::DmgResistExplosion,
+        DamageKind::Radiation => Stat::RadResist,
+        DamageKind::Poison => Stat::PoisonResist,
+    })
+}
+
+/// Hit chance penalty for aiming at `location`.
+pub fn location_penalty(location: HitLocation) -> i32 {
+    match location {
+        HitLocation::Head => 40,
+        HitLocation::LeftArm | HitLocation::RightArm => 30,
+        HitLocation::Torso | HitLocation::Uncalled => 0,
+        HitLocation::RightLeg | HitLocation::LeftLeg => 20,
+        HitLocation::Eyes => 60,
+        HitLocation::Groin => 30,
+    }
+}
+
+/// Returns chance to hit in percents.
+// determine_to_hit_func()
+pub fn hit_chance(attacker: &Attacker, defender: &Defender, situation: &Situation) -> i32 {
+    let mut r = attacker.skill;
+
+    if is_ranged(attacker.kind) {
+        // Each point of Perception above 2 adds 2 hexes of range.
+        let perception_bonus = cmp::max(attacker.perception - 2, 0) * 8;
+        let distance = cmp::max(situation.distance as i32 * 4 - perception_bonus,
+            -2 * attacker.perception);
+        let penalty = if distance >= 0 && attacker.is_blind {
+            BLIND_DISTANCE_PENALTY
+        } else {
+            DISTANCE_PENALTY
+        };
+        r -= penalty * distance / 4;
+    }
+
+    let ammo_ac_mod = attacker.ammo.map(|a| a.ac_modifier).unwrap_or(0);
+    r -= cmp::max(defender.armor_class + ammo_ac_mod, 0);
+
+    r -= location_penalty(situation.location) / if is_ranged(attacker.kind) { 1 } else { 2 };
+
+    r -= match situation.light {
+        0..=0x6666 => 40,
+        0x6667..=0x9999 => 25,
+        0x999a..=0xcccc => 10,
+        _ => 0,
+    };
+
+    cmp::min(r, MAX_HIT_CHANCE)
+}
+
+/// Computes damage of a single hit of `raw` damage. `crit_mult` is the critical damage
+/// multiplier in halves (2 means no multiplication). If `bypass` is set armor is mostly
+/// ignored.
+// compute_damage()
+pub fn damage(raw: i32, kind: DamageKind, ammo: Option<&proto::Ammo>, crit_mult: i32,
+    bypass: bool, defender: &Defender) -> i32
+{
+    let mut dt = defender.damage_threshold[kind];
+    let mut dr = defender.damage_resistance[kind];
+    if bypass {
+        dt = dt * 20 / 100;
+        dr = dr * 20 / 100;
+    }
+    if let Some(ammo) = ammo {
+        dr += ammo.dr_modifier;
+    }
+    let dr = num_traits::clamp(dr, 0, 100);
+
+    let (ammo_mult, ammo_div) = ammo
+        .map(|a| (cmp::max(a.damage_mult, 1), cmp::max(a.damage_div, 1)))
+        .unwrap_or((1, 1));
+
+    // Multipliers apply before the armor.
+    let r = raw * crit_mult * ammo_mult / ammo_div / 2 - dt;
+    if r <= 0 {
+        return 0;
+    }
+    r - r * dr / 100
+}
+
+/// Resolves the attack. `rng(from_inclusive, to_inclusive)` is the source of random numbers.
+// attack_crit_success(), attack_crit_failure(), compute_attack()
+pub fn resolve(attacker: &Attacker, defender: &Defender, situation: &Situation,
+    rng: &mut impl FnMut(i32, i32) -> i32) -> Outcome
+{
+    let chance = hit_chance(attacker, defender, situation);
+    let crit_chance = attacker.crit_chance + location_penalty(situation.location) / 2;
+    let (roll, _) = roll::check(chance, crit_chance, rng);
+
+    let location = if situation.location == HitLocation::Uncalled {
+        HitLocation::Torso
+    } else {
+        situation.location
+    };
+    let mut r = Outcome {
+        roll,
+        location,
+        damage: 0,
+        defender_flags: BitFlags::empty(),
+        attacker_flags: BitFlags::empty(),
+    };
+
+    match roll {
+        Roll::CriticalFailure => {
+            r.attacker_flags = critical_failure(attacker, rng);
+            return r;
+        }
+        Roll::Failure => return r,
+        Roll::Success | Roll::CriticalSuccess => {}
+    }
+
+    r.defender_flags |= DamageFlag::Hit;
+
+    let mut crit_mult = 2;
+    if roll == Roll::CriticalSuccess {
+        let effect = critical_effect(location, rng(1, 100) + attacker.better_crit);
+        crit_mult = effect.damage_mult;
+        r.defender_flags |= DamageFlag::Critical;
+        r.defender_flags |= effect.flags;
+        if let Some((stat, modifier, flags)) = effect.stat_check {
+            let value = match stat {
+                Stat::Endurance => defender.endurance,
+                Stat::Agility => defender.agility,
+                Stat::Luck => defender.luck,
+                _ => unreachable!(),
+            };
+            if rng(1, 10) > value + modifier {
+                r.defender_flags |= flags;
+            }
+        }
+    }
+
+    let weapon = attacker.weapon.and_then(|w| w.sub.item()).and_then(|i| i.sub.weapon());
+    let (min, max, kind) = if let Some(w) = weapon {
+        (*w.damage.start(), *w.damage.end(), w.damage_kind)
+    } else {
+        (1, 2, DamageKind::Melee)
+    };
+    let mut raw = rng(min, max);
+    if !is_ranged(attacker.kind) {
+        raw += attacker.melee_damage;
+    }
+    r.damage = damage(raw, kind, attacker.ammo, crit_mult,
+        r.defender_flags.contains(DamageFlag::Bypass), defender);
+
+    r
+}
+
+struct CriticalEffect {
+    /// Damage multiplier in halves.
+    damage_mult: i32,
+    flags: BitFlags<DamageFlag>,
+    /// Defender's stat, modifier and effects applied if the stat check fails.
+    stat_check: Option<(Stat, i32, BitFlags<DamageFlag>)>,
+}
+
+/// Returns critical hit effect for the critical `roll`.
+// TODO Use separate tables for each kill type. This is the table for humans.
+// crit_succ_eff
+fn critical_effect(location: HitLocation, roll: i32) -> CriticalEffect {
+    use DamageFlag::*;
+    use HitLocation::*;
+
+    let level = match roll {
+        i32::MIN..=20 => 0,
+        21..=45 => 1,
+        46..=70 => 2,
+        71..=90 => 3,
+        91..=100 => 4,
+        _ => 5,
+    };
+
+    let e = |damage_mult, flags: BitFlags<DamageFlag>| CriticalEffect {
+        damage_mult,
+        flags,
+        stat_check: None,
+    };
+    let c = |damage_mult, flags: BitFlags<DamageFlag>, stat, modifier, fail_flags: DamageFlag|
+        CriticalEffect {
+            damage_mult,
+            flags,
+            stat_check: Some((stat, modifier, fail_flags.into())),
+        };
+    let none = BitFlags::empty();
+    match (location, level) {
+        (Head, 0) => e(4, none),
+        (Head, 1) => c(4, Bypass.into(), Stat::Endurance, 0, KnockedOut),
+        (Head, 2) => c(5, Bypass.into(), Stat::Endurance, -3, KnockedOut),
+        (Head, 3) => c(5, KnockedDown | Bypass, Stat::Endurance, -3, KnockedOut),
+        (Head, 4) => e(6, KnockedOut | Bypass),
+        (Head, _) => e(6, Dead.into()),
+
+        (LeftArm, 0) | (RightArm, 0) => e(3, none),
+        (LeftArm, 1) | (RightArm, 1) => e(3, LoseTurn.into()),
+        (LeftArm, 2) => c(4, none, Stat::Endurance, -3, CripArmLeft),
+        (RightArm, 2) => c(4, none, Stat::Endurance, -3, CripArmRight),
+        (LeftArm, _) => e(4, CripArmLeft | Bypass),
+        (RightArm, _) => e(4, CripArmRight | Bypass),
+
+        (Torso, 0) | (Uncalled, 0) => e(3, none),
+        (Torso, 1) | (Uncalled, 1) => e(3, Bypass.into()),
+        (Torso, 2) | (Uncalled, 2) => e(4, Bypass.into()),
+        (Torso, 3) | (Uncalled, 3) => e(4, KnockedDown | Bypass),
+        (Torso, 4) | (Uncalled, 4) => e(6, KnockedDown | Bypass),
+        (Torso, _) | (Uncalled, _) => e(6, Dead.into()),
+
+        (RightLeg, 0) | (LeftLeg, 0) => e(3, KnockedDown.into()),
+        (RightLeg, 1) => c(3, KnockedDown.into(), Stat::Endurance, 0, CripLegRight),
+        (LeftLeg, 1) => c(3, KnockedDown.into(), Stat::Endurance, 0, CripLegLeft),
+        (RightLeg, 2) => c(4, KnockedDown.into(), Stat::Endurance, -3, CripLegRight),
+        (LeftLeg, 2) => c(4, KnockedDown.into(), Stat::Endurance, -3, CripLegLeft),
+        (RightLeg, 3) => e(4, CripLegRight | Bypass),
+        (LeftLeg, 3) => e(4, CripLegLeft | Bypass),
+        (RightLeg, 4) => c(4, KnockedDown | CripLegRight | Bypass, Stat::Endurance, 0, KnockedOut),
+        (LeftLeg, 4) => c(4, KnockedDown | CripLegLeft | Bypass, Stat::Endurance, 0, KnockedOut),
+        (RightLeg, _) => e(6, KnockedOut | CripLegRight | Bypass),
+        (LeftLeg, _) => e(6, KnockedOut | CripLegLeft | Bypass),
+
+        (Eyes, 0) => c(4, none, Stat::Luck, 4, Blind),
+        (Eyes, 1) => c(4, Bypass.into(), Stat::Luck, 3, Blind),
+        (Eyes, 2) => c(6, Bypass.into(), Stat::Luck, 2, Blind),
+        (Eyes, 3) => e(6, Blind | Bypass | LoseTurn),
+        (Eyes, 4) => e(8, KnockedOut | Blind | Bypass),
+        (Eyes, _) => e(8, Dead.into()),
+
+        (Groin, 0) => e(3, none),
+        (Groin, 1) => c(3, Bypass.into(), Stat::Endurance, -3, KnockedDown),
+        (Groin, 2) => c(3, KnockedDown.into(), Stat::Endurance, -3, KnockedOut),
+        (Groin, 3) => e(3, KnockedOut.into()),
+        (Groin, 4) => c(4, KnockedDown | Bypass, Stat::Endurance, -3, KnockedOut),
+        (Groin, _) => e(4, KnockedOut | Bypass),
+    }
+}
+
+/// Returns effects of critical failure on the attacker.
+// attack_crit_failure(), cf_table
+fn critical_failure(attacker: &Attacker, rng: &mut impl FnMut(i32, i32) -> i32)
+    -> BitFlags<DamageFlag>
+{
+    use DamageFlag::*;
+
+    let weapon = attacker.weapon.and_then(|w| w.sub.item()).and_then(|i| i.sub.weapon());
+    let table = weapon.map(|w| w.crit_failure_table).unwrap_or(0);
+
+    let level = match rng(1, 100) - 5 * (attacker.luck - 5) {
+        i32::MIN..=20 => 0,
+        21..=50 => 1,
+        51..=75 => 2,
+        76..=95 => 3,
+        _ => 4,
+    };
+
+    let none = BitFlags::empty();
+    let flags: [BitFlags<DamageFlag>; 5] = match table {
+        // Unarmed.
+        0 => [none, LoseTurn.into(), LoseTurn.into(), HurtSelf | KnockedDown, CripRandom.into()],
+        // Melee.
+        1 => [none, LoseTurn.into(), Drop.into(), RandomHit.into(), HurtSelf | KnockedDown],
+        // Firearms.
+        2 => [none, LoseAmmo.into(), Drop.into(), RandomHit.into(), Destroy.into()],
+        // Energy weapons.
+        3 => [LoseTurn.into(), LoseTurn | LoseAmmo, Drop | LoseTurn, RandomHit.into(),
+            Explode | LoseTurn],
+        // Grenades.
+        4 => [none, Dud.into(), Drop | Dud, HitSelf.into(), Explode.into()],
+        // Rocket launchers.
+        5 => [none, Dud.into(), Explode.into(), RandomHit.into(), Explode | LoseTurn | KnockedDown],
+        // Flamers.
+        _ => [none, LoseTurn.into(), RandomHit.into(), Destroy.into(), Explode | LoseTurn],
+    };
+    flags[level]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rng(rolls: &[i32]) -> impl FnMut(i32, i32) -> i32 + '_ {
+        let mut rolls = rolls.iter();
+        move |from, to| {
+            let r = *rolls.next().unwrap();
+            assert!(r >= from && r <= to, "{} not in [{}..{}]", r, from, to);
+            r
+        }
+    }
+
+    fn attacker(kind: AttackKind) -> Attacker<'static> {
+        Attacker {
+            weapon: None,
+            ammo: None,
+            kind,
+            skill: 80,
+            perception: 5,
+            luck: 5,
+            crit_chance: 5,
+            better_crit: 0,
+            melee_damage: 1,
+            is_blind: false,
+        }
+    }
+
+    fn defender() -> Defender {
+        Defender {
+            armor_class: 10,
+            damage_threshold: EnumMap::new(),
+            damage_resistance: EnumMap::new(),
+            endurance: 5,
+            agility: 5,
+            luck: 5,
+        }
+    }
+
+    fn situation(distance: u32) -> Situation {
+        Situation {
+            distance,
+            light: 0x10000,
+            location: HitLocation::Uncalled,
+        }
+    }
+
+    #[test]
+    fn hit_chance_() {
+        let a = attacker(AttackKind::Punch);
+        assert_eq!(hit_chance(&a, &defender(), &situation(1)), 70);
+
+        // Distance doesn't matter for melee.
+        assert_eq!(hit_chance(&a, &defender(), &situation(10)), 70);
+
+        // Perception compensates distance.
+        let a = attacker(AttackKind::FireSingle);
+        assert_eq!(hit_chance(&a, &defender(), &situation(6)), 70);
+        assert_eq!(hit_chance(&a, &defender(), &situation(16)), 30);
+
+        let mut s = situation(6);
+        s.light = 0;
+        assert_eq!(hit_chance(&a, &defender(), &s), 30);
+
+        s.light = 0x10000;
+        s.location = HitLocation::Eyes;
+        assert_eq!(hit_chance(&a, &defender(), &s), 10);
+
+        let mut a = attacker(AttackKind::Punch);
+        a.skill = 200;
+        assert_eq!(hit_chance(&a, &defender(), &situation(1)), MAX_HIT_CHANCE);
+    }
+
+    #[test]
+    fn damage_() {
+        let mut d = defender();
+        d.damage_threshold[DamageKind::Laser] = 4;
+        d.damage_resistance[DamageKind::Laser] = 50;
+
+        assert_eq!(damage(10, DamageKind::Melee, None, 2, false, &d), 10);
+        assert_eq!(damage(14, DamageKind::Laser, None, 2, false, &d), 5);
+        assert_eq!(damage(3, DamageKind::Laser, None, 2, false, &d), 0);
+        // Critical multiplier applies before DT.
+        assert_eq!(damage(14, DamageKind::Laser, None, 4, false, &d), 12);
+
+        // Bypass leaves 20% of DT and DR.
+        assert_eq!(damage(14, DamageKind::Laser, None, 2, true, &d), 13);
+
+        let ammo = proto::Ammo {
+            caliber: 0,
+            magazine_size: 0,
+            ac_modifier: 0,
+            dr_modifier: -30,
+            damage_mult: 3,
+            damage_div: 2,
+        };
+        assert_eq!(damage(14, DamageKind::Laser, Some(&ammo), 2, false, &d), 14);
+
+        // Ammo DR modifier applies to bypassed armor too.
+        assert_eq!(damage(14, DamageKind::Laser, Some(&ammo), 2, true, &d), 21);
+    }
+
+    #[test]
+    fn resolve_miss() {
+        let a = attacker(AttackKind::Punch);
+        let r = resolve(&a, &defender(), &situation(1), &mut rng(&[71, 100]));
+        assert_eq!(r.roll, Roll::Failure);
+        assert_eq!(r.damage, 0);
+        assert!(r.defender_flags.is_empty());
+    }
+
+    #[test]
+    fn resolve_hit() {
+        let a = attacker(AttackKind::Punch);
+        let r = resolve(&a, &defender(), &situation(1), &mut rng(&[50, 100, 2]));
+        assert_eq!(r.roll, Roll::Success);
+        assert_eq!(r.location, HitLocation::Torso);
+        assert_eq!(r.damage, 3);
+        assert_eq!(r.defender_flags, BitFlags::from(DamageFlag::Hit));
+    }
+
+    #[test]
+    fn resolve_critical() {
+        let a = attacker(AttackKind::Punch);
+        let mut s = situation(1);
+        s.location = HitLocation::Head;
+
+        // Knocked out if the Endurance check fails.
+        let r = resolve(&a, &defender(), &s, &mut rng(&[1, 1, 50, 10, 2]));
+        assert_eq!(r.roll, Roll::CriticalSuccess);
+        assert_eq!(r.defender_flags,
+            DamageFlag::Hit | DamageFlag::Critical | DamageFlag::Bypass | DamageFlag::KnockedOut);
+        assert_eq!(r.damage, 7);
+
+        let r = resolve(&a, &defender(), &s, &mut rng(&[1, 1, 50, 1, 2]));
+        assert!(!r.defender_flags.contains(DamageFlag::KnockedOut));
+    }
+
+    #[test]
+    fn resolve_critical_failure() {
+        let a = attacker(AttackKind::Punch);
+        let r = resolve(&a, &defender(), &situation(1), &mut rng(&[100, 1, 30]));
+        assert_eq!(r.roll, Roll::CriticalFailure);
+        assert_eq!(r.attacker_flags, BitFlags::from(DamageFlag::LoseTurn));
+        assert!(r.defender_flags.is_empty());
+    }
+
+    #[test]
+    fn spread_rounds_() {
+        assert_eq!(spread_rounds(0), (0, 0, 0));
+        assert_eq!(spread_rounds(1), (1, 0, 0));
+        assert_eq!(spread_rounds(2), (1, 0, 1));
+        assert_eq!(spread_rounds(5), (1, 1, 3));
+        assert_eq!(spread_rounds(10), (3, 3, 4));
+    }
+
+    #[test]
+    fn explosion_damage_() {
+        assert_eq!(explosion_damage(30, 0), 30);
+        assert_eq!(explosion_damage(30, 1), 20);
+        assert_eq!(explosion_damage(30, 2), 10);
+        assert_eq!(explosion_damage(30, 3), 0);
+    }
+
+    #[test]
+    fn merge() {
+        let a = attacker(AttackKind::FireBurst);
+        let mut r = resolve(&a, &defender(), &situation(1), &mut rng(&[100, 100]));
+        assert_eq!(r.roll, Roll::Failure);
+        r.merge(&resolve(&a, &defender(), &situation(1), &mut rng(&[50, 100, 2])));
+        assert_eq!(r.roll, Roll::Success);
+        assert!(r.damage > 0);
+        assert!(r.defender_flags.contains(DamageFlag::Hit));
+    }
+}
@@ -4,12 +4,16 @@ use crate::asset::frame::FrameId;
 use crate::asset::message::BULLET_STR;
 use crate::game::object;
 use crate::game::script::Sid;
+use crate::game::ui::barter::Barter;
+use crate::game::ui::combat_control::{self, CombatControl};
 use crate::game::world::World;
 use crate::graphics::{Point, Rect};
 use crate::graphics::color::{Rgb15, GREEN};
 use crate::graphics::font::FontKey;
 use crate::graphics::sprite::{Sprite, Effect};
 use crate::ui::*;
+use crate::ui::button::Button;
+use crate::ui::command::UiCommandData;
 use crate::ui::message_panel::{MessagePanel, MouseControl};
 use crate::ui::panel::Panel;
 
@@ -23,8 +27,16 @@ pub struct Dialog {
     options_widget: Handle,
     options: Vec<OptionInfo>,
     sid: Sid,
+    obj: object::Handle,
     saved_camera_origin: Point,
     pub running: bool,
+    /// Barter price modifier in percents set by `gdialog_set_barter_mod()`.
+    pub barter_mod: i32,
+    /// Set by `gdialog_mod_barter()` to the barter modifier for the barter screen to be shown
+    /// once the current dialog procedure finishes.
+    pub barter_request: Option<i32>,
+    pub barter: Option<Barter>,
+    pub combat_control: Option<CombatControl>,
 }
 
 impl Dialog {
@@ -32,8 +44,15 @@ impl Dialog {
         let window = ui.new_window(Rect::with_size(0, 0, 640, 480),
             Some(Sprite::new(FrameId::ALLTLK)));
 
+        let party_member = world.party().contains(obj);
         ui.new_widget(window, Rect::with_size(0, 480 - 190, 640, 480), None,
-            Some(Sprite::new(FrameId::DI_TALK)), Panel::new());
+            Some(Sprite::new(if party_member { FrameId::DI_TALKP } else { FrameId::DI_TALK })),
+            Panel::new());
+        if party_member {
+            ui.new_widget(window, Rect::with_size(593, 480 - 190 + 41, 15, 16), None, None,
+                Button::new(FrameId::DI_RDBT1, FrameId::DI_RDBT2,
+                    Some(UiCommandData::CombatControl(combat_control::Command::Show))));
+        }
 
         let reply = MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(1), GREEN);
         let reply = ui.new_widget(window, Rect::with_size(135, 235, 382, 47), None, None, reply);
@@ -70,11 +89,18 @@ impl Dialog {
             options: Vec::new(),
             running: false,
             sid,
+            obj,
+            barter_mod: 0,
+            barter_request: None,
+            barter: None,
+            combat_control: None,
             saved_camera_origin,
         }
     }
 
-    pub fn hide(self, ui: &mut Ui, world: &mut World) {
+    pub fn hide(mut self, ui: &mut Ui, world: &mut World) {
+        self.hide_barter(ui);
+        self.hide_combat_control(ui);
         ui.remove(self.window);
         world.camera_mut().origin = self.saved_camera_origin;
     }
@@ -114,6 +140,35 @@ impl Dialog {
         self.sid
     }
 
+    /// The object being talked to.
+    pub fn obj(&self) -> object::Handle {
+        self.obj
+    }
+
+    pub fn show_barter(&mut self, ui: &mut Ui, player: object::Handle, barter_mod: i32) {
+        if self.barter.is_none() {
+            self.barter = Some(Barter::show(ui, player, self.obj, barter_mod));
+        }
+    }
+
+    pub fn hide_barter(&mut self, ui: &mut Ui) {
+        if let Some(barter) = self.barter.take() {
+            barter.hide(ui);
+        }
+    }
+
+    pub fn show_combat_control(&mut self, ui: &mut Ui) {
+        if self.combat_control.is_none() {
+            self.combat_control = Some(CombatControl::show(ui));
+        }
+    }
+
+    pub fn hide_combat_control(&mut self, ui: &mut Ui) {
+        if let Some(v) = self.combat_control.take() {
+            v.hide(ui);
+        }
+    }
+
     fn build_option(option: &bstr) -> BString {
         BString::concat(&[&b"  "[..], BULLET_STR, &b" "[..], option.as_bytes()])
     }
@@ -1,5 +1,6 @@
 use enumflags2::BitFlags;
 use enumflags2_derive::EnumFlags;
+use enum_map_derive::Enum;
 use enum_primitive_derive::Primitive;
 use if_chain::if_chain;
 use slotmap::{SecondaryMap, SlotMap};
@@ -8,7 +9,7 @@ use std::{cmp, fmt};
 use std::mem;
 use std::rc::Rc;
 
-use crate::asset::{CritterAnim, EntityKind, Flag, FlagExt, ItemKind, WeaponKind};
+use crate::asset::{CritterAnim, EntityKind, Flag, FlagExt, ItemKind, SceneryKind, WeaponKind};
 use crate::asset::frame::{FrameId, FrameDb};
 use crate::asset::proto::{self, CritterKillKind, ProtoId, ProtoDb};
 use crate::asset::script::ProgramId;
@@ -104,6 +105,50 @@ impl Inventory {
             items: Vec::new(),
         }
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn position(&self, obj: Handle) -> Option<usize> {
+        self.items.iter().position(|i| i.object == obj)
+    }
+
+    pub fn contains(&self, obj: Handle) -> bool {
+        self.position(obj).is_some()
+    }
+
+    /// Returns number of items in stack of `obj` or 0 if `obj` is not in the inventory.
+    pub fn count(&self, obj: Handle) -> usize {
+        self.position(obj).map(|i| self.items[i].count).unwrap_or(0)
+    }
+}
+
+/// Equipment slot of a critter. Items occupying slots stay in the inventory and are marked with
+/// the corresponding object flag.
+#[derive(Clone, Copy, Debug, Enum, Eq, PartialEq)]
+pub enum EquipmentSlot {
+    Armor,
+    LeftHand,
+    RightHand,
+}
+
+impl EquipmentSlot {
+    pub fn flag(self) -> Flag {
+        match self {
+            EquipmentSlot::Armor => Flag::Worn,
+            EquipmentSlot::LeftHand => Flag::LeftHand,
+            EquipmentSlot::RightHand => Flag::RightHand,
+        }
+    }
+}
+
+/// State of a lockable object (container or door).
+#[derive(Clone, Copy, Debug, EnumFlags, Eq, PartialEq)]
+#[repr(u32)]
+pub enum LockFlag {
+    Locked = 0x2000000,
+    Jammed = 0x4000000,
 }
 
 #[derive(Clone, Debug)]
@@ -165,6 +210,13 @@ impl Handle {
         use slotmap::Key;
         Handle(Key::null())
     }
+
+    /// Returns `count` distinct handles not bound to any object.
+    #[cfg(test)]
+    pub fn distinct(count: usize) -> Vec<Self> {
+        let mut handles = SlotMap::<SmKey, ()>::with_key();
+        (0..count).map(|_| Handle(handles.insert(()))).collect()
+    }
 }
 
 #[derive(Debug)]
@@ -182,6 +234,7 @@ pub struct Object {
     pub outline: Option<Outline>,
     pub sequence: Option<Cancel>,
     pub script: Option<(Sid, ProgramId)>,
+    pub lock_flags: BitFlags<LockFlag>,
     pub sub: SubObject,
 }
 
@@ -204,8 +257,10 @@ impl Object {
             outline: None,
             sequence: None,
             script: None,
+            lock_flags: BitFlags::empty(),
             sub: match fid.kind() {
                 EntityKind::Critter => SubObject::Critter(Default::default()),
+                EntityKind::Item => SubObject::Item(Default::default()),
                 _ => SubObject::None,
             }
         }
@@ -215,6 +270,17 @@ impl Object {
         self.fid.kind()
     }
 
+    // obj_is_locked()
+    pub fn is_locked(&self) -> bool {
+        self.lock_flags.contains(LockFlag::Locked)
+    }
+
+    /// Whether the door or container is open. Closed ones show the first frame.
+    // obj_is_open()
+    pub fn is_open(&self) -> bool {
+        self.frame_idx != 0
+    }
+
     pub fn has_running_sequence(&self) -> bool {
         self.sequence.as_ref().map(|seq| seq.is_running()).unwrap_or(false)
     }
@@ -269,8 +335,7 @@ impl Object {
 
     // critter_is_dead()
     pub fn is_critter_dead(&self) -> bool {
-        // FIXME
-        false
+        self.sub.critter().map(|c| c.is_dead()).unwrap_or(false)
     }
 
     // obj_intersects_with
@@ -491,6 +556,32 @@ impl Objects {
         self.detached.clear();
     }
 
+    /// Removes all objects except the ones for which `f` returns `true`.
+    pub fn retain(&mut self, f: impl Fn(Handle) -> bool) {
+        let removed: Vec<_> = self.iter().filter(|&h| !f(h)).collect();
+        for h in removed {
+            self.remove(h);
+        }
+    }
+
+    /// Object that has `item` in its inventory.
+    pub fn inventory_owner(&self, item: Handle) -> Option<Handle> {
+        self.iter().find(|&h| self.get(h).borrow().inventory.contains(item))
+    }
+
+    /// Returns `h` followed by the objects in its inventory, recursively.
+    pub fn with_inventory(&self, h: Handle) -> Vec<Handle> {
+        fn collect(objects: &Objects, h: Handle, r: &mut Vec<Handle>) {
+            r.push(h);
+            for item in &objects.get(h).borrow().inventory.items {
+                collect(objects, item.object, r);
+            }
+        }
+        let mut r = Vec::new();
+        collect(self, h, &mut r);
+        r
+    }
+
     pub fn insert(&mut self, obj: Object) -> Handle {
         let pos = obj.pos;
 
@@ -602,6 +693,16 @@ impl Objects {
         self.handles.keys().map(|k| Handle(k))
     }
 
+    /// Objects on the map made from the proto `pid`. Objects in inventories are skipped.
+    pub fn find_on_map(&self, pid: ProtoId) -> Vec<Handle> {
+        self.iter()
+            .filter(|&h| {
+                let obj = self.get(h).borrow();
+                obj.pos.is_some() && obj.pid.proto_id() == Some(pid)
+            })
+            .collect()
+    }
+
     pub fn set_pos(&mut self, h: Handle, pos: EPoint) {
         self.remove_from_tile_grid(h);
         self.insert_into_tile_grid(h, Some(pos), true);
@@ -671,8 +772,21 @@ impl Objects {
     // obj_blocking_at()
     #[must_use]
     pub fn is_blocked_at(&self, obj: Handle, pos: EPoint) -> bool {
+        self.is_blocked_at_except(obj, pos, |_| false)
+    }
+
+    /// Same as `is_blocked_at()` but the closed doors the `obj` can open aren't blockers.
+    #[must_use]
+    pub fn is_blocked_for_walker_at(&self, obj: Handle, pos: EPoint) -> bool {
+        self.is_blocked_at_except(obj, pos, |h| self.can_use_door(obj, h))
+    }
+
+    /// Same as `is_blocked_at()` but objects for which `ignore` returns `true` aren't blockers.
+    fn is_blocked_at_except(&self, obj: Handle, pos: EPoint, ignore: impl Fn(Handle) -> bool)
+        -> bool
+    {
         let check = |h| {
-            if h == obj {
+            if h == obj || ignore(h) {
                 return false;
             }
             let o = self.get(h).borrow();
@@ -772,6 +886,28 @@ impl Objects {
         None
     }
 
+    /// Traces the shot fired by `shooter` from its position via `via` tile up to `range` hexes.
+    /// Returns the first object in the way of the shot and the tile where the shot ends.
+    // compute_spray()
+    #[must_use]
+    pub fn trace_shot(&self, shooter: Handle, via: Point, range: u32) -> (Option<Handle>, EPoint) {
+        let pos = self.get(shooter).borrow().pos.unwrap();
+        let mut end = pos;
+        if via == pos.point {
+            return (None, end);
+        }
+        for p in hex::ray(pos.point, via).skip(1) {
+            if hex::distance(pos.point, p) > range || self.tile_grid.to_linear(p).is_none() {
+                break;
+            }
+            end = p.elevated(pos.elevation);
+            if let Some(blocker) = self.shot_blocker_at(shooter, end) {
+                return (Some(blocker), end);
+            }
+        }
+        (None, end)
+    }
+
     // combat_is_shot_blocked()
     #[must_use]
     pub fn is_shot_blocked(&self, shooter: Handle, target: Handle) -> bool {
@@ -874,18 +1010,430 @@ impl Objects {
         }
     }
 
+    pub fn is_door(&self, obj: Handle) -> bool {
+        let pid = if let Some(v) = self.get(obj).borrow().pid.proto_id() {
+            v
+        } else {
+            return false;
+        };
+        pid.kind() == EntityKind::Scenery
+            && self.proto_db.kind(pid).scenery() == Some(SceneryKind::Door)
+    }
+
+    /// Whether the critter `obj` can open the `door` on its way.
+    // anim_can_use_door_()
+    pub fn can_use_door(&self, obj: Handle, door: Handle) -> bool {
+        if !self.is_door(door) {
+            return false;
+        }
+        let objo = self.get(obj).borrow();
+        if !objo.sub.critter().map(|c| !c.is_dead()).unwrap_or(false) {
+            return false;
+        }
+        let dooro = self.get(door).borrow();
+        if dooro.is_open() || dooro.is_locked() {
+            return false;
+        }
+        match objo.pid {
+            ObjectProtoId::Dude => {
+                let proto = self.proto_db.proto(dooro.pid.proto_id().unwrap()).unwrap();
+                proto.sub.scenery().unwrap().sub.door().unwrap().is_walk_thru()
+            }
+            ObjectProtoId::ProtoId(pid) => {
+                self.proto_db.proto(pid).unwrap().sub.critter().unwrap().can_open_doors()
+            }
+            ObjectProtoId::None => false,
+        }
+    }
+
     // item_get_type()
     pub fn item_kind(&self, obj: Handle) -> Option<ItemKind> {
         let obj = self.get(obj).borrow();
         if obj.kind() == EntityKind::Item {
             let pid = obj.pid.proto_id().unwrap();
             if pid == ProtoId::SHIV {
-                return Some(self.proto_db.proto(pid).unwrap()
-                    .sub.item().unwrap()
-                    .sub.kind());
+                return Some(ItemKind::Misc);
+            }
+            Some(self.proto_db.proto(pid).unwrap()
+                .sub.item().unwrap()
+                .sub.kind())
+        } else {
+            None
+        }
+    }
+
+    /// Removes object from the map and from the object set. Objects in its inventory are not
+    /// removed.
+    pub fn remove(&mut self, h: Handle) -> Object {
+        self.remove_from_tile_grid(h);
+        self.handles.remove(h.0);
+        self.objects.remove(h.0).unwrap().into_inner()
+    }
+
+    /// Removes object from the map leaving it in detached state (e.g. when it's put into
+    /// an inventory).
+    pub fn detach(&mut self, h: Handle) {
+        if self.get(h).borrow().pos.is_some() {
+            self.remove_from_tile_grid(h);
+            self.insert_into_tile_grid(h, None, true);
+        }
+    }
+
+    // item_weight()
+    /// Weight of single unit of the `item`, including the contents if it's a container.
+    pub fn item_weight(&self, item: Handle) -> u32 {
+        let obj = self.get(item).borrow();
+        let weight = obj.pid.proto_id()
+            .and_then(|pid| self.proto_db.proto(pid).unwrap().sub.item().map(|i| i.weight))
+            .unwrap_or(0);
+        cmp::max(weight, 0) as u32 + self.items_weight(&obj.inventory)
+    }
+
+    // item_size()
+    pub fn item_size(&self, item: Handle) -> u32 {
+        let pid = self.get(item).borrow().pid.proto_id();
+        let size = pid
+            .and_then(|pid| self.proto_db.proto(pid).unwrap().sub.item().map(|i| i.size))
+            .unwrap_or(0);
+        cmp::max(size, 0) as u32
+    }
+
+    // item_total_weight()
+    pub fn inventory_weight(&self, owner: Handle) -> u32 {
+        self.items_weight(&self.get(owner).borrow().inventory)
+    }
+
+    // item_c_curr_size()
+    pub fn inventory_size(&self, owner: Handle) -> u32 {
+        self.get(owner).borrow().inventory.items.iter()
+            .map(|i| self.item_size(i.object) * i.count as u32)
+            .sum()
+    }
+
+    // item_cost()
+    /// Cost of single unit of the `item`, including the loaded ammo and the contents if it's
+    /// a container.
+    pub fn item_cost(&self, item: Handle) -> u32 {
+        let obj = self.get(item).borrow();
+        let mut r = 0;
+        if let Some(pid) = obj.pid.proto_id() {
+            let proto = self.proto_db.proto(pid).unwrap();
+            if let Some(proto_item) = proto.sub.item() {
+                r = cmp::max(proto_item.price, 0) as u32;
+                if let (proto::SubItem::Weapon(_), Some(item)) = (&proto_item.sub, obj.sub.item()) {
+                    if let Some(ammo_pid) = item.ammo_pid {
+                        r += self.ammo_cost(ammo_pid, item.charges);
+                    }
+                }
+            }
+        }
+        r + self.inventory_cost0(&obj.inventory)
+    }
+
+    // item_total_cost()
+    pub fn inventory_cost(&self, owner: Handle) -> u32 {
+        self.inventory_cost0(&self.get(owner).borrow().inventory)
+    }
+
+    /// Total number of bottle caps carried by `owner`, including the caps inside containers.
+    // item_caps_total()
+    pub fn caps_total(&self, owner: Handle) -> u32 {
+        self.get(owner).borrow().inventory.items.iter()
+            .map(|i| if self.get(i.object).borrow().pid == ProtoId::BOTTLE_CAPS.into() {
+                i.count as u32
+            } else {
+                self.caps_total(i.object) * i.count as u32
+            })
+            .sum()
+    }
+
+    /// Adds `amount` of bottle caps to inventory of `owner` or removes them if `amount` is
+    /// negative. Returns `false` if there's not enough caps to remove, in which case nothing is
+    /// removed.
+    // item_caps_adjust()
+    pub fn adjust_caps(&mut self, owner: Handle, amount: i32) -> bool {
+        if amount > 0 {
+            let fid = self.proto_db.proto(ProtoId::BOTTLE_CAPS).unwrap().fid;
+            let caps = self.insert(Object::new(fid, ProtoId::BOTTLE_CAPS.into(), None));
+            self.add_new_to_inventory(owner, caps, amount as usize);
+            true
+        } else if amount < 0 {
+            let amount = -amount as u32;
+            if self.caps_total(owner) < amount {
+                return false;
+            }
+            let left = self.remove_caps(owner, amount);
+            assert_eq!(left, 0);
+            true
+        } else {
+            true
+        }
+    }
+
+    /// Checks whether `count` units of `item` fit into `owner` which is a container.
+    /// Always returns `true` for non-containers.
+    pub fn fits_into_container(&self, owner: Handle, item: Handle, count: usize) -> bool {
+        let capacity = self.get(owner).borrow().pid.proto_id()
+            .and_then(|pid| {
+                let proto = self.proto_db.proto(pid).unwrap();
+                if let Some(proto::SubItem::Container(c)) = proto.sub.item().map(|i| &i.sub) {
+                    Some(c.capacity)
+                } else {
+                    None
+                }
+            });
+        if let Some(capacity) = capacity {
+            let size = self.inventory_size(owner) + self.item_size(item) * count as u32;
+            size <= cmp::max(capacity, 0) as u32
+        } else {
+            true
+        }
+    }
+
+    // item_identical()
+    fn is_stackable(&self, item1: Handle, item2: Handle) -> bool {
+        let o1 = self.get(item1).borrow();
+        let o2 = self.get(item2).borrow();
+        let equipped = Flag::Worn | Flag::LeftHand | Flag::RightHand;
+        item1 != item2
+            && o1.pid == o2.pid
+            && o1.script.is_none() && o2.script.is_none()
+            && o1.inventory.is_empty() && o2.inventory.is_empty()
+            && o1.sub.item().map(|i| i.ammo_pid) == o2.sub.item().map(|i| i.ammo_pid)
+            && !o1.flags.intersects(equipped) && !o2.flags.intersects(equipped)
+    }
+
+    /// Puts `count` units of `item` into the inventory of `owner` detaching the `item` from map.
+    /// If the inventory has a stack of identical items the `item` object is merged into
+    /// the stack, the handle of the stack is returned in this case. The merged `item` is left
+    /// detached and should be destroyed with `World::destroy_object()`, see
+    /// `World::add_to_inventory()`.
+    /// No weight or size limits are checked.
+    // item_add_force()
+    pub fn add_to_inventory(&mut self, owner: Handle, item: Handle, count: usize) -> Handle {
+        assert_ne!(owner, item);
+        assert!(count > 0);
+
+        self.detach(item);
+
+        let stack = self.get(owner).borrow().inventory.items.iter()
+            .map(|i| i.object)
+            .find(|&i| self.is_stackable(i, item));
+        if let Some(stack) = stack {
+            let mut ownero = self.get(owner).borrow_mut();
+            let i = ownero.inventory.position(stack).unwrap();
+            ownero.inventory.items[i].count += count;
+            stack
+        } else {
+            let mut ownero = self.get(owner).borrow_mut();
+            if let Some(i) = ownero.inventory.position(item) {
+                ownero.inventory.items[i].count += count;
+            } else {
+                ownero.inventory.items.push(InventoryItem {
+                    object: item,
+                    count,
+                });
+            }
+            item
+        }
+    }
+
+    /// Removes up to `count` units of `item` from inventory of `owner`. Returns detached object
+    /// representing the removed units and their count. If only part of the stack is removed,
+    /// a new object is created for the removed units.
+    /// Unequips the `item` if the whole stack is removed.
+    // item_remove_mult()
+    pub fn remove_from_inventory(&mut self, owner: Handle, item: Handle, count: usize)
+        -> Option<(Handle, usize)>
+    {
+        let (i, have) = {
+            let ownero = self.get(owner).borrow();
+            let i = ownero.inventory.position(item)?;
+            (i, ownero.inventory.items[i].count)
+        };
+        let count = cmp::min(count, have);
+        if count == 0 {
+            return None;
+        }
+        if count == have {
+            self.unequip_item(owner, item);
+            self.get(owner).borrow_mut().inventory.items.remove(i);
+            Some((item, count))
+        } else {
+            self.get(owner).borrow_mut().inventory.items[i].count -= count;
+            let split = {
+                let itemo = self.get(item).borrow();
+                let mut split = Object::new(itemo.fid, itemo.pid, None);
+                split.flags = itemo.flags;
+                split.light_emitter = itemo.light_emitter;
+                if let SubObject::Item(item) = itemo.sub {
+                    split.sub = SubObject::Item(item);
+                }
+                split
+            };
+            Some((self.insert(split), count))
+        }
+    }
+
+    /// Takes out all ammo loaded into the `weapon` and puts it into inventory of `owner`.
+    /// Returns the ammo stack or `None` if the weapon is not loaded.
+    // item_w_unload()
+    pub fn unload_weapon(&mut self, owner: Handle, weapon: Handle) -> Option<Handle> {
+        let (ammo_pid, rounds) = {
+            let mut weapono = self.get(weapon).borrow_mut();
+            let item = weapono.sub.item_mut()?;
+            let ammo_pid = item.ammo_pid?;
+            if item.charges <= 0 {
+                return None;
+            }
+            (ammo_pid, mem::replace(&mut item.charges, 0))
+        };
+        let (fid, magazine_size) = {
+            let proto = self.proto_db.proto(ammo_pid).unwrap();
+            let magazine_size = if let proto::SubItem::Ammo(a) = &proto.sub.item().unwrap().sub {
+                cmp::max(a.magazine_size, 1)
+            } else {
+                1
+            };
+            (proto.fid, magazine_size)
+        };
+        let count = (rounds + magazine_size - 1) / magazine_size;
+        let mut ammo = Object::new(fid, ammo_pid.into(), None);
+        if let SubObject::Item(item) = &mut ammo.sub {
+            item.charges = rounds - (count - 1) * magazine_size;
+        }
+        let ammo = self.insert(ammo);
+        Some(self.add_new_to_inventory(owner, ammo, count as usize))
+    }
+
+    /// Same as `add_to_inventory()` but removes the merged `item` right away. Only for the objects
+    /// just created, nothing else refers to them yet.
+    fn add_new_to_inventory(&mut self, owner: Handle, item: Handle, count: usize) -> Handle {
+        let stack = self.add_to_inventory(owner, item, count);
+        if stack != item {
+            self.remove(item);
+        }
+        stack
+    }
+
+    /// Empties inventory of `owner`. Returns the unequipped items along with their counts.
+    pub fn take_inventory(&mut self, owner: Handle) -> Vec<(Handle, usize)> {
+        let items = mem::take(&mut self.get(owner).borrow_mut().inventory.items);
+        items.into_iter()
+            .map(|item| {
+                self.unequip_item0(item.object);
+                (item.object, item.count)
+            })
+            .collect()
+    }
+
+    /// Returns inventory item of `owner` with the specified `pid`.
+    // inven_pid_is_carried_ptr()
+    pub fn find_in_inventory(&self, owner: Handle, pid: ProtoId) -> Option<Handle> {
+        self.get(owner).borrow().inventory.items.iter()
+            .map(|i| i.object)
+            .find(|&i| self.get(i).borrow().pid == ObjectProtoId::ProtoId(pid))
+    }
+
+    // inven_pid_quantity_carried()
+    pub fn inventory_pid_count(&self, owner: Handle, pid: ProtoId) -> usize {
+        self.get(owner).borrow().inventory.items.iter()
+            .filter(|i| self.get(i.object).borrow().pid == ObjectProtoId::ProtoId(pid))
+            .map(|i| i.count)
+            .sum()
+    }
+
+    /// Returns item of `critter` occupying the equipment `slot`.
+    // inven_worn(), inven_left_hand(), inven_right_hand()
+    pub fn equipped(&self, critter: Handle, slot: EquipmentSlot) -> Option<Handle> {
+        let flag = slot.flag();
+        self.get(critter).borrow().inventory.items.iter()
+            .map(|i| i.object)
+            .find(|&i| self.get(i).borrow().flags.contains(flag))
+    }
+
+    /// Equips the `item` into the `slot` of `critter`. Armor always goes into
+    /// `EquipmentSlot::Armor` regardless of the `slot`. The `item` must be in the inventory of
+    /// `critter`. Previously equipped item in the slot is unequipped.
+    // inven_wield()
+    pub fn equip(&mut self, critter: Handle, item: Handle, slot: EquipmentSlot) {
+        let slot = if self.item_kind(item) == Some(ItemKind::Armor) {
+            EquipmentSlot::Armor
+        } else if slot == EquipmentSlot::Armor {
+            EquipmentSlot::RightHand
+        } else {
+            slot
+        };
+
+        assert!(self.get(critter).borrow().inventory.contains(item));
+
+        // Equipped item must be a stack of its own.
+        let item = if self.get(critter).borrow().inventory.count(item) > 1 {
+            let (split, _) = self.remove_from_inventory(critter, item, 1).unwrap();
+            self.get(critter).borrow_mut().inventory.items.push(InventoryItem {
+                object: split,
+                count: 1,
+            });
+            split
+        } else {
+            item
+        };
+
+        self.unequip(critter, slot);
+        self.unequip_item(critter, item);
+        self.get(item).borrow_mut().flags.insert(slot.flag());
+
+        if slot == EquipmentSlot::RightHand {
+            let weapon = self.weapon_kind(item);
+            self.set_weapon_fid(critter, weapon);
+        }
+    }
+
+    /// Unequips item in the `slot`. Does nothing if the slot is empty.
+    // inven_unwield()
+    pub fn unequip(&mut self, critter: Handle, slot: EquipmentSlot) {
+        if let Some(item) = self.equipped(critter, slot) {
+            self.unequip_item(critter, item);
+        }
+    }
+
+    fn unequip_item(&mut self, critter: Handle, item: Handle) {
+        let was_right_hand = self.unequip_item0(item);
+        if was_right_hand {
+            let item = self.equipped(critter, EquipmentSlot::RightHand);
+            let weapon = item.map(|i| self.weapon_kind(i)).unwrap_or(WeaponKind::Unarmed);
+            self.set_weapon_fid(critter, weapon);
+        }
+    }
+
+    fn unequip_item0(&mut self, item: Handle) -> bool {
+        let mut itemo = self.get(item).borrow_mut();
+        let was_right_hand = itemo.flags.contains(Flag::RightHand);
+        itemo.flags.remove(Flag::Worn | Flag::LeftHand | Flag::RightHand);
+        was_right_hand
+    }
+
+    fn weapon_kind(&self, item: Handle) -> WeaponKind {
+        self.get(item).borrow().pid.proto_id()
+            .and_then(|pid| {
+                let proto = self.proto_db.proto(pid).unwrap();
+                if let Some(proto::SubItem::Weapon(w)) = proto.sub.item().map(|i| &i.sub) {
+                    Some(w.animation_code)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(WeaponKind::Unarmed)
+    }
+
+    fn set_weapon_fid(&mut self, critter: Handle, weapon: WeaponKind) {
+        let mut obj = self.get(critter).borrow_mut();
+        if let FrameId::Critter(fid) = obj.fid {
+            let new_fid = fid.with_weapon(weapon);
+            if self.frm_db.get(new_fid.into()).is_ok() {
+                obj.fid = new_fid.into();
             }
         }
-        None
     }
 
     // action_can_be_pushed()
@@ -906,19 +1454,15 @@ impl Objects {
             return false;
         }
         if in_combat {
-            unimplemented!("TODO")
-//            pushed_team_num = pushed->_._.critter.combat_data.team_num;
-//          pushed_ = &pushed->_._;
-//          if ( pushed_team_num == pusher->_._.critter.combat_data.team_num
-//            && pusher == pushed_->critter.combat_data.who_hit_me )
-//          {
-//            return 0;
-//          }
-//          v7 = pushed_->critter.combat_data.who_hit_me;
-//          if ( v7 && v7->_._.critter.combat_data.team_num == pusher->_._.critter.combat_data.team_num )
-//            result = 0;
-//          else
-//            result = 1;
+            // Critters attacked by the pusher's team don't let it push them around.
+            let team_id = |h| self.get(h).borrow().sub.critter().map(|c| c.combat.team_id);
+            let who_hit_me = pushedo.sub.critter().unwrap().combat.who_hit_me
+                .filter(|&h| self.contains(h));
+            if let Some(who_hit_me) = who_hit_me {
+                if team_id(who_hit_me) == team_id(pusher) {
+                    return false;
+                }
+            }
         }
         true
     }
@@ -947,7 +1491,7 @@ impl Objects {
             |p| {
                 let p = EPoint::new(from.elevation, p);
                 if (!allow_neighbor_tile || p.point != to) &&
-                    self.is_blocked_at(obj, p) // TODO check anim_can_use_door_(obj, v22)
+                    self.is_blocked_for_walker_at(obj, p)
                 {
                     TileState::Blocked
                 } else if let Some(pid) = o.pid.proto_id() {
@@ -1177,12 +1721,61 @@ impl Objects {
         list.retain(|&hh| hh != h);
         old_pos
     }
+
+    fn inventory_cost0(&self, inventory: &Inventory) -> u32 {
+        inventory.items.iter()
+            .map(|i| self.item_cost(i.object) * i.count as u32)
+            .sum()
+    }
+
+    /// Cost of `rounds` of ammo `pid`. Full magazine costs as much as the ammo proto price.
+    fn ammo_cost(&self, pid: ProtoId, rounds: i32) -> u32 {
+        let proto = self.proto_db.proto(pid).unwrap();
+        let item = proto.sub.item().unwrap();
+        if let proto::SubItem::Ammo(a) = &item.sub {
+            (cmp::max(item.price, 0) * cmp::max(rounds, 0) / cmp::max(a.magazine_size, 1)) as u32
+        } else {
+            0
+        }
+    }
+
+    /// Removes up to `amount` of bottle caps from `owner` and containers in its inventory.
+    /// Returns amount that couldn't be removed.
+    fn remove_caps(&mut self, owner: Handle, mut amount: u32) -> u32 {
+        let items: Vec<_> = self.get(owner).borrow().inventory.items.iter()
+            .map(|i| (i.object, i.count))
+            .collect();
+        for (item, count) in items {
+            if amount == 0 {
+                break;
+            }
+            if self.get(item).borrow().pid == ProtoId::BOTTLE_CAPS.into() {
+                let (removed, count) = self.remove_from_inventory(owner, item,
+                    cmp::min(count, amount as usize)).unwrap();
+                self.remove(removed);
+                amount -= count as u32;
+            } else {
+                amount = self.remove_caps(item, amount);
+            }
+        }
+        amount
+    }
+
+    /// Total weight of the items in the `inventory`.
+    pub fn items_weight(&self, inventory: &Inventory) -> u32 {
+        inventory.items.iter()
+            .map(|i| self.item_weight(i.object) * i.count as u32)
+            .sum()
+    }
 }
 
 #[derive(Debug)]
 pub enum SubObject {
     None,
     Critter(Critter),
+    Item(Item),
+    Exit(Exit),
+    Elevator(Elevator),
 }
 
 impl SubObject {
@@ -1193,6 +1786,83 @@ impl SubObject {
             None
         }
     }
+
+    pub fn critter_mut(&mut self) -> Option<&mut Critter> {
+        if let SubObject::Critter(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    pub fn item(&self) -> Option<&Item> {
+        if let SubObject::Item(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    pub fn item_mut(&mut self) -> Option<&mut Item> {
+        if let SubObject::Item(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    pub fn exit(&self) -> Option<&Exit> {
+        if let SubObject::Exit(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    pub fn exit_mut(&mut self) -> Option<&mut Exit> {
+        if let SubObject::Exit(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    pub fn elevator(&self) -> Option<&Elevator> {
+        if let SubObject::Elevator(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+}
+
+/// Destination of an exit grid, stairs or ladder.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Exit {
+    /// Destination map ID. Exit grids lead to the world map if it's negative, stairs and ladders
+    /// lead to another elevation of the current map if it's not positive.
+    pub map_id: i32,
+    /// Destination position or `None` for the map entrance.
+    pub pos: Option<EPoint>,
+    pub direction: Direction,
+}
+
+/// Elevator panel shown on using the elevator scenery.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Elevator {
+    pub kind: u32,
+    /// Level the elevator is at.
+    pub level: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Item {
+    /// Number of rounds for weapons and ammo, number of charges for misc items.
+    pub charges: i32,
+    /// Ammo loaded into weapon.
+    pub ammo_pid: Option<ProtoId>,
+    /// Code of the locks the key opens.
+    pub key_code: i32,
 }
 
 #[derive(Debug, Default)]
@@ -1214,22 +1884,40 @@ impl Critter {
 
     // critter_is_dead()
     pub fn is_dead(&self) -> bool {
-        self.combat.damage_flags.contains(DamageFlag::Dead)
-        // TODO
-//        if ( stat_level_(result, STAT_current_hp) <= 0 )
-//      return 1;
+        self.combat.damage_flags.contains(DamageFlag::Dead) || self.health <= 0
     }
 }
 
+/// Combat maneuver state of a critter.
+#[derive(Clone, Copy, Debug, EnumFlags, Eq, PartialEq)]
+#[repr(u32)]
+pub enum Maneuver {
+    Engaging = 0x1,
+    Disengaging = 0x2,
+    Fleeing = 0x4,
+}
+
 #[derive(Debug)]
 pub struct CritterCombat {
     pub damage_flags: BitFlags<DamageFlag>,
+    pub maneuver: BitFlags<Maneuver>,
+    /// Action points left in the current combat turn.
+    pub ap: i32,
+    pub ai_packet: u32,
+    pub team_id: u32,
+    /// The last critter that attacked this one.
+    pub who_hit_me: Option<Handle>,
 }
 
 impl Default for CritterCombat {
     fn default() -> Self {
         Self {
             damage_flags: BitFlags::empty(),
+            maneuver: BitFlags::empty(),
+            ap: 0,
+            ai_packet: 0,
+            team_id: 0,
+            who_hit_me: None,
         }
     }
 }
@@ -1267,6 +1955,7 @@ pub enum DamageFlag {
 mod test {
     use super::*;
     use crate::graphics::geometry::hex::View;
+    use crate::util::test::TestAssets;
 
     #[test]
     fn bounds() {
@@ -1279,4 +1968,61 @@ mod test {
             Rect::with_points(Point::new(1, -51), Point::new(30, 12))
                 .translate(base));
     }
-}
\ No newline at end of file
+
+    /// Returns the first step of the path of critter `pid` from a tile walled off on all sides
+    /// but the east one where the door `door_pid` stands.
+    fn first_step_through_door(assets: &TestAssets, pid: ObjectProtoId, door_pid: ProtoId,
+        locked: bool) -> Option<Direction>
+    {
+        let door_fid = assets.proto_db().proto(door_pid).unwrap().fid;
+        let wall_fid = FrameId::new_generic(EntityKind::Wall, 0).unwrap();
+        let mut objects = Objects::new(TileGrid::default(), 1, assets.proto_db(),
+            assets.frm_db());
+
+        let pos = EPoint::new(0, Point::new(50, 50));
+        for dir in Direction::iter() {
+            let p = EPoint::new(0, objects.tile_grid.go(pos.point, dir, 1).unwrap());
+            if dir == Direction::E {
+                let mut door = Object::new(door_fid, ObjectProtoId::ProtoId(door_pid), Some(p));
+                if locked {
+                    door.lock_flags.insert(LockFlag::Locked);
+                }
+                objects.insert(door);
+            } else {
+                objects.insert(Object::new(wall_fid, ObjectProtoId::None, Some(p)));
+            }
+        }
+
+        let fid = FrameId::new_critter(None, CritterAnim::Stand, WeaponKind::Unarmed, 0).unwrap();
+        let mut critter = Object::new(fid, pid, Some(pos));
+        critter.sub.critter_mut().unwrap().health = 1;
+        let critter = objects.insert(critter);
+
+        let proto_db = assets.proto_db();
+        objects.path(critter, Point::new(60, 50), false, false, &proto_db)
+            .map(|path| path[0])
+    }
+
+    #[test]
+    fn path_through_door() {
+        let mut assets = TestAssets::new();
+        assets.add_frm(EntityKind::Wall);
+        let door_fid = assets.add_frm(EntityKind::Scenery);
+        let door = assets.add_door(door_fid, 0);
+        let walk_thru_door = assets.add_door(door_fid, 1);
+        let man = ObjectProtoId::ProtoId(assets.add_critter(0, CritterKillKind::Man));
+        let robot = ObjectProtoId::ProtoId(assets.add_critter(2, CritterKillKind::Robot));
+        let dog = ObjectProtoId::ProtoId(assets.add_critter(1, CritterKillKind::Dog));
+        let gecko = ObjectProtoId::ProtoId(assets.add_critter(0, CritterKillKind::Gecko));
+
+        let first_step = |pid, door_pid, locked|
+            first_step_through_door(&assets, pid, door_pid, locked);
+        assert_eq!(first_step(man, door, false), Some(Direction::E));
+        assert_eq!(first_step(robot, door, false), Some(Direction::E));
+        assert_eq!(first_step(dog, door, false), None);
+        assert_eq!(first_step(gecko, door, false), None);
+        assert_eq!(first_step(man, door, true), None);
+        assert_eq!(first_step(ObjectProtoId::Dude, door, false), None);
+        assert_eq!(first_step(ObjectProtoId::Dude, walk_thru_door, false), Some(Direction::E));
+    }
+}
@@ -0,0 +1,310 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use enum_map::Enum;
+use std::io::{self, Error, ErrorKind, prelude::*};
+
+use crate::asset::ai::{AiPacket, AreaAttackMode, BestWeapon, Distance};
+use crate::asset::party::MemberDef;
+use crate::game::object::{self, ObjectProtoId};
+
+/// Combat orders given to a party member via dialog. Settings that weren't ordered are taken
+/// from the member's AI packet. `None` area attack mode is also the `no_pref` order.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Orders {
+    pub area_attack_mode: Option<AreaAttackMode>,
+    pub best_weapon: Option<BestWeapon>,
+    pub distance: Option<Distance>,
+}
+
+impl Orders {
+    /// Overrides the settings of the `packet` with the given orders.
+    pub fn apply(&self, packet: &mut AiPacket) {
+        if let Some(v) = self.area_attack_mode {
+            packet.area_attack_mode = v;
+        }
+        if let Some(v) = self.best_weapon {
+            packet.best_weapon = v;
+        }
+        if let Some(v) = self.distance {
+            packet.distance = v;
+        }
+    }
+}
+
+/// Setting of `Orders`.
+#[derive(Clone, Copy, Debug, Enum, Eq, PartialEq)]
+pub enum Order {
+    Distance,
+    BestWeapon,
+    AreaAttackMode,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Member {
+    pub obj: object::Handle,
+    pub orders: Orders,
+}
+
+/// Critters that joined the dude. The dude itself is not a member.
+pub struct Party {
+    defs: Vec<MemberDef>,
+    members: Vec<Member>,
+}
+
+impl Party {
+    pub fn new(defs: Vec<MemberDef>) -> Self {
+        Self {
+            defs,
+            members: Vec::new(),
+        }
+    }
+
+    /// Definition of the possible party member with `pid`.
+    pub fn def(&self, pid: ObjectProtoId) -> Option<&MemberDef> {
+        self.defs.iter().find(|d| d.pid == pid)
+    }
+
+    pub fn members(&self) -> &[Member] {
+        &self.members
+    }
+
+    pub fn member(&self, obj: object::Handle) -> Option<&Member> {
+        self.members.iter().find(|m| m.obj == obj)
+    }
+
+    pub fn member_mut(&mut self, obj: object::Handle) -> Option<&mut Member> {
+        self.members.iter_mut().find(|m| m.obj == obj)
+    }
+
+    pub fn contains(&self, obj: object::Handle) -> bool {
+        self.member(obj).is_some()
+    }
+
+    /// Adds `obj` to the party. Returns `false` if it's already a member.
+    // partyMemberAdd()
+    pub fn add(&mut self, obj: object::Handle) -> bool {
+        if self.contains(obj) {
+            return false;
+        }
+        self.members.push(Member {
+            obj,
+            orders: Orders::default(),
+        });
+        true
+    }
+
+    /// Removes `obj` from the party. Returns `false` if it's not a member.
+    // partyMemberRemove()
+    pub fn remove(&mut self, obj: object::Handle) -> bool {
+        let len = self.members.len();
+        self.members.retain(|m| m.obj != obj);
+        self.members.len() != len
+    }
+
+    /// Orders the member `obj` to use the `order` value following the current one among
+    /// the values allowed for its PID. `packet` is the member's AI packet with the orders
+    /// applied. The current area attack mode is the ordered one since the `no_pref` order
+    /// can be among the allowed values. Returns `false` if the member can't be given the
+    /// `order`.
+    // gdCustomSelect()
+    pub fn change_order(&mut self, obj: object::Handle, pid: ObjectProtoId, order: Order,
+        packet: &AiPacket) -> bool
+    {
+        fn next<T: Copy + Eq>(values: &[T], current: T) -> Option<T> {
+            let i = values.iter().position(|&v| v == current).map(|i| i + 1).unwrap_or(0);
+            values.get(i % values.len().max(1)).cloned()
+        }
+
+        let def = if let Some(v) = self.defs.iter().find(|d| d.pid == pid) {
+            v
+        } else {
+            return false;
+        };
+        let orders = if let Some(v) = self.members.iter_mut().find(|m| m.obj == obj) {
+            &mut v.orders
+        } else {
+            return false;
+        };
+        let changed = match order {
+            Order::Distance => next(&def.distances, packet.distance)
+                .map(|v| Orders { distance: Some(v), ..*orders }),
+            Order::BestWeapon => next(&def.best_weapons, packet.best_weapon)
+                .map(|v| Orders { best_weapon: Some(v), ..*orders }),
+            Order::AreaAttackMode => next(&def.area_attack_modes, orders.area_attack_mode)
+                .map(|v| Orders { area_attack_mode: v, ..*orders }),
+        };
+        if let Some(v) = changed {
+            *orders = v;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn retain(&mut self, mut f: impl FnMut(object::Handle) -> bool) {
+        self.members.retain(|m| f(m.obj));
+    }
+
+    pub fn clear(&mut self) {
+        self.members.clear();
+    }
+
+    /// Writes the members along with their orders. `obj_id` maps object handle to persistent
+    /// object ID. Members that map to `None` are not written.
+    // partyMemberSave()
+    pub fn write(&self, wr: &mut impl Write, obj_id: impl Fn(object::Handle) -> Option<u32>)
+        -> io::Result<()>
+    {
+        let members: Vec<_> = self.members.iter()
+            .filter_map(|m| obj_id(m.obj).map(|id| (m, id)))
+            .collect();
+        wr.write_u32::<BigEndian>(members.len() as u32)?;
+        for (member, obj_id) in members {
+            wr.write_u32::<BigEndian>(obj_id)?;
+            let Orders { area_attack_mode, best_weapon, distance } = member.orders;
+            wr.write_u8(area_attack_mode.map(|v| v as u8 + 1).unwrap_or(0))?;
+            wr.write_u8(best_weapon.map(|v| v as u8 + 1).unwrap_or(0))?;
+            wr.write_u8(distance.map(|v| v as u8 + 1).unwrap_or(0))?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the members with the ones written with `write()`. `obj` maps persistent object
+    /// ID back to handle. Members that can't be resolved are dropped.
+    // partyMemberLoad()
+    pub fn read(&mut self, rd: &mut impl Read, obj: impl Fn(u32) -> Option<object::Handle>)
+        -> io::Result<()>
+    {
+        fn read_order<T: Copy>(rd: &mut impl Read, values: &[T]) -> io::Result<Option<T>> {
+            Ok(match rd.read_u8()? {
+                0 => None,
+                v => Some(*values.get(v as usize - 1)
+                    .ok_or_else(|| Error::new(ErrorKind::InvalidData,
+                        "invalid party member order"))?),
+            })
+        }
+
+        self.members.clear();
+        let len = rd.read_u32::<BigEndian>()?;
+        for _ in 0..len {
+            let obj_id = rd.read_u32::<BigEndian>()?;
+            let orders = Orders {
+                area_attack_mode: read_order(rd, AreaAttackMode::values())?,
+                best_weapon: read_order(rd, BestWeapon::values())?,
+                distance: read_order(rd, Distance::values())?,
+            };
+            if let Some(obj) = obj(obj_id) {
+                self.members.push(Member {
+                    obj,
+                    orders,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Max distance from the dude at which a member with the `distance` setting stays out of combat.
+/// `None` means the member doesn't follow the dude.
+pub fn follow_distance(distance: Distance) -> Option<u32> {
+    Some(match distance {
+        Distance::StayClose => 2,
+        Distance::Charge => 3,
+        Distance::Snipe => 4,
+        Distance::OnYourOwn => 6,
+        Distance::Stay => return None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn add_remove() {
+        let h = object::Handle::distinct(2);
+        let mut p = Party::new(Vec::new());
+        assert!(p.add(h[0]));
+        assert!(!p.add(h[0]));
+        assert!(p.add(h[1]));
+        assert_eq!(p.members().len(), 2);
+
+        assert!(p.remove(h[0]));
+        assert!(!p.remove(h[0]));
+        assert!(!p.contains(h[0]));
+        assert!(p.contains(h[1]));
+    }
+
+    #[test]
+    fn change_order() {
+        let h = object::Handle::distinct(1);
+        let pid = ObjectProtoId::from_packed(0x1000061).unwrap();
+        let mut p = Party::new(vec![MemberDef {
+            pid,
+            area_attack_modes: Vec::new(),
+            best_weapons: Vec::new(),
+            distances: vec![Distance::StayClose, Distance::Charge],
+        }]);
+        let mut packet = crate::asset::ai::AiPackets::read(&mut &b"[A]\npacket_num=0\n"[..])
+            .unwrap().get(0).unwrap().clone();
+        assert!(!p.change_order(h[0], pid, Order::Distance, &packet));
+
+        p.add(h[0]);
+        packet.distance = Distance::Snipe;
+        assert!(p.change_order(h[0], pid, Order::Distance, &packet));
+        assert_eq!(p.member(h[0]).unwrap().orders.distance, Some(Distance::StayClose));
+
+        packet.distance = Distance::StayClose;
+        assert!(p.change_order(h[0], pid, Order::Distance, &packet));
+        assert_eq!(p.member(h[0]).unwrap().orders.distance, Some(Distance::Charge));
+
+        assert!(!p.change_order(h[0], pid, Order::BestWeapon, &packet));
+        assert_eq!(p.member(h[0]).unwrap().orders.best_weapon, None);
+    }
+
+    #[test]
+    fn change_order_no_pref() {
+        let h = object::Handle::distinct(1);
+        let pid = ObjectProtoId::from_packed(0x1000061).unwrap();
+        let mut p = Party::new(vec![MemberDef {
+            pid,
+            area_attack_modes: vec![None, Some(AreaAttackMode::Always)],
+            best_weapons: Vec::new(),
+            distances: Vec::new(),
+        }]);
+        let packet = crate::asset::ai::AiPackets::read(&mut &b"[A]\npacket_num=0\n"[..])
+            .unwrap().get(0).unwrap().clone();
+        p.add(h[0]);
+
+        assert!(p.change_order(h[0], pid, Order::AreaAttackMode, &packet));
+        assert_eq!(p.member(h[0]).unwrap().orders.area_attack_mode,
+            Some(AreaAttackMode::Always));
+
+        assert!(p.change_order(h[0], pid, Order::AreaAttackMode, &packet));
+        assert_eq!(p.member(h[0]).unwrap().orders.area_attack_mode, None);
+    }
+
+    #[test]
+    fn write_read() {
+        let h = object::Handle::distinct(3);
+        let mut p = Party::new(Vec::new());
+        p.add(h[0]);
+        p.add(h[1]);
+        p.add(h[2]);
+        p.member_mut(h[1]).unwrap().orders = Orders {
+            area_attack_mode: Some(AreaAttackMode::BeAbsolutelySure),
+            best_weapon: None,
+            distance: Some(Distance::StayClose),
+        };
+
+        let mut data = Vec::new();
+        p.write(&mut data, |obj| h.iter().position(|&o| o == obj)
+            .filter(|&i| i != 2)
+            .map(|i| i as u32 + 10)).unwrap();
+
+        let mut actual = Party::new(Vec::new());
+        actual.add(h[2]);
+        actual.read(&mut Cursor::new(data), |id| h.get(id as usize - 10).cloned()).unwrap();
+        assert_eq!(actual.members(), &p.members()[..2]);
+    }
+}
@@ -0,0 +1,96 @@
+use enum_primitive_derive::Primitive;
+
+/// Outcome of a percentile roll. Outcomes are ordered from the worst to the best.
+/// The values are the ones used by scripts.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Primitive)]
+pub enum Roll {
+    CriticalFailure = 0,
+    Failure = 1,
+    Success = 2,
+    CriticalSuccess = 3,
+}
+
+impl Roll {
+    pub fn is_success(self) -> bool {
+        self == Roll::Success || self == Roll::CriticalSuccess
+    }
+
+    pub fn is_critical(self) -> bool {
+        self == Roll::CriticalFailure || self == Roll::CriticalSuccess
+    }
+}
+
+/// Rolls against `chance` percent. Critical success happens with `crit_chance` percent plus
+/// one percent per each 10 points the roll succeeded by. Critical failure happens with one
+/// percent per each 10 points the roll failed by.
+/// Returns the outcome and how much the roll succeeded (positive) or failed (negative) by.
+/// `rng(from_inclusive, to_inclusive)` is the source of random numbers.
+// roll_check()
+pub fn check(chance: i32, crit_chance: i32, rng: &mut impl FnMut(i32, i32) -> i32) -> (Roll, i32) {
+    let delta = chance - rng(1, 100);
+    let roll = if delta < 0 {
+        if rng(1, 100) <= -delta / 10 {
+            Roll::CriticalFailure
+        } else {
+            Roll::Failure
+        }
+    } else if rng(1, 100) <= crit_chance + delta / 10 {
+        Roll::CriticalSuccess
+    } else {
+        Roll::Success
+    };
+    (roll, delta)
+}
+
+/// Resolves a contest between the `attacker` roll and the `defender` roll which is made only
+/// if the attacker succeeds. When both succeed the attacker wins only if their roll
+/// succeeded by more than the defender's one.
+// skill_contest()
+pub fn contest(attacker: (Roll, i32), defender: impl FnOnce() -> (Roll, i32)) -> Roll {
+    let (roll, delta) = attacker;
+    if !roll.is_success() {
+        return roll;
+    }
+    let (defender_roll, defender_delta) = defender();
+    if defender_roll.is_success() && defender_delta >= delta {
+        Roll::Failure
+    } else {
+        roll
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rng(rolls: &[i32]) -> impl FnMut(i32, i32) -> i32 + '_ {
+        let mut rolls = rolls.iter();
+        move |from, to| {
+            let r = *rolls.next().unwrap();
+            assert!(r >= from && r <= to);
+            r
+        }
+    }
+
+    #[test]
+    fn check_() {
+        assert_eq!(check(50, 0, &mut rng(&[50, 100])), (Roll::Success, 0));
+        assert_eq!(check(50, 0, &mut rng(&[51, 100])), (Roll::Failure, -1));
+        assert_eq!(check(50, 5, &mut rng(&[10, 9])), (Roll::CriticalSuccess, 40));
+        assert_eq!(check(50, 5, &mut rng(&[10, 10])), (Roll::Success, 40));
+        assert_eq!(check(10, 0, &mut rng(&[100, 9])), (Roll::CriticalFailure, -90));
+        assert_eq!(check(10, 0, &mut rng(&[100, 10])), (Roll::Failure, -90));
+    }
+
+    #[test]
+    fn contest_() {
+        let no_defender = || -> (Roll, i32) { panic!() };
+        assert_eq!(contest((Roll::Failure, -5), no_defender), Roll::Failure);
+        assert_eq!(contest((Roll::CriticalFailure, -50), no_defender), Roll::CriticalFailure);
+        assert_eq!(contest((Roll::Success, 10), || (Roll::Failure, -10)), Roll::Success);
+        assert_eq!(contest((Roll::Success, 10), || (Roll::Success, 20)), Roll::Failure);
+        assert_eq!(contest((Roll::Success, 10), || (Roll::Success, 10)), Roll::Failure);
+        assert_eq!(contest((Roll::CriticalSuccess, 30), || (Roll::Success, 20)),
+            Roll::CriticalSuccess);
+    }
+}
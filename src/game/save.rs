@@ -0,0 +1,407 @@
+use bstring::{bstr, BString};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use std::collections::HashMap;
+use std::io::{self, Error, ErrorKind, prelude::*};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::asset::save as save_dat;
+use crate::game::GameTime;
+use crate::game::object::{self, ObjectProtoId};
+use crate::game::world::World;
+
+pub const SLOT_COUNT: u32 = 10;
+
+/// Slot used by quicksave and quickload.
+pub const QUICKSAVE_SLOT: u32 = 0;
+
+/// Slots autosaves rotate through.
+pub const AUTOSAVE_SLOTS: Range<u32> = 7..SLOT_COUNT;
+
+/// Whether the `slot` is reserved for quicksaves or autosaves. Reserved slots can be loaded from
+/// the save/load window but not saved to.
+pub fn is_reserved_slot(slot: u32) -> bool {
+    slot == QUICKSAVE_SLOT || AUTOSAVE_SLOTS.contains(&slot)
+}
+
+pub const THUMBNAIL_WIDTH: i32 = 224;
+pub const THUMBNAIL_HEIGHT: i32 = 133;
+
+/// Persistent ID of the map script object. The object itself isn't saved since it's recreated
+/// when the map is read, but the timer events of the map script refer to it.
+pub const MAP_SCRIPT_OBJ_ID: u32 = 0;
+
+const SIGNATURE: &[u8; 24] = b"VAULT13 SAVE FILE\0\0\0\0\0\0\0";
+const VERSION: u32 = 6;
+const DUDE_NAME_LEN: usize = 32;
+/// Size of the save description field. The description is zero terminated.
+pub const DESCRIPTION_LEN: usize = 30;
+const MAP_NAME_LEN: usize = 16;
+
+/// Path of the save file of the `slot` in the `dir`. Slots are numbered from 0.
+pub fn slot_path(dir: &Path, slot: u32) -> PathBuf {
+    assert!(slot < SLOT_COUNT);
+    dir.join(format!("slot{:02}", slot + 1)).join("SAVE.DAT")
+}
+
+/// Part of the save file shown in the save/load slot list.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Header {
+    pub dude_name: BString,
+    pub description: BString,
+    /// Real time of saving as seconds since the Unix epoch.
+    pub saved_at: u64,
+    pub game_time: GameTime,
+    pub map_name: String,
+    /// Screenshot of `THUMBNAIL_WIDTH` x `THUMBNAIL_HEIGHT` palette color indices.
+    pub thumbnail: Box<[u8]>,
+}
+
+impl Header {
+    pub fn write(&self, wr: &mut impl Write) -> io::Result<()> {
+        assert_eq!(self.thumbnail.len(), (THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT) as usize);
+
+        wr.write_all(&SIGNATURE[..])?;
+        wr.write_u32::<BigEndian>(VERSION)?;
+        write_fixed_str(wr, &self.dude_name, DUDE_NAME_LEN)?;
+        write_fixed_str(wr, &self.description, DESCRIPTION_LEN)?;
+        wr.write_u64::<BigEndian>(self.saved_at)?;
+        wr.write_u32::<BigEndian>(self.game_time.as_decis())?;
+        write_fixed_str(wr, self.map_name.as_str().into(), MAP_NAME_LEN)?;
+        wr.write_all(&self.thumbnail)?;
+        Ok(())
+    }
+
+    pub fn read(rd: &mut impl Read) -> io::Result<Self> {
+        let mut signature = [0; 24];
+        rd.read_exact(&mut signature[..])?;
+        if &signature != SIGNATURE {
+            return Err(Error::new(ErrorKind::InvalidData, "not a save file"));
+        }
+        let version = rd.read_u32::<BigEndian>()?;
+        if version != VERSION {
+            return Err(Error::new(ErrorKind::InvalidData,
+                format!("unsupported save file version: {}", version)));
+        }
+        let dude_name = read_fixed_str(rd, DUDE_NAME_LEN)?;
+        let description = read_fixed_str(rd, DESCRIPTION_LEN)?;
+        let saved_at = rd.read_u64::<BigEndian>()?;
+        let game_time = GameTime::from_decis(rd.read_u32::<BigEndian>()?);
+        let map_name = read_fixed_str(rd, MAP_NAME_LEN)?.into_string()
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid map name"))?;
+        let mut thumbnail = vec![0; (THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT) as usize];
+        rd.read_exact(&mut thumbnail)?;
+        Ok(Self {
+            dude_name,
+            description,
+            saved_at,
+            game_time,
+            map_name,
+            thumbnail: thumbnail.into(),
+        })
+    }
+}
+
+impl From<save_dat::Header> for Header {
+    fn from(v: save_dat::Header) -> Self {
+        let (year, month, day) = v.saved_on;
+        let map_name = v.map_file_name.to_lowercase();
+        let map_name = map_name.trim_end_matches(".sav").into();
+        Self {
+            dude_name: v.dude_name,
+            description: v.description,
+            saved_at: epoch_secs(year as i64, month as i64, day as i64).max(0) as u64,
+            game_time: v.game_time,
+            map_name,
+            thumbnail: v.thumbnail,
+        }
+    }
+}
+
+/// Picks the autosave slot to save to: the first empty one or the one saved the longest ago.
+/// `saved_at` returns the `Header::saved_at` of the slot or `None` if the slot is empty.
+pub fn autosave_slot(saved_at: impl Fn(u32) -> Option<u64>) -> u32 {
+    AUTOSAVE_SLOTS
+        .min_by_key(|&slot| saved_at(slot).map(|t| t + 1).unwrap_or(0))
+        .unwrap()
+}
+
+/// Breaks down `Header::saved_at` into UTC year, month, day, hour and minute.
+pub fn saved_at_date(saved_at: u64) -> (i64, u32, u32, u32, u32) {
+    let days = (saved_at / (24 * 60 * 60)) as i64 + 719_468;
+    let secs = saved_at % (24 * 60 * 60);
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524
+        - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day, (secs / 3600) as u32, (secs / 60 % 60) as u32)
+}
+
+/// Reads the header of either our own save file or the original `SAVE.DAT`.
+pub fn read_header(rd: &mut impl BufRead) -> io::Result<Header> {
+    if save_dat::is_save_dat(rd.fill_buf()?) {
+        save_dat::read_header(rd).map(|h| h.into())
+    } else {
+        Header::read(rd)
+    }
+}
+
+/// Contents of the save file.
+// SAVE.DAT
+pub struct SaveGame {
+    pub header: Header,
+    pub global_vars: Box<[i32]>,
+    /// Stats state written with `Stats::write()`.
+    pub stats: Vec<u8>,
+    pub dude_sneaking: bool,
+    pub ambient_light: u32,
+    /// The current map including the dude, written with `MapWriter`.
+    pub map: Vec<u8>,
+    /// Timer events written with `Timer::write()`. Object IDs refer to the objects of `map`.
+    pub timer: Vec<u8>,
+    /// World map state written with `WorldMap::write()`.
+    pub world_map: Vec<u8>,
+    /// Party members written with `Party::write()`. Object IDs refer to the objects of `map`.
+    pub party: Vec<u8>,
+    /// Other maps visited before, by map name. Written with `MapWriter`.
+    pub visited_maps: HashMap<String, Vec<u8>>,
+}
+
+impl SaveGame {
+    pub fn write(&self, wr: &mut impl Write) -> io::Result<()> {
+        self.header.write(wr)?;
+
+        wr.write_u32::<BigEndian>(self.global_vars.len() as u32)?;
+        for &v in self.global_vars.iter() {
+            wr.write_i32::<BigEndian>(v)?;
+        }
+        write_blob(wr, &self.stats)?;
+        wr.write_u8(self.dude_sneaking as u8)?;
+        wr.write_u32::<BigEndian>(self.ambient_light)?;
+        write_blob(wr, &self.map)?;
+        write_blob(wr, &self.timer)?;
+        write_blob(wr, &self.world_map)?;
+        write_blob(wr, &self.party)?;
+
+        let mut visited_maps: Vec<_> = self.visited_maps.iter().collect();
+        visited_maps.sort_by_key(|&(name, _)| name);
+        wr.write_u32::<BigEndian>(visited_maps.len() as u32)?;
+        for (name, map) in visited_maps {
+            write_blob(wr, name.as_bytes())?;
+            write_blob(wr, map)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read(rd: &mut impl Read) -> io::Result<Self> {
+        let header = Header::read(rd)?;
+
+        let global_var_count = rd.read_u32::<BigEndian>()?;
+        let mut global_vars = Vec::with_capacity(global_var_count as usize);
+        for _ in 0..global_var_count {
+            global_vars.push(rd.read_i32::<BigEndian>()?);
+        }
+        let stats = read_blob(rd)?;
+        let dude_sneaking = rd.read_u8()? != 0;
+        let ambient_light = rd.read_u32::<BigEndian>()?;
+        let map = read_blob(rd)?;
+        let timer = read_blob(rd)?;
+        let world_map = read_blob(rd)?;
+        let party = read_blob(rd)?;
+
+        let mut visited_maps = HashMap::new();
+        for _ in 0..rd.read_u32::<BigEndian>()? {
+            let name = String::from_utf8(read_blob(rd)?)
+                .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid map name"))?;
+            visited_maps.insert(name, read_blob(rd)?);
+        }
+
+        Ok(Self {
+            header,
+            global_vars: global_vars.into(),
+            stats,
+            dude_sneaking,
+            ambient_light,
+            map,
+            timer,
+            world_map,
+            party,
+            visited_maps,
+        })
+    }
+}
+
+/// Assigns persistent IDs to the objects of the `world` that are to be saved. The dude, its party
+/// members and the items they carry are included only if `with_dude` is set.
+pub fn object_ids(world: &World, with_dude: bool) -> HashMap<object::Handle, u32> {
+    let excluded = match (with_dude, world.dude_obj()) {
+        (false, Some(dude_obj)) => world.party().members().iter()
+            .map(|m| m.obj)
+            .chain(std::iter::once(dude_obj))
+            .flat_map(|obj| world.objects().with_inventory(obj))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut r = HashMap::new();
+    for obj in world.objects().iter() {
+        if world.objects().get(obj).borrow().pid != ObjectProtoId::None
+            && !excluded.contains(&obj)
+        {
+            let id = MAP_SCRIPT_OBJ_ID + r.len() as u32 + 1;
+            r.insert(obj, id);
+        }
+    }
+    r
+}
+
+/// Seconds since the Unix epoch at the start of the day given in the proleptic Gregorian
+/// calendar.
+fn epoch_secs(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    (era * 146_097 + day_of_era - 719_468) * 24 * 60 * 60
+}
+
+fn write_fixed_str(wr: &mut impl Write, s: &bstr, len: usize) -> io::Result<()> {
+    let mut buf = vec![0; len];
+    let s_len = s.len().min(len - 1);
+    buf[..s_len].copy_from_slice(&s.as_bytes()[..s_len]);
+    wr.write_all(&buf)
+}
+
+fn read_fixed_str(rd: &mut impl Read, len: usize) -> io::Result<BString> {
+    let mut buf = vec![0; len];
+    rd.read_exact(&mut buf)?;
+    let s_len = buf.iter().position(|&c| c == 0).unwrap_or(len);
+    buf.truncate(s_len);
+    Ok(buf.into())
+}
+
+fn write_blob(wr: &mut impl Write, data: &[u8]) -> io::Result<()> {
+    wr.write_u32::<BigEndian>(data.len() as u32)?;
+    wr.write_all(data)
+}
+
+fn read_blob(rd: &mut impl Read) -> io::Result<Vec<u8>> {
+    let len = rd.read_u32::<BigEndian>()?;
+    let mut r = vec![0; len as usize];
+    rd.read_exact(&mut r)?;
+    Ok(r)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    fn header() -> Header {
+        Header {
+            dude_name: "Chitsa".into(),
+            description: "Before the temple".into(),
+            saved_at: 1_500_000_000,
+            game_time: GameTime::from_decis(302_400),
+            map_name: "artemple".into(),
+            thumbnail: (0..THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT).map(|v| v as u8).collect(),
+        }
+    }
+
+    #[test]
+    fn write_read() {
+        let mut visited_maps = HashMap::new();
+        visited_maps.insert("arcaves".into(), vec![1, 2, 3]);
+        visited_maps.insert("arvillag".into(), vec![]);
+        let save = SaveGame {
+            header: header(),
+            global_vars: vec![0, -1, 100].into(),
+            stats: vec![4, 5],
+            dude_sneaking: true,
+            ambient_light: 0x8000,
+            map: vec![6; 100],
+            timer: vec![7],
+            world_map: vec![8, 9],
+            party: vec![10],
+            visited_maps,
+        };
+
+        let mut data = Vec::new();
+        save.write(&mut data).unwrap();
+
+        assert_eq!(Header::read(&mut Cursor::new(&data)).unwrap(), save.header);
+
+        let actual = SaveGame::read(&mut Cursor::new(&data)).unwrap();
+        assert_eq!(actual.header, save.header);
+        assert_eq!(actual.global_vars, save.global_vars);
+        assert_eq!(actual.stats, save.stats);
+        assert_eq!(actual.dude_sneaking, save.dude_sneaking);
+        assert_eq!(actual.ambient_light, save.ambient_light);
+        assert_eq!(actual.map, save.map);
+        assert_eq!(actual.timer, save.timer);
+        assert_eq!(actual.world_map, save.world_map);
+        assert_eq!(actual.party, save.party);
+        assert_eq!(actual.visited_maps, save.visited_maps);
+    }
+
+    #[test]
+    fn header_truncates_strings() {
+        let mut header = header();
+        header.description = "A very long description that doesn't fit".into();
+
+        let mut data = Vec::new();
+        header.write(&mut data).unwrap();
+        let actual = Header::read(&mut Cursor::new(&data)).unwrap();
+        assert_eq!(actual.description, "A very long description that ");
+    }
+
+    #[test]
+    fn epoch_secs_() {
+        assert_eq!(epoch_secs(1970, 1, 1), 0);
+        assert_eq!(epoch_secs(2000, 3, 1), 951_868_800);
+        assert_eq!(epoch_secs(2017, 7, 14), 1_499_990_400);
+        assert_eq!(epoch_secs(1969, 12, 31), -86400);
+    }
+
+    #[test]
+    fn saved_at_date_() {
+        assert_eq!(saved_at_date(0), (1970, 1, 1, 0, 0));
+        assert_eq!(saved_at_date(951_868_800 - 60), (2000, 2, 29, 23, 59));
+        assert_eq!(saved_at_date(1_500_000_000), (2017, 7, 14, 2, 40));
+        assert_eq!(saved_at_date(epoch_secs(2002, 3, 17) as u64), (2002, 3, 17, 0, 0));
+    }
+
+    #[test]
+    fn autosave_slot_() {
+        assert_eq!(autosave_slot(|_| None), AUTOSAVE_SLOTS.start);
+        assert_eq!(autosave_slot(|slot| if slot == 8 { None } else { Some(10) }), 8);
+        assert_eq!(autosave_slot(|slot| Some(if slot == 9 { 5 } else { 10 })), 9);
+    }
+
+    #[test]
+    fn reserved_slots() {
+        assert!(is_reserved_slot(QUICKSAVE_SLOT));
+        assert!(AUTOSAVE_SLOTS.into_iter().all(is_reserved_slot));
+        assert!(!AUTOSAVE_SLOTS.contains(&QUICKSAVE_SLOT));
+        let user_slots = (0..SLOT_COUNT).filter(|&slot| !is_reserved_slot(slot)).count();
+        assert_eq!(user_slots, 6);
+        assert!(is_reserved_slot(autosave_slot(|_| None)));
+        assert!(is_reserved_slot(autosave_slot(|slot| Some(slot as u64))));
+    }
+
+    #[test]
+    fn bad_signature() {
+        let mut data = Vec::new();
+        header().write(&mut data).unwrap();
+        data[0] = b'X';
+        assert_eq!(Header::read(&mut Cursor::new(&data)).unwrap_err().kind(),
+            ErrorKind::InvalidData);
+    }
+}
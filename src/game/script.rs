@@ -85,6 +85,7 @@ pub struct Context<'a> {
     pub ui: &'a mut crate::ui::Ui,
     pub world: &'a mut crate::game::world::World,
     pub sequencer: &'a mut crate::sequence::Sequencer,
+    pub stats: &'a mut crate::game::stats::Stats,
     pub dialog: &'a mut Option<crate::game::dialog::Dialog>,
     pub message_panel: crate::ui::Handle,
     pub map_id: i32,
@@ -113,6 +114,25 @@ pub struct Script {
     pub program: vm::Handle,
     pub local_vars: Box<[i32]>,
     pub object: Option<object::Handle>,
+    /// Value returned by `fixed_param()` instruction. Set before running `timed_event_p_proc`.
+    pub fixed_param: i32,
+    /// Value returned by `source_obj()` instruction. Set before running `use_skill_on_p_proc`.
+    pub source_obj: Option<object::Handle>,
+    /// Value returned by `action_being_used()` instruction. Set before running
+    /// `use_skill_on_p_proc`.
+    pub action_being_used: i32,
+    /// Script flags as stored in the map file.
+    pub flags: u32,
+    pub spatial: Spatial,
+}
+
+/// Trigger area of spatial and time scripts as stored in the map file.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Spatial {
+    /// Packed elevation and tile.
+    pub elevation_and_tile: i32,
+    /// Trigger radius. Only spatial scripts have it.
+    pub radius: i32,
 }
 
 pub struct Scripts {
@@ -144,6 +164,29 @@ impl Scripts {
         self.map_sid
     }
 
+    /// Makes the already instantiated script the map script.
+    pub fn set_map_sid(&mut self, sid: Sid) {
+        assert!(self.map_sid.is_none());
+        assert!(self.scripts.contains_key(&sid));
+        self.map_sid = Some(sid);
+    }
+
+    /// Removes all scripts along with the map variables. Global variables are kept.
+    pub fn clear(&mut self) {
+        for script in self.scripts.values() {
+            self.vm.remove(script.program);
+        }
+        self.scripts.clear();
+        self.map_sid = None;
+        self.vars.map_vars = Vec::new().into();
+        self.vars.external_vars.clear();
+        self.suspend_stack.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=(Sid, &Script)> {
+        self.scripts.iter().map(|(&sid, script)| (sid, script))
+    }
+
     pub fn instantiate(&mut self, sid: Sid, program_id: ProgramId, local_vars: Option<Box<[i32]>>)
         -> io::Result<()>
     {
@@ -178,6 +221,11 @@ impl Scripts {
             program,
             local_vars,
             object: None,
+            fixed_param: 0,
+            source_obj: None,
+            action_being_used: 0,
+            flags: 0,
+            spatial: Spatial::default(),
         });
         if let Some(existing) = existing {
             panic!("{:?} #{} duplicates existing #{}",
@@ -194,6 +242,17 @@ impl Scripts {
         Ok(sid)
     }
 
+    /// Instantiates a new script of `kind` and attaches it to `obj`. The script starts with
+    /// zeroed local vars if `local_vars` is `None`.
+    pub fn instantiate_object_script(&mut self, kind: ScriptKind, program_id: ProgramId,
+        local_vars: Option<Box<[i32]>>, obj: object::Handle) -> io::Result<Sid>
+    {
+        let sid = self.next_sid(kind);
+        self.instantiate(sid, program_id, local_vars)?;
+        self.attach_to_object(sid, obj);
+        Ok(sid)
+    }
+
     pub fn get(&self, sid: Sid) -> Option<&Script> {
         self.scripts.get(&sid)
     }
@@ -202,6 +261,29 @@ impl Scripts {
         self.scripts.get_mut(&sid).unwrap().object = Some(obj);
     }
 
+    pub fn set_fixed_param(&mut self, sid: Sid, fixed_param: i32) {
+        self.scripts.get_mut(&sid).unwrap().fixed_param = fixed_param;
+    }
+
+    pub fn set_source_obj(&mut self, sid: Sid, source_obj: Option<object::Handle>) {
+        self.scripts.get_mut(&sid).unwrap().source_obj = source_obj;
+    }
+
+    /// Sets the fields the map file keeps for the script.
+    pub fn set_map_fields(&mut self, sid: Sid, flags: u32, spatial: Spatial) {
+        let script = self.scripts.get_mut(&sid).unwrap();
+        script.flags = flags;
+        script.spatial = spatial;
+    }
+
+    pub fn set_action(&mut self, sid: Sid, source_obj: Option<object::Handle>,
+        action_being_used: i32)
+    {
+        let script = self.scripts.get_mut(&sid).unwrap();
+        script.source_obj = source_obj;
+        script.action_being_used = action_being_used;
+    }
+
     pub fn execute_proc(&mut self, sid: Sid, proc_id: ProcedureId,
         ctx: &mut Context) -> InvocationResult
     {
@@ -219,6 +301,9 @@ impl Scripts {
             script.inited = true;
         }
         vm_ctx.self_obj = script.object;
+        vm_ctx.fixed_param = script.fixed_param;
+        vm_ctx.source_obj = script.source_obj;
+        vm_ctx.action_being_used = script.action_being_used;
         let prg = self.vm.program_state_mut(script.program);
         debug!("[{:?}#{}:{}] executing proc {:?} ({:?})",
             sid,
@@ -268,7 +353,11 @@ impl Scripts {
         // TODO avoid allocation
         let sids: Vec<_> = self.scripts.keys().cloned().collect();
         for sid in sids {
-            if filter(sid) {
+            // Skip the scripts of destroyed objects.
+            let destroyed = self.scripts[&sid].object
+                .map(|o| !ctx.world.objects().contains(o))
+                .unwrap_or(false);
+            if !destroyed && filter(sid) {
                 if let Some(r) = self.execute_predefined_proc(sid, proc, ctx) {
                     assert!(r.suspend.is_none(), "can't suspend in {:?}", proc);
                 }
@@ -308,6 +397,9 @@ impl Scripts {
             &mut self.db,
             &self.proto_db,
             ctx);
+        vm_ctx.fixed_param = script.fixed_param;
+        vm_ctx.source_obj = script.source_obj;
+        vm_ctx.action_being_used = script.action_being_used;
         self.vm.program_state_mut(script.program).resume(vm_ctx).unwrap()
     }
 
@@ -337,9 +429,13 @@ impl Scripts {
             external_vars: &mut vars.external_vars,
 
             self_obj: None,
+            fixed_param: 0,
+            source_obj: None,
+            action_being_used: 0,
             ui: ctx.ui,
             world: ctx.world,
             sequencer: ctx.sequencer,
+            stats: ctx.stats,
             dialog: ctx.dialog,
             message_panel: ctx.message_panel,
             script_db,
@@ -1,3 +1,6 @@
+pub mod door;
 pub mod frame_anim;
 pub mod move_seq;
+pub mod projectile;
+pub mod remove;
 pub mod stand;
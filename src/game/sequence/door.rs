@@ -0,0 +1,192 @@
+use crate::asset::Flag;
+use crate::game::object::Handle;
+use crate::game::world::World;
+use crate::sequence::*;
+
+use super::frame_anim::{AnimDirection, FrameAnim};
+
+/// Starts opening or closing the `door`. Does nothing if the door is in that state already, is
+/// locked and should be opened or has a running sequence. The cancel handle is stored on the
+/// door and a cancelled swing leaves the door in its final state. Returns `true` if the swing
+/// was started.
+// obj_use_door()
+pub fn start(door: Handle, open: bool, world: &World, sequencer: &mut Sequencer) -> bool {
+    let mut dooro = world.objects().get(door).borrow_mut();
+    if dooro.is_open() == open || open && dooro.is_locked() || dooro.has_running_sequence() {
+        return false;
+    }
+    let seq = if open {
+        Door::open(door)
+    } else {
+        Door::close(door)
+    };
+    let (seq, cancel) = seq.cancellable();
+    dooro.sequence = Some(cancel);
+    sequencer.start(seq.then(Finish { obj: door, open }));
+    true
+}
+
+/// Swings the door open or closed. A closing door blocks right away while an opening one lets
+/// things through only once it's fully open.
+// check_door_state()
+pub struct Door {
+    obj: Handle,
+    open: bool,
+    anim: FrameAnim,
+    started: bool,
+}
+
+impl Door {
+    pub fn open(obj: Handle) -> Self {
+        Self::new(obj, true)
+    }
+
+    pub fn close(obj: Handle) -> Self {
+        Self::new(obj, false)
+    }
+
+    fn new(obj: Handle, open: bool) -> Self {
+        let direction = if open {
+            AnimDirection::Forward
+        } else {
+            AnimDirection::Backward
+        };
+        Self {
+            obj,
+            open,
+            anim: FrameAnim::new(obj, None, direction, false),
+            started: false,
+        }
+    }
+}
+
+impl Sequence for Door {
+    fn update(&mut self, ctx: &mut Update) -> Result {
+        if !self.started {
+            self.started = true;
+            if !self.open {
+                ctx.world.set_door_passable(self.obj, false);
+            }
+        }
+        match self.anim.update(ctx) {
+            Result::Done => {
+                if self.open {
+                    ctx.world.set_door_passable(self.obj, true);
+                }
+                Result::Done
+            }
+            r => r,
+        }
+    }
+}
+
+/// Puts the door in its final state in case the swing was cancelled halfway. Does nothing if
+/// another sequence has taken over the door since.
+struct Finish {
+    obj: Handle,
+    open: bool,
+}
+
+impl Sequence for Finish {
+    fn update(&mut self, ctx: &mut Update) -> Result {
+        let update_passable = {
+            let mut obj = ctx.world.objects().get(self.obj).borrow_mut();
+            if obj.has_running_sequence() {
+                return Result::Done;
+            }
+            obj.frame_idx = if self.open {
+                let frame_set = ctx.world.frm_db().get(obj.fid).unwrap();
+                frame_set.frame_lists[obj.direction].frames.len() - 1
+            } else {
+                0
+            };
+            obj.flags.contains(Flag::NoBlock) != self.open
+        };
+        if update_passable {
+            ctx.world.set_door_passable(self.obj, self.open);
+        }
+        Result::Done
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::asset::EntityKind;
+    use crate::game::object::{LockFlag, Object, ObjectProtoId};
+    use crate::util::test::TestAssets;
+
+    fn update(sequencer: &mut Sequencer, world: &mut World, time: &mut Instant) {
+        for _ in 0..10 {
+            *time += Duration::from_millis(100);
+            sequencer.update(&mut Update {
+                time: *time,
+                world,
+                out: &mut Vec::new(),
+            });
+        }
+    }
+
+    fn is_passable(world: &World, door: Handle) -> bool {
+        world.objects().get(door).borrow().flags.contains(Flag::NoBlock)
+    }
+
+    #[test]
+    fn open_close() {
+        let mut assets = TestAssets::new();
+        let fid = assets.add_anim_frm(EntityKind::Scenery, 3);
+        let pid = assets.add_door(fid, 0);
+        let mut world = assets.world();
+        let door = world.insert_object(Object::new(fid, ObjectProtoId::ProtoId(pid),
+            Some((0, (10, 10)).into())));
+        let mut time = Instant::now();
+        let mut sequencer = Sequencer::new(time);
+
+        assert!(!start(door, false, &world, &mut sequencer));
+        assert!(start(door, true, &world, &mut sequencer));
+        assert!(world.objects().get(door).borrow().has_running_sequence());
+        assert!(!start(door, true, &world, &mut sequencer));
+        update(&mut sequencer, &mut world, &mut time);
+        assert!(!sequencer.is_running());
+        assert!(world.objects().get(door).borrow().is_open());
+        assert!(is_passable(&world, door));
+
+        assert!(start(door, false, &world, &mut sequencer));
+        update(&mut sequencer, &mut world, &mut time);
+        assert!(!world.objects().get(door).borrow().is_open());
+        assert!(!is_passable(&world, door));
+
+        world.objects().get(door).borrow_mut().lock_flags.insert(LockFlag::Locked);
+        assert!(!start(door, true, &world, &mut sequencer));
+    }
+
+    #[test]
+    fn cancel() {
+        let mut assets = TestAssets::new();
+        let fid = assets.add_anim_frm(EntityKind::Scenery, 3);
+        let pid = assets.add_door(fid, 0);
+        let mut world = assets.world();
+        let door = world.insert_object(Object::new(fid, ObjectProtoId::ProtoId(pid),
+            Some((0, (10, 10)).into())));
+        let time = Instant::now();
+        let mut sequencer = Sequencer::new(time);
+
+        assert!(start(door, true, &world, &mut sequencer));
+        sequencer.update(&mut Update {
+            time,
+            world: &mut world,
+            out: &mut Vec::new(),
+        });
+        assert!(!is_passable(&world, door));
+
+        world.objects().get(door).borrow_mut().cancel_sequence();
+        sequencer.cleanup(&mut Cleanup {
+            world: &mut world,
+        });
+        assert!(!sequencer.is_running());
+        assert_eq!(world.objects().get(door).borrow().frame_idx, 2);
+        assert!(is_passable(&world, door));
+    }
+}
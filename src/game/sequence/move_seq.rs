@@ -1,6 +1,6 @@
 use std::time::{Duration, Instant};
 
-use crate::asset::CritterAnim;
+use crate::asset::{CritterAnim, Flag};
 use crate::game::object::Handle;
 use crate::game::world::World;
 use crate::graphics::{EPoint, Point};
@@ -20,8 +20,11 @@ pub struct Move {
     anim: CritterAnim,
     frame_len: Duration,
     path: Vec<Direction>,
+    max_path_len: Option<usize>,
     state: State,
     path_pos: usize,
+    /// Door on the next tile the object is waiting for to open.
+    door: Option<Handle>,
 }
 
 impl Move {
@@ -32,11 +35,19 @@ impl Move {
             anim,
             frame_len: Duration::from_millis(1000 / 10),
             path: Vec::new(),
+            max_path_len: None,
             state: State::Started,
             path_pos: 0,
+            door: None,
         }
     }
 
+    /// Limits the number of steps to take.
+    pub fn max_path_len(mut self, max_path_len: usize) -> Self {
+        self.max_path_len = Some(max_path_len);
+        self
+    }
+
     fn init_step(&mut self, world: &mut World) {
         let mut obj = world.objects().get(self.obj).borrow_mut();
 
@@ -60,13 +71,59 @@ impl Move {
     fn rebuild_path(&mut self, world: &mut World) {
         // TODO non-smooth
         self.path = world.path_for_object(self.obj, self.to, true, true)
-            .unwrap_or(Vec::new())
+            .unwrap_or(Vec::new());
+        if let Some(max) = self.max_path_len {
+            self.path.truncate(max);
+        }
+    }
+
+    /// Makes the object wait for the closed door on the next tile of the path if there's one.
+    /// The door is requested to open if it's not opening already.
+    fn wait_for_door(&mut self, ctx: &mut Update) -> bool {
+        let world = &ctx.world;
+        let pos = world.objects().get(self.obj).borrow().pos.unwrap();
+        let next = if let Some(v) = world.hex_grid().go(pos.point, self.path[self.path_pos], 1) {
+            v
+        } else {
+            return false;
+        };
+        let next = EPoint::new(pos.elevation, next);
+        let door = world.objects().at(next).iter()
+            .cloned()
+            .find(|&h| world.objects().is_door(h)
+                && !world.objects().get(h).borrow().flags.contains(Flag::NoBlock));
+        let door = if let Some(v) = door {
+            v
+        } else {
+            return false;
+        };
+        if world.objects().can_use_door(self.obj, door) {
+            ctx.out.push(Event::OpenDoor { door });
+        } else if !world.objects().get(door).borrow().has_running_sequence() {
+            return false;
+        }
+        self.door = Some(door);
+        true
     }
 }
 
 impl Sequence for Move {
     // object_move()
     fn update(&mut self, ctx: &mut Update) -> Result {
+        if let Some(door) = self.door {
+            let dooro = ctx.world.objects().get(door).borrow();
+            if dooro.flags.contains(Flag::NoBlock) {
+                self.door = None;
+                self.state = State::Running(ctx.time);
+            } else if dooro.has_running_sequence() {
+                return Result::Running(Running::NotLagging);
+            } else {
+                // The door didn't open.
+                self.state = State::Done;
+                return Result::Done;
+            }
+        }
+
         match self.state {
             State::Started => {
                 self.rebuild_path(ctx.world);
@@ -78,6 +135,11 @@ impl Sequence for Move {
                     self.state = State::Done;
                     return Result::Done;
                 }
+
+                if self.wait_for_door(ctx) {
+                    self.state = State::Running(ctx.time);
+                    return Result::Running(Running::NotLagging);
+                }
             },
             State::Running(last_time) => {
                 if ctx.time - last_time < self.frame_len {
@@ -134,7 +196,6 @@ impl Sequence for Move {
             });
 
             // TODO check for blocker and rebuild path
-            // TODO use door
 
             self.path_pos += 1;
             if self.path_pos >= self.path.len() {
@@ -148,6 +209,11 @@ impl Sequence for Move {
             }
             ctx.world.objects_mut().add_screen_shift(self.obj, shift);
             self.init_step(ctx.world);
+
+            if self.wait_for_door(ctx) {
+                self.state = State::Running(ctx.time);
+                return Result::Running(Running::NotLagging);
+            }
         }
         let new_last_time = if let State::Running(last_time) = self.state {
             last_time + self.frame_len
@@ -0,0 +1,71 @@
+use std::time::{Duration, Instant};
+
+use crate::game::object::Handle;
+use crate::graphics::EPoint;
+use crate::graphics::geometry::hex;
+use crate::sequence::*;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum State {
+    Started,
+    Running(Instant),
+    Done,
+}
+
+/// Moves projectile hex by hex along the ray cast from `from` to `to`.
+pub struct Projectile {
+    obj: Handle,
+    path: Vec<EPoint>,
+    path_pos: usize,
+    step_len: Duration,
+    state: State,
+}
+
+impl Projectile {
+    pub fn new(obj: Handle, from: EPoint, to: EPoint) -> Self {
+        assert_eq!(from.elevation, to.elevation);
+        let path = if from.point == to.point {
+            vec![to]
+        } else {
+            let max_len = hex::distance(from.point, to.point) as usize * 2 + 1;
+            let mut path: Vec<_> = hex::ray(from.point, to.point)
+                .take(max_len)
+                .take_while(|&p| p != to.point)
+                .map(|p| p.elevated(from.elevation))
+                .collect();
+            path.push(to);
+            path
+        };
+        Self {
+            obj,
+            path,
+            path_pos: 0,
+            step_len: Duration::from_millis(20),
+            state: State::Started,
+        }
+    }
+}
+
+impl Sequence for Projectile {
+    fn update(&mut self, ctx: &mut Update) -> Result {
+        match self.state {
+            State::Started => {}
+            State::Running(last_time) => {
+                if ctx.time - last_time < self.step_len {
+                    return Result::Running(Running::NotLagging);
+                }
+            }
+            State::Done => return Result::Done,
+        }
+
+        if self.path_pos == self.path.len() {
+            self.state = State::Done;
+            return Result::Done;
+        }
+        ctx.world.set_object_pos(self.obj, self.path[self.path_pos]);
+        self.path_pos += 1;
+        self.state = State::Running(ctx.time);
+
+        Result::Running(Running::NotLagging)
+    }
+}
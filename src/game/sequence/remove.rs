@@ -0,0 +1,25 @@
+use crate::game::object::Handle;
+use crate::sequence::*;
+
+/// Removes the object from the world. Used to get rid of temporary objects like projectiles
+/// and explosions once their animation is over.
+pub struct Remove {
+    obj: Handle,
+}
+
+impl Remove {
+    pub fn new(obj: Handle) -> Self {
+        Self {
+            obj,
+        }
+    }
+}
+
+impl Sequence for Remove {
+    fn update(&mut self, ctx: &mut Update) -> Result {
+        if ctx.world.objects().contains(self.obj) {
+            ctx.world.remove_object(self.obj);
+        }
+        Result::Done
+    }
+}
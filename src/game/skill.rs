@@ -0,0 +1,34 @@
+use crate::asset::Skill;
+
+/// Range of hit points healed by a successful use of the healing `skill`. Each rank of the
+/// Healer perk widens the range.
+pub fn heal_range(skill: Skill, healer_rank: i32) -> (i32, i32) {
+    let (min, max) = match skill {
+        Skill::FirstAid => (1, 5),
+        Skill::Doctor | Skill::Repair => (4, 10),
+        _ => panic!("{:?} doesn't heal", skill),
+    };
+    (min + 4 * healer_rank, max + 10 * healer_rank)
+}
+
+/// Game time in seconds spent on use of the `skill`.
+pub fn use_time(skill: Skill) -> u32 {
+    match skill {
+        Skill::FirstAid => 30 * 60,
+        Skill::Doctor | Skill::Repair => 60 * 60,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn heal_range_() {
+        assert_eq!(heal_range(Skill::FirstAid, 0), (1, 5));
+        assert_eq!(heal_range(Skill::FirstAid, 1), (5, 15));
+        assert_eq!(heal_range(Skill::Doctor, 0), (4, 10));
+        assert_eq!(heal_range(Skill::Repair, 2), (12, 30));
+    }
+}
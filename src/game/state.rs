@@ -1,5 +1,6 @@
 use bstring::{bstr, BString};
 use enum_map::{enum_map, EnumMap};
+use enumflags2::BitFlags;
 use if_chain::if_chain;
 use log::*;
 use measure_time::*;
@@ -7,32 +8,72 @@ use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use std::cell::RefCell;
 use std::cmp;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Error, ErrorKind, prelude::*};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::time::{Instant, Duration};
+use std::time::{Instant, Duration, SystemTime, UNIX_EPOCH};
 
-use crate::asset::{self, EntityKind, CritterAnim, ItemKind};
+use crate::asset::{self, AttackKind, DamageKind, EntityKind, CritterAnim, Flag, ItemKind, PcStat,
+    Perk, Skill, Stat, Trait, WeaponKind};
+use crate::asset::ai::{AiPacket, AiPackets, BestWeapon, Distance as AiDistance};
+use crate::asset::elevator::ElevatorDb;
 use crate::asset::frame::{FrameDb, FrameId};
-use crate::asset::map::{MapReader, ELEVATION_COUNT};
+use crate::asset::gcd;
+use crate::asset::map::{Map, MapReader, MapWriter, ELEVATION_COUNT};
+use crate::asset::map::db::MapDb;
 use crate::asset::message::{BULLET, Messages};
-use crate::asset::proto::{CritterFlag, ProtoDb};
+use crate::asset::proto::{self, CritterFlag, CritterKillKind, ProtoDb, ProtoId};
 use crate::asset::script::db::ScriptDb;
+use crate::asset::worldmap::{EncounterCritter, GroupAttitude, WorldMapDef, SUBTILE_SIZE};
 use crate::fs::FileSystem;
+use crate::game::barter::Pricing;
+use crate::game::combat::{self, ai, attack, Combat};
 use crate::game::dialog::Dialog;
 use crate::game::fidget::Fidget;
-use crate::game::object::{self, LightEmitter, Object, ObjectProtoId};
+use crate::game::object::{self, DamageFlag, EquipmentSlot, LightEmitter, LockFlag, Maneuver,
+    Object, ObjectProtoId};
+use crate::game::party::{self, Order, Party};
+use crate::game::roll::Roll;
+use crate::game::save::{self, SaveGame};
+use crate::game::sequence::door;
+use crate::game::sequence::frame_anim::{AnimDirection, FrameAnim};
 use crate::game::sequence::move_seq::Move;
+use crate::game::sequence::projectile::Projectile;
+use crate::game::sequence::remove::Remove;
 use crate::game::sequence::stand::Stand;
 use crate::game::script::{self, Scripts, ScriptKind};
+use crate::game::skill;
+use crate::game::stats::{self, Stats};
+use crate::game::timer;
 use crate::game::ui::action_menu::{self, Action};
+use crate::game::ui::barter;
 use crate::game::ui::hud;
+use crate::game::ui::inventory::{self, DropTarget, InventoryWindow};
+use crate::game::ui::inventory_list;
+use crate::game::ui::called_shot::{self, CalledShotWindow};
+use crate::game::ui::character::{self, CharacterWindow};
+use crate::game::ui::combat_control;
+use crate::game::ui::elevator::{self, ElevatorWindow};
+use crate::game::ui::encounter::{self, EncounterWindow};
+use crate::game::ui::loot::{self, LootWindow, Side};
 use crate::game::ui::scroll_area::ScrollArea;
+use crate::game::ui::perk_select::{self, PerkSelectWindow};
+use crate::game::ui::save_load::{self, SaveLoadWindow};
+use crate::game::ui::skilldex::{self, SkilldexWindow};
 use crate::game::ui::world::{HexCursorStyle, WorldView};
-use crate::game::world::{ScrollDirection, World};
-use crate::graphics::Rect;
-use crate::graphics::font::Fonts;
+use crate::game::ui::worldmap::{self, WorldMapWindow};
+use crate::game::world::{floating_text, MapRef, MapTransition, ScrollDirection, World};
+use crate::game::worldmap::{day_time, formation_pos, RandomEncounter, CAR_FUEL_MAX,
+    TRAVEL_STEP_TIME, WorldMap};
+use crate::graphics::{EPoint, Point, Rect};
+use crate::graphics::color::{BLACK, RED, WHITE};
+use crate::graphics::font::{FontKey, Fonts};
 use crate::graphics::geometry::hex::{self, Direction};
 use crate::sequence::{self, *};
 use crate::sequence::event::PushEvent;
+use crate::sequence::sleep::Sleep;
 use crate::state::AppState;
 use crate::ui::{self, Ui};
 use crate::ui::command::{UiCommand, UiCommandData, ObjectPickKind};
@@ -43,6 +84,46 @@ use crate::vm::{Vm, PredefinedProc, Suspend};
 
 const SCROLL_STEP: i32 = 10;
 
+/// Real time taken by each step of the world map travel.
+const TRAVEL_STEP_INTERVAL: Duration = Duration::from_millis(30);
+
+/// Game time in decis taken by each combat round.
+const COMBAT_ROUND_TIME: u32 = 50;
+
+/// Real time between the checks for a random encounter while travelling on the world map.
+const ENCOUNTER_CHECK_INTERVAL: Duration = Duration::from_millis(1500);
+
+/// Range of distances from the dude to the center of each random encounter critter group.
+const ENCOUNTER_MIN_DISTANCE: i32 = 5;
+const ENCOUNTER_MAX_DISTANCE: i32 = 9;
+
+/// Team of the first random encounter critter group fighting another group. The following
+/// groups get the next teams.
+const ENCOUNTER_FIGHTING_TEAM: u32 = 100;
+
+/// Fuel the car is charged with by a single energy cell.
+// obj_use_power_on_car()
+const CAR_CELL_FUEL: [(ProtoId, u32); 2] = [
+    (ProtoId::SMALL_ENERGY_CELL, 16000),
+    (ProtoId::MICRO_FUSION_CELL, 40000),
+];
+
+/// Maximum distance to the car from which energy cells can be used on it.
+const CAR_CHARGE_DISTANCE: u32 = 2;
+
+/// Maximum distance from the dude at which the party members are placed on entering a map.
+const PARTY_PLACE_DISTANCE: u32 = 5;
+
+/// Party members farther than this from the dude run rather than walk to catch up.
+const PARTY_RUN_DISTANCE: u32 = 8;
+
+/// Premade characters offered on character creation. Each has `.gcd` and `.bio` files.
+const PREMADES: [&str; 3] = ["premade/combat", "premade/diplomat", "premade/stealth"];
+const DEFAULT_CHAR_POINTS: i32 = 5;
+const NAME_MAX_LEN: usize = 11;
+const MIN_AGE: i32 = 16;
+const MAX_AGE: i32 = 35;
+
 pub struct GameState {
     time: PausableTime,
     fs: Rc<FileSystem>,
@@ -50,6 +131,7 @@ pub struct GameState {
     frm_db: Rc<FrameDb>,
     world: Rc<RefCell<World>>,
     scripts: Scripts,
+    stats: Stats,
     sequencer: Sequencer,
     fidget: Fidget,
     message_panel: ui::Handle,
@@ -58,11 +140,44 @@ pub struct GameState {
     shift_key_down: bool,
     last_picked_obj: Option<object::Handle>,
     object_action_menu: Option<ObjectActionMenu>,
+    inventory: Option<InventoryWindow>,
+    loot: Option<LootWindow>,
+    /// Whether the loot window is open for stealing. Every item moved then needs a Steal roll.
+    stealing: bool,
+    called_shot: Option<CalledShotWindow>,
+    skilldex: Option<SkilldexWindow>,
+    perk_select: Option<PerkSelectWindow>,
+    character: Option<CharacterEditor>,
+    save_load: Option<SaveLoadWindow>,
+    /// Save to make once the world view captures the thumbnail.
+    pending_save: Option<PendingSave>,
+    /// Thumbnail of the map being left for the autosave.
+    autosave_thumbnail: Option<Box<[u8]>>,
+    /// Elevator panel and the kind of the elevator.
+    elevator: Option<(ElevatorWindow, u32)>,
+    /// World map screen with the travel and random encounter state.
+    world_map: Option<WorldMapScreen>,
+    /// Skill chosen in the skilldex to use on the object picked next.
+    skill_to_use: Option<Skill>,
+    /// Whether the dude's attacks are aimed at a chosen hit location.
+    aimed_attack: bool,
     user_paused: bool,
+    map_db: MapDb,
+    elevator_db: ElevatorDb,
     map_id: Option<i32>,
-    in_combat: bool,
+    map: Option<CurrentMap>,
+    /// States of the maps visited before, by map name. Written with `MapWriter`.
+    visited_maps: HashMap<String, Vec<u8>>,
+    /// Directory where save slots are kept.
+    save_dir: PathBuf,
+    /// Real time not yet accounted in game time.
+    game_time_frac: Duration,
     seq_events: Vec<sequence::Event>,
     misc_msgs: Rc<Messages>,
+    combat_msgs: Messages,
+    inventory_msgs: Messages,
+    worldmap_msgs: Messages,
+    lsgame_msgs: Messages,
     scroll_areas: EnumMap<ScrollDirection, ui::Handle>,
 }
 
@@ -70,6 +185,7 @@ impl GameState {
     pub fn new(
         fs: Rc<FileSystem>,
         language: &str,
+        save_dir: PathBuf,
         proto_db: Rc<ProtoDb>,
         frm_db: Rc<FrameDb>,
         fonts: Rc<Fonts>,
@@ -83,19 +199,35 @@ impl GameState {
         let hex_grid = hex::TileGrid::default();
 
         let critter_names = Messages::read_file(&fs, language, "game/scrname.msg").unwrap();
+        let combat_msgs = Messages::read_file(&fs, language, "game/combat.msg").unwrap();
+        let inventory_msgs = Messages::read_file(&fs, language, "game/inventry.msg").unwrap();
+        let worldmap_msgs = Messages::read_file(&fs, language, "game/worldmap.msg").unwrap();
+        let lsgame_msgs = Messages::read_file(&fs, language, "game/lsgame.msg").unwrap();
+        let ai_packets = AiPackets::read(&mut fs.reader("data/ai.txt").unwrap()).unwrap();
+        let party = Party::new(asset::party::read_members(
+            &mut fs.reader("data/party.txt").unwrap()).unwrap());
+        let map_db = MapDb::new(&fs).unwrap();
+        let elevator_db = ElevatorDb::new(&fs).unwrap();
+        let world_map = WorldMap::new(
+            Rc::new(WorldMapDef::new(&fs, &map_db).unwrap()),
+            asset::worldmap::read_areas_file(&fs, &map_db).unwrap().into());
 
         let scripts = Scripts::new(
             proto_db.clone(),
             ScriptDb::new(fs.clone(), language).unwrap(),
             Vm::default());
+        let stats = Stats::new(&fs, proto_db.clone(), language).unwrap();
         let world = World::new(
             proto_db.clone(),
             frm_db.clone(),
             critter_names,
+            ai_packets,
             hex_grid.clone(),
             viewport,
             now,
-            fonts.clone());
+            fonts.clone(),
+            world_map,
+            party);
         let world = Rc::new(RefCell::new(world));
         let sequencer = Sequencer::new(now);
         let fidget = Fidget::new(now);
@@ -116,6 +248,7 @@ impl GameState {
             proto_db,
             world,
             scripts,
+            stats,
             sequencer,
             fidget,
             message_panel,
@@ -124,11 +257,34 @@ impl GameState {
             shift_key_down: false,
             last_picked_obj: None,
             object_action_menu: None,
+            inventory: None,
+            loot: None,
+            stealing: false,
+            called_shot: None,
+            skilldex: None,
+            perk_select: None,
+            character: None,
+            save_load: None,
+            pending_save: None,
+            autosave_thumbnail: None,
+            elevator: None,
+            world_map: None,
+            skill_to_use: None,
+            aimed_attack: false,
             user_paused: false,
+            map_db,
+            elevator_db,
             map_id: None,
-            in_combat: false,
+            map: None,
+            visited_maps: HashMap::new(),
+            save_dir,
+            game_time_frac: Duration::from_secs(0),
             seq_events: Vec::new(),
             misc_msgs,
+            combat_msgs,
+            inventory_msgs,
+            worldmap_msgs,
+            lsgame_msgs,
             scroll_areas,
         }
     }
@@ -146,23 +302,94 @@ impl GameState {
     }
 
     pub fn new_game(&mut self, map_name: &str, dude_name: &bstr, ui: &mut Ui) {
-        self.world.borrow_mut().clear();
+        self.scripts.vars.global_vars = asset::read_game_global_vars(
+            &mut self.fs.reader("data/vault13.gam").unwrap()).unwrap().into();
+        self.visited_maps.clear();
+
+        let fs = self.fs.clone();
+        self.load_map(map_name, &mut fs.reader(&format!("maps/{}.map", map_name)).unwrap(), &[],
+            ui).unwrap();
+
+        {
+            let world = &mut self.world.borrow_mut();
+            let map = self.map.as_ref().unwrap();
+
+            let dude_obj = self.new_dude_obj(Some(map.entrance), world);
+            let dude_objh = world.insert_object(dude_obj);
+            debug!("dude obj: {:?}", dude_objh);
+            world.set_dude_obj(dude_objh);
+            world.dude_name = dude_name.into();
+            world.world_map_mut().reset();
+
+            world.make_object_standing(dude_objh);
+
+            world.camera_mut().look_at(map.entrance.point);
+        }
+
+        self.enter_map(ui);
+    }
+
+    /// Replaces the current map with the one read from `rd`. The `kept` objects are carried over
+    /// to the new map along with their scripts. Returns objects of the map by their persistent IDs.
+    fn load_map(&mut self, map_name: &str, rd: &mut impl Read, kept: &[object::Handle],
+        ui: &mut Ui) -> io::Result<HashMap<u32, object::Handle>>
+    {
+        self.sequencer.stop_all();
+        let kept_scripts: Vec<_> = {
+            let world = self.world.borrow();
+            kept.iter()
+                .filter_map(|&obj| {
+                    let (sid, program_id) = world.objects().get(obj).borrow().script?;
+                    let local_vars = self.scripts.get(sid)?.local_vars.clone();
+                    Some((obj, sid.kind(), program_id, local_vars))
+                })
+                .collect()
+        };
+        self.scripts.clear();
+        self.last_picked_obj = None;
+        {
+            let world = &mut self.world.borrow_mut();
+            world.clear_except(kept);
+            for &obj in kept {
+                world.objects().get(obj).borrow_mut().script = None;
+            }
+        }
         // Reinsert the hex cursor. Needs `world` to be not borrowed.
         ui.widget_mut::<WorldView>(self.world_view).ensure_hex_cursor();
 
         let world = &mut self.world.borrow_mut();
 
-        let map = MapReader {
-            reader: &mut self.fs.reader(&format!("maps/{}.map", map_name)).unwrap(),
+        let Map {
+            id,
+            savegame,
+            entrance,
+            entrance_direction,
+            sqr_tiles,
+            map_vars,
+            objects_by_id,
+        } = MapReader {
+            reader: rd,
             objects: world.objects_mut(),
             proto_db: &self.proto_db,
             frm_db: &self.frm_db,
             scripts: &mut self.scripts,
-        }.read().unwrap();
+        }.read()?;
 
-        self.map_id = Some(map.id);
+        // The scripts of the kept objects get new SIDs that don't clash with the map's ones.
+        for (obj, kind, program_id, local_vars) in kept_scripts {
+            let sid = self.scripts.instantiate_object_script(kind, program_id, Some(local_vars),
+                obj)?;
+            world.objects().get(obj).borrow_mut().script = Some((sid, program_id));
+        }
+
+        self.map_id = Some(id);
+        self.map = Some(CurrentMap {
+            name: map_name.into(),
+            entrance,
+            entrance_direction,
+        });
 
-        for elev in &map.sqr_tiles {
+        for elev in &sqr_tiles {
             if let Some(ref elev) = elev {
                 for &(floor, roof) in elev.as_slice() {
                     self.frm_db.get(FrameId::new_generic(EntityKind::SqrTile, floor).unwrap()).unwrap();
@@ -190,414 +417,4361 @@ impl GameState {
         }
         self.frm_db.get(FrameId::EGG).unwrap();
 
-        world.set_sqr_tiles(map.sqr_tiles);
+        world.set_sqr_tiles(sqr_tiles);
         world.rebuild_light_grid();
 
-        let dude_fid = FrameId::from_packed(0x100003E).unwrap();
-        //    let dude_fid = FrameId::from_packed(0x101600A).unwrap();
-        let mut dude_obj = Object::new(dude_fid, ObjectProtoId::Dude, Some(map.entrance));
-        dude_obj.direction = Direction::NE;
-        dude_obj.light_emitter = LightEmitter {
-            intensity: 0x10000,
-            radius: 4,
-        };
-        let dude_objh = world.insert_object(dude_obj);
-        debug!("dude obj: {:?}", dude_objh);
-        world.set_dude_obj(dude_objh);
-        world.dude_name = dude_name.into();
-
-        world.make_object_standing(dude_objh);
-
-        world.camera_mut().look_at(map.entrance.point);
-
-        self.scripts.vars.global_vars = if map.savegame {
-            unimplemented!("read save.dat")
-        } else {
-            asset::read_game_global_vars(&mut self.fs.reader("data/vault13.gam").unwrap()).unwrap().into()
-        };
-        self.scripts.vars.map_vars = if map.savegame {
-            map.map_vars.clone()
+        self.scripts.vars.map_vars = if savegame {
+            map_vars
         } else {
             let path = format!("maps/{}.gam", map_name);
             if self.fs.exists(&path) {
-                asset::read_map_global_vars(&mut self.fs.reader(&path).unwrap()).unwrap().into()
+                asset::read_map_global_vars(&mut self.fs.reader(&path)?)?.into()
             } else {
                 Vec::new().into()
             }
         };
 
-        // Init scripts.
-        {
-            let ctx = &mut script::Context {
-                world,
-                sequencer: &mut self.sequencer,
-                dialog: &mut self.dialog,
-                message_panel: self.message_panel,
-                ui,
-                map_id: map.id,
-            };
+        Ok(objects_by_id)
+    }
 
-            // PredefinedProc::Start for map script is never called.
-            // MapEnter in map script is called before anything else.
-            if let Some(sid) = self.scripts.map_sid() {
-                self.scripts.execute_predefined_proc(sid, PredefinedProc::MapEnter, ctx)
-                    .map(|r| r.suspend.map(|_| panic!("can't suspend in MapEnter")));
-            }
+    /// Runs the scripts on entering the current map. The world map area of the map is marked
+    /// visited.
+    fn enter_map(&mut self, ui: &mut Ui) {
+        let world = &mut self.world.borrow_mut();
+        if let Some(area) = world.world_map().area_of_map(self.map_id.unwrap()) {
+            world.world_map_mut().enter_area(area);
+        }
+
+        let ctx = &mut script::Context {
+            world,
+            sequencer: &mut self.sequencer,
+            stats: &mut self.stats,
+            dialog: &mut self.dialog,
+            message_panel: self.message_panel,
+            ui,
+            map_id: self.map_id.unwrap(),
+        };
 
-            self.scripts.execute_procs(PredefinedProc::Start, ctx, |sid| sid.kind() != ScriptKind::System);
-            self.scripts.execute_map_procs(PredefinedProc::MapEnter, ctx);
+        // PredefinedProc::Start for map script is never called.
+        // MapEnter in map script is called before anything else.
+        if let Some(sid) = self.scripts.map_sid() {
+            self.scripts.execute_predefined_proc(sid, PredefinedProc::MapEnter, ctx)
+                .map(|r| r.suspend.map(|_| panic!("can't suspend in MapEnter")));
         }
+
+        self.scripts.execute_procs(PredefinedProc::Start, ctx, |sid| sid.kind() != ScriptKind::System);
+        self.scripts.execute_map_procs(PredefinedProc::MapEnter, ctx);
     }
 
-    fn handle_action(
-        &mut self,
-        ui: &mut Ui,
-        obj: object::Handle,
-        action: Action,
-    ) {
-        match action {
-            Action::Cancel => {},
-            Action::Drop | Action::Unload => unreachable!(),
-            Action::Inventory => {
-                // TODO
-            }
-            Action::Look => {
-                self.dude_examine_object(obj, ui);
-            }
-            Action::Push => {
-                // TODO
-            }
-            Action::Rotate => {
-                let world = self.world.borrow_mut();
-                let mut obj = world.objects().get(obj).borrow_mut();
-                if let Some(signal) = obj.sequence.take() {
-                    signal.cancel();
-                }
-                obj.direction = obj.direction.rotate_cw();
-            }
-            Action::Talk => {
-                let talker = self.world.borrow().dude_obj().unwrap();
-                self.action_talk(talker, obj, ui);
-            }
-            Action::UseHand => {
-                // TODO
-            }
-            Action::UseSkill => {
-                // TODO
-            }
+    /// Whether the game can be saved now. It can't be saved in combat, in dialog or while
+    /// a script is suspended.
+    pub fn can_save(&self) -> bool {
+        let world = self.world.borrow();
+        self.map.is_some()
+            && world.dude_obj().is_some()
+            && world.combat.is_none()
+            && self.dialog.is_none()
+            && !self.scripts.can_resume()
+    }
+
+    /// Saves the game to the `slot`. The `thumbnail` is a screenshot of the map as described in
+    /// `save::Header`.
+    // SaveGame()
+    pub fn save_game(&self, slot: u32, description: &bstr, thumbnail: Box<[u8]>)
+        -> io::Result<()>
+    {
+        if !self.can_save() {
+            return Err(Error::new(ErrorKind::Other, "can't save the game now"));
+        }
+
+        let world = self.world.borrow();
+        let current_map = self.map.as_ref().unwrap();
+        let obj_ids = save::object_ids(&world, true);
+
+        let mut map = Vec::new();
+        MapWriter {
+            writer: &mut map,
+            name: &current_map.name,
+            id: self.map_id.unwrap(),
+            entrance: current_map.entrance,
+            entrance_direction: current_map.entrance_direction,
+            game_time: world.game_time,
+            sqr_tiles: world.sqr_tiles(),
+            map_vars: &self.scripts.vars.map_vars,
+            objects: world.objects(),
+            proto_db: &self.proto_db,
+            scripts: &self.scripts,
+            obj_ids: &obj_ids,
+        }.write()?;
+
+        let map_script_obj = self.map_script_obj();
+        let mut timer = Vec::new();
+        world.timer.write(&mut timer, |obj| if Some(obj) == map_script_obj {
+            Some(save::MAP_SCRIPT_OBJ_ID)
+        } else {
+            obj_ids.get(&obj).cloned()
+        })?;
+
+        let mut stats = Vec::new();
+        self.stats.write(&mut stats)?;
+
+        let mut world_map = Vec::new();
+        world.world_map().write(&mut world_map)?;
+
+        let mut party = Vec::new();
+        world.party().write(&mut party, |obj| obj_ids.get(&obj).cloned())?;
+
+        let save = SaveGame {
+            header: save::Header {
+                dude_name: world.dude_name.clone(),
+                description: description.into(),
+                saved_at: SystemTime::now().duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0),
+                game_time: world.game_time,
+                map_name: current_map.name.clone(),
+                thumbnail,
+            },
+            global_vars: self.scripts.vars.global_vars.clone(),
+            stats,
+            dude_sneaking: world.dude_sneaking,
+            ambient_light: world.ambient_light,
+            map,
+            timer,
+            world_map,
+            party,
+            visited_maps: self.visited_maps.clone(),
+        };
+
+        // Write to a temporary file first so a failed save doesn't destroy the existing one.
+        let path = save::slot_path(&self.save_dir, slot);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        let tmp_path = path.with_extension("TMP");
+        {
+            let mut wr = BufWriter::new(File::create(&tmp_path)?);
+            save.write(&mut wr)?;
+            wr.flush()?;
         }
+        std::fs::rename(&tmp_path, &path)?;
+        info!("saved game to {}", path.display());
+
+        Ok(())
     }
 
-    fn handle_seq_events(&mut self, ui: &mut Ui) {
-        use sequence::Event::*;
-        let mut events = std::mem::replace(&mut self.seq_events, Vec::new());
-        for event in events.drain(..) {
-            match event {
-                ObjectMoved { obj, old_pos, new_pos } => {
-                    dbg!((obj, old_pos, new_pos));
-                }
-                Talk { talker, talked } => {
-                    self.talk(talker, talked, ui);
-                }
-                _ => {}
+    fn map_script_obj(&self) -> Option<object::Handle> {
+        self.scripts.map_sid().and_then(|sid| self.scripts.get(sid).unwrap().object)
+    }
+
+    /// Header of the game saved in the `slot` or `None` if the slot is empty or unreadable.
+    pub fn save_slot_header(&self, slot: u32) -> Option<save::Header> {
+        let path = save::slot_path(&self.save_dir, slot);
+        match File::open(&path).and_then(|f| save::read_header(&mut BufReader::new(f))) {
+            Ok(v) => Some(v),
+            Err(ref e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => {
+                warn!("couldn't read save file {}: {}", path.display(), e);
+                None
             }
         }
-        std::mem::replace(&mut self.seq_events, events);
     }
 
-    fn actions(&self, objh: object::Handle) -> Vec<Action> {
-        let mut r = Vec::new();
-        let world = self.world.borrow();
-        let obj = world.objects().get(objh).borrow();
-        match obj.kind() {
-            EntityKind::Critter => {
-                if Some(objh) == world.dude_obj() {
-                    r.push(Action::Rotate);
+    /// Replaces the game state with the one saved in the `slot`.
+    // LoadGame()
+    pub fn load_game(&mut self, slot: u32, ui: &mut Ui) -> io::Result<()> {
+        let path = save::slot_path(&self.save_dir, slot);
+        let mut data = Vec::new();
+        File::open(&path)?.read_to_end(&mut data)?;
+        info!("loading game from {}", path.display());
+        if asset::save::is_save_dat(&data) {
+            return self.import_save_dat(path.parent().unwrap(), &data, ui);
+        }
+        let save = SaveGame::read(&mut &data[..])?;
+
+        self.stats.read(&mut &save.stats[..])?;
+        self.scripts.vars.global_vars = save.global_vars;
+        self.visited_maps = save.visited_maps;
+
+        let objects_by_id = self.load_map(&save.header.map_name, &mut &save.map[..], &[], ui)?;
+        let map_script_obj = self.map_script_obj();
+
+        {
+            let world = &mut self.world.borrow_mut();
+
+            let dude_obj = objects_by_id.values()
+                .cloned()
+                .find(|&obj| world.objects().get(obj).borrow().pid == ObjectProtoId::Dude)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "no dude in saved map"))?;
+            world.set_dude_obj(dude_obj);
+            world.dude_name = save.header.dude_name;
+            world.game_time = save.header.game_time;
+            world.dude_sneaking = save.dude_sneaking;
+            world.ambient_light = save.ambient_light;
+            world.timer = timer::Timer::read(&mut &save.timer[..], |id| {
+                if id == save::MAP_SCRIPT_OBJ_ID {
+                    map_script_obj
                 } else {
-                    if world.objects().can_talk_to(objh) {
-                        if !self.in_combat {
-                            r.push(Action::Talk);
-                        }
-                    } else if !self.proto_db.proto(obj.pid.proto_id().unwrap()).unwrap()
-                        .sub.critter().unwrap()
-                        .flags.contains(CritterFlag::NoSteal)
-                    {
-                        r.push(Action::UseHand);
-                    }
-                    if world.objects().can_push(world.dude_obj().unwrap(), objh,
-                        &self.scripts, self.in_combat)
-                    {
-                        r.push(Action::Push);
-                    }
-                }
-                r.extend_from_slice(&[Action::Look, Action::Inventory, Action::UseSkill])
-            }
-            EntityKind::Item => {
-                r.extend_from_slice(&[Action::UseHand, Action::Look]);
-                if world.objects().item_kind(objh) == Some(ItemKind::Container) {
-                    r.extend_from_slice(&[Action::UseSkill, Action::Inventory]);
-                }
-            }
-            EntityKind::Scenery => {
-                if world.objects().can_use(objh) {
-                    r.push(Action::UseHand)
-                }
-                r.extend_from_slice(&[Action::Look, Action::Inventory, Action::UseSkill])
-            }
-            EntityKind::Wall => {
-                r.push(Action::Look);
-                if world.objects().can_use(objh) {
-                    r.push(Action::UseHand)
+                    objects_by_id.get(&id).cloned()
                 }
-            }
-            _ => {}
+            })?;
+            world.world_map_mut().read(&mut &save.world_map[..])?;
+            // The game could be saved on the world map while driving.
+            world.world_map_mut().park_car(self.map_id.unwrap());
+            world.party_mut().read(&mut &save.party[..], |id| objects_by_id.get(&id).cloned())?;
+
+            let dude_pos = world.objects().get(dude_obj).borrow().pos.unwrap();
+            world.camera_mut().look_at(dude_pos.point);
+
+            world.loading_game = true;
         }
-        if r.len() > 0 {
-            r.push(Action::Cancel)
+        self.game_time_frac = Duration::from_secs(0);
+
+        self.enter_map(ui);
+        self.world.borrow_mut().loading_game = false;
+
+        Ok(())
+    }
+
+    /// Saves the game to the `slot` once the world view captures the thumbnail on the next
+    /// render.
+    fn start_save(&mut self, slot: u32, description: BString, ui: &mut Ui) {
+        if !self.can_save() {
+            return;
         }
-        r
+        ui.widget_mut::<WorldView>(self.world_view).request_thumbnail();
+        self.pending_save = Some(PendingSave {
+            slot,
+            description,
+        });
     }
 
-    fn look_at_object(&mut self, looker: object::Handle, looked: object::Handle, ui: &mut Ui)
-        -> Option<BString>
+    /// Makes the pending save if the world view has captured the thumbnail.
+    fn update_pending_save(&mut self, ui: &mut Ui) {
+        if self.pending_save.is_none() {
+            return;
+        }
+        let thumbnail = ui.widget_mut::<WorldView>(self.world_view).take_thumbnail();
+        if let Some(thumbnail) = thumbnail {
+            let PendingSave { slot, description } = self.pending_save.take().unwrap();
+            self.save_game_verbose(slot, &description, thumbnail, ui);
+        }
+    }
+
+    /// Saves the game to the `slot` reporting errors in the message panel.
+    fn save_game_verbose(&self, slot: u32, description: &bstr, thumbnail: Box<[u8]>,
+        ui: &mut Ui)
     {
-        let sid = {
-            let world = self.world.borrow();
-            let lookero = world.objects().get(looker).borrow();
-            let lookedo = world.objects().get(looked).borrow();
-            if lookero.sub.critter().map(|c| c.is_dead()).unwrap_or(true)
-                // TODO This is only useful for mapper?
-                || lookedo.kind() == EntityKind::SqrTile
-                || lookedo.pid != ObjectProtoId::Dude &&
-                    (lookedo.pid.proto_id().is_none()
-                        || self.proto_db.proto(lookedo.pid.proto_id().unwrap()).is_err())
-            {
-                return None;
+        match self.save_game(slot, description, thumbnail) {
+            Ok(()) => if slot == save::QUICKSAVE_SLOT {
+                let msg = &self.misc_msgs.get(5).unwrap().text;
+                self.push_message(msg, ui);
             }
-            lookedo.script.map(|(v, _)| v)
-        };
-
-        if_chain! {
-            if let Some(sid) = sid;
-            if let Some(r) = self.scripts.execute_predefined_proc(sid, PredefinedProc::LookAt,
-                &mut script::Context {
-                    world: &mut self.world.borrow_mut(),
-                    sequencer: &mut self.sequencer,
-                    dialog: &mut self.dialog,
-                    ui,
-                    message_panel: self.message_panel,
-                    map_id: self.map_id.unwrap(),
-                });
-            then {
-                assert!(r.suspend.is_none(), "can't suspend");
-                if r.script_overrides {
-                    return None;
-                }
+            Err(e) => {
+                warn!("error saving game to slot {}: {}", slot, e);
+                let msg = &self.lsgame_msgs.get(132).unwrap().text;
+                self.push_message(msg, ui);
             }
         }
+    }
 
-        let world = self.world.borrow();
-        let lookedo = world.objects().get(looked).borrow();
-        let msg_id = if lookedo.sub.critter().map(|c| c.is_dead()).unwrap_or(false) {
-            491 + random(0, 1)
+    /// Loads the game from the `slot` reporting errors in the message panel.
+    fn load_game_verbose(&mut self, slot: u32, ui: &mut Ui) {
+        if let Err(e) = self.load_game(slot, ui) {
+            warn!("error loading game from slot {}: {}", slot, e);
+            let msg = &self.lsgame_msgs.get(134).unwrap().text;
+            self.push_message(msg, ui);
+        }
+    }
+
+    fn quicksave(&mut self, ui: &mut Ui) {
+        self.start_save(save::QUICKSAVE_SLOT, "Quicksave".into(), ui);
+    }
+
+    /// Loads the quicksave or lets the player pick the save to load if there's no quicksave.
+    fn quickload(&mut self, ui: &mut Ui) {
+        if self.save_slot_header(save::QUICKSAVE_SLOT).is_some() {
+            self.load_game_verbose(save::QUICKSAVE_SLOT, ui);
         } else {
-            490
-        };
-        if_chain! {
-            if let Some(msg) = self.proto_db.messages().get(msg_id);
-            if let Some(name) = world.object_name(looked);
-            then {
-                Some(sprintf(&msg.text, &[&*name]))
-            } else {
-                None
-            }
+            self.show_save_load(save_load::Mode::Load, ui);
         }
     }
 
-    fn dude_look_at_object(&mut self, obj: object::Handle, ui: &mut Ui) {
-        let dude_obj = self.world().borrow().dude_obj().unwrap();
-        if let Some(msg) = self.look_at_object(dude_obj, obj, ui) {
-            self.push_message(&msg, ui);
+    /// Saves the game to the least recently used of `save::AUTOSAVE_SLOTS`. The thumbnail is
+    /// the one captured in `handle_map_transition()`.
+    fn autosave(&mut self) {
+        let thumbnail = self.autosave_thumbnail.take();
+        if !self.can_save() {
+            return;
+        }
+        let thumbnail = thumbnail.unwrap_or_else(||
+            vec![0; (save::THUMBNAIL_WIDTH * save::THUMBNAIL_HEIGHT) as usize].into());
+        let slot = save::autosave_slot(|slot| self.save_slot_header(slot).map(|h| h.saved_at));
+        if let Err(e) = self.save_game(slot, "Autosave".into(), thumbnail) {
+            warn!("error autosaving to slot {}: {}", slot, e);
         }
     }
 
-    // obj_examine_func()
-    fn examine_object(&mut self, examiner: object::Handle, examined: object::Handle, ui: &mut Ui)
-        -> Vec<BString>
-    {
-        let sid = {
-            let world = self.world.borrow();
-            let examinero = world.objects().get(examiner).borrow();
-            let examinedo = world.objects().get(examined).borrow();
-            if examinero.sub.critter().map(|c| c.is_dead()).unwrap_or(false)
-                // TODO This is only useful for mapper?
-                || examinedo.kind() == EntityKind::SqrTile
-            {
-                return Vec::new();
-            }
-            examinedo.script.map(|(v, _)| v)
-        };
+    /// Called when the dude is about to leave the current map.
+    fn leave_map(&mut self, ui: &mut Ui) {
+        self.autosave();
 
-        let script_overrides = if_chain! {
+        let world = &mut self.world.borrow_mut();
+        self.scripts.execute_map_procs(PredefinedProc::MapExit, &mut script::Context {
+            world,
+            sequencer: &mut self.sequencer,
+            stats: &mut self.stats,
+            dialog: &mut self.dialog,
+            message_panel: self.message_panel,
+            ui,
+            map_id: self.map_id.unwrap(),
+        });
+    }
+
+    /// Stores the state of the current map in `visited_maps` unless the map isn't meant to be
+    /// saved. The dude, its party members and their inventories are not stored.
+    fn save_visited_map(&mut self) -> io::Result<()> {
+        let current_map = self.map.as_ref().unwrap();
+        let map_id = self.map_id.unwrap();
+        if !self.map_db.get(map_id).map(|d| d.saved).unwrap_or(true) {
+            self.visited_maps.remove(&current_map.name);
+            return Ok(());
+        }
+
+        let world = self.world.borrow();
+        let obj_ids = save::object_ids(&world, false);
+        let mut map = Vec::new();
+        MapWriter {
+            writer: &mut map,
+            name: &current_map.name,
+            id: map_id,
+            entrance: current_map.entrance,
+            entrance_direction: current_map.entrance_direction,
+            game_time: world.game_time,
+            sqr_tiles: world.sqr_tiles(),
+            map_vars: &self.scripts.vars.map_vars,
+            objects: world.objects(),
+            proto_db: &self.proto_db,
+            scripts: &self.scripts,
+            obj_ids: &obj_ids,
+        }.write()?;
+        self.visited_maps.insert(current_map.name.clone(), map);
+
+        Ok(())
+    }
+
+    /// Moves the dude to the map `map_id` or within the current map if `map_id` is `None` or is
+    /// the current map. The dude is placed at the map entrance if `pos` is `None`. If the party
+    /// drives the car it's parked at the map.
+    // map_load_idx(), map_leave_map()
+    fn switch_map(&mut self, map_id: Option<i32>, pos: Option<EPoint>,
+        direction: Option<Direction>, ui: &mut Ui) -> io::Result<()>
+    {
+        let dude_obj = self.world.borrow().dude_obj().unwrap();
+        let map_id = map_id.filter(|&id| Some(id) != self.map_id);
+        let mut car = Vec::new();
+        if let Some(map_id) = map_id {
+            let map_name = self.map_db.get(map_id)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidInput,
+                    format!("unknown map ID {}", map_id)))?
+                .name.clone();
+            info!("switching to map {} ({})", map_name, map_id);
+
+            self.leave_map(ui);
+            car = self.take_car_objects();
+            self.save_visited_map()?;
+
+            let mut kept = self.world.borrow().objects().with_inventory(dude_obj);
+            let members: Vec<_> = self.world.borrow().party().members().iter()
+                .map(|m| m.obj)
+                .collect();
+            for &obj in car.iter().chain(&members) {
+                kept.extend(self.world.borrow().objects().with_inventory(obj));
+            }
+            if let Some(map) = self.visited_maps.get(&map_name).cloned() {
+                self.load_map(&map_name, &mut &map[..], &kept, ui)?;
+            } else {
+                let fs = self.fs.clone();
+                self.load_map(&map_name, &mut fs.reader(&format!("maps/{}.map", map_name))?,
+                    &kept, ui)?;
+            }
+        } else {
+            self.world.borrow().objects().get(dude_obj).borrow_mut().cancel_sequence();
+        }
+
+        {
+            let world = &mut self.world.borrow_mut();
+            let map = self.map.as_ref().unwrap();
+            let (pos, direction) = if let Some(pos) = pos {
+                (pos, direction)
+            } else {
+                (map.entrance, direction.or(Some(map.entrance_direction)))
+            };
+            world.set_object_pos(dude_obj, pos);
+            if let Some(direction) = direction {
+                world.objects().get(dude_obj).borrow_mut().direction = direction;
+            }
+            world.make_object_standing(dude_obj);
+            world.camera_mut().look_at(pos.point);
+        }
+        self.place_party();
+
+        if self.world.borrow().world_map().is_driving() {
+            if map_id.is_some() {
+                self.place_car(&car);
+            }
+            self.world.borrow_mut().world_map_mut().park_car(self.map_id.unwrap());
+        }
+
+        if map_id.is_some() {
+            self.enter_map(ui);
+        }
+
+        Ok(())
+    }
+
+    /// Places the party members around the dude.
+    fn place_party(&mut self) {
+        let world = &mut self.world.borrow_mut();
+        let dude_obj = world.dude_obj().unwrap();
+        let dude_pos = world.objects().get(dude_obj).borrow().pos.unwrap();
+        let members: Vec<_> = world.party().members().iter()
+            .map(|m| m.obj)
+            .collect();
+        for obj in members {
+            world.objects().get(obj).borrow_mut().cancel_sequence();
+            let pos = world.free_pos_near(obj, dude_pos, PARTY_PLACE_DISTANCE)
+                .unwrap_or(dude_pos);
+            world.set_object_pos(obj, pos);
+            world.make_object_standing(obj);
+        }
+    }
+
+    /// Takes the car and its trunk off the current map if the party drives away from it.
+    fn take_car_objects(&mut self) -> Vec<object::Handle> {
+        let world = &mut self.world.borrow_mut();
+        if !world.world_map().is_driving() {
+            return Vec::new();
+        }
+        let r: Vec<_> = [ProtoId::DRIVABLE_CAR, ProtoId::CAR_TRUNK].iter()
+            .filter_map(|&pid| world.objects().find_on_map(pid).first().cloned())
+            .collect();
+        for &obj in &r {
+            world.objects_mut().detach(obj);
+        }
+        r
+    }
+
+    /// Places the `car` objects brought from the previous map next to the dude. The car and its
+    /// trunk are created if they weren't brought.
+    fn place_car(&mut self, car: &[object::Handle]) {
+        let world = &mut self.world.borrow_mut();
+        let dude_obj = world.dude_obj().unwrap();
+        let dude_pos = world.objects().get(dude_obj).borrow().pos.unwrap();
+        let mut pos = dude_pos;
+        for &pid in &[ProtoId::DRIVABLE_CAR, ProtoId::CAR_TRUNK] {
+            let obj = car.iter().cloned()
+                .find(|&h| world.objects().get(h).borrow().pid.proto_id() == Some(pid));
+            let obj = if let Some(v) = obj {
+                v
+            } else {
+                match self.proto_db.proto(pid) {
+                    Ok(proto) => world.insert_object(Object::new(proto.fid, pid.into(), None)),
+                    Err(e) => {
+                        warn!("error loading car proto {:?}: {}", pid, e);
+                        continue;
+                    }
+                }
+            };
+            pos = world.free_pos_near(obj, pos, 5).unwrap_or(dude_pos);
+            world.set_object_pos(obj, pos);
+        }
+    }
+
+    fn handle_map_transition(&mut self, ui: &mut Ui) {
+        if self.dialog.is_some() {
+            return;
+        }
+        let elevator_request = self.world.borrow_mut().elevator_request.take();
+        if let Some(kind) = elevator_request {
+            self.show_elevator(kind, ui);
+        }
+        let transition = if let Some(v) = self.world.borrow_mut().map_transition.take() {
+            v
+        } else {
+            return;
+        };
+        match transition {
+            MapTransition::Map { map, pos, direction } => {
+                let map_id = match map {
+                    None => None,
+                    Some(MapRef::Id(id)) => Some(id),
+                    Some(MapRef::Name(name)) => if let Some(id) = self.map_db.find_by_name(&name) {
+                        Some(id)
+                    } else {
+                        warn!("unknown map: {}", name);
+                        return;
+                    }
+                };
+                if map_id.is_some() && map_id != self.map_id && self.can_save()
+                    && self.autosave_thumbnail.is_none()
+                {
+                    // Leaving the map autosaves the game. Delay the transition until the world
+                    // view captures the thumbnail of the map.
+                    let mut world_view = ui.widget_mut::<WorldView>(self.world_view);
+                    self.autosave_thumbnail = world_view.take_thumbnail();
+                    if self.autosave_thumbnail.is_none() {
+                        world_view.request_thumbnail();
+                        self.world.borrow_mut().map_transition = Some(MapTransition::Map {
+                            map: map_id.map(MapRef::Id),
+                            pos,
+                            direction,
+                        });
+                        return;
+                    }
+                }
+                if let Err(e) = self.switch_map(map_id, pos, direction, ui) {
+                    warn!("error switching map: {}", e);
+                }
+                self.autosave_thumbnail = None;
+            }
+            MapTransition::WorldMap => self.show_world_map(ui),
+        }
+    }
+
+    fn show_save_load(&mut self, mode: save_load::Mode, ui: &mut Ui) {
+        if self.save_load.is_some() || mode == save_load::Mode::Save && !self.can_save() {
+            return;
+        }
+        let slots = (0..save::SLOT_COUNT).map(|slot| self.save_slot_header(slot)).collect();
+        let default_description = self.map.as_ref()
+            .map(|m| m.name.to_uppercase().into())
+            .unwrap_or_default();
+        self.save_load = Some(SaveLoadWindow::show(ui, mode, slots, default_description,
+            &self.lsgame_msgs));
+    }
+
+    fn handle_save_load_command(&mut self, cmd: save_load::Command, ui: &mut Ui) {
+        if cmd == save_load::Command::Done {
+            let window = self.save_load.as_ref().unwrap();
+            let refused = match window.mode() {
+                save_load::Mode::Save => !window.is_editing(),
+                save_load::Mode::Load => window.selected_header().is_none(),
+            };
+            if refused {
+                return;
+            }
+        }
+
+        let window = self.save_load.take().unwrap();
+        let mode = window.mode();
+        let slot = window.selected();
+        let description = window.description().to_owned();
+        window.hide(ui);
+
+        if cmd == save_load::Command::Done {
+            match mode {
+                save_load::Mode::Save => self.start_save(slot, description, ui),
+                save_load::Mode::Load => self.load_game_verbose(slot, ui),
+            }
+        }
+    }
+
+    fn handle_save_load_event(&mut self, event: &Event, ui: &mut Ui) -> bool {
+        let window = self.save_load.as_mut().unwrap();
+        let editing = window.is_editing();
+        let mut description = window.description().to_owned();
+        match event {
+            Event::TextInput { text, .. } if editing => {
+                for c in text.bytes().filter(|&c| c.is_ascii_graphic() || c == b' ') {
+                    if description.len() < save::DESCRIPTION_LEN - 1 {
+                        description.push(c);
+                    }
+                }
+            }
+            Event::KeyDown { keycode: Some(Keycode::Backspace), .. } if editing => {
+                description.pop();
+            }
+            Event::KeyDown { keycode: Some(Keycode::Return), .. } => {
+                self.handle_save_load_command(save_load::Command::Done, ui);
+                return true;
+            }
+            Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
+                self.handle_save_load_command(save_load::Command::Cancel, ui);
+                return true;
+            }
+            _ => return false,
+        }
+        window.set_description(ui, description);
+        true
+    }
+
+    /// Whether any window that blocks playing the game is shown.
+    fn is_modal_window_shown(&self) -> bool {
+        self.dialog.is_some() || self.object_action_menu.is_some() || self.inventory.is_some()
+            || self.loot.is_some() || self.called_shot.is_some() || self.skilldex.is_some()
+            || self.perk_select.is_some() || self.character.is_some()
+            || self.save_load.is_some() || self.elevator.is_some() || self.world_map.is_some()
+    }
+
+    // wmWorldMap()
+    fn show_world_map(&mut self, ui: &mut Ui) {
+        if self.world_map.is_some() {
+            return;
+        }
+        let dude_obj = self.world.borrow().dude_obj().unwrap();
+        self.world.borrow().objects().get(dude_obj).borrow_mut().cancel_sequence();
+        self.world.borrow_mut().world_map_mut().enter_car(self.map_id.unwrap());
+        let window = WorldMapWindow::show(ui, self.world.clone());
+        self.world_map = Some(WorldMapScreen {
+            window,
+            step_time: Duration::from_secs(0),
+            encounter_time: Duration::from_secs(0),
+            encounter: None,
+        });
+    }
+
+    fn hide_world_map(&mut self, ui: &mut Ui) {
+        if let Some(WorldMapScreen { window, encounter, .. }) = self.world_map.take() {
+            if let Some((_, prompt)) = encounter {
+                prompt.hide(ui);
+            }
+            window.hide(ui);
+        }
+    }
+
+    fn handle_world_map_command(&mut self, cmd: worldmap::Command, ui: &mut Ui) {
+        match cmd {
+            worldmap::Command::Pick(pos) => {
+                let area = {
+                    let world = &mut self.world.borrow_mut();
+                    let wm = world.world_map_mut();
+                    let area = wm.area_at(pos);
+                    if area.is_none() || wm.is_travelling() || area != wm.area_at(wm.pos()) {
+                        wm.travel_to(pos);
+                        return;
+                    }
+                    area.unwrap()
+                };
+                self.show_town_map(area, ui);
+            }
+            worldmap::Command::Enter { area, entrance } => self.enter_area(area, entrance, ui),
+        }
+    }
+
+    /// Shows the town map of the `area` with the entrances that are known or lead to the maps
+    /// visited before. If the area has no town map the first of such entrances is used right
+    /// away.
+    // wmTownMapFunc()
+    fn show_town_map(&mut self, area: usize, ui: &mut Ui) {
+        let def = self.world.borrow().world_map().areas()[area].clone();
+        if def.locked {
+            return;
+        }
+        let entrances: Vec<_> = (0..def.entrances.len())
+            .filter(|&i| {
+                let entrance = &def.entrances[i];
+                self.world.borrow().world_map().is_entrance_known(area, i) || entrance.map_id
+                    .filter(|&id| Some(id) == self.map_id || self.map_db.get(id)
+                        .map(|d| self.visited_maps.contains_key(&d.name))
+                        .unwrap_or(false))
+                    .is_some()
+            })
+            .collect();
+        if def.townmap_fid.is_none() {
+            if let Some(&entrance) = entrances.first() {
+                self.enter_area(area, entrance, ui);
+            } else {
+                warn!("no known entrances in area {} ({})", area, def.name);
+            }
+            return;
+        }
+        let window = &mut self.world_map.as_mut().unwrap().window;
+        if let Err(e) = window.show_town(ui, &self.frm_db, area, &def, &entrances) {
+            warn!("error showing town map of area {} ({}): {}", area, def.name, e);
+        }
+    }
+
+    /// Leaves the world map for the map of the `entrance` of the `area`.
+    fn enter_area(&mut self, area: usize, entrance: usize, ui: &mut Ui) {
+        {
+            let world = &mut self.world.borrow_mut();
+            let def = world.world_map().areas()[area].entrances[entrance].clone();
+            let map_id = if let Some(v) = def.map_id {
+                v
+            } else {
+                warn!("entrance {} of area {} leads to unknown map", entrance, area);
+                return;
+            };
+            let pos = def.pos_on_map.map(|(elevation, tile_num)|
+                world.hex_grid().from_linear_inv(tile_num).elevated(elevation));
+            world.map_transition = Some(MapTransition::Map {
+                map: Some(MapRef::Id(map_id)),
+                pos,
+                direction: pos.map(|_| def.direction),
+            });
+        }
+        self.hide_world_map(ui);
+    }
+
+    /// Moves the party on the world map. Each travel step advances the game time by
+    /// `TRAVEL_STEP_TIME`. The town map is shown when the party arrives at an area. While
+    /// travelling random encounters are checked every `ENCOUNTER_CHECK_INTERVAL`.
+    // wmWorldMapFunc()
+    fn update_world_map(&mut self, delta: Duration, ui: &mut Ui) {
+        let screen = self.world_map.as_mut().unwrap();
+        if screen.encounter.is_some() {
+            return;
+        }
+        let (arrived_at, check_encounter) = {
+            let world = &mut self.world.borrow_mut();
+            if !world.world_map().is_travelling() {
+                screen.step_time = Duration::from_secs(0);
+                return;
+            }
+            screen.step_time += delta;
+            while screen.step_time >= TRAVEL_STEP_INTERVAL && world.world_map().is_travelling() {
+                screen.step_time -= TRAVEL_STEP_INTERVAL;
+                world.world_map_mut().travel_step();
+                world.game_time = world.game_time.add_decis(TRAVEL_STEP_TIME);
+            }
+            screen.encounter_time += delta;
+            let check_encounter = screen.encounter_time >= ENCOUNTER_CHECK_INTERVAL;
+            if check_encounter {
+                screen.encounter_time = Duration::from_secs(0);
+            }
+            let wm = world.world_map();
+            if wm.is_travelling() {
+                (None, check_encounter)
+            } else {
+                (wm.area_at(wm.pos()), false)
+            }
+        };
+        screen.window.follow_party(ui);
+        if let Some(area) = arrived_at {
+            self.show_town_map(area, ui);
+        } else if check_encounter {
+            self.check_encounter(ui);
+        }
+    }
+
+    /// Rolls for a random encounter at the party position. If the dude passes an Outdoorsman
+    /// roll the encounter is detected beforehand and can be avoided, otherwise it's faced right
+    /// away.
+    // wmRndEncounterOccurred()
+    fn check_encounter(&mut self, ui: &mut Ui) {
+        let (encounter, detected) = {
+            let world = &mut self.world.borrow_mut();
+            let day_time = day_time(world.game_time.hour());
+            let encounter = if let Some(v) = world.world_map_mut()
+                .roll_encounter(day_time, &mut random)
+            {
+                v
+            } else {
+                return;
+            };
+            let dude = world.get_dude_obj().unwrap().borrow();
+            let detected = self.stats
+                .roll_skill(Skill::Outdoorsman, &dude, world, 0, &mut random).0
+                .is_success();
+            (encounter, detected)
+        };
+        debug!("random encounter {:?} detected={}", encounter, detected);
+
+        if detected {
+            let text = self.encounter_prompt_text(encounter);
+            match EncounterWindow::show(ui, &self.frm_db, text.as_ref()) {
+                Ok(prompt) => {
+                    self.world_map.as_mut().unwrap().encounter = Some((encounter, prompt));
+                    return;
+                }
+                Err(e) => warn!("error showing encounter prompt: {}", e),
+            }
+        }
+        self.start_encounter(encounter, ui);
+    }
+
+    fn encounter_prompt_text(&self, encounter: RandomEncounter) -> BString {
+        let msg = |id| self.worldmap_msgs.get(id).map(|m| m.text.clone());
+        let mut r = msg(2999).unwrap_or_else(|| "You encounter:".into());
+        if let Some(desc) = msg(3000 + 50 * encounter.table as i32 + encounter.encounter as i32) {
+            r.push(b' ');
+            r.push_str(desc);
+        }
+        r
+    }
+
+    fn handle_encounter_command(&mut self, cmd: encounter::Command, ui: &mut Ui) {
+        let (encounter, prompt) = if let Some(v) = self.world_map.as_mut()
+            .and_then(|s| s.encounter.take())
+        {
+            v
+        } else {
+            return;
+        };
+        prompt.hide(ui);
+        match cmd {
+            encounter::Command::Accept => self.start_encounter(encounter, ui),
+            encounter::Command::Avoid => {}
+        }
+    }
+
+    /// Leaves the world map for the map of the random `encounter` and spawns the encounter
+    /// critters there. The dude is placed at a random start point of the map if it has any.
+    // wmRndEncounterOccurred(), wmSetupRandomEncounter()
+    fn start_encounter(&mut self, encounter: RandomEncounter, ui: &mut Ui) {
+        self.world.borrow_mut().world_map_mut().stop();
+        self.hide_world_map(ui);
+
+        let pos = self.map_db.get(encounter.map_id)
+            .map(|d| &d.random_start_points)
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                let (elevation, tile_num) = p[random(0, p.len() as i32 - 1) as usize];
+                self.world.borrow().hex_grid().from_linear_inv(tile_num).elevated(elevation)
+            });
+        let direction = pos.map(|_| Direction::from_ordinal(random(0, 5) as usize));
+        if let Err(e) = self.switch_map(Some(encounter.map_id), pos, direction, ui) {
+            warn!("error switching to random encounter map {}: {}", encounter.map_id, e);
+            return;
+        }
+        self.spawn_encounter(encounter, ui);
+    }
+
+    /// Places the critter groups of the random `encounter` around the dude. Ambushing groups
+    /// attack the dude and fighting groups attack the next group.
+    // wmSetupRandomEncounter(), wmSetupCritterObjs()
+    fn spawn_encounter(&mut self, encounter: RandomEncounter, ui: &mut Ui) {
+        let (groups, critter_groups) = {
+            let world = self.world.borrow();
+            let def = world.world_map().def();
+            let groups = def.encounter_tables[encounter.table]
+                .encounters[encounter.encounter].groups.clone();
+            let critter_groups: Vec<_> = groups.iter()
+                .map(|g| def.critter_groups[g.critters].clone())
+                .collect();
+            (groups, critter_groups)
+        };
+        let dude_obj = self.world.borrow().dude_obj().unwrap();
+        let dude_pos = self.world.borrow().objects().get(dude_obj).borrow().pos.unwrap();
+
+        let mut sids = Vec::new();
+        let mut spawned = Vec::with_capacity(groups.len());
+        for (group, critters) in groups.iter().zip(&critter_groups) {
+            let mut objs = Vec::new();
+            let total_ratio: u32 = critters.critters.iter().map(|c| c.ratio).sum();
+            if total_ratio > 0 {
+                let count = random(group.count.0 as i32, group.count.1 as i32) as u32;
+                let center = hex::go(dude_pos.point,
+                    Direction::from_ordinal(random(0, 5) as usize),
+                    random(ENCOUNTER_MIN_DISTANCE, ENCOUNTER_MAX_DISTANCE) as u32);
+                for i in 0..count {
+                    let mut roll = random(1, total_ratio as i32) as u32;
+                    let critter = critters.critters.iter()
+                        .find(|c| if roll <= c.ratio {
+                            true
+                        } else {
+                            roll -= c.ratio;
+                            false
+                        })
+                        .unwrap();
+                    let pos = formation_pos(critters.formation, dude_pos.point, center,
+                        critters.spacing, i);
+                    if let Some(obj) = self.spawn_encounter_critter(critter,
+                        pos.elevated(dude_pos.elevation), dude_pos.point, &mut sids)
+                    {
+                        objs.push(obj);
+                    }
+                }
+            }
+            spawned.push(objs);
+        }
+
+        {
+            let world = &mut self.world.borrow_mut();
+            let is_alive = |world: &World, h| world.objects().get(h).borrow().sub.critter()
+                .map(|c| !c.is_dead())
+                .unwrap_or(false);
+            let set_target = |world: &World, h, team_id: Option<u32>, target| {
+                let mut obj = world.objects().get(h).borrow_mut();
+                let critter = obj.sub.critter_mut().unwrap();
+                critter.combat.who_hit_me = Some(target);
+                if let Some(team_id) = team_id {
+                    critter.combat.team_id = team_id;
+                }
+            };
+            for (i, group) in groups.iter().enumerate() {
+                let attackers: Vec<_> = spawned[i].iter().cloned()
+                    .filter(|&h| is_alive(world, h))
+                    .collect();
+                let targets: Vec<_> = match group.attitude {
+                    GroupAttitude::Neutral => continue,
+                    GroupAttitude::Ambush => vec![dude_obj],
+                    GroupAttitude::Fighting => spawned.get(i + 1)
+                        .map(|g| g.iter().cloned().filter(|&h| is_alive(world, h)).collect())
+                        .unwrap_or_default(),
+                };
+                if attackers.is_empty() || targets.is_empty() {
+                    continue;
+                }
+                let team_id = if group.attitude == GroupAttitude::Fighting {
+                    let team_id = ENCOUNTER_FIGHTING_TEAM + i as u32;
+                    for (j, &target) in targets.iter().enumerate() {
+                        set_target(world, target, Some(team_id + 1),
+                            attackers[j % attackers.len()]);
+                    }
+                    Some(team_id)
+                } else {
+                    None
+                };
+                for (j, &attacker) in attackers.iter().enumerate() {
+                    set_target(world, attacker, team_id, targets[j % targets.len()]);
+                }
+                world.combat_requests.push(combat::Request::Attack {
+                    attacker: attackers[0],
+                    target: targets[0],
+                });
+            }
+        }
+
+        let ctx = &mut script::Context {
+            world: &mut self.world.borrow_mut(),
+            sequencer: &mut self.sequencer,
+            stats: &mut self.stats,
+            dialog: &mut self.dialog,
+            message_panel: self.message_panel,
+            ui,
+            map_id: self.map_id.unwrap(),
+        };
+        self.scripts.execute_procs(PredefinedProc::Start, ctx, |sid| sids.contains(&sid));
+    }
+
+    /// Creates the random encounter `critter` at the free tile nearest to `pos` facing the dude
+    /// at `dude_pos`. Returns `None` if there's no free tile around. The script of the critter
+    /// is instantiated and its SID is added to `sids`.
+    fn spawn_encounter_critter(&mut self, critter: &EncounterCritter, pos: EPoint,
+        dude_pos: Point, sids: &mut Vec<script::Sid>) -> Option<object::Handle>
+    {
+        let world = &mut self.world.borrow_mut();
+        let (fid, health, ai_packet, team_id) = match self.proto_db.proto(critter.pid) {
+            Ok(proto) => {
+                let c = proto.sub.critter();
+                (proto.fid,
+                    c.map(|c| c.base_stats[Stat::HitPoints] + c.bonus_stats[Stat::HitPoints]),
+                    c.map(|c| c.ai_packet).unwrap_or(0),
+                    c.map(|c| c.team_id).unwrap_or(0))
+            }
+            Err(e) => {
+                warn!("error loading encounter critter proto {:?}: {}", critter.pid, e);
+                return None;
+            }
+        };
+        let mut obj = Object::new(fid, critter.pid.into(), None);
+        obj.direction = hex::direction(pos.point, dude_pos);
+        if let Some(c) = obj.sub.critter_mut() {
+            c.health = health.unwrap_or(1);
+            c.combat.ai_packet = ai_packet;
+            c.combat.team_id = team_id;
+        }
+        let obj = world.insert_object(obj);
+
+        let free_pos = if let Some(v) = world.free_pos_near(obj, pos, 3) {
+            v
+        } else {
+            world.remove_object(obj);
+            return None;
+        };
+        world.set_object_pos(obj, free_pos);
+
+        for item in &critter.items {
+            let count = random(item.count.0 as i32, item.count.1 as i32) as usize;
+            if count == 0 {
+                continue;
+            }
+            let fid = match self.proto_db.proto(item.pid) {
+                Ok(proto) => proto.fid,
+                Err(e) => {
+                    warn!("error loading encounter item proto {:?}: {}", item.pid, e);
+                    continue;
+                }
+            };
+            let itemh = world.insert_object(Object::new(fid, item.pid.into(), None));
+            let itemh = world.add_to_inventory(obj, itemh, count);
+            if item.wielded {
+                world.objects_mut().equip(obj, itemh, EquipmentSlot::RightHand);
+            }
+        }
+
+        if critter.dead {
+            let mut objr = world.objects().get(obj).borrow_mut();
+            objr.flags.insert(Flag::NoBlock);
+            if let FrameId::Critter(fid) = objr.fid {
+                objr.fid = fid.with_anim(CritterAnim::FallBackSf).into();
+            }
+            let c = objr.sub.critter_mut().unwrap();
+            c.health = 0;
+            c.combat.damage_flags.insert(DamageFlag::Dead);
+        } else {
+            world.make_object_standing(obj);
+        }
+
+        if let Some(program_id) = critter.script {
+            let sid = self.scripts.instantiate_object_script(ScriptKind::Critter, program_id,
+                None, obj);
+            match sid {
+                Ok(sid) => {
+                    world.objects().get(obj).borrow_mut().script = Some((sid, program_id));
+                    sids.push(sid);
+                }
+                Err(e) => warn!("error instantiating encounter critter script {}: {}",
+                    program_id.val(), e),
+            }
+        }
+
+        Some(obj)
+    }
+
+    /// Shows the panel of the elevator `kind`. The level the dude is at is found by the current
+    /// map and the dude's elevation.
+    // elevator_select()
+    fn show_elevator(&mut self, kind: u32, ui: &mut Ui) {
+        if self.elevator.is_some() {
+            return;
+        }
+        let def = if let Some(v) = self.elevator_db.get(kind).filter(|d| !d.levels.is_empty()) {
+            v
+        } else {
+            warn!("no levels defined for elevator kind {}", kind);
+            return;
+        };
+        let elevation = self.world.borrow().get_dude_obj().unwrap().borrow().pos.unwrap()
+            .elevation;
+        let current_level = def.levels.iter()
+            .position(|l| Some(l.map_id) == self.map_id && l.elevation == elevation)
+            .unwrap_or(0) as u32;
+        match ElevatorWindow::show(ui, &self.frm_db, def, current_level) {
+            Ok(window) => self.elevator = Some((window, kind)),
+            Err(e) => warn!("error showing elevator panel: {}", e),
+        }
+    }
+
+    fn hide_elevator(&mut self, ui: &mut Ui) {
+        if let Some((window, _)) = self.elevator.take() {
+            window.hide(ui);
+        }
+    }
+
+    fn handle_elevator_command(&mut self, cmd: elevator::Command, ui: &mut Ui) {
+        match cmd {
+            elevator::Command::Pick(level) => {
+                let window = &mut self.elevator.as_mut().unwrap().0;
+                if level == window.current_level() {
+                    self.hide_elevator(ui);
+                } else {
+                    window.move_to(ui, level);
+                }
+            }
+            elevator::Command::Arrived(level) => {
+                let kind = self.elevator.as_ref().unwrap().1;
+                self.hide_elevator(ui);
+
+                let level = self.elevator_db.get(kind).unwrap().levels[level as usize];
+                let world = &mut self.world.borrow_mut();
+                let pos = world.hex_grid().from_linear_inv(level.tile_num)
+                    .elevated(level.elevation);
+                world.map_transition = Some(MapTransition::Map {
+                    map: Some(MapRef::Id(level.map_id)),
+                    pos: Some(pos),
+                    direction: None,
+                });
+            }
+        }
+    }
+
+    /// Replaces the game state with the one saved by the original game. The `dir` is the slot
+    /// directory with the map states and `data` is the contents of `SAVE.DAT`.
+    ///
+    /// Timer events, kill counts, the state of the party members and the world map aren't
+    /// imported.
+    // LoadSlot()
+    fn import_save_dat(&mut self, dir: &Path, data: &[u8], ui: &mut Ui) -> io::Result<()> {
+        fn map_name(file_name: &str) -> String {
+            let name = file_name.to_lowercase();
+            name.trim_end_matches(".sav").into()
+        }
+
+        let rd = &mut &data[..];
+        let header = asset::save::read_header(rd)?;
+        let global_var_count = asset::read_game_global_vars(
+            &mut self.fs.reader("data/vault13.gam")?)?.len();
+        let game = asset::save::read_game(rd, global_var_count)?;
+
+        let mut visited_maps = HashMap::new();
+        let mut current_map = None;
+        for file_name in &game.map_file_names {
+            let map = asset::save::unpack_map(std::fs::read(dir.join(file_name))?)?;
+            if file_name.eq_ignore_ascii_case(&header.map_file_name) {
+                current_map = Some(map);
+            } else {
+                visited_maps.insert(map_name(file_name), map);
+            }
+        }
+        let current_map = current_map.ok_or_else(|| Error::new(ErrorKind::InvalidData,
+            format!("missing current map {}", header.map_file_name)))?;
+
+        self.scripts.vars.global_vars = game.global_vars.into();
+        self.visited_maps = visited_maps;
+
+        self.load_map(&map_name(&header.map_file_name), &mut &current_map[..], &[], ui)?;
+
+        let dude_obj = MapReader {
+            reader: rd,
+            objects: self.world.borrow_mut().objects_mut(),
+            proto_db: &self.proto_db,
+            frm_db: &self.frm_db,
+            scripts: &mut self.scripts,
+        }.read_object()?;
+
+        let party_member_pids = asset::party::read_member_pids(
+            &mut self.fs.reader("data/party.txt")?)?;
+        let dude = asset::save::read_dude(rd, party_member_pids.len())?;
+        self.stats.load_save_dat(&dude, &party_member_pids);
+
+        {
+            let world = &mut self.world.borrow_mut();
+
+            if world.objects().get(dude_obj).borrow().pid != ObjectProtoId::Dude {
+                return Err(Error::new(ErrorKind::InvalidData, "invalid dude object"));
+            }
+            for direction in Direction::iter() {
+                let fid = world.objects().get(dude_obj).borrow().fid;
+                if let Some(fid) = fid.with_direction(Some(direction)) {
+                    self.frm_db.get(fid)?;
+                }
+            }
+            world.rebuild_light_grid();
+            world.set_dude_obj(dude_obj);
+            world.dude_name = header.dude_name;
+            world.game_time = header.game_time;
+            world.dude_sneaking = dude.sneaking;
+            world.ambient_light = 0x10000;
+            world.world_map_mut().reset();
+
+            world.camera_mut().look_at(dude.view_center);
+
+            world.loading_game = true;
+        }
+        self.game_time_frac = Duration::from_secs(0);
+
+        self.enter_map(ui);
+        self.world.borrow_mut().loading_game = false;
+
+        Ok(())
+    }
+
+    fn new_dude_obj(&self, pos: Option<EPoint>, world: &World) -> Object {
+        let art = if self.stats.dude_base_stat(Stat::Gender) == 0 {
+            "hmjmps"
+        } else {
+            "hfjmps"
+        };
+        let fid = self.frm_db.find_id(EntityKind::Critter, art)
+            .and_then(|id| FrameId::new_critter(None, CritterAnim::Stand, WeaponKind::Unarmed, id))
+            .unwrap();
+        let mut dude_obj = Object::new(fid, ObjectProtoId::Dude, pos);
+        dude_obj.direction = Direction::NE;
+        dude_obj.light_emitter = LightEmitter {
+            intensity: 0x10000,
+            radius: 4,
+        };
+        dude_obj.sub.critter_mut().unwrap().health =
+            self.stats.stat(Stat::HitPoints, &dude_obj, world);
+        dude_obj
+    }
+
+    /// Shows the character editor for creating the dude. The game starts on `map_name` once
+    /// the character is done.
+    pub fn start_new_game(&mut self, map_name: &str, ui: &mut Ui) {
+        assert!(self.character.is_none());
+        self.character = Some(CharacterEditor {
+            window: CharacterWindow::show(ui, character::Mode::Create),
+            new_game_map: Some(map_name.into()),
+            name: "None".into(),
+            char_points: DEFAULT_CHAR_POINTS,
+            editing_name: false,
+            premade: 0,
+        });
+        self.load_premade(0, ui);
+        self.sync_character_window(ui);
+    }
+
+    fn show_character_sheet(&mut self, ui: &mut Ui) {
+        if self.character.is_some() || self.world.borrow().dude_obj().is_none() {
+            return;
+        }
+        self.character = Some(CharacterEditor {
+            window: CharacterWindow::show(ui, character::Mode::View),
+            new_game_map: None,
+            name: self.world.borrow().dude_name.clone(),
+            char_points: 0,
+            editing_name: false,
+            premade: 0,
+        });
+        self.sync_character_window(ui);
+    }
+
+    fn hide_character_window(&mut self, ui: &mut Ui) {
+        if let Some(editor) = self.character.take() {
+            editor.window.hide(ui);
+        }
+    }
+
+    fn load_premade(&mut self, index: usize, ui: &mut Ui) {
+        let path = PREMADES[index];
+        let editor = self.character.as_mut().unwrap();
+        editor.premade = index;
+
+        let gcd = match self.fs.reader(&format!("{}.gcd", path))
+            .and_then(|mut rd| gcd::read_gcd(&mut rd))
+        {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("couldn't read premade character {}: {}", path, e);
+                return;
+            }
+        };
+        self.stats.load_gcd(&gcd);
+        editor.name = gcd.name;
+        editor.char_points = gcd.char_points;
+
+        let mut bio = Vec::new();
+        if let Ok(mut rd) = self.fs.reader(&format!("{}.bio", path)) {
+            if let Err(e) = rd.read_to_end(&mut bio) {
+                warn!("couldn't read premade character bio {}: {}", path, e);
+            }
+        }
+        editor.window.set_description(ui, &editor.name, bio.as_ref());
+    }
+
+    fn sync_character_window(&self, ui: &mut Ui) {
+        let editor = self.character.as_ref().unwrap();
+        let mode = editor.window.mode();
+        let world = self.world.borrow();
+
+        // The dude object doesn't exist until the character is created.
+        let dude_ref;
+        let new_dude;
+        let dude_obj = if let Some(dude_obj) = world.dude_obj() {
+            dude_ref = world.objects().get(dude_obj).borrow();
+            &*dude_ref
+        } else {
+            new_dude = self.new_dude_obj(None, &world);
+            &new_dude
+        };
+        let stat = |stat| self.stats.stat(stat, dude_obj, &world);
+
+        let mut name = editor.name.clone();
+        if editor.editing_name {
+            name.push(b'_');
+        }
+
+        let primary_stats = character::PRIMARY_STATS.iter()
+            .map(|&s| (stat(s), self.stats.stat_level_description(stat(s))))
+            .collect();
+
+        // TODO take the text from editor.msg
+        let info = match mode {
+            character::Mode::Create => vec![
+                format!("Char Points: {}", editor.char_points).into(),
+                format!("Tag Skills: {}", self.stats.tagged_skills().len()).into(),
+            ],
+            character::Mode::View => {
+                let level = self.stats.pc_stat(PcStat::Level);
+                let mut info: Vec<BString> = [PcStat::Level, PcStat::Experience].iter()
+                    .map(|&s| {
+                        let mut line = self.stats.pc_stat_name(s).to_owned();
+                        line.push_str(format!(": {}", self.stats.pc_stat(s)));
+                        line
+                    })
+                    .collect();
+                info.push(format!("Next Level: {}", stats::level_experience(level + 1)).into());
+                info.push(format!("Skill Points: {}",
+                    self.stats.pc_stat(PcStat::UnspentSkillPoints)).into());
+                info
+            }
+        };
+
+        let health = dude_obj.sub.critter().unwrap().health;
+        let derived_stats = [
+            Stat::HitPoints,
+            Stat::ArmorClass,
+            Stat::ActionPoints,
+            Stat::CarryWeight,
+            Stat::MeleeDmg,
+            Stat::DmgResist,
+            Stat::PoisonResist,
+            Stat::RadResist,
+            Stat::Sequence,
+            Stat::HealRate,
+            Stat::CritChance,
+        ].iter()
+            .map(|&s| {
+                let mut line = self.stats.stat_name(s).to_owned();
+                line.push_str(if s == Stat::HitPoints {
+                    format!(" {}/{}", health, stat(s))
+                } else {
+                    format!(" {}", stat(s))
+                });
+                line
+            })
+            .collect();
+
+        let traits = match mode {
+            character::Mode::Create => Trait::iter()
+                .map(|tr| (self.stats.trait_name(tr), self.stats.has_trait(tr)))
+                .collect(),
+            character::Mode::View => self.stats.traits().iter()
+                .map(|&tr| (self.stats.trait_name(tr), false))
+                .chain(Perk::iter()
+                    .filter(|&perk| self.stats.has_perk(perk, ObjectProtoId::Dude))
+                    .map(|perk| (self.stats.perk_name(perk), false)))
+                .collect(),
+        };
+
+        let skills = Skill::iter()
+            .map(|skill| (self.stats.skill_name(skill), self.stats.skill(skill, dude_obj, &world),
+                self.stats.is_skill_tagged(skill)))
+            .collect();
+
+        editor.window.sync(ui, &character::Sheet {
+            name: &name,
+            age: stat(Stat::Age),
+            female: stat(Stat::Gender) == 1,
+            primary_stats,
+            info,
+            derived_stats,
+            traits,
+            skills,
+        });
+    }
+
+    fn handle_character_command(&mut self, cmd: character::Command, ui: &mut Ui) {
+        use character::Command::*;
+
+        let editor = if let Some(editor) = &mut self.character {
+            editor
+        } else {
+            return;
+        };
+        match cmd {
+            StatUp(stat) | StatDown(stat) => {
+                let amount = if cmd == StatUp(stat) { 1 } else { -1 };
+                if amount < 0 || editor.char_points > 0 {
+                    let value = self.stats.dude_base_stat(stat);
+                    self.stats.set_dude_base_stat(stat, value + amount);
+                    editor.char_points -= self.stats.dude_base_stat(stat) - value;
+                }
+                editor.window.set_description(ui, self.stats.stat_name(stat),
+                    self.stats.stat_description(stat));
+            }
+            EditName => editor.editing_name = !editor.editing_name,
+            NextAge => {
+                let age = self.stats.dude_base_stat(Stat::Age);
+                self.stats.set_dude_base_stat(Stat::Age,
+                    if age >= MAX_AGE { MIN_AGE } else { age + 1 });
+            }
+            ToggleGender => {
+                let gender = self.stats.dude_base_stat(Stat::Gender);
+                self.stats.set_dude_base_stat(Stat::Gender, 1 - gender);
+            }
+            NextPremade => {
+                let index = (editor.premade + 1) % PREMADES.len();
+                self.load_premade(index, ui);
+            }
+            Perks => {
+                self.show_perk_select(ui);
+                return;
+            }
+            Done if editor.window.mode() == character::Mode::Create => {
+                // TODO take the text from editor.msg
+                let error = if editor.char_points > 0 {
+                    Some("You must use all character points.")
+                } else if self.stats.tagged_skills().len() < 3 {
+                    Some("You must select all tag skills.")
+                } else {
+                    None
+                };
+                if let Some(error) = error {
+                    editor.window.set_description(ui, "Done".into(), error.into());
+                    return;
+                }
+                let map_name = editor.new_game_map.take().unwrap();
+                let name = editor.name.clone();
+                self.hide_character_window(ui);
+                self.new_game(&map_name, &name, ui);
+                return;
+            }
+            Done | Cancel => {
+                self.hide_character_window(ui);
+                return;
+            }
+        }
+        self.sync_character_window(ui);
+    }
+
+    fn toggle_trait(&mut self, index: u32, ui: &mut Ui) {
+        if let Some(tr) = Trait::try_from_ordinal(index as usize) {
+            if !self.stats.remove_trait(tr) {
+                self.stats.add_trait(tr);
+            }
+            self.character.as_ref().unwrap().window.set_description(ui,
+                self.stats.trait_name(tr), self.stats.trait_description(tr));
+            self.sync_character_window(ui);
+        }
+    }
+
+    fn toggle_tagged_skill(&mut self, index: u32, ui: &mut Ui) {
+        let skill = Skill::from_ordinal(index as usize);
+        if !self.stats.untag_skill(skill) {
+            self.stats.tag_skill(skill);
+        }
+        self.character.as_ref().unwrap().window.set_description(ui,
+            self.stats.skill_name(skill), self.stats.skill_description(skill));
+        self.sync_character_window(ui);
+    }
+
+    fn handle_character_event(&mut self, event: &Event, ui: &mut Ui) -> bool {
+        let editor = self.character.as_mut().unwrap();
+        match event {
+            Event::TextInput { text, .. } if editor.editing_name => {
+                for c in text.bytes().filter(|&c| c.is_ascii_graphic() || c == b' ') {
+                    if editor.name.len() < NAME_MAX_LEN {
+                        editor.name.push(c);
+                    }
+                }
+            }
+            Event::KeyDown { keycode: Some(Keycode::Backspace), .. } if editor.editing_name => {
+                editor.name.pop();
+            }
+            Event::KeyDown { keycode: Some(Keycode::Return), .. } if editor.editing_name => {
+                editor.editing_name = false;
+            }
+            Event::KeyDown { keycode: Some(Keycode::Escape), .. }
+                if editor.window.mode() == character::Mode::View =>
+            {
+                self.hide_character_window(ui);
+                return true;
+            }
+            Event::KeyDown { keycode: Some(Keycode::Escape), .. } | Event::Quit { .. } => {
+                return false;
+            }
+            _ => return true,
+        }
+        self.sync_character_window(ui);
+        true
+    }
+
+
+    fn handle_action(
+        &mut self,
+        ui: &mut Ui,
+        obj: object::Handle,
+        action: Action,
+    ) {
+        match action {
+            Action::Cancel => {},
+            Action::Drop | Action::Unload => unreachable!(),
+            Action::Inventory => {
+                let dude_obj = self.world.borrow().dude_obj().unwrap();
+                if obj == dude_obj {
+                    self.show_inventory(ui);
+                } else {
+                    self.action_loot(dude_obj, obj, ui);
+                }
+            }
+            Action::Look => {
+                self.dude_examine_object(obj, ui);
+            }
+            Action::Push => {
+                // TODO
+            }
+            Action::Rotate => {
+                let world = self.world.borrow_mut();
+                let mut obj = world.objects().get(obj).borrow_mut();
+                if let Some(signal) = obj.sequence.take() {
+                    signal.cancel();
+                }
+                obj.direction = obj.direction.rotate_cw();
+            }
+            Action::Talk => {
+                let talker = self.world.borrow().dude_obj().unwrap();
+                self.action_talk(talker, obj, ui);
+            }
+            Action::UseHand => {
+                let is_container = self.world.borrow().objects().item_kind(obj)
+                    == Some(ItemKind::Container);
+                if is_container {
+                    let dude_obj = self.world.borrow().dude_obj().unwrap();
+                    self.action_loot(dude_obj, obj, ui);
+                } else {
+                    let dude_obj = self.world.borrow().dude_obj().unwrap();
+                    self.action_use_obj(dude_obj, obj, ui);
+                }
+            }
+            Action::UseSkill => self.show_skilldex(Some(obj), ui),
+        }
+    }
+
+    fn handle_seq_events(&mut self, ui: &mut Ui) {
+        use sequence::Event::*;
+        let mut events = std::mem::replace(&mut self.seq_events, Vec::new());
+        let mut dude_moved = false;
+        for event in events.drain(..) {
+            match event {
+                ObjectMoved { obj, new_pos, .. } => {
+                    let world = &mut self.world.borrow_mut();
+                    if world.combat.is_some() {
+                        if let Some(critter) = world.objects().get(obj).borrow_mut()
+                            .sub.critter_mut()
+                        {
+                            critter.combat.ap -= combat::MOVE_AP_COST;
+                        }
+                    } else if Some(obj) == world.dude_obj() {
+                        self.check_exit_area(new_pos, world);
+                        dude_moved = true;
+                    }
+                }
+                Attack { attacker, target, location } => {
+                    {
+                        let world = &mut self.world.borrow_mut();
+                        if let Some(critter) = world.objects().get(target).borrow_mut()
+                            .sub.critter_mut()
+                        {
+                            critter.combat.who_hit_me = Some(attacker);
+                        }
+                        if let Some(combat) = &mut world.combat {
+                            combat.add(target);
+                        }
+                    }
+                    self.resolve_attack(attacker, target, location, ui);
+                }
+                Loot { looter, target } => {
+                    self.loot(looter, target, ui);
+                }
+                Talk { talker, talked } => {
+                    self.talk(talker, talked, ui);
+                }
+                UseObj { user, target } => {
+                    self.use_obj(user, target, ui);
+                }
+                UseSkill { user, target, skill } => {
+                    self.use_skill(user, target, skill, ui);
+                }
+                OpenDoor { door: obj } => {
+                    door::start(obj, true, &self.world.borrow(), &mut self.sequencer);
+                }
+                _ => {}
+            }
+        }
+        std::mem::replace(&mut self.seq_events, events);
+        if dude_moved {
+            self.follow_dude();
+        }
+    }
+
+    /// Makes the party members that fell behind the dude catch up. Members ordered to
+    /// stay where they are don't follow.
+    fn follow_dude(&mut self) {
+        let world = self.world.borrow();
+        if world.combat.is_some() {
+            return;
+        }
+        let dude_obj = world.dude_obj().unwrap();
+        let dude_pos = world.objects().get(dude_obj).borrow().pos.unwrap();
+        for member in world.party().members() {
+            let obj = member.obj;
+            let distance = world.ai_packet(obj)
+                .map(|p| p.distance)
+                .unwrap_or(AiDistance::StayClose);
+            let follow_distance = if let Some(v) = party::follow_distance(distance) {
+                v
+            } else {
+                continue;
+            };
+            let pos = {
+                let objr = world.objects().get(obj).borrow();
+                let can_move = !objr.has_running_sequence()
+                    && objr.sub.critter().map(|c| !c.is_dead()).unwrap_or(false);
+                match objr.pos {
+                    Some(pos) if can_move && pos.elevation == dude_pos.elevation => pos.point,
+                    _ => continue,
+                }
+            };
+            let distance = hex::distance(pos, dude_pos.point);
+            if distance <= follow_distance {
+                continue;
+            }
+            // The path ends next to the dude.
+            let steps = world.path_for_object(obj, dude_pos.point, true, true)
+                .map(|p| p.len().saturating_sub(follow_distance as usize - 1))
+                .unwrap_or(0);
+            if steps == 0 {
+                continue;
+            }
+            let anim = if distance > PARTY_RUN_DISTANCE {
+                CritterAnim::Running
+            } else {
+                CritterAnim::Walk
+            };
+            let (seq, signal) = Move::new(obj, dude_pos.point, anim)
+                .max_path_len(steps)
+                .cancellable();
+            world.objects().get(obj).borrow_mut().sequence = Some(signal);
+            self.sequencer.start(seq.then(Stand::new(obj)));
+        }
+    }
+
+    fn actions(&self, objh: object::Handle) -> Vec<Action> {
+        let mut r = Vec::new();
+        let world = self.world.borrow();
+        let obj = world.objects().get(objh).borrow();
+        match obj.kind() {
+            EntityKind::Critter => {
+                if Some(objh) == world.dude_obj() {
+                    r.push(Action::Rotate);
+                } else {
+                    if world.objects().can_talk_to(objh) {
+                        if world.combat.is_none() {
+                            r.push(Action::Talk);
+                        }
+                    } else if !self.proto_db.proto(obj.pid.proto_id().unwrap()).unwrap()
+                        .sub.critter().unwrap()
+                        .flags.contains(CritterFlag::NoSteal)
+                    {
+                        r.push(Action::UseHand);
+                    }
+                    if world.objects().can_push(world.dude_obj().unwrap(), objh,
+                        &self.scripts, world.combat.is_some())
+                    {
+                        r.push(Action::Push);
+                    }
+                }
+                r.extend_from_slice(&[Action::Look, Action::Inventory, Action::UseSkill])
+            }
+            EntityKind::Item => {
+                r.extend_from_slice(&[Action::UseHand, Action::Look]);
+                if world.objects().item_kind(objh) == Some(ItemKind::Container) {
+                    r.extend_from_slice(&[Action::UseSkill, Action::Inventory]);
+                }
+            }
+            EntityKind::Scenery => {
+                if world.objects().can_use(objh) {
+                    r.push(Action::UseHand)
+                }
+                r.extend_from_slice(&[Action::Look, Action::Inventory, Action::UseSkill])
+            }
+            EntityKind::Wall => {
+                r.push(Action::Look);
+                if world.objects().can_use(objh) {
+                    r.push(Action::UseHand)
+                }
+            }
+            _ => {}
+        }
+        if r.len() > 0 {
+            r.push(Action::Cancel)
+        }
+        r
+    }
+
+    fn look_at_object(&mut self, looker: object::Handle, looked: object::Handle, ui: &mut Ui)
+        -> Option<BString>
+    {
+        let sid = {
+            let world = self.world.borrow();
+            let lookero = world.objects().get(looker).borrow();
+            let lookedo = world.objects().get(looked).borrow();
+            if lookero.sub.critter().map(|c| c.is_dead()).unwrap_or(true)
+                // TODO This is only useful for mapper?
+                || lookedo.kind() == EntityKind::SqrTile
+                || lookedo.pid != ObjectProtoId::Dude &&
+                    (lookedo.pid.proto_id().is_none()
+                        || self.proto_db.proto(lookedo.pid.proto_id().unwrap()).is_err())
+            {
+                return None;
+            }
+            lookedo.script.map(|(v, _)| v)
+        };
+
+        if_chain! {
+            if let Some(sid) = sid;
+            if let Some(r) = self.scripts.execute_predefined_proc(sid, PredefinedProc::LookAt,
+                &mut script::Context {
+                    world: &mut self.world.borrow_mut(),
+                    sequencer: &mut self.sequencer,
+                    stats: &mut self.stats,
+                    dialog: &mut self.dialog,
+                    ui,
+                    message_panel: self.message_panel,
+                    map_id: self.map_id.unwrap(),
+                });
+            then {
+                assert!(r.suspend.is_none(), "can't suspend");
+                if r.script_overrides {
+                    return None;
+                }
+            }
+        }
+
+        let world = self.world.borrow();
+        let lookedo = world.objects().get(looked).borrow();
+        let msg_id = if lookedo.sub.critter().map(|c| c.is_dead()).unwrap_or(false) {
+            491 + random(0, 1)
+        } else {
+            490
+        };
+        if_chain! {
+            if let Some(msg) = self.proto_db.messages().get(msg_id);
+            if let Some(name) = world.object_name(looked);
+            then {
+                Some(sprintf(&msg.text, &[&*name]))
+            } else {
+                None
+            }
+        }
+    }
+
+    fn dude_look_at_object(&mut self, obj: object::Handle, ui: &mut Ui) {
+        let dude_obj = self.world().borrow().dude_obj().unwrap();
+        if let Some(msg) = self.look_at_object(dude_obj, obj, ui) {
+            self.push_message(&msg, ui);
+        }
+    }
+
+    // obj_examine_func()
+    fn examine_object(&mut self, examiner: object::Handle, examined: object::Handle, ui: &mut Ui)
+        -> Vec<BString>
+    {
+        let sid = {
+            let world = self.world.borrow();
+            let examinero = world.objects().get(examiner).borrow();
+            let examinedo = world.objects().get(examined).borrow();
+            if examinero.sub.critter().map(|c| c.is_dead()).unwrap_or(false)
+                // TODO This is only useful for mapper?
+                || examinedo.kind() == EntityKind::SqrTile
+            {
+                return Vec::new();
+            }
+            examinedo.script.map(|(v, _)| v)
+        };
+
+        let script_overrides = if_chain! {
             if let Some(sid) = sid;
             if let Some(r) = self.scripts.execute_predefined_proc(sid, PredefinedProc::Description,
                 &mut script::Context {
                     world: &mut self.world.borrow_mut(),
                     sequencer: &mut self.sequencer,
+                    stats: &mut self.stats,
+                    dialog: &mut self.dialog,
+                    ui,
+                    message_panel: self.message_panel,
+                    map_id: self.map_id.unwrap(),
+                });
+            then {
+                assert!(r.suspend.is_none(), "can't suspend");
+                r.script_overrides
+            } else {
+                false
+            }
+        };
+
+        let mut r = Vec::new();
+
+        if !script_overrides {
+            let world = self.world.borrow();
+            let examinedo = world.objects().get(examined).borrow();
+            if !examinedo.sub.critter().map(|c| c.is_dead()).unwrap_or(false) {
+                let descr = if let Some(descr) = examinedo.pid.proto_id()
+                    .and_then(|pid| self.proto_db.description(pid).unwrap())
+                    .filter(|desr| {
+                        // Compare to "<None>".
+                        desr != &self.proto_db.messages().get(10).unwrap().text
+                    })
+                {
+                    descr.to_owned()
+                } else {
+                    self.proto_db.messages().get(493).unwrap().text.clone()
+                };
+                r.push(descr);
+            }
+        }
+
+        // TODO critter state/hp, weapon/ammo description, car info etc
+
+        r
+    }
+
+    fn dude_examine_object(&mut self, obj: object::Handle, ui: &mut Ui) {
+        let dude_obj = self.world().borrow().dude_obj().unwrap();
+        for msg in self.examine_object(dude_obj, obj, ui) {
+            self.push_message(&msg, ui);
+        }
+    }
+
+    fn push_message(&self, msg: &bstr, ui: &mut Ui) {
+        let mut mp = ui.widget_mut::<MessagePanel>(self.message_panel);
+        let mut m = BString::new();
+        m.push(BULLET);
+        m.push_str(msg);
+        mp.push_message(m);
+    }
+
+    // action_talk_to()
+    fn action_talk(&mut self, talker: object::Handle, talked: object::Handle, ui: &mut Ui) {
+        // TODO handle combat state
+
+        {
+            let world = self.world.borrow();
+            let objs = world.objects();
+
+            if objs.distance(talker, talked).unwrap() >= 9 || // TODO this value is different (12) in can_talk2()
+                objs.is_shot_blocked(talker, talked)
+            {
+                // TODO original cancels only Walk/Run animation, is this important?
+                objs.get(talker).borrow_mut().cancel_sequence();
+
+                let dest = objs.get(talked).borrow().pos.unwrap().point;
+                // TODO (move_to_object()) shorten the move path by 1 tile if the `talked` is MultiHex
+                let (seq, cancel) = Move::new(talker, dest, CritterAnim::Running).cancellable();
+                objs.get(talker).borrow_mut().sequence = Some(cancel);
+                self.sequencer.start(seq
+                    .then(Stand::new(talker))
+                    .then(PushEvent::new(sequence::Event::Talk { talker, talked })));
+                return;
+            }
+        }
+
+        self.talk(talker, talked, ui);
+    }
+
+    // talk_to(), gdialogEnter()
+    fn talk(&mut self, talker: object::Handle, talked: object::Handle, ui: &mut Ui) {
+        if self.world.borrow().objects().can_talk_now(talker, talked) {
+            let world = &mut self.world.borrow_mut();
+            // TODO optimize this.
+            for obj in world.objects().iter() {
+                world.objects().get(obj).borrow_mut().cancel_sequence();
+            }
+            self.sequencer.cleanup(&mut sequence::Cleanup {
+                world,
+            });
+            let script = world.objects().get(talked).borrow().script;
+            if let Some((sid, _)) = script {
+                match self.scripts.execute_predefined_proc(sid, PredefinedProc::Talk,
+                    &mut script::Context {
+                        world,
+                        sequencer: &mut self.sequencer,
+                        stats: &mut self.stats,
+                        dialog: &mut self.dialog,
+                        ui,
+                        message_panel: self.message_panel,
+                        map_id: self.map_id.unwrap(),
+                    }).and_then(|r| r.suspend)
+                    {
+                        None | Some(Suspend::GsayEnd) => {}
+                    }
+            }
+        } else {
+            assert_eq!(talker, self.world.borrow().dude_obj().unwrap());
+            let msg = &self.misc_msgs.get(2000).unwrap().text;
+            self.push_message(&msg, ui);
+        }
+    }
+
+    fn finish_dialog(&mut self, ui: &mut Ui) {
+        self.scripts.resume(&mut script::Context {
+            ui,
+            world: &mut self.world.borrow_mut(),
+            sequencer: &mut self.sequencer,
+            stats: &mut self.stats,
+            dialog: &mut self.dialog,
+            message_panel: self.message_panel,
+            map_id: self.map_id.unwrap(),
+        }).assert_no_suspend();
+        assert!(!self.scripts.can_resume());
+        // TODO call MapUpdate (multiple times?), see gdialogEnter()
+    }
+
+    // gdialog_barter_pressed()
+    fn show_barter(&mut self, barter_mod: i32, ui: &mut Ui) {
+        let dude_obj = self.world.borrow().dude_obj().unwrap();
+        let dialog = self.dialog.as_mut().unwrap();
+        let barter_mod = dialog.barter_mod + barter_mod;
+        dialog.show_barter(ui, dude_obj, barter_mod);
+        self.sync_barter(ui);
+    }
+
+    fn barter_pricing(&self) -> Pricing {
+        let barter = self.dialog.as_ref().unwrap().barter.as_ref().unwrap();
+        let world = self.world.borrow();
+        let objects = world.objects();
+        let player = objects.get(barter.object(barter::Side::Player)).borrow();
+        let trader = objects.get(barter.object(barter::Side::Trader)).borrow();
+        Pricing {
+            player_skill: self.stats.party_best_skill(Skill::Barter, &world),
+            trader_skill: self.stats.skill(Skill::Barter, &trader, &world),
+            barter_mod: barter.barter_mod,
+            master_trader: self.stats.has_perk(Perk::MasterTrader, player.pid),
+        }
+    }
+
+    /// Returns the value of the player's offer and the price of the trader's offer.
+    fn barter_totals(&self) -> EnumMap<barter::Side, u32> {
+        let pricing = self.barter_pricing();
+        let barter = self.dialog.as_ref().unwrap().barter.as_ref().unwrap();
+        let world = self.world.borrow();
+        let objects = world.objects();
+        let total = |side| {
+            let owner = objects.get(barter.object(side)).borrow();
+            let mut cost = 0;
+            let mut caps = 0;
+            for &item in barter.offer(side) {
+                let count = owner.inventory.count(item) as u32;
+                cost += objects.item_cost(item) * count;
+                if objects.get(item).borrow().pid == ProtoId::BOTTLE_CAPS.into() {
+                    caps += count;
+                } else {
+                    caps += objects.caps_total(item) * count;
+                }
+            }
+            (cost, caps)
+        };
+        let (player_cost, _) = total(barter::Side::Player);
+        let (trader_cost, trader_caps) = total(barter::Side::Trader);
+        enum_map! {
+            barter::Side::Player => player_cost,
+            barter::Side::Trader => pricing.price(trader_cost, trader_caps),
+        }
+    }
+
+    fn sync_barter(&mut self, ui: &mut Ui) {
+        let totals = self.barter_totals();
+        let barter = self.dialog.as_mut().unwrap().barter.as_mut().unwrap();
+        barter.sync(ui, &self.world.borrow(), totals);
+    }
+
+    // barter_attempt_transaction()
+    fn barter_offer(&mut self, ui: &mut Ui) {
+        let totals = self.barter_totals();
+        let (player, trader, player_offer, trader_offer) = {
+            let barter = self.dialog.as_ref().unwrap().barter.as_ref().unwrap();
+            (barter.object(barter::Side::Player), barter.object(barter::Side::Trader),
+                barter.offer(barter::Side::Player).to_vec(),
+                barter.offer(barter::Side::Trader).to_vec())
+        };
+        if player_offer.is_empty() && trader_offer.is_empty() {
+            return;
+        }
+
+        // TODO take the texts from inventry.msg
+        let reply = {
+            let world = self.world.borrow();
+            let objects = world.objects();
+            let weight = |owner, items: &[object::Handle]| -> u32 {
+                let ownero = objects.get(owner).borrow();
+                items.iter()
+                    .map(|&i| objects.item_weight(i) * ownero.inventory.count(i) as u32)
+                    .sum()
+            };
+            let carry_weight = self.stats.stat(Stat::CarryWeight,
+                &objects.get(player).borrow(), &world);
+            let player_weight = objects.inventory_weight(player)
+                - weight(player, &player_offer) + weight(trader, &trader_offer);
+            if player_weight > cmp::max(carry_weight, 0) as u32 {
+                Some("You cannot carry that much.")
+            } else if totals[barter::Side::Player] < totals[barter::Side::Trader] {
+                Some("No, your offer is not good enough.")
+            } else {
+                None
+            }
+        };
+        if let Some(reply) = reply {
+            self.dialog.as_ref().unwrap().set_reply(ui, reply);
+            return;
+        }
+
+        {
+            let world = &mut self.world.borrow_mut();
+            for &(src, dst, ref items) in &[(player, trader, player_offer), (trader, player, trader_offer)] {
+                for &item in items {
+                    let count = world.objects().get(src).borrow().inventory.count(item);
+                    if let Some((item, count)) = world.objects_mut()
+                        .remove_from_inventory(src, item, count)
+                    {
+                        world.add_to_inventory(dst, item, count);
+                    }
+                }
+            }
+        }
+        let dialog = self.dialog.as_mut().unwrap();
+        dialog.barter.as_mut().unwrap().clear_offers();
+        dialog.set_reply(ui, "Ok, that's a good trade.");
+        self.sync_barter(ui);
+    }
+
+    fn handle_barter_command(&mut self, cmd: barter::Command, ui: &mut Ui) {
+        match cmd {
+            barter::Command::ScrollUp(table) => self.dialog.as_ref().unwrap()
+                .barter.as_ref().unwrap().scroll(ui, table, true),
+            barter::Command::ScrollDown(table) => self.dialog.as_ref().unwrap()
+                .barter.as_ref().unwrap().scroll(ui, table, false),
+            barter::Command::Offer => self.barter_offer(ui),
+            barter::Command::Talk => {
+                let dialog = self.dialog.as_mut().unwrap();
+                dialog.hide_barter(ui);
+                if dialog.is_empty() {
+                    self.finish_dialog(ui);
+                }
+            }
+        }
+    }
+
+    fn handle_combat_control_command(&mut self, cmd: combat_control::Command, ui: &mut Ui) {
+        let dialog = if let Some(v) = &mut self.dialog {
+            v
+        } else {
+            return;
+        };
+        match cmd {
+            combat_control::Command::Show => dialog.show_combat_control(ui),
+            combat_control::Command::Change(order) => {
+                let world = &mut self.world.borrow_mut();
+                let obj = dialog.obj();
+                let pid = world.objects().get(obj).borrow().pid;
+                if let Some(packet) = world.ai_packet(obj) {
+                    world.party_mut().change_order(obj, pid, order, &packet);
+                }
+            }
+            combat_control::Command::Done => {
+                dialog.hide_combat_control(ui);
+                return;
+            }
+        }
+        self.sync_combat_control(ui);
+    }
+
+    /// Shows the orders of the party member being talked to in the combat control panel.
+    fn sync_combat_control(&self, ui: &mut Ui) {
+        let dialog = self.dialog.as_ref().unwrap();
+        let panel = if let Some(v) = &dialog.combat_control {
+            v
+        } else {
+            return;
+        };
+        let world = self.world.borrow();
+        let obj = dialog.obj();
+        let packet = world.ai_packet(obj);
+        // Whether the member is ordered to use the area attack mode of its AI packet.
+        let area_attack_no_pref = world.party().member(obj)
+            .filter(|m| m.orders.area_attack_mode.is_none())
+            .and_then(|_| world.party().def(world.objects().get(obj).borrow().pid))
+            .map(|d| d.area_attack_modes.contains(&None))
+            .unwrap_or(false);
+        for order in Order::iter() {
+            let value = packet.as_ref()
+                .map(|p| match order {
+                    Order::Distance => p.distance.name(),
+                    Order::BestWeapon => p.best_weapon.name(),
+                    Order::AreaAttackMode if area_attack_no_pref => "no_pref",
+                    Order::AreaAttackMode => p.area_attack_mode.name(),
+                })
+                .unwrap_or("-")
+                .replace('_', " ");
+            panel.set_value(ui, order, value.as_bytes().into());
+        }
+    }
+
+    fn create_scroll_areas(rect: Rect, ui: &mut Ui) -> EnumMap<ScrollDirection, ui::Handle> {
+        let mut new = |rect, cur, curx| {
+            let win = ui.new_window(rect, None);
+            ui.new_widget(win, Rect::with_size(0, 0, rect.width(), rect.height()), None, None,
+                ScrollArea::new(cur, curx, Duration::from_millis(0), Duration::from_millis(30)))
+        };
+        use ui::Cursor::*;
+        use ScrollDirection::*;
+        enum_map! {
+            N => new(
+                Rect::new(rect.left + 1, rect.top, rect.right - 1, rect.top + 1),
+                ScrollNorth, ScrollNorthX),
+            NE => new(
+                Rect::new(rect.right - 1, rect.top, rect.right, rect.top + 1),
+                ScrollNorthEast, ScrollNorthEastX),
+            E => new(
+                Rect::new(rect.right - 1, rect.top + 1, rect.right, rect.bottom - 1),
+                ScrollEast, ScrollEastX),
+            SE => new(
+                Rect::new(rect.right - 1, rect.bottom - 1, rect.right, rect.bottom),
+                ScrollSouthEast, ScrollSouthEastX),
+            S => new(
+                Rect::new(rect.left + 1, rect.bottom - 1, rect.right - 1, rect.bottom),
+                ScrollSouth, ScrollSouthX),
+            SW => new(
+                Rect::new(rect.left, rect.bottom - 1, rect.left + 1, rect.bottom),
+                ScrollSouthWest, ScrollSouthWestX),
+            W => new(
+                Rect::new(rect.left, rect.top + 1, rect.left + 1, rect.bottom - 1),
+                ScrollWest, ScrollWestX),
+            NW => new(
+                Rect::new(rect.left, rect.top, rect.left + 1, rect.top + 1),
+                ScrollNorthWest, ScrollNorthWestX),
+        }
+    }
+    fn is_bartering(&self) -> bool {
+        self.dialog.as_ref().map(|d| d.barter.is_some()).unwrap_or(false)
+    }
+
+    fn show_inventory(&mut self, ui: &mut Ui) {
+        if self.inventory.is_some() {
+            return;
+        }
+        let dude_obj = self.world.borrow().dude_obj().unwrap();
+        self.inventory = Some(InventoryWindow::show(ui, dude_obj));
+        self.sync_inventory(ui);
+    }
+
+    fn hide_inventory(&mut self, ui: &mut Ui) {
+        if let Some(mut inv) = self.inventory.take() {
+            if let Some((menu, _)) = inv.item_action_menu.take() {
+                action_menu::hide(menu, ui);
+            }
+            inv.hide(ui);
+        }
+    }
+
+    fn sync_inventory(&mut self, ui: &mut Ui) {
+        if let Some(inv) = &self.inventory {
+            let world = self.world.borrow();
+            let carry_weight = self.stats.stat(Stat::CarryWeight,
+                &world.objects().get(inv.owner()).borrow(), &world);
+            inv.sync(ui, &world, carry_weight, &self.inventory_msgs);
+        }
+    }
+
+    // inven_action_cursor()
+    fn inventory_item_actions(&self, item: object::Handle) -> Vec<Action> {
+        let world = self.world.borrow();
+        let objects = world.objects();
+        let mut r = vec![Action::Look];
+        let owner = self.inventory.as_ref().unwrap().owner();
+        if objects.can_use(item) || self.car_cell_fuel(&world, owner, item).is_some() {
+            r.push(Action::UseHand);
+        }
+        r.push(Action::Drop);
+        let loaded = objects.get(item).borrow().sub.item()
+            .map(|i| i.ammo_pid.is_some() && i.charges > 0)
+            .unwrap_or(false);
+        if loaded {
+            r.push(Action::Unload);
+        }
+        r.push(Action::Cancel);
+        r
+    }
+
+    fn handle_inventory_action(&mut self, item: object::Handle, action: Action, ui: &mut Ui) {
+        let owner = self.inventory.as_ref().unwrap().owner();
+        match action {
+            Action::Cancel => {}
+            Action::Drop => {
+                let world = &mut self.world.borrow_mut();
+                let objects = world.objects_mut();
+                let count = objects.get(owner).borrow().inventory.count(item);
+                if let Some((item, _)) = objects.remove_from_inventory(owner, item, count) {
+                    let pos = objects.get(owner).borrow().pos;
+                    if let Some(pos) = pos {
+                        objects.set_pos(item, pos);
+                    }
+                }
+            }
+            Action::Look => {
+                let mut lines = Vec::new();
+                lines.extend(self.world.borrow().object_name(item));
+                lines.extend(self.examine_object(owner, item, ui));
+                let lines: Vec<_> = lines.iter().map(|l| l.as_ref()).collect();
+                self.inventory.as_ref().unwrap().set_info(ui, &lines);
+                return;
+            }
+            Action::Unload => {
+                self.world.borrow_mut().objects_mut().unload_weapon(owner, item);
+            }
+            Action::UseHand => {
+                let script = self.world.borrow().objects().get(item).borrow().script;
+                let script_overrides = if let Some((sid, _)) = script {
+                    self.scripts.execute_predefined_proc(sid, PredefinedProc::Use,
+                        &mut script::Context {
+                            world: &mut self.world.borrow_mut(),
+                            sequencer: &mut self.sequencer,
+                            stats: &mut self.stats,
+                            dialog: &mut self.dialog,
+                            ui,
+                            message_panel: self.message_panel,
+                            map_id: self.map_id.unwrap(),
+                        })
+                        .map(|r| match r.suspend {
+                            // The dialog started by the script resumes it when finished.
+                            None | Some(Suspend::GsayEnd) => r.script_overrides,
+                        })
+                        .unwrap_or(false)
+                } else {
+                    false
+                };
+                if self.scripts.can_resume() {
+                    self.hide_inventory(ui);
+                    return;
+                }
+                let is_drug = self.world.borrow().objects().item_kind(item)
+                    == Some(ItemKind::Drug);
+                let car_fuel = self.car_cell_fuel(&self.world.borrow(), owner, item);
+                if !script_overrides {
+                    if is_drug {
+                        self.use_drug(owner, item);
+                    } else if let Some(fuel) = car_fuel {
+                        self.charge_car(owner, item, fuel, ui);
+                    }
+                }
+            }
+            Action::Inventory | Action::Push | Action::Rotate | Action::Talk | Action::UseSkill =>
+                unreachable!(),
+        }
+        self.sync_inventory(ui);
+    }
+
+    /// Fuel the `item` carried by the `user` would charge the car with. `None` if the item is not
+    /// an energy cell or the party's car isn't parked next to the `user`.
+    fn car_cell_fuel(&self, world: &World, user: object::Handle, item: object::Handle)
+        -> Option<u32>
+    {
+        let pid = world.objects().get(item).borrow().pid.proto_id()?;
+        let fuel = CAR_CELL_FUEL.iter().find(|&&(p, _)| p == pid).map(|&(_, fuel)| fuel)?;
+        world.world_map().car().filter(|c| c.map_id.is_some() && c.map_id == self.map_id)?;
+        world.objects().find_on_map(ProtoId::DRIVABLE_CAR).into_iter()
+            .find(|&car| world.objects().distance(user, car)
+                .map(|d| d <= CAR_CHARGE_DISTANCE)
+                .unwrap_or(false))
+            .map(|_| fuel)
+    }
+
+    /// Uses a single energy cell `item` of the `user` to charge the car with `fuel`.
+    // obj_use_power_on_car()
+    fn charge_car(&mut self, user: object::Handle, item: object::Handle, fuel: u32, ui: &mut Ui) {
+        let charged = {
+            let world = &mut self.world.borrow_mut();
+            let car = world.world_map().car().unwrap();
+            if car.fuel < CAR_FUEL_MAX {
+                world.world_map_mut().refuel_car(fuel);
+                let (item, _) = world.objects_mut().remove_from_inventory(user, item, 1).unwrap();
+                world.objects_mut().remove(item);
+                true
+            } else {
+                false
+            }
+        };
+        self.push_message(if charged {
+            b"You charge the car with more power.".as_ref().into()
+        } else {
+            b"The car is already full of power.".as_ref().into()
+        }, ui);
+    }
+
+    // inven_pickup()
+    fn handle_inventory_drop(&mut self, item: object::Handle, target: DropTarget, ui: &mut Ui) {
+        let owner = self.inventory.as_ref().unwrap().owner();
+        {
+            let world = &mut self.world.borrow_mut();
+            let objects = world.objects_mut();
+            match target {
+                DropTarget::List => {
+                    let slot = EquipmentSlot::iter()
+                        .find(|&s| objects.equipped(owner, s) == Some(item));
+                    if let Some(slot) = slot {
+                        objects.unequip(owner, slot);
+                    }
+                }
+                DropTarget::Slot(slot) => {
+                    let is_armor = objects.item_kind(item) == Some(ItemKind::Armor);
+                    if is_armor == (slot == EquipmentSlot::Armor) {
+                        objects.equip(owner, item, slot);
+                    }
+                }
+            }
+        }
+        self.sync_inventory(ui);
+    }
+
+    // action_loot_container()
+    fn action_loot(&mut self, looter: object::Handle, target: object::Handle, ui: &mut Ui) {
+        {
+            let world = self.world.borrow();
+            let objs = world.objects();
+            let can_loot = {
+                let targeto = objs.get(target).borrow();
+                match targeto.sub.critter() {
+                    Some(c) => c.is_dead(),
+                    // TODO steal from alive critters
+                    None => objs.item_kind(target) == Some(ItemKind::Container),
+                }
+            };
+            if !can_loot {
+                return;
+            }
+
+            if objs.distance(looter, target).unwrap() > 1 {
+                objs.get(looter).borrow_mut().cancel_sequence();
+
+                let dest = objs.get(target).borrow().pos.unwrap().point;
+                let (seq, cancel) = Move::new(looter, dest, CritterAnim::Running).cancellable();
+                objs.get(looter).borrow_mut().sequence = Some(cancel);
+                self.sequencer.start(seq
+                    .then(Stand::new(looter))
+                    .then(PushEvent::new(sequence::Event::Loot { looter, target })));
+                return;
+            }
+        }
+
+        self.loot(looter, target, ui);
+    }
+
+    fn action_use_obj(&mut self, user: object::Handle, target: object::Handle, ui: &mut Ui) {
+        {
+            let world = self.world.borrow();
+            let objs = world.objects();
+            if objs.distance(user, target).unwrap() > 1 {
+                objs.get(user).borrow_mut().cancel_sequence();
+
+                let dest = objs.get(target).borrow().pos.unwrap().point;
+                let (seq, cancel) = Move::new(user, dest, CritterAnim::Running).cancellable();
+                objs.get(user).borrow_mut().sequence = Some(cancel);
+                self.sequencer.start(seq
+                    .then(Stand::new(user))
+                    .then(PushEvent::new(sequence::Event::UseObj { user, target })));
+                return;
+            }
+        }
+
+        self.use_obj(user, target, ui);
+    }
+
+    /// Uses scenery `target`. Stairs and ladders move the `user` to their destination, elevators
+    /// show the elevator panel.
+    // obj_use()
+    fn use_obj(&mut self, user: object::Handle, target: object::Handle, ui: &mut Ui) {
+        let script = self.world.borrow().objects().get(target).borrow().script;
+        if let Some((sid, _)) = script {
+            let script_overrides = self.scripts.execute_predefined_proc(sid, PredefinedProc::Use,
+                &mut script::Context {
+                    world: &mut self.world.borrow_mut(),
+                    sequencer: &mut self.sequencer,
+                    stats: &mut self.stats,
+                    dialog: &mut self.dialog,
+                    ui,
+                    message_panel: self.message_panel,
+                    map_id: self.map_id.unwrap(),
+                })
+                .map(|r| match r.suspend {
+                    // The dialog started by the script resumes it when finished.
+                    None | Some(Suspend::GsayEnd) => r.script_overrides,
+                })
+                .unwrap_or(false);
+            if script_overrides {
+                return;
+            }
+        }
+
+        let is_door = self.world.borrow().objects().is_door(target);
+        if is_door {
+            self.use_door(user, target, ui);
+            return;
+        }
+        let world = &mut self.world.borrow_mut();
+        if Some(user) != world.dude_obj() {
+            return;
+        }
+        let is_car = world.objects().get(target).borrow().pid.proto_id()
+            == Some(ProtoId::DRIVABLE_CAR);
+        if is_car {
+            // The party drives away if the car is parked here.
+            if world.world_map().car().and_then(|c| c.map_id) == self.map_id {
+                world.map_transition = Some(MapTransition::WorldMap);
+            }
+            return;
+        }
+        let (exit, elevator) = {
+            let targeto = world.objects().get(target).borrow();
+            (targeto.sub.exit().cloned(), targeto.sub.elevator().cloned())
+        };
+        if let Some(exit) = exit {
+            let map = if exit.map_id > 0 {
+                Some(MapRef::Id(exit.map_id))
+            } else {
+                None
+            };
+            world.map_transition = Some(MapTransition::Map {
+                map,
+                pos: exit.pos,
+                direction: Some(exit.direction),
+            });
+        } else if let Some(elevator) = elevator {
+            world.elevator_request = Some(elevator.kind);
+        }
+    }
+
+    /// Opens or closes the door `target` unless it's locked or something is in the doorway.
+    // obj_use_door()
+    fn use_door(&mut self, user: object::Handle, target: object::Handle, ui: &mut Ui) {
+        let world = &mut self.world.borrow_mut();
+        let (locked, open, has_seq, pos) = {
+            let targeto = world.objects().get(target).borrow();
+            (targeto.is_locked(), targeto.is_open(), targeto.has_running_sequence(),
+                targeto.pos.unwrap())
+        };
+        if has_seq {
+            return;
+        }
+        let is_dude = Some(user) == world.dude_obj();
+        if locked {
+            if is_dude {
+                let msg = self.proto_db.messages().get(487).unwrap().text.clone();
+                self.push_message(&msg, ui);
+            }
+            return;
+        }
+        if open {
+            let in_the_way = world.objects().at(pos).iter()
+                .any(|&h| h != target && world.objects().get(h).borrow().sub.critter().is_some());
+            if in_the_way {
+                if is_dude {
+                    let msg = self.proto_db.messages().get(597).unwrap().text.clone();
+                    self.push_message(&msg, ui);
+                }
+                return;
+            }
+        }
+        door::start(target, !open, world, &mut self.sequencer);
+    }
+
+    /// Requests map transition if there's exit grid at `pos`.
+    // check_for_exit_grid()
+    fn check_exit_area(&self, pos: EPoint, world: &mut World) {
+        let exit = world.objects().at(pos).iter()
+            .filter_map(|&h| {
+                let obj = world.objects().get(h).borrow();
+                if obj.pid.proto_id().map(|pid| pid.is_exit_area()).unwrap_or(false) {
+                    obj.sub.exit().cloned()
+                } else {
+                    None
+                }
+            })
+            .next();
+        if let Some(exit) = exit {
+            world.map_transition = Some(if exit.map_id < 0 {
+                MapTransition::WorldMap
+            } else {
+                MapTransition::Map {
+                    map: Some(MapRef::Id(exit.map_id)),
+                    pos: exit.pos,
+                    direction: Some(exit.direction),
+                }
+            });
+        }
+    }
+
+    // obj_use_container(), loot_container()
+    fn loot(&mut self, looter: object::Handle, target: object::Handle, ui: &mut Ui) {
+        if self.loot.is_some() {
+            return;
+        }
+
+        let (locked, script) = {
+            let world = self.world.borrow();
+            let targeto = world.objects().get(target).borrow();
+            (targeto.is_locked(), targeto.script)
+        };
+        if locked {
+            if looter == self.world.borrow().dude_obj().unwrap() {
+                let msg = self.proto_db.messages().get(487).unwrap().text.clone();
+                self.push_message(&msg, ui);
+            }
+            return;
+        }
+
+        if let Some((sid, _)) = script {
+            self.scripts.set_source_obj(sid, Some(looter));
+            let script_overrides = self.scripts.execute_predefined_proc(sid,
+                PredefinedProc::Pickup,
+                &mut script::Context {
+                    world: &mut self.world.borrow_mut(),
+                    sequencer: &mut self.sequencer,
+                    stats: &mut self.stats,
+                    dialog: &mut self.dialog,
+                    ui,
+                    message_panel: self.message_panel,
+                    map_id: self.map_id.unwrap(),
+                })
+                .map(|r| {
+                    assert!(r.suspend.is_none(), "can't suspend in Pickup");
+                    r.script_overrides
+                })
+                .unwrap_or(false);
+            if script_overrides {
+                return;
+            }
+        }
+
+        let loot = LootWindow::show(ui, looter, target);
+        loot.sync(ui, &self.world.borrow());
+        self.loot = Some(loot);
+    }
+
+    fn hide_loot(&mut self, ui: &mut Ui) {
+        if let Some(loot) = self.loot.take() {
+            loot.hide(ui);
+        }
+        self.stealing = false;
+    }
+
+    /// Moves the whole stack of `item` from the `from` side of the loot window to the other side.
+    /// Returns `false` if the item can't be moved.
+    // move_inventory()
+    fn loot_move_item(&mut self, from: Side, item: object::Handle, ui: &mut Ui) -> bool {
+        let (src, dst) = {
+            let loot = self.loot.as_ref().unwrap();
+            match from {
+                Side::Looter => (loot.object(Side::Looter), loot.object(Side::Target)),
+                Side::Target => (loot.object(Side::Target), loot.object(Side::Looter)),
+            }
+        };
+
+        let count = self.world.borrow().objects().get(src).borrow().inventory.count(item);
+        if count == 0 {
+            return false;
+        }
+
+        {
+            let world = self.world.borrow();
+            let objs = world.objects();
+            let fits = if objs.get(dst).borrow().sub.critter().is_some() {
+                let carry_weight = self.stats.stat(Stat::CarryWeight, &objs.get(dst).borrow(),
+                    &world);
+                objs.inventory_weight(dst) + objs.item_weight(item) * count as u32
+                    <= cmp::max(carry_weight, 0) as u32
+            } else {
+                objs.fits_into_container(dst, item, count)
+            };
+            if !fits {
+                // TODO show message from inventry.msg
+                return false;
+            }
+        }
+
+        if self.stealing {
+            let (thief, target) = {
+                let loot = self.loot.as_ref().unwrap();
+                (loot.object(Side::Looter), loot.object(Side::Target))
+            };
+            if self.is_caught_stealing(thief, target) {
+                self.hide_loot(ui);
+                self.caught_stealing(thief, target, ui);
+                return false;
+            }
+        }
+
+        if from == Side::Target {
+            let script = self.world.borrow().objects().get(item).borrow().script;
+            if let Some((sid, _)) = script {
+                let script_overrides = self.scripts.execute_predefined_proc(sid,
+                    PredefinedProc::Pickup,
+                    &mut script::Context {
+                        world: &mut self.world.borrow_mut(),
+                        sequencer: &mut self.sequencer,
+                        stats: &mut self.stats,
+                        dialog: &mut self.dialog,
+                        ui,
+                        message_panel: self.message_panel,
+                        map_id: self.map_id.unwrap(),
+                    })
+                    .map(|r| {
+                        assert!(r.suspend.is_none(), "can't suspend in Pickup");
+                        r.script_overrides
+                    })
+                    .unwrap_or(false);
+                if script_overrides {
+                    return false;
+                }
+            }
+        }
+
+        let world = &mut self.world.borrow_mut();
+        if let Some((item, count)) = world.objects_mut().remove_from_inventory(src, item, count) {
+            world.add_to_inventory(dst, item, count);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn handle_loot_command(&mut self, cmd: loot::Command, ui: &mut Ui) {
+        match cmd {
+            loot::Command::ScrollUp(side) => self.loot.as_ref().unwrap().scroll(ui, side, true),
+            loot::Command::ScrollDown(side) => self.loot.as_ref().unwrap().scroll(ui, side, false),
+            loot::Command::TakeAll => {
+                let target = self.loot.as_ref().unwrap().object(Side::Target);
+                let items: Vec<_> = self.world.borrow().objects().get(target).borrow()
+                    .inventory.items.iter()
+                    .map(|i| i.object)
+                    .collect();
+                for item in items {
+                    self.loot_move_item(Side::Target, item, ui);
+                    if self.loot.is_none() {
+                        return;
+                    }
+                }
+                self.loot.as_ref().unwrap().sync(ui, &self.world.borrow());
+            }
+            loot::Command::Done => self.hide_loot(ui),
+        }
+    }
+
+    // skilldex_select()
+    fn show_skilldex(&mut self, target: Option<object::Handle>, ui: &mut Ui) {
+        if self.skilldex.is_some() {
+            return;
+        }
+        let world = self.world.borrow();
+        let dude_obj = world.objects().get(world.dude_obj().unwrap()).borrow();
+        let skills: Vec<_> = skilldex::SKILLS.iter()
+            .map(|&skill| (self.stats.skill_name(skill),
+                self.stats.skill(skill, &dude_obj, &world)))
+            .collect();
+        self.skilldex = Some(SkilldexWindow::show(ui, target, &skills));
+    }
+
+    fn handle_skilldex_command(&mut self, cmd: skilldex::Command, ui: &mut Ui) {
+        let target = if let Some(win) = self.skilldex.take() {
+            let target = win.target();
+            win.hide(ui);
+            target
+        } else {
+            return;
+        };
+        match cmd {
+            skilldex::Command::Pick(Skill::Sneak) => {
+                let world = &mut self.world.borrow_mut();
+                world.dude_sneaking = !world.dude_sneaking;
+            }
+            skilldex::Command::Pick(skill) => if let Some(target) = target {
+                let dude_obj = self.world.borrow().dude_obj().unwrap();
+                self.action_use_skill(dude_obj, target, skill, ui);
+            } else {
+                self.skill_to_use = Some(skill);
+                ui.widget_mut::<WorldView>(self.world_view).enter_skill_pick_mode();
+            }
+            skilldex::Command::Cancel => {}
+        }
+    }
+
+    fn show_perk_select(&mut self, ui: &mut Ui) {
+        if self.perk_select.is_some() || self.stats.free_perks() == 0 {
+            return;
+        }
+        let world = self.world.borrow();
+        let dude_obj = world.objects().get(world.dude_obj().unwrap()).borrow();
+        let perks: Vec<_> = self.stats.eligible_perks(&dude_obj, &world).into_iter()
+            .map(|perk| (perk, self.stats.perk_name(perk)))
+            .collect();
+        self.perk_select = Some(PerkSelectWindow::show(ui, &perks));
+    }
+
+    fn handle_perk_select_command(&mut self, cmd: perk_select::Command, ui: &mut Ui) {
+        let perk = match cmd {
+            perk_select::Command::ScrollUp | perk_select::Command::ScrollDown => {
+                if let Some(win) = &mut self.perk_select {
+                    win.scroll(ui, cmd == perk_select::Command::ScrollUp);
+                }
+                return;
+            }
+            perk_select::Command::Done => if let Some(perk) = self.perk_select.as_ref()
+                .and_then(|w| w.selected())
+            {
+                perk
+            } else {
+                return;
+            }
+            perk_select::Command::Cancel => {
+                if let Some(win) = self.perk_select.take() {
+                    win.hide(ui);
+                }
+                return;
+            }
+        };
+        self.perk_select.take().unwrap().hide(ui);
+
+        {
+            let world = self.world.borrow();
+            let mut dude_obj = world.objects().get(world.dude_obj().unwrap()).borrow_mut();
+            self.stats.pick_perk(perk, &mut dude_obj, &world);
+        }
+        if self.character.is_some() {
+            self.sync_character_window(ui);
+        }
+    }
+
+    fn select_perk(&mut self, index: u32, ui: &mut Ui) {
+        let win = self.perk_select.as_mut().unwrap();
+        let perk = win.perk_at(index);
+        win.select(ui, perk, self.stats.perk_name(perk), self.stats.perk_description(perk));
+    }
+
+    // action_use_skill_on()
+    fn action_use_skill(&mut self, user: object::Handle, target: object::Handle, skill: Skill,
+        ui: &mut Ui)
+    {
+        {
+            let world = self.world.borrow();
+            let objs = world.objects();
+            if objs.distance(user, target).unwrap() > 1 {
+                objs.get(user).borrow_mut().cancel_sequence();
+
+                let dest = objs.get(target).borrow().pos.unwrap().point;
+                let (seq, cancel) = Move::new(user, dest, CritterAnim::Running).cancellable();
+                objs.get(user).borrow_mut().sequence = Some(cancel);
+                self.sequencer.start(seq
+                    .then(Stand::new(user))
+                    .then(PushEvent::new(sequence::Event::UseSkill { user, target, skill })));
+                return;
+            }
+        }
+
+        self.use_skill(user, target, skill, ui);
+    }
+
+    /// Runs `use_skill_on_p_proc` of the `target` and falls back to the default skill behavior
+    /// if the script doesn't override it.
+    // obj_use_skill_on()
+    fn use_skill(&mut self, user: object::Handle, target: object::Handle, skill: Skill,
+        ui: &mut Ui)
+    {
+        let script = self.world.borrow().objects().get(target).borrow().script;
+        if let Some((sid, _)) = script {
+            self.scripts.set_action(sid, Some(user), skill as i32);
+            let script_overrides = self.scripts.execute_predefined_proc(sid,
+                PredefinedProc::UseSkillOn,
+                &mut script::Context {
+                    world: &mut self.world.borrow_mut(),
+                    sequencer: &mut self.sequencer,
+                    stats: &mut self.stats,
+                    dialog: &mut self.dialog,
+                    ui,
+                    message_panel: self.message_panel,
+                    map_id: self.map_id.unwrap(),
+                })
+                .map(|r| {
+                    assert!(r.suspend.is_none(), "can't suspend in UseSkillOn");
+                    r.script_overrides
+                })
+                .unwrap_or(false);
+            if script_overrides {
+                return;
+            }
+        }
+
+        match skill {
+            Skill::FirstAid | Skill::Doctor | Skill::Repair => self.heal_with_skill(user, target,
+                skill, ui),
+            Skill::Lockpick => self.pick_lock(user, target, ui),
+            Skill::Steal => self.steal(user, target, ui),
+            Skill::Science => {
+                // TODO take the text from skill.msg
+                self.push_message(b"You fail to learn anything.".as_ref().into(), ui);
+            }
+            _ => {}
+        }
+    }
+
+    /// First Aid and Doctor heal living critters, Repair heals robots.
+    // skill_use()
+    fn heal_with_skill(&mut self, user: object::Handle, target: object::Handle, skill: Skill,
+        ui: &mut Ui)
+    {
+        let (is_robot, is_dead, health, max_health, injuries) = {
+            let world = self.world.borrow();
+            let obj = world.objects().get(target).borrow();
+            let critter = if let Some(c) = obj.sub.critter() {
+                c
+            } else {
+                return;
+            };
+            let is_robot = obj.pid.proto_id()
+                .map(|pid| self.proto_db.proto(pid).unwrap().sub.critter().unwrap().kill_kind
+                    == CritterKillKind::Robot)
+                .unwrap_or(false);
+            let injuries = critter.combat.damage_flags & (DamageFlag::CripLegLeft
+                | DamageFlag::CripLegRight
+                | DamageFlag::CripArmLeft
+                | DamageFlag::CripArmRight
+                | DamageFlag::Blind);
+            (is_robot, critter.is_dead(), critter.health,
+                self.stats.stat(Stat::HitPoints, &obj, &world),
+                injuries)
+        };
+
+        // TODO take the texts from skill.msg
+        if is_dead {
+            self.push_message(b"That is beyond your help.".as_ref().into(), ui);
+            return;
+        }
+        if is_robot != (skill == Skill::Repair) {
+            self.push_message(if is_robot {
+                b"You can't heal a robot.".as_ref().into()
+            } else {
+                b"You cannot repair that.".as_ref().into()
+            }, ui);
+            return;
+        }
+        let heals_injuries = skill != Skill::FirstAid;
+        if health >= max_health && (!heals_injuries || injuries.is_empty()) {
+            self.push_message(b"There is no damage to heal.".as_ref().into(), ui);
+            return;
+        }
+
+        {
+            let world = &mut self.world.borrow_mut();
+            world.game_time = world.game_time.add_decis(skill::use_time(skill) * 10);
+        }
+
+        let world = self.world.borrow();
+        let usero = world.objects().get(user).borrow();
+        let healer_rank = self.stats.has_perk(Perk::Healer, usero.pid) as i32;
+        let mut healed_injuries = BitFlags::empty();
+        if heals_injuries {
+            for injury in injuries.iter() {
+                if self.stats.roll_skill(skill, &usero, &world, 0, &mut random).0.is_success() {
+                    healed_injuries.insert(injury);
+                }
+            }
+        }
+        let roll = self.stats.roll_skill(skill, &usero, &world, 0, &mut random).0;
+        let healed = if roll.is_success() {
+            let (min, max) = skill::heal_range(skill, healer_rank);
+            cmp::min(random(min, max), max_health - health)
+        } else {
+            0
+        };
+        drop(usero);
+
+        {
+            let mut obj = world.objects().get(target).borrow_mut();
+            let critter = obj.sub.critter_mut().unwrap();
+            critter.health += healed;
+            critter.combat.damage_flags.remove(healed_injuries);
+        }
+        drop(world);
+
+        let msg = if healed > 0 {
+            format!("You heal {} hit points.", healed)
+        } else if !healed_injuries.is_empty() {
+            "You heal the injuries.".into()
+        } else {
+            "You fail to do any healing.".into()
+        };
+        self.push_message(BString::from(msg).as_ref(), ui);
+    }
+
+    fn pick_lock(&mut self, user: object::Handle, target: object::Handle, ui: &mut Ui) {
+        let world = self.world.borrow();
+        let mut targeto = world.objects().get(target).borrow_mut();
+        // TODO take the texts from skill.msg
+        let msg: &bstr = if !targeto.is_locked() {
+            b"That isn't locked.".as_ref().into()
+        } else if targeto.lock_flags.contains(LockFlag::Jammed) {
+            b"The lock is jammed.".as_ref().into()
+        } else {
+            let roll = {
+                let usero = world.objects().get(user).borrow();
+                self.stats.roll_skill(Skill::Lockpick, &usero, &world, 0, &mut random).0
+            };
+            match roll {
+                Roll::CriticalSuccess | Roll::Success => {
+                    targeto.lock_flags.remove(LockFlag::Locked);
+                    b"You pick the lock.".as_ref().into()
+                }
+                Roll::Failure => b"You fail to pick the lock.".as_ref().into(),
+                Roll::CriticalFailure => {
+                    targeto.lock_flags.insert(LockFlag::Jammed);
+                    b"You jammed the lock.".as_ref().into()
+                }
+            }
+        };
+        drop(targeto);
+        drop(world);
+        self.push_message(msg, ui);
+    }
+
+    fn steal(&mut self, thief: object::Handle, target: object::Handle, ui: &mut Ui) {
+        let can_steal = {
+            let world = self.world.borrow();
+            let targeto = world.objects().get(target).borrow();
+            targeto.sub.critter().is_some() && Some(target) != world.dude_obj()
+                && !targeto.pid.proto_id()
+                    .map(|pid| self.proto_db.proto(pid).unwrap().sub.critter().unwrap()
+                        .flags.contains(CritterFlag::NoSteal))
+                    .unwrap_or(false)
+        };
+        if !can_steal || self.is_in_combat() {
+            // TODO take the text from skill.msg
+            self.push_message(b"You can't steal from that.".as_ref().into(), ui);
+            return;
+        }
+
+        self.loot(thief, target, ui);
+        if self.loot.is_some() {
+            self.stealing = true;
+        }
+    }
+
+    /// Rolls Steal for moving a single item between `thief` and `target`.
+    // skill_check_stealing()
+    fn is_caught_stealing(&self, thief: object::Handle, target: object::Handle) -> bool {
+        let world = self.world.borrow();
+        let thiefo = world.objects().get(thief).borrow();
+        let targeto = world.objects().get(target).borrow();
+        let is_dead = targeto.sub.critter().unwrap().is_dead();
+        // Stealing from the front is harder.
+        let in_front = combat::is_in_front(targeto.pos.unwrap().point, targeto.direction,
+            thiefo.pos.unwrap().point);
+        let modifier = if !is_dead && in_front {
+            -25
+        } else {
+            0
+        };
+        !is_dead && !self.stats.roll_skill(Skill::Steal, &thiefo, &world, modifier,
+            &mut random).0
+            .is_success()
+    }
+
+    fn caught_stealing(&mut self, thief: object::Handle, target: object::Handle, ui: &mut Ui) {
+        // TODO take the text from skill.msg
+        self.push_message(b"You were caught stealing.".as_ref().into(), ui);
+        self.world.borrow_mut().combat_requests.push(combat::Request::Attack {
+            attacker: target,
+            target: thief,
+        });
+    }
+
+    fn is_in_combat(&self) -> bool {
+        self.world.borrow().combat.is_some()
+    }
+
+    fn is_dude_turn(&self) -> bool {
+        let world = self.world.borrow();
+        world.combat.as_ref().map(|c| Some(c.current()) == world.dude_obj()).unwrap_or(false)
+    }
+
+    fn has_running_sequence(&self, obj: object::Handle) -> bool {
+        self.world.borrow().objects().get(obj).borrow().has_running_sequence()
+    }
+
+    fn execute_combat_procs(&mut self, proc: PredefinedProc, ui: &mut Ui) {
+        let world = &mut self.world.borrow_mut();
+        let mut sids: Vec<_> = self.scripts.map_sid().into_iter().collect();
+        if let Some(combat) = &world.combat {
+            sids.extend(combat.combatants().iter()
+                .filter_map(|&obj| world.objects().get(obj).borrow().script)
+                .map(|(sid, _)| sid));
+        }
+        for sid in sids {
+            self.scripts.execute_predefined_proc(sid, proc,
+                &mut script::Context {
+                    world,
+                    sequencer: &mut self.sequencer,
+                    stats: &mut self.stats,
+                    dialog: &mut self.dialog,
+                    ui,
+                    message_panel: self.message_panel,
+                    map_id: self.map_id.unwrap(),
+                })
+                .map(|r| r.suspend.map(|_| panic!("can't suspend in {:?}", proc)));
+        }
+    }
+
+    /// Starts combat with all living critters on the dude's elevation. `attacker` takes
+    /// the first turn.
+    // combat_begin()
+    fn start_combat(&mut self, attacker: Option<object::Handle>, ui: &mut Ui) {
+        assert!(!self.is_in_combat());
+        {
+            let world = &mut self.world.borrow_mut();
+            let dude_obj = world.dude_obj().unwrap();
+            let elevation = world.objects().get(dude_obj).borrow().pos.unwrap().elevation;
+            let combatants = world.objects().iter()
+                .filter_map(|h| {
+                    let obj = world.objects().get(h).borrow();
+                    if obj.pos.map(|p| p.elevation) == Some(elevation)
+                        && obj.sub.critter().map(|c| !c.is_dead()).unwrap_or(false)
+                    {
+                        Some((h, self.stats.stat(Stat::Sequence, &obj, &world)))
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            let combatants = combat::turn_order(combatants, attacker);
+            for &obj in &combatants {
+                world.objects().get(obj).borrow_mut().cancel_sequence();
+            }
+            debug!("starting combat: {:?}", combatants);
+            world.combat = Some(Combat::new(combatants));
+        }
+        self.execute_combat_procs(PredefinedProc::CombatIsStarting, ui);
+        self.begin_turn(ui);
+    }
+
+    // combat_over()
+    fn end_combat(&mut self, ui: &mut Ui) {
+        if !self.is_in_combat() {
+            return;
+        }
+        self.execute_combat_procs(PredefinedProc::CombatIsOver, ui);
+
+        let world = &mut self.world.borrow_mut();
+        let combat = world.combat.take().unwrap();
+        debug!("combat is over after {} round(s)", combat.round());
+        for &obj in combat.combatants() {
+            if let Some(critter) = world.objects().get(obj).borrow_mut().sub.critter_mut() {
+                critter.combat.ap = 0;
+                critter.combat.who_hit_me = None;
+                critter.combat.maneuver = BitFlags::empty();
+            }
+        }
+    }
+
+    /// Returns `true` if none of the combatants has a living hostile target.
+    fn is_combat_over(&self) -> bool {
+        let world = self.world.borrow();
+        let combat = if let Some(c) = &world.combat {
+            c
+        } else {
+            return true;
+        };
+        let is_alive = |obj| world.objects().get(obj).borrow().sub.critter()
+            .map(|c| !c.is_dead())
+            .unwrap_or(false);
+        !combat.combatants().iter().any(|&obj| {
+            let target = world.objects().get(obj).borrow().sub.critter().unwrap().combat.who_hit_me;
+            is_alive(obj) && target
+                .map(|t| t != obj && combat.contains(t) && is_alive(t))
+                .unwrap_or(false)
+        })
+    }
+
+    /// Returns `true` if some combatant is hostile to the dude.
+    fn is_dude_targeted(&self) -> bool {
+        let world = self.world.borrow();
+        let dude_obj = world.dude_obj().unwrap();
+        world.combat.as_ref().map(|combat| combat.combatants().iter()
+            .any(|&obj| world.objects().get(obj).borrow().sub.critter().unwrap().combat.who_hit_me
+                == Some(dude_obj)))
+            .unwrap_or(false)
+    }
+
+    /// Prepares the current combatant for its turn. Dead combatants are removed from combat.
+    /// Critters that lost their turn or whose `combat_p_proc` overrides the turn are skipped.
+    // combat_turn()
+    fn begin_turn(&mut self, ui: &mut Ui) {
+        loop {
+            let (obj, is_dude, is_dead) = {
+                let world = self.world.borrow();
+                let obj = if let Some(c) = &world.combat {
+                    c.current()
+                } else {
+                    return;
+                };
+                let is_dead = world.objects().get(obj).borrow().sub.critter().unwrap().is_dead();
+                (obj, Some(obj) == world.dude_obj(), is_dead)
+            };
+
+            if is_dead {
+                let has_combatants = self.world.borrow_mut().combat.as_mut().unwrap().remove(obj);
+                if is_dude || !has_combatants {
+                    self.end_combat(ui);
+                    return;
+                }
+                self.world.borrow_mut().combat.as_mut().unwrap().next_turn();
+                continue;
+            }
+
+            let lost_turn = {
+                let world = self.world.borrow();
+                let mut objr = world.objects().get(obj).borrow_mut();
+                let flags = &mut objr.sub.critter_mut().unwrap().combat.damage_flags;
+                let r = flags.contains(DamageFlag::LoseTurn);
+                flags.remove(DamageFlag::LoseTurn);
+                r
+            };
+            if lost_turn {
+                self.world.borrow_mut().combat.as_mut().unwrap().next_turn();
+                continue;
+            }
+
+            // Knocked out critters skip the turn and come to themselves knocked down.
+            // Knocked down critters spend the beginning of the turn getting up.
+            let (knocked_out, knocked_down) = {
+                let world = self.world.borrow();
+                let mut objr = world.objects().get(obj).borrow_mut();
+                let flags = &mut objr.sub.critter_mut().unwrap().combat.damage_flags;
+                let r = (flags.contains(DamageFlag::KnockedOut),
+                    flags.contains(DamageFlag::KnockedDown));
+                if r.0 {
+                    flags.remove(DamageFlag::KnockedOut);
+                    flags.insert(DamageFlag::KnockedDown);
+                }
+                r
+            };
+            if knocked_out {
+                self.world.borrow_mut().combat.as_mut().unwrap().next_turn();
+                continue;
+            }
+            if knocked_down {
+                self.stand_up(obj);
+            }
+
+            let script = {
+                let world = self.world.borrow();
+                let mut objr = world.objects().get(obj).borrow_mut();
+                let ap = self.stats.stat(Stat::ActionPoints, &objr, &world);
+                objr.sub.critter_mut().unwrap().combat.ap = ap;
+                objr.script
+            };
+
+            if is_dude {
+                return;
+            }
+
+            let overrides = if let Some((sid, _)) = script {
+                self.scripts.set_fixed_param(sid, combat::COMBAT_PROC_TURN);
+                self.scripts.execute_predefined_proc(sid, PredefinedProc::Combat,
+                    &mut script::Context {
+                        world: &mut self.world.borrow_mut(),
+                        sequencer: &mut self.sequencer,
+                        stats: &mut self.stats,
+                        dialog: &mut self.dialog,
+                        ui,
+                        message_panel: self.message_panel,
+                        map_id: self.map_id.unwrap(),
+                    })
+                    .map(|r| {
+                        assert!(r.suspend.is_none(), "can't suspend in Combat");
+                        r.script_overrides
+                    })
+                    .unwrap_or(false)
+            } else {
+                false
+            };
+            if !overrides || !self.is_in_combat() {
+                return;
+            }
+            self.world.borrow_mut().combat.as_mut().unwrap().next_turn();
+        }
+    }
+
+    fn end_turn(&mut self, ui: &mut Ui) {
+        let new_round = {
+            let world = &mut self.world.borrow_mut();
+            let combat = if let Some(c) = world.combat.as_mut() {
+                c
+            } else {
+                return;
+            };
+            let round = combat.round();
+            combat.next_turn();
+            combat.round() != round
+        };
+        if new_round {
+            // Game time in combat advances only by rounds.
+            {
+                let world = &mut self.world.borrow_mut();
+                world.game_time = world.game_time.add_decis(COMBAT_ROUND_TIME);
+            }
+            self.handle_timer_events(ui);
+        }
+        if new_round && self.is_combat_over() {
+            self.end_combat(ui);
+        } else {
+            self.begin_turn(ui);
+        }
+    }
+
+    /// Returns AP cost, range and kind of the primary attack with the weapon in the right hand
+    /// of `critter`.
+    fn attack_params(&self, critter: object::Handle) -> (i32, u32, AttackKind) {
+        let world = self.world.borrow();
+        let objects = world.objects();
+        let proto = objects.equipped(critter, EquipmentSlot::RightHand)
+            .and_then(|item| objects.get(item).borrow().pid.proto_id())
+            .map(|pid| self.proto_db.proto(pid).unwrap());
+        let weapon = proto.as_ref()
+            .and_then(|p| p.sub.item())
+            .and_then(|i| if let proto::SubItem::Weapon(w) = &i.sub { Some(w) } else { None });
+        (combat::attack_ap_cost(weapon),
+            combat::attack_range(weapon),
+            weapon.map(|w| w.attack_kind.primary).unwrap_or(AttackKind::Punch))
+    }
+
+    /// Returns AP cost of the attack `attacker` makes at `location`.
+    fn attack_ap_cost(&self, attacker: object::Handle, location: attack::HitLocation) -> i32 {
+        let (ap_cost, _, _) = self.attack_params(attacker);
+        if location == attack::HitLocation::Uncalled {
+            ap_cost
+        } else {
+            ap_cost + combat::CALLED_SHOT_AP_COST
+        }
+    }
+
+    /// Starts attack animation of `attacker` against `location` of `target` if the target is
+    /// within range and the attacker has enough AP. Returns `false` if the attack can't be made.
+    fn combat_attack(&mut self, attacker: object::Handle, target: object::Handle,
+        location: attack::HitLocation) -> bool
+    {
+        let ap_cost = self.attack_ap_cost(attacker, location);
+        let (_, range, kind) = self.attack_params(attacker);
+        let world = self.world.borrow();
+        let in_range = world.objects().distance(attacker, target)
+            .map(|d| d <= range)
+            .unwrap_or(false);
+        let target_pos = world.objects().get(target).borrow().pos.unwrap().point;
+        let mut obj = world.objects().get(attacker).borrow_mut();
+        let critter = obj.sub.critter_mut().unwrap();
+        if !in_range || critter.combat.ap < ap_cost {
+            return false;
+        }
+        critter.combat.ap -= ap_cost;
+
+        obj.cancel_sequence();
+        obj.direction = hex::direction(obj.pos.unwrap().point, target_pos);
+        let (seq, signal) = FrameAnim::new(attacker, Some(combat::attack_anim(kind)),
+                AnimDirection::Forward, false)
+            .then(Stand::new(attacker))
+            .then(PushEvent::new(sequence::Event::Attack { attacker, target, location }))
+            .cancellable();
+        obj.sequence = Some(signal);
+        self.sequencer.start(seq);
+
+        true
+    }
+
+    fn attack_defender(&self, target: object::Handle) -> attack::Defender {
+        let world = self.world.borrow();
+        let objects = world.objects();
+        let obj = objects.get(target).borrow();
+        let stat = |stat| self.stats.stat(stat, &obj, &world);
+
+        let mut r = attack::Defender {
+            armor_class: self.stats.armor_class(target, &world),
+            damage_threshold: EnumMap::from(|kind| attack::damage_threshold_stat(kind)
+                .map(&stat).unwrap_or(0)),
+            damage_resistance: EnumMap::from(|kind| attack::damage_resistance_stat(kind)
+                .map(&stat).unwrap_or(0)),
+            endurance: stat(Stat::Endurance),
+            agility: stat(Stat::Agility),
+            luck: stat(Stat::Luck),
+        };
+
+        let armor = objects.equipped(target, EquipmentSlot::Armor)
+            .and_then(|item| objects.get(item).borrow().pid.proto_id())
+            .map(|pid| self.proto_db.proto(pid).unwrap());
+        if let Some(armor) = armor.as_ref().and_then(|p| p.sub.item()).and_then(|i| i.sub.armor()) {
+            r.armor_class += armor.armor_class;
+            for (kind, v) in &mut r.damage_threshold {
+                *v += armor.damage_threshold[kind];
+            }
+            for (kind, v) in &mut r.damage_resistance {
+                *v += armor.damage_resistance[kind];
+            }
+        }
+
+        r
+    }
+
+    /// Calls `f` with the attacker side of the attack `attacker` makes with the weapon in its
+    /// right hand.
+    fn with_attacker<R>(&self, attacker: object::Handle, f: impl FnOnce(&attack::Attacker) -> R)
+        -> R
+    {
+        let (_, _, kind) = self.attack_params(attacker);
+        let world = self.world.borrow();
+        let objects = world.objects();
+        let obj = objects.get(attacker).borrow();
+        let stat = |stat| self.stats.stat(stat, &obj, &world);
+
+        let weapon = objects.equipped(attacker, EquipmentSlot::RightHand);
+        let weapon_proto = weapon
+            .and_then(|item| objects.get(item).borrow().pid.proto_id())
+            .map(|pid| self.proto_db.proto(pid).unwrap());
+        let ammo_proto = weapon
+            .and_then(|item| objects.get(item).borrow().sub.item().and_then(|i| i.ammo_pid))
+            .map(|pid| self.proto_db.proto(pid).unwrap());
+        let weapon_proto = weapon_proto.as_ref().map(|p| &**p);
+
+        f(&attack::Attacker {
+            weapon: weapon_proto,
+            ammo: ammo_proto.as_ref().and_then(|p| p.sub.item()).and_then(|i| i.sub.ammo()),
+            kind,
+            skill: self.stats.skill(attack::weapon_skill(weapon_proto, kind), &obj, &world),
+            perception: stat(Stat::Perception),
+            luck: stat(Stat::Luck),
+            crit_chance: stat(Stat::CritChance),
+            better_crit: stat(Stat::BetterCrit),
+            melee_damage: stat(Stat::MeleeDmg),
+            is_blind: obj.sub.critter().unwrap().combat.damage_flags.contains(DamageFlag::Blind),
+        })
+    }
+
+    fn attack_situation(&self, attacker: object::Handle, target: object::Handle,
+        location: attack::HitLocation) -> attack::Situation
+    {
+        let world = self.world.borrow();
+        let objects = world.objects();
+        let pos = objects.get(target).borrow().pos.unwrap();
+        attack::Situation {
+            distance: objects.distance(attacker, target).unwrap_or(1),
+            light: world.light_at(pos),
+            location,
+        }
+    }
+
+    /// Returns chance in percents of `attacker` hitting `location` of `target`.
+    // determine_to_hit()
+    fn hit_chance(&self, attacker: object::Handle, target: object::Handle,
+        location: attack::HitLocation) -> i32
+    {
+        let defender = self.attack_defender(target);
+        let situation = self.attack_situation(attacker, target, location);
+        self.with_attacker(attacker, |a| attack::hit_chance(a, &defender, &situation))
+    }
+
+    fn attack_outcome(&self, attacker: object::Handle, target: object::Handle,
+        location: attack::HitLocation) -> attack::Outcome
+    {
+        let defender = self.attack_defender(target);
+        let situation = self.attack_situation(attacker, target, location);
+        let outcome = self.with_attacker(attacker,
+            |a| attack::resolve(a, &defender, &situation, &mut random));
+        debug!("attack {:?} -> {:?}: {:?}", attacker, target, outcome);
+        outcome
+    }
+
+    /// Returns the projectile and the number of rounds per burst of the weapon in the right hand
+    /// of `critter`.
+    fn weapon_projectile(&self, critter: object::Handle) -> (Option<ProtoId>, u32) {
+        let world = self.world.borrow();
+        let objects = world.objects();
+        let proto = objects.equipped(critter, EquipmentSlot::RightHand)
+            .and_then(|item| objects.get(item).borrow().pid.proto_id())
+            .map(|pid| self.proto_db.proto(pid).unwrap());
+        proto.as_ref()
+            .and_then(|p| p.sub.item())
+            .and_then(|i| i.sub.weapon())
+            .map(|w| (w.projectile_pid, cmp::max(w.burst_bullet_count, 1) as u32))
+            .unwrap_or((None, 1))
+    }
+
+    /// Starts animation of `projectile` flying from `shooter` to `to` after `delay`.
+    fn launch_projectile(&mut self, shooter: object::Handle, projectile: ProtoId, to: EPoint,
+        delay: Duration)
+    {
+        let fid = self.proto_db.proto(projectile).unwrap().fid;
+        let world = &mut self.world.borrow_mut();
+        let from = world.objects().get(shooter).borrow().pos.unwrap();
+        if from.elevation != to.elevation {
+            return;
+        }
+        let mut obj = Object::new(fid, projectile.into(), None);
+        if from.point != to.point {
+            obj.direction = hex::direction(from.point, to.point);
+        }
+        obj.flags.insert(Flag::NoBlock | Flag::ShootThru | Flag::LightThru);
+        let obj = world.insert_object(obj);
+        self.sequencer.start(Sleep::new(delay)
+            .then(Projectile::new(obj, from, to))
+            .then(Remove::new(obj)));
+    }
+
+    /// Resolves the attack once the attack animation has finished and applies the outcome.
+    // combat_attack(), apply_damage()
+    fn resolve_attack(&mut self, attacker: object::Handle, target: object::Handle,
+        location: attack::HitLocation, ui: &mut Ui)
+    {
+        let (_, range, kind) = self.attack_params(attacker);
+        let (projectile, rounds) = self.weapon_projectile(attacker);
+        let outcomes = if attack::is_burst(kind) {
+            self.resolve_burst(attacker, target, range, rounds, projectile)
+        } else {
+            let outcome = self.attack_outcome(attacker, target, location);
+            if let Some(projectile) = projectile {
+                let to = self.world.borrow().objects().get(target).borrow().pos.unwrap();
+                self.launch_projectile(attacker, projectile, to, Duration::from_millis(0));
+            }
+            vec![(target, outcome)]
+        };
+        for (target, outcome) in outcomes {
+            self.apply_attack_outcome(attacker, target, &outcome, ui);
+        }
+    }
+
+    /// Sprays `rounds` of a burst around `target`. Each round hits the first object in its way.
+    /// Returns the combined outcome for each critter that was shot at.
+    // compute_spray()
+    fn resolve_burst(&mut self, attacker: object::Handle, target: object::Handle, range: u32,
+        rounds: u32, projectile: Option<ProtoId>) -> Vec<(object::Handle, attack::Outcome)>
+    {
+        let (center, left, right) = attack::spread_rounds(rounds);
+        let vias = {
+            let world = self.world.borrow();
+            let objects = world.objects();
+            let from = objects.get(attacker).borrow().pos.unwrap().point;
+            let to = objects.get(target).borrow().pos.unwrap().point;
+            let dir = hex::direction(from, to);
+            let side = |dir| world.hex_grid().go(to, dir, 1).unwrap_or(to);
+            [(center, to), (left, side(dir.rotate_ccw())), (right, side(dir.rotate_cw()))]
+        };
+
+        let mut r: Vec<(object::Handle, attack::Outcome)> = Vec::new();
+        let mut i = 0;
+        for &(count, via) in &vias {
+            for _ in 0..count {
+                let (blocker, end) = self.world.borrow().objects().trace_shot(attacker, via, range);
+                if let Some(projectile) = projectile {
+                    self.launch_projectile(attacker, projectile, end,
+                        Duration::from_millis(i * 60));
+                }
+                i += 1;
+
+                let victim = blocker.filter(|&b| self.world.borrow().objects().get(b).borrow()
+                    .sub.critter().map(|c| !c.is_dead()).unwrap_or(false));
+                if let Some(victim) = victim {
+                    let outcome = self.attack_outcome(attacker, victim,
+                        attack::HitLocation::Uncalled);
+                    if let Some((_, o)) = r.iter_mut().find(|(v, _)| *v == victim) {
+                        o.merge(&outcome);
+                    } else {
+                        r.push((victim, outcome));
+                    }
+                }
+            }
+        }
+        r
+    }
+
+    fn apply_attack_outcome(&mut self, attacker: object::Handle, target: object::Handle,
+        outcome: &attack::Outcome, ui: &mut Ui)
+    {
+        let source = self.attack_damage_source(attacker);
+        self.apply_damage(target, outcome.damage, outcome.defender_flags, &source, ui);
+        let self_source = combat::DamageSource {
+            pos: None,
+            attacker: None,
+            ..source
+        };
+        self.apply_damage(attacker, 0, outcome.attacker_flags, &self_source, ui);
+
+        let msg = {
+            let world = self.world.borrow();
+            // TODO take the text from combat.msg
+            let mut msg = world.object_name(attacker).unwrap_or_default();
+            if outcome.roll.is_success() {
+                msg.push_str(if outcome.roll.is_critical() { " critically hit " } else { " hit " });
+                msg.push_str(world.object_name(target).unwrap_or_default());
+                msg.push_str(format!(" for {} hit points", outcome.damage));
+                if world.objects().get(target).borrow().sub.critter().unwrap().is_dead() {
+                    msg.push_str(" and killed them");
+                }
+            } else {
+                msg.push_str(" missed ");
+                msg.push_str(world.object_name(target).unwrap_or_default());
+            }
+            msg.push(b'.');
+            msg
+        };
+        self.push_message(&msg, ui);
+        let damage = if outcome.roll.is_success() { Some(outcome.damage) } else { None };
+        self.show_damage_text(target, damage);
+    }
+
+    /// Shows floating text with the `damage` dealt over `target`. `None` means miss.
+    fn show_damage_text(&mut self, target: object::Handle, damage: Option<i32>) {
+        // TODO take the text from combat.msg
+        let text = BString::from(damage
+            .map(|d| format!("-{}", d))
+            .unwrap_or_else(|| "Miss".into()));
+        self.world.borrow_mut().show_floating_text(Some(target), &text, floating_text::Options {
+            font_key: FontKey::antialiased(1),
+            color: if damage.is_some() { RED } else { WHITE },
+            outline_color: Some(BLACK),
+        });
+    }
+
+    /// Damages critters around `pos` and shows the explosion animation.
+    // action_explode()
+    fn explode(&mut self, pos: EPoint, damage: i32, ui: &mut Ui) {
+        {
+            let world = &mut self.world.borrow_mut();
+            let mut obj = Object::new(FrameId::EXPLOSION, ObjectProtoId::None, Some(pos));
+            obj.flags.insert(Flag::NoBlock | Flag::ShootThru | Flag::LightThru);
+            let obj = world.insert_object(obj);
+            self.sequencer.start(FrameAnim::new(obj, None, AnimDirection::Forward, false)
+                .then(Remove::new(obj)));
+        }
+
+        let victims: Vec<_> = {
+            let world = self.world.borrow();
+            let objects = world.objects();
+            objects.iter()
+                .filter_map(|h| {
+                    let obj = objects.get(h).borrow();
+                    let p = obj.pos?;
+                    let alive = obj.sub.critter().map(|c| !c.is_dead()).unwrap_or(false);
+                    let distance = hex::distance(p.point, pos.point);
+                    if alive && p.elevation == pos.elevation
+                        && distance <= attack::EXPLOSION_RADIUS
+                    {
+                        Some((h, distance))
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        };
+        for (victim, distance) in victims {
+            let defender = self.attack_defender(victim);
+            let damage = attack::damage(attack::explosion_damage(damage, distance),
+                DamageKind::Explosion, None, 2, false, &defender);
+            let source = combat::DamageSource {
+                pos: Some(pos.point),
+                attacker: None,
+                damage_kind: DamageKind::Explosion,
+                attack_kind: AttackKind::Throw,
+            };
+            self.apply_damage(victim, damage, DamageFlag::Hit | DamageFlag::KnockedDown, &source, ui);
+
+            let msg = {
+                let world = self.world.borrow();
+                // TODO take the text from combat.msg
+                let mut msg = world.object_name(victim).unwrap_or_default();
+                msg.push_str(format!(" was hit for {} hit points", damage));
+                if world.objects().get(victim).borrow().sub.critter().unwrap().is_dead() {
+                    msg.push_str(" and killed");
+                }
+                msg.push(b'.');
+                msg
+            };
+            self.push_message(&msg, ui);
+            self.show_damage_text(victim, Some(damage));
+        }
+    }
+
+    fn attack_damage_source(&self, attacker: object::Handle) -> combat::DamageSource {
+        let (_, _, attack_kind) = self.attack_params(attacker);
+        let world = self.world.borrow();
+        let objects = world.objects();
+        let damage_kind = objects.equipped(attacker, EquipmentSlot::RightHand)
+            .and_then(|item| objects.get(item).borrow().pid.proto_id())
+            .and_then(|pid| self.proto_db.proto(pid).unwrap()
+                .sub.item()
+                .and_then(|i| i.sub.weapon())
+                .map(|w| w.damage_kind))
+            .unwrap_or(DamageKind::Melee);
+        let pos = objects.get(attacker).borrow().pos.map(|p| p.point);
+        combat::DamageSource {
+            pos,
+            attacker: Some(attacker),
+            damage_kind,
+            attack_kind,
+        }
+    }
+
+    /// Applies `damage` and the persistent effects among `flags` to `critter` and plays
+    /// the matching hit reaction, knockdown or death animation.
+    // action_dmg(), show_damage_to_object()
+    fn apply_damage(&mut self, critter: object::Handle, damage: i32, flags: BitFlags<DamageFlag>,
+        source: &combat::DamageSource, ui: &mut Ui)
+    {
+        let down = DamageFlag::KnockedDown | DamageFlag::KnockedOut;
+
+        let (was_dead, was_down, health, from_front) = {
+            let world = self.world.borrow();
+            let mut obj = world.objects().get(critter).borrow_mut();
+            let from_front = source.pos
+                .map(|p| combat::is_in_front(obj.pos.unwrap().point, obj.direction, p))
+                .unwrap_or(true);
+            let critter = obj.sub.critter_mut().unwrap();
+            let was_dead = critter.is_dead();
+            let was_down = critter.combat.damage_flags.intersects(down);
+            let health = critter.health;
+            critter.health -= damage;
+            critter.combat.damage_flags |= flags & (DamageFlag::KnockedOut
+                | DamageFlag::KnockedDown
+                | DamageFlag::CripLegLeft
+                | DamageFlag::CripLegRight
+                | DamageFlag::CripArmLeft
+                | DamageFlag::CripArmRight
+                | DamageFlag::Blind
+                | DamageFlag::LoseTurn);
+            (was_dead, was_down, health, from_front)
+        };
+        if was_dead {
+            return;
+        }
+
+        if health - damage <= 0 || flags.contains(DamageFlag::Dead) {
+            let anim = combat::death_anim(source.damage_kind, source.attack_kind,
+                damage - health, from_front);
+            self.kill_critter(critter, Some(anim), source.attacker, ui);
+        } else if flags.intersects(down) && !was_down {
+            self.knock_down(critter, from_front);
+        } else if flags.contains(DamageFlag::Hit) && !was_down
+            && !self.has_running_sequence(critter)
+        {
+            let anim = if from_front { CritterAnim::HitFromFront } else { CritterAnim::HitFromBack };
+            self.start_critter_sequence(critter,
+                FrameAnim::new(critter, Some(anim), AnimDirection::Forward, false)
+                    .then(Stand::new(critter)));
+        }
+    }
+
+    /// Cancels the running sequence of `critter` and starts `seq` in its place.
+    fn start_critter_sequence(&mut self, critter: object::Handle, seq: impl Sequence + 'static) {
+        let world = self.world.borrow();
+        let mut obj = world.objects().get(critter).borrow_mut();
+        obj.cancel_sequence();
+        let (seq, signal) = seq.cancellable();
+        obj.sequence = Some(signal);
+        self.sequencer.start(seq);
+    }
+
+    fn has_critter_anim(&self, critter: object::Handle, anim: CritterAnim) -> bool {
+        let fid = self.world.borrow().objects().get(critter).borrow().fid;
+        fid.critter()
+            .map(|fid| self.frm_db.get(fid.with_anim(anim).into()).is_ok())
+            .unwrap_or(false)
+    }
+
+    /// Plays the knockdown animation. Outside of combat the critter gets up right away,
+    /// in combat it stays down until its turn.
+    fn knock_down(&mut self, critter: object::Handle, from_front: bool) {
+        let anim = combat::fall_anim(from_front);
+        if self.is_in_combat() {
+            self.start_critter_sequence(critter,
+                FrameAnim::new(critter, Some(anim), AnimDirection::Forward, false));
+        } else {
+            self.world.borrow().objects().get(critter).borrow_mut()
+                .sub.critter_mut().unwrap()
+                .combat.damage_flags.remove(DamageFlag::KnockedDown | DamageFlag::KnockedOut);
+            self.start_critter_sequence(critter,
+                FrameAnim::new(critter, Some(anim), AnimDirection::Forward, false)
+                    .then(FrameAnim::new(critter, Some(combat::stand_up_anim(anim)),
+                        AnimDirection::Forward, false))
+                    .then(Stand::new(critter)));
+        }
+    }
+
+    /// Plays the animation of getting up for the knocked down `critter`.
+    // dude_standup()
+    fn stand_up(&mut self, critter: object::Handle) {
+        let fall_anim = {
+            let world = self.world.borrow();
+            let mut obj = world.objects().get(critter).borrow_mut();
+            obj.sub.critter_mut().unwrap()
+                .combat.damage_flags.remove(DamageFlag::KnockedDown);
+            obj.fid.critter().map(|fid| fid.anim()).unwrap_or(CritterAnim::FallFront)
+        };
+        self.start_critter_sequence(critter,
+            FrameAnim::new(critter, Some(combat::stand_up_anim(fall_anim)),
+                    AnimDirection::Forward, false)
+                .then(Stand::new(critter)));
+    }
+
+    /// Marks `critter` dead, plays its death `anim` and runs its `destroy_p_proc`.
+    /// If the critter lacks `anim` the plain falling animation is used.
+    // critter_kill()
+    /// Kills `critter`. If `killer` is the dude, the dude gets the experience for the kill.
+    fn kill_critter(&mut self, critter: object::Handle, anim: Option<CritterAnim>,
+        killer: Option<object::Handle>, ui: &mut Ui)
+    {
+        let (script, pid) = {
+            let world = self.world.borrow();
+            let mut obj = world.objects().get(critter).borrow_mut();
+            obj.flags.insert(Flag::NoBlock);
+            let c = obj.sub.critter_mut().unwrap();
+            c.combat.damage_flags.insert(DamageFlag::Dead);
+            c.combat.damage_flags.remove(DamageFlag::KnockedDown | DamageFlag::KnockedOut);
+            (obj.script, obj.pid.proto_id())
+        };
+        self.world.borrow_mut().party_mut().remove(critter);
+
+        let dude_obj = self.world.borrow().dude_obj();
+        if let Some(pid) = pid.filter(|_| killer.is_some() && killer == dude_obj) {
+            let exp = self.proto_db.proto(pid).unwrap().sub.critter().unwrap().experience;
+            let levels = {
+                let world = self.world.borrow();
+                let mut dude_obj = world.objects().get(dude_obj.unwrap()).borrow_mut();
+                self.stats.add_experience(exp, &mut dude_obj, &world)
+            };
+            if levels > 0 {
+                self.push_message(self.stats.level_up_message(), ui);
+            }
+        }
+
+        let anim = anim
+            .filter(|&a| self.has_critter_anim(critter, a))
+            .unwrap_or(CritterAnim::FallBack);
+        self.start_critter_sequence(critter,
+            FrameAnim::new(critter, Some(anim), AnimDirection::Forward, false));
+
+        if let Some((sid, _)) = script {
+            self.scripts.execute_predefined_proc(sid, PredefinedProc::Destroy,
+                &mut script::Context {
+                    world: &mut self.world.borrow_mut(),
+                    sequencer: &mut self.sequencer,
+                    stats: &mut self.stats,
                     dialog: &mut self.dialog,
                     ui,
                     message_panel: self.message_panel,
                     map_id: self.map_id.unwrap(),
-                });
-            then {
-                assert!(r.suspend.is_none(), "can't suspend");
-                r.script_overrides
+                })
+                .map(|r| r.suspend.map(|_| panic!("can't suspend in Destroy")));
+        }
+    }
+
+    fn dude_attack(&mut self, target: object::Handle, ui: &mut Ui) {
+        let dude_obj = self.world.borrow().dude_obj().unwrap();
+        if target == dude_obj || !self.is_dude_turn() || self.has_running_sequence(dude_obj)
+            || self.called_shot.is_some()
+        {
+            return;
+        }
+        let (_, _, kind) = self.attack_params(dude_obj);
+        if self.aimed_attack && attack::can_call_shot(kind) {
+            self.show_called_shot(target, ui);
+        } else {
+            self.dude_attack_location(target, attack::HitLocation::Uncalled, ui);
+        }
+    }
+
+    fn dude_attack_location(&mut self, target: object::Handle, location: attack::HitLocation,
+        ui: &mut Ui)
+    {
+        let dude_obj = self.world.borrow().dude_obj().unwrap();
+        if !self.combat_attack(dude_obj, target, location) {
+            // TODO take the text from combat.msg
+            let ap_cost = self.attack_ap_cost(dude_obj, location);
+            let (_, range, _) = self.attack_params(dude_obj);
+            let ap = self.world.borrow().objects().get(dude_obj).borrow()
+                .sub.critter().unwrap().combat.ap;
+            let msg = if ap < ap_cost {
+                format!("You need {} action points.", ap_cost)
+            } else {
+                format!("Your target is out of range ({} hexes max).", range)
+            };
+            self.push_message(BString::from(msg).as_ref(), ui);
+        }
+    }
+
+    // called_shot_display()
+    fn show_called_shot(&mut self, target: object::Handle, ui: &mut Ui) {
+        let dude_obj = self.world.borrow().dude_obj().unwrap();
+        let hit_chances = EnumMap::from(|loc| self.hit_chance(dude_obj, target, loc));
+        let (fid, pid) = {
+            let world = self.world.borrow();
+            let targeto = world.objects().get(target).borrow();
+            (targeto.fid, targeto.pid.proto_id())
+        };
+        let kill_kind = pid
+            .and_then(|pid| self.proto_db.proto(pid).unwrap().sub.critter().map(|c| c.kill_kind))
+            .map(|k| k as i32)
+            .unwrap_or(0);
+        // hit_location_get_name()
+        let location_names = EnumMap::from(|loc| self.combat_msgs
+            .get(1000 + 10 * kill_kind + loc as i32)
+            .map(|m| m.text.clone())
+            .unwrap_or_default());
+        self.called_shot = Some(CalledShotWindow::show(ui, target, fid, &hit_chances,
+            &location_names));
+    }
+
+    fn handle_called_shot_command(&mut self, cmd: called_shot::Command, ui: &mut Ui) {
+        let target = if let Some(win) = self.called_shot.take() {
+            let target = win.target();
+            win.hide(ui);
+            target
+        } else {
+            return;
+        };
+        match cmd {
+            called_shot::Command::Pick(location) => self.dude_attack_location(target, location, ui),
+            called_shot::Command::Cancel => {}
+        }
+    }
+
+    fn toggle_aimed_attack(&mut self, ui: &mut Ui) {
+        self.aimed_attack = !self.aimed_attack;
+        // TODO take the text from combat.msg
+        let msg = if self.aimed_attack {
+            b"Aimed attacks on.".as_ref()
+        } else {
+            b"Aimed attacks off.".as_ref()
+        };
+        self.push_message(msg.into(), ui);
+    }
+
+    /// Makes the NPC whose turn it is act according to its AI packet.
+    /// Returns `false` if there's nothing to do and the turn should end.
+    // combat_ai()
+    fn combat_ai(&mut self, obj: object::Handle) -> bool {
+        let (packet, health, max_health, damage_flags, maneuver, ap) = {
+            let world = self.world.borrow();
+            let objr = world.objects().get(obj).borrow();
+            let critter = objr.sub.critter().unwrap();
+            (world.ai_packet(obj),
+                critter.health,
+                self.stats.stat(Stat::HitPoints, &objr, &world),
+                critter.combat.damage_flags,
+                critter.combat.maneuver,
+                critter.combat.ap)
+        };
+
+        if let Some(packet) = &packet {
+            if maneuver.contains(Maneuver::Fleeing)
+                || ai::should_flee(packet, health, max_health, damage_flags)
+            {
+                self.world.borrow().objects().get(obj).borrow_mut()
+                    .sub.critter_mut().unwrap()
+                    .combat.maneuver.insert(Maneuver::Fleeing);
+                return self.ai_flee(obj);
+            }
+            if ap >= ai::USE_ITEM_AP_COST
+                && ai::wants_chem(packet.chem_use, health, max_health, random(1, 100))
+                && self.ai_use_healing_drug(obj)
+            {
+                return true;
+            }
+        }
+        if maneuver.contains(Maneuver::Disengaging) {
+            return false;
+        }
+
+        let target = if let Some(t) = self.ai_target(obj, packet.as_ref()) {
+            t
+        } else {
+            return false;
+        };
+        self.world.borrow().objects().get(obj).borrow_mut()
+            .sub.critter_mut().unwrap()
+            .combat.who_hit_me = Some(target);
+
+        if let Some(packet) = &packet {
+            self.ai_choose_weapon(obj, packet.best_weapon);
+        }
+
+        let (_, _, kind) = self.attack_params(obj);
+        let may_attack = !attack::is_burst(kind) || packet.as_ref()
+            .map(|p| ai::may_use_area_attack(p.area_attack_mode,
+                self.friend_distance(obj, target), random(1, 100)))
+            .unwrap_or(true);
+        let location = packet.as_ref()
+            .map(|p| (p, ai::called_shot_location(p, kind, &mut random)))
+            .filter(|&(p, loc)| self.hit_chance(obj, target, loc) >= p.min_to_hit)
+            .map(|(_, loc)| loc)
+            .unwrap_or(attack::HitLocation::Uncalled);
+        let may_attack_uncalled = packet.as_ref()
+            .map(|p| self.hit_chance(obj, target, attack::HitLocation::Uncalled) >= p.min_to_hit)
+            .unwrap_or(true);
+        if may_attack && (location != attack::HitLocation::Uncalled
+                && self.combat_attack(obj, target, location)
+            || may_attack_uncalled
+                && self.combat_attack(obj, target, attack::HitLocation::Uncalled))
+        {
+            return true;
+        }
+
+        if packet.map(|p| p.distance == AiDistance::Stay).unwrap_or(false) {
+            return false;
+        }
+        let (_, range, _) = self.attack_params(obj);
+        let world = self.world.borrow();
+        let steps = (ap / combat::MOVE_AP_COST) as usize;
+        if steps == 0 || world.objects().distance(obj, target).map(|d| d <= range).unwrap_or(true) {
+            return false;
+        }
+        let target_pos = world.objects().get(target).borrow().pos.unwrap().point;
+        if world.path_for_object(obj, target_pos, true, true).map(|p| p.is_empty()).unwrap_or(true) {
+            return false;
+        }
+        let (seq, signal) = Move::new(obj, target_pos, CritterAnim::Walk)
+            .max_path_len(steps)
+            .cancellable();
+        world.objects().get(obj).borrow_mut().sequence = Some(signal);
+        self.sequencer.start(seq.then(Stand::new(obj)));
+
+        true
+    }
+
+    /// Distance from `target` to the closest living combatant on the team of `obj`.
+    fn friend_distance(&self, obj: object::Handle, target: object::Handle) -> Option<u32> {
+        let world = self.world.borrow();
+        let objects = world.objects();
+        let team_id = objects.get(obj).borrow().sub.critter()?.combat.team_id;
+        world.combat.as_ref()?.combatants().iter()
+            .cloned()
+            .filter(|&h| h != obj)
+            .filter(|&h| objects.get(h).borrow().sub.critter()
+                .map(|c| c.combat.team_id == team_id && !c.is_dead())
+                .unwrap_or(false))
+            .filter_map(|h| objects.distance(h, target))
+            .min()
+    }
+
+    /// Chooses the target for `obj` among the combatants that are hostile to its team.
+    fn ai_target(&self, obj: object::Handle, packet: Option<&AiPacket>) -> Option<object::Handle> {
+        let world = self.world.borrow();
+        let objects = world.objects();
+        let combat = world.combat.as_ref().unwrap();
+        let critter = |h| objects.get(h).borrow().sub.critter()
+            .map(|c| (c.combat.team_id, c.combat.who_hit_me, c.health, c.is_dead()))
+            .unwrap();
+        let (team_id, current_target, _, _) = critter(obj);
+
+        let candidates: Vec<_> = combat.combatants().iter()
+            .cloned()
+            .filter(|&h| h != obj)
+            .filter_map(|h| {
+                let (other_team_id, other_target, health, is_dead) = critter(h);
+                let hostile = Some(h) == current_target
+                    || other_target.map(|t| t != h && critter(t).0 == team_id).unwrap_or(false);
+                if is_dead || other_team_id == team_id || !hostile {
+                    return None;
+                }
+                Some(ai::Candidate {
+                    obj: h,
+                    distance: objects.distance(obj, h)?,
+                    health,
+                    attacking_me: other_target == Some(obj),
+                })
+            })
+            .collect();
+
+        if let Some(packet) = packet {
+            ai::pick_target(packet, &candidates)
+        } else {
+            current_target.filter(|&t| candidates.iter().any(|c| c.obj == t))
+        }
+    }
+
+    /// Equips the weapon from the inventory of `obj` that is the best for `pref`.
+    fn ai_choose_weapon(&mut self, obj: object::Handle, pref: BestWeapon) {
+        let mut world = self.world.borrow_mut();
+        let weapons: Vec<_> = {
+            let objects = world.objects();
+            let items: Vec<_> = objects.get(obj).borrow().inventory.items.iter()
+                .map(|i| i.object)
+                .collect();
+            items.into_iter()
+                .filter_map(|item| {
+                    let pid = objects.get(item).borrow().pid.proto_id()?;
+                    let proto = self.proto_db.proto(pid).unwrap();
+                    let weapon = proto.sub.item()?.sub.weapon()?;
+                    Some((Some(item), ai::WeaponClass::of(weapon.attack_kind.primary)))
+                })
+                .chain(std::iter::once((None, ai::WeaponClass::Unarmed)))
+                .collect()
+        };
+        let best = ai::pick_weapon(pref, &weapons);
+        if best != world.objects().equipped(obj, EquipmentSlot::RightHand) {
+            if let Some(weapon) = best {
+                world.objects_mut().equip(obj, weapon, EquipmentSlot::RightHand);
+            } else {
+                world.objects_mut().unequip(obj, EquipmentSlot::RightHand);
+            }
+        }
+    }
+
+    /// Makes `obj` run away from its attacker.
+    fn ai_flee(&mut self, obj: object::Handle) -> bool {
+        let world = self.world.borrow();
+        let (ap, threat) = {
+            let objr = world.objects().get(obj).borrow();
+            let critter = objr.sub.critter().unwrap();
+            (critter.combat.ap, critter.combat.who_hit_me.or_else(|| world.dude_obj()))
+        };
+        let steps = (ap / combat::MOVE_AP_COST) as u32;
+        let threat = if let Some(t) = threat.filter(|&t| t != obj) {
+            t
+        } else {
+            return false;
+        };
+        if steps == 0 {
+            return false;
+        }
+        let pos = world.objects().get(obj).borrow().pos.unwrap().point;
+        let threat_pos = world.objects().get(threat).borrow().pos.unwrap().point;
+        let distance = hex::distance(threat_pos, pos);
+        let to = world.hex_grid().beyond(threat_pos, pos, distance + steps);
+        if to == pos || world.path_for_object(obj, to, true, true)
+            .map(|p| p.is_empty()).unwrap_or(true)
+        {
+            return false;
+        }
+        let (seq, signal) = Move::new(obj, to, CritterAnim::Running)
+            .max_path_len(steps as usize)
+            .cancellable();
+        world.objects().get(obj).borrow_mut().sequence = Some(signal);
+        self.sequencer.start(seq.then(Stand::new(obj)));
+
+        true
+    }
+
+    /// Uses a drug that restores hit points from the inventory of `obj`.
+    /// Returns `false` if there's no such drug.
+    fn ai_use_healing_drug(&mut self, obj: object::Handle) -> bool {
+        let drug = {
+            let world = self.world.borrow();
+            let objr = world.objects().get(obj).borrow();
+            objr.inventory.items.iter()
+                .map(|i| i.object)
+                .find(|&item| {
+                    let pid = if let Some(v) = world.objects().get(item).borrow().pid.proto_id() {
+                        v
+                    } else {
+                        return false;
+                    };
+                    let proto = self.proto_db.proto(pid).unwrap();
+                    if let Some(proto::SubItem::Drug(drug)) = proto.sub.item().map(|i| &i.sub) {
+                        drug.effects.iter().any(|e| e.delay == 0
+                            && e.stat == Stat::CurrentHitPoints
+                            && match e.modifier {
+                                proto::DrugEffectModifier::Fixed(v) => v > 0,
+                                proto::DrugEffectModifier::Random(_, to) => to > 0,
+                            })
+                    } else {
+                        false
+                    }
+                })
+        };
+        let item = if let Some(v) = drug {
+            v
+        } else {
+            return false;
+        };
+        debug!("{:?} uses drug {:?} to heal", obj, item);
+
+        self.use_drug(obj, item);
+
+        let world = self.world.borrow();
+        let mut objr = world.objects().get(obj).borrow_mut();
+        objr.sub.critter_mut().unwrap().combat.ap -= ai::USE_ITEM_AP_COST;
+
+        true
+    }
+
+    /// Consumes one unit of the drug `item` from the inventory of `critter`. Immediate effects
+    /// of the drug are applied right away, the delayed ones are scheduled on the timer.
+    // item_d_take_drug()
+    fn use_drug(&mut self, critter: object::Handle, item: object::Handle) {
+        let world = &mut *self.world.borrow_mut();
+        let effects: Vec<_> = {
+            let pid = world.objects().get(item).borrow().pid.proto_id().unwrap();
+            let proto = self.proto_db.proto(pid).unwrap();
+            if let Some(proto::SubItem::Drug(drug)) = proto.sub.item().map(|i| &i.sub) {
+                drug.effects.iter()
+                    .map(|e| (e.delay, e.stat, match e.modifier {
+                        proto::DrugEffectModifier::Fixed(v) => v,
+                        proto::DrugEffectModifier::Random(from, to) => random(from, to),
+                    }))
+                    .collect()
             } else {
-                false
+                panic!("{:?} is not a drug", item);
             }
         };
+        // TODO addiction
 
-        let mut r = Vec::new();
+        let (item, _) = world.objects_mut().remove_from_inventory(critter, item, 1).unwrap();
+        world.objects_mut().remove(item);
 
-        if !script_overrides {
-            let world = self.world.borrow();
-            let examinedo = world.objects().get(examined).borrow();
-            if !examinedo.sub.critter().map(|c| c.is_dead()).unwrap_or(false) {
-                let descr = if let Some(descr) = examinedo.pid.proto_id()
-                    .and_then(|pid| self.proto_db.description(pid).unwrap())
-                    .filter(|desr| {
-                        // Compare to "<None>".
-                        desr != &self.proto_db.messages().get(10).unwrap().text
-                    })
-                {
-                    descr.to_owned()
-                } else {
-                    self.proto_db.messages().get(493).unwrap().text.clone()
-                };
-                r.push(descr);
+        let now = world.game_time;
+        for (delay, stat, amount) in effects {
+            if delay == 0 {
+                let mut obj = world.objects().get(critter).borrow_mut();
+                self.stats.apply_drug_effect(stat, amount, &mut obj, world);
+            } else {
+                world.timer.push(timer::Event {
+                    time: now.add_decis(delay * 60 * 10),
+                    obj: critter,
+                    kind: timer::EventKind::DrugEffect { stat, amount },
+                });
             }
         }
+    }
 
-        // TODO critter state/hp, weapon/ammo description, car info etc
-
-        r
+    fn handle_combat_requests(&mut self, ui: &mut Ui) {
+        loop {
+            let request = {
+                let world = &mut self.world.borrow_mut();
+                if world.combat_requests.is_empty() {
+                    break;
+                }
+                world.combat_requests.remove(0)
+            };
+            match request {
+                combat::Request::Attack { attacker, target } => {
+                    {
+                        let world = &mut self.world.borrow_mut();
+                        if let Some(critter) = world.objects().get(attacker).borrow_mut()
+                            .sub.critter_mut()
+                        {
+                            critter.combat.who_hit_me = Some(target);
+                        }
+                        if let Some(combat) = &mut world.combat {
+                            combat.add(attacker);
+                            combat.add(target);
+                        }
+                    }
+                    if !self.is_in_combat() {
+                        self.start_combat(Some(attacker), ui);
+                    }
+                }
+                combat::Request::Explode { pos, damage } => self.explode(pos, damage, ui),
+                combat::Request::Damage { target, damage, kind, bypass, animate } => {
+                    let defender = self.attack_defender(target);
+                    let damage = attack::damage(damage, kind, None, 2, bypass, &defender);
+                    let source = combat::DamageSource {
+                        pos: None,
+                        attacker: None,
+                        damage_kind: kind,
+                        attack_kind: AttackKind::Stand,
+                    };
+                    let flags = if animate { DamageFlag::Hit.into() } else { BitFlags::empty() };
+                    self.apply_damage(target, damage, flags, &source, ui);
+                }
+                combat::Request::Kill { target, anim } => {
+                    let is_dead = self.world.borrow().objects().get(target).borrow()
+                        .sub.critter().map(|c| c.is_dead()).unwrap_or(true);
+                    if !is_dead {
+                        self.kill_critter(target, anim, None, ui);
+                    }
+                }
+                combat::Request::Terminate => self.end_combat(ui),
+            }
+        }
     }
 
-    fn dude_examine_object(&mut self, obj: object::Handle, ui: &mut Ui) {
-        let dude_obj = self.world().borrow().dude_obj().unwrap();
-        for msg in self.examine_object(dude_obj, obj, ui) {
-            self.push_message(&msg, ui);
+    fn dude_end_turn(&mut self, ui: &mut Ui) {
+        let dude_obj = self.world.borrow().dude_obj().unwrap();
+        if self.is_dude_turn() && !self.has_running_sequence(dude_obj) {
+            self.end_turn(ui);
         }
     }
 
-    fn push_message(&self, msg: &bstr, ui: &mut Ui) {
-        let mut mp = ui.widget_mut::<MessagePanel>(self.message_panel);
-        let mut m = BString::new();
-        m.push(BULLET);
-        m.push_str(msg);
-        mp.push_message(m);
+    /// Ends combat if no one is hostile to the dude.
+    fn dude_end_combat(&mut self, ui: &mut Ui) {
+        let dude_obj = self.world.borrow().dude_obj().unwrap();
+        if self.is_dude_turn() && !self.has_running_sequence(dude_obj) {
+            if self.is_dude_targeted() {
+                // TODO take the text from combat.msg
+                self.push_message(b"You cannot end combat with enemies nearby.".as_ref().into(), ui);
+            } else {
+                self.end_combat(ui);
+            }
+        }
     }
 
-    // action_talk_to()
-    fn action_talk(&mut self, talker: object::Handle, talked: object::Handle, ui: &mut Ui) {
-        // TODO handle combat state
+    fn update_combat(&mut self, ui: &mut Ui) {
+        self.handle_combat_requests(ui);
 
-        {
-            let world = self.world.borrow();
-            let objs = world.objects();
+        let current = if let Some(c) = &self.world.borrow().combat {
+            c.current()
+        } else {
+            return;
+        };
+        if self.has_running_sequence(current) {
+            return;
+        }
+        if self.is_dude_turn() {
+            let ap = self.world.borrow().objects().get(current).borrow()
+                .sub.critter().unwrap().combat.ap;
+            if ap <= 0 {
+                self.end_turn(ui);
+            }
+        } else if !self.combat_ai(current) {
+            self.end_turn(ui);
+        }
+    }
 
-            if objs.distance(talker, talked).unwrap() >= 9 || // TODO this value is different (12) in can_talk2()
-                objs.is_shot_blocked(talker, talked)
-            {
-                // TODO original cancels only Walk/Run animation, is this important?
-                objs.get(talker).borrow_mut().cancel_sequence();
+    fn update_game_time(&mut self, delta: Duration, ui: &mut Ui) {
+        const DECI: Duration = Duration::from_millis(100);
 
-                let dest = objs.get(talked).borrow().pos.unwrap().point;
-                // TODO (move_to_object()) shorten the move path by 1 tile if the `talked` is MultiHex
-                let (seq, cancel) = Move::new(talker, dest, CritterAnim::Running).cancellable();
-                objs.get(talker).borrow_mut().sequence = Some(cancel);
-                self.sequencer.start(seq
-                    .then(Stand::new(talker))
-                    .then(PushEvent::new(sequence::Event::Talk { talker, talked })));
-                return;
-            }
+        if self.is_in_combat() {
+            return;
         }
 
-        self.talk(talker, talked, ui);
+        self.game_time_frac += delta;
+        let mut decis = 0;
+        while self.game_time_frac >= DECI {
+            self.game_time_frac -= DECI;
+            decis += 1;
+        }
+        if decis > 0 {
+            let world = &mut self.world.borrow_mut();
+            world.game_time = world.game_time.add_decis(decis);
+        }
+
+        self.handle_timer_events(ui);
     }
 
-    // talk_to(), gdialogEnter()
-    fn talk(&mut self, talker: object::Handle, talked: object::Handle, ui: &mut Ui) {
-        if self.world.borrow().objects().can_talk_now(talker, talked) {
+    // queue_process()
+    fn handle_timer_events(&mut self, ui: &mut Ui) {
+        loop {
             let world = &mut self.world.borrow_mut();
-            // TODO optimize this.
-            for obj in world.objects().iter() {
-                world.objects().get(obj).borrow_mut().cancel_sequence();
+            let now = world.game_time;
+            let event = if let Some(e) = world.timer.pop_due(now) {
+                e
+            } else {
+                break;
+            };
+            if !world.objects().contains(event.obj) {
+                continue;
             }
-            self.sequencer.cleanup(&mut sequence::Cleanup {
-                world,
-            });
-            let script = world.objects().get(talked).borrow().script;
-            if let Some((sid, _)) = script {
-                match self.scripts.execute_predefined_proc(sid, PredefinedProc::Talk,
-                    &mut script::Context {
-                        world,
-                        sequencer: &mut self.sequencer,
-                        dialog: &mut self.dialog,
-                        ui,
-                        message_panel: self.message_panel,
-                        map_id: self.map_id.unwrap(),
-                    }).and_then(|r| r.suspend)
-                    {
-                        None | Some(Suspend::GsayEnd) => {}
+            match event.kind {
+                timer::EventKind::Script { fixed_param } => {
+                    let script = world.objects().get(event.obj).borrow().script;
+                    if let Some((sid, _)) = script {
+                        self.scripts.set_fixed_param(sid, fixed_param);
+                        self.scripts.execute_predefined_proc(sid, PredefinedProc::TimedEvent,
+                            &mut script::Context {
+                                world,
+                                sequencer: &mut self.sequencer,
+                                stats: &mut self.stats,
+                                dialog: &mut self.dialog,
+                                ui,
+                                message_panel: self.message_panel,
+                                map_id: self.map_id.unwrap(),
+                            })
+                            .map(|r| match r.suspend {
+                                // The dialog started by the script resumes it when finished.
+                                None | Some(Suspend::GsayEnd) => {}
+                            });
                     }
+                }
+                timer::EventKind::DrugEffect { stat, amount } => {
+                    let mut obj = world.objects().get(event.obj).borrow_mut();
+                    self.stats.apply_drug_effect(stat, amount, &mut obj, world);
+                }
+            }
+            if self.scripts.can_resume() {
+                // The rest of the events fire once the dialog is over.
+                break;
             }
-        } else {
-            assert_eq!(talker, self.world.borrow().dude_obj().unwrap());
-            let msg = &self.misc_msgs.get(2000).unwrap().text;
-            self.push_message(&msg, ui);
-        }
-    }
-
-    fn create_scroll_areas(rect: Rect, ui: &mut Ui) -> EnumMap<ScrollDirection, ui::Handle> {
-        let mut new = |rect, cur, curx| {
-            let win = ui.new_window(rect, None);
-            ui.new_widget(win, Rect::with_size(0, 0, rect.width(), rect.height()), None, None,
-                ScrollArea::new(cur, curx, Duration::from_millis(0), Duration::from_millis(30)))
-        };
-        use ui::Cursor::*;
-        use ScrollDirection::*;
-        enum_map! {
-            N => new(
-                Rect::new(rect.left + 1, rect.top, rect.right - 1, rect.top + 1),
-                ScrollNorth, ScrollNorthX),
-            NE => new(
-                Rect::new(rect.right - 1, rect.top, rect.right, rect.top + 1),
-                ScrollNorthEast, ScrollNorthEastX),
-            E => new(
-                Rect::new(rect.right - 1, rect.top + 1, rect.right, rect.bottom - 1),
-                ScrollEast, ScrollEastX),
-            SE => new(
-                Rect::new(rect.right - 1, rect.bottom - 1, rect.right, rect.bottom),
-                ScrollSouthEast, ScrollSouthEastX),
-            S => new(
-                Rect::new(rect.left + 1, rect.bottom - 1, rect.right - 1, rect.bottom),
-                ScrollSouth, ScrollSouthX),
-            SW => new(
-                Rect::new(rect.left, rect.bottom - 1, rect.left + 1, rect.bottom),
-                ScrollSouthWest, ScrollSouthWestX),
-            W => new(
-                Rect::new(rect.left, rect.top + 1, rect.left + 1, rect.bottom - 1),
-                ScrollWest, ScrollWestX),
-            NW => new(
-                Rect::new(rect.left, rect.top, rect.left + 1, rect.top + 1),
-                ScrollNorthWest, ScrollNorthWestX),
         }
     }
 }
 
 impl AppState for GameState {
     fn handle_event(&mut self, event: &Event, ui: &mut Ui) -> bool {
+        if self.character.is_some() {
+            return self.handle_character_event(event, ui);
+        }
+
+        if self.save_load.is_some() {
+            return self.handle_save_load_event(event, ui);
+        }
+
+        if self.elevator.is_some() {
+            if let Event::KeyDown { keycode: Some(Keycode::Escape), .. } = event {
+                self.hide_elevator(ui);
+                return true;
+            }
+            return false;
+        }
+
+        if let Some(WorldMapScreen { window, encounter, .. }) = &mut self.world_map {
+            if encounter.is_some() {
+                if let Event::KeyDown { keycode: Some(Keycode::Escape), .. } = event {
+                    self.handle_encounter_command(encounter::Command::Avoid, ui);
+                    return true;
+                }
+                return false;
+            }
+            let scroll = match event {
+                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
+                    window.hide_town(ui);
+                    return true;
+                }
+                Event::KeyDown { keycode: Some(Keycode::Right), .. } => Point::new(1, 0),
+                Event::KeyDown { keycode: Some(Keycode::Left), .. } => Point::new(-1, 0),
+                Event::KeyDown { keycode: Some(Keycode::Up), .. } => Point::new(0, -1),
+                Event::KeyDown { keycode: Some(Keycode::Down), .. } => Point::new(0, 1),
+                _ => return false,
+            };
+            window.scroll(ui, scroll * SUBTILE_SIZE);
+            return true;
+        }
+
+        match event {
+            Event::KeyDown { keycode: Some(Keycode::F4), .. } if !self.is_modal_window_shown() => {
+                self.show_save_load(save_load::Mode::Save, ui);
+                return true;
+            }
+            Event::KeyDown { keycode: Some(Keycode::F5), .. } if !self.is_modal_window_shown() => {
+                self.show_save_load(save_load::Mode::Load, ui);
+                return true;
+            }
+            Event::KeyDown { keycode: Some(Keycode::F6), .. } if !self.is_modal_window_shown() => {
+                self.quicksave(ui);
+                return true;
+            }
+            Event::KeyDown { keycode: Some(Keycode::F7), .. } if !self.is_modal_window_shown() => {
+                self.quickload(ui);
+                return true;
+            }
+            _ => {}
+        }
+
+        if let Event::KeyDown { keycode: Some(Keycode::I), .. } = event {
+            if self.loot.is_some() {
+                return true;
+            } else if self.inventory.is_some() {
+                self.hide_inventory(ui);
+            } else {
+                self.show_inventory(ui);
+            }
+            return true;
+        }
+
+        match event {
+            Event::KeyDown { keycode: Some(Keycode::C), .. } => {
+                self.show_character_sheet(ui);
+                return true;
+            }
+            Event::KeyDown { keycode: Some(Keycode::Space), .. } => {
+                self.dude_end_turn(ui);
+                return true;
+            }
+            Event::KeyDown { keycode: Some(Keycode::Return), .. } => {
+                if self.is_in_combat() {
+                    self.dude_end_combat(ui);
+                } else {
+                    let dude_obj = self.world.borrow().dude_obj().unwrap();
+                    self.start_combat(Some(dude_obj), ui);
+                }
+                return true;
+            }
+            _ => {}
+        }
+
         let mut world = self.world.borrow_mut();
         match event {
             Event::KeyDown { keycode: Some(Keycode::Right), .. } => {
@@ -670,6 +4844,12 @@ impl AppState for GameState {
     fn handle_ui_command(&mut self, command: UiCommand, ui: &mut Ui) {
         match command.data {
             UiCommandData::ObjectPick { kind, obj: objh } => {
+                if self.skilldex.is_some() || self.perk_select.is_some()
+                    || self.character.is_some() || self.save_load.is_some()
+                    || self.elevator.is_some()
+                {
+                    return;
+                }
                 let actions = self.actions(objh);
                 let default_action = actions.first().cloned();
                 match kind {
@@ -698,13 +4878,44 @@ impl AppState for GameState {
 
                         self.time.set_paused(true);
                     }
-                    ObjectPickKind::DefaultAction => if let Some(a) = default_action {
-                        self.handle_action(ui, objh, a);
+                    ObjectPickKind::DefaultAction => {
+                        let is_critter = self.world.borrow().objects().get(objh).borrow().kind()
+                            == EntityKind::Critter;
+                        if self.is_in_combat() && is_critter {
+                            self.dude_attack(objh, ui);
+                        } else if let Some(a) = default_action {
+                            self.handle_action(ui, objh, a);
+                        }
+                    }
+                    ObjectPickKind::Skill => if let Some(skill) = self.skill_to_use.take() {
+                        let dude_obj = self.world.borrow().dude_obj().unwrap();
+                        self.action_use_skill(dude_obj, objh, skill, ui);
                     }
                 }
             }
             UiCommandData::HexPick { action, pos } => {
                 if action {
+                    if self.skilldex.is_some() || self.perk_select.is_some()
+                        || self.character.is_some() || self.save_load.is_some()
+                        || self.elevator.is_some()
+                    {
+                        return;
+                    }
+                    let max_path_len = if self.is_in_combat() {
+                        if !self.is_dude_turn() || self.called_shot.is_some() {
+                            return;
+                        }
+                        let world = self.world.borrow();
+                        let dude_obj = world.objects().get(world.dude_obj().unwrap()).borrow();
+                        if dude_obj.has_running_sequence() {
+                            return;
+                        }
+                        Some((dude_obj.sub.critter().unwrap().combat.ap / combat::MOVE_AP_COST)
+                            .max(0) as usize)
+                    } else {
+                        None
+                    };
+
                     let world = self.world.borrow();
                     let dude_objh = world.dude_obj().unwrap();
                     if let Some(signal) = world.objects().get(dude_objh).borrow_mut().sequence.take() {
@@ -716,7 +4927,11 @@ impl AppState for GameState {
                     } else {
                         CritterAnim::Running
                     };
-                    let (seq, signal) = Move::new(dude_objh, pos.point, anim).cancellable();
+                    let mut seq = Move::new(dude_objh, pos.point, anim);
+                    if let Some(n) = max_path_len {
+                        seq = seq.max_path_len(n);
+                    }
+                    let (seq, signal) = seq.cancellable();
                     world.objects().get(dude_objh).borrow_mut().sequence = Some(signal);
                     self.sequencer.start(seq.then(Stand::new(dude_objh)));
                 } else {
@@ -731,12 +4946,43 @@ impl AppState for GameState {
                     };
                 }
             }
+            UiCommandData::Action { action } if self.inventory.as_ref()
+                .map(|inv| inv.item_action_menu.is_some()).unwrap_or(false) =>
+            {
+                let (menu, item) = self.inventory.as_mut().unwrap().item_action_menu.take().unwrap();
+                action_menu::hide(menu, ui);
+                self.handle_inventory_action(item, action, ui);
+            }
             UiCommandData::Action { action } => {
                 let object_action = self.object_action_menu.take().unwrap();
                 self.handle_action(ui, object_action.obj, action);
                 action_menu::hide(object_action.menu, ui);
                 self.time.set_paused(false);
             }
+            UiCommandData::Pick { id } if self.save_load.as_ref()
+                .map(|w| w.is_list(command.source)).unwrap_or(false) =>
+            {
+                if id < save::SLOT_COUNT {
+                    self.save_load.as_mut().unwrap().select(ui, id);
+                }
+            }
+            UiCommandData::Pick { id } if self.perk_select.as_ref()
+                .map(|w| w.is_list(command.source)).unwrap_or(false) =>
+            {
+                self.select_perk(id, ui);
+            }
+            UiCommandData::Pick { id } if self.character.as_ref()
+                .and_then(|e| e.window.trait_list_offset(command.source)).is_some() =>
+            {
+                let offset = self.character.as_ref().unwrap().window
+                    .trait_list_offset(command.source).unwrap();
+                self.toggle_trait(offset + id, ui);
+            }
+            UiCommandData::Pick { id } if self.character.as_ref()
+                .map(|e| e.window.is_skill_list(command.source)).unwrap_or(false) =>
+            {
+                self.toggle_tagged_skill(id, ui);
+            }
             UiCommandData::Pick { id } => {
                 let (sid, proc_id) = {
                     let dialog = self.dialog.as_mut().unwrap();
@@ -753,26 +4999,23 @@ impl AppState for GameState {
                             ui,
                             world: &mut self.world.borrow_mut(),
                             sequencer: &mut self.sequencer,
+                            stats: &mut self.stats,
                             dialog: &mut self.dialog,
                             message_panel: self.message_panel,
                             map_id: self.map_id.unwrap(),
                         }).assert_no_suspend();
+                    let barter_request = self.dialog.as_mut().unwrap().barter_request.take();
+                    if let Some(barter_mod) = barter_request {
+                        self.show_barter(barter_mod, ui);
+                        return;
+                    }
                     // No dialog options means the dialog is finished.
                     self.dialog.as_ref().unwrap().is_empty()
                 } else {
                     true
                 };
                 if finished {
-                    self.scripts.resume(&mut script::Context {
-                        ui,
-                        world: &mut self.world.borrow_mut(),
-                        sequencer: &mut self.sequencer,
-                        dialog: &mut self.dialog,
-                        message_panel: self.message_panel,
-                        map_id: self.map_id.unwrap(),
-                    }).assert_no_suspend();
-                    assert!(!self.scripts.can_resume());
-                    // TODO call MapUpdate (multiple times?), see gdialogEnter()
+                    self.finish_dialog(ui);
                 }
 
             }
@@ -784,6 +5027,74 @@ impl AppState for GameState {
                 let scrolled = self.world.borrow_mut().scroll(dir, 1) > 0;
                 ui.widget_mut::<ScrollArea>(*widg).set_enabled(scrolled);
             }
+            UiCommandData::Hud(hud::Command::Inventory) => {
+                self.show_inventory(ui);
+            }
+            UiCommandData::Hud(hud::Command::EndTurn) => self.dude_end_turn(ui),
+            UiCommandData::Hud(hud::Command::EndCombat) => self.dude_end_combat(ui),
+            UiCommandData::Hud(hud::Command::ToggleAimed) => self.toggle_aimed_attack(ui),
+            UiCommandData::Hud(hud::Command::Skilldex) => self.show_skilldex(None, ui),
+            UiCommandData::Hud(hud::Command::Character) => self.show_character_sheet(ui),
+            UiCommandData::Character(cmd) => self.handle_character_command(cmd, ui),
+            UiCommandData::Skilldex(cmd) => self.handle_skilldex_command(cmd, ui),
+            UiCommandData::PerkSelect(cmd) => self.handle_perk_select_command(cmd, ui),
+            UiCommandData::SaveLoad(cmd) => self.handle_save_load_command(cmd, ui),
+            UiCommandData::Elevator(cmd) => self.handle_elevator_command(cmd, ui),
+            UiCommandData::Encounter(cmd) => self.handle_encounter_command(cmd, ui),
+            UiCommandData::WorldMap(cmd) => self.handle_world_map_command(cmd, ui),
+            UiCommandData::CalledShot(cmd) => self.handle_called_shot_command(cmd, ui),
+            UiCommandData::Inventory(cmd) => {
+                match cmd {
+                    inventory::Command::ScrollUp => self.inventory.as_ref().unwrap().scroll(ui, true),
+                    inventory::Command::ScrollDown => self.inventory.as_ref().unwrap().scroll(ui, false),
+                    inventory::Command::Done => self.hide_inventory(ui),
+                }
+            }
+            UiCommandData::Loot(cmd) => self.handle_loot_command(cmd, ui),
+            UiCommandData::Barter(cmd) => self.handle_barter_command(cmd, ui),
+            UiCommandData::CombatControl(cmd) => self.handle_combat_control_command(cmd, ui),
+            UiCommandData::InventoryList(cmd) if self.is_bartering() => {
+                if let inventory_list::Command::Drop { pos, object } = cmd {
+                    let barter = self.dialog.as_mut().unwrap().barter.as_mut().unwrap();
+                    let from = barter.table_of(command.source).unwrap();
+                    if let Some(to) = barter.table_at(ui, pos) {
+                        if to.side() == from.side() {
+                            barter.set_offered(to.side(), object, to.is_offer());
+                            self.sync_barter(ui);
+                        }
+                    }
+                }
+            }
+            UiCommandData::InventoryList(cmd) if self.loot.is_some() => {
+                let loot = self.loot.as_ref().unwrap();
+                if let inventory_list::Command::Drop { pos, object } = cmd {
+                    let from = loot.side_of(command.source).unwrap();
+                    if loot.side_at(ui, pos).map(|to| to != from).unwrap_or(false) {
+                        self.loot_move_item(from, object, ui);
+                        if let Some(loot) = &self.loot {
+                            loot.sync(ui, &self.world.borrow());
+                        }
+                    }
+                }
+            }
+            UiCommandData::InventoryList(cmd) => {
+                match cmd {
+                    inventory_list::Command::Click { object } => {
+                        let actions = self.inventory_item_actions(object);
+                        let inv = self.inventory.as_mut().unwrap();
+                        if inv.item_action_menu.is_none() {
+                            let menu = action_menu::show(actions, inv.window(), ui);
+                            inv.item_action_menu = Some((menu, object));
+                        }
+                    }
+                    inventory_list::Command::Drop { pos, object } => {
+                        let target = self.inventory.as_ref().unwrap().drop_target_at(ui, pos);
+                        if let Some(target) = target {
+                            self.handle_inventory_drop(object, target, ui);
+                        }
+                    }
+                }
+            }
             _ => {}
         }
     }
@@ -791,7 +5102,13 @@ impl AppState for GameState {
     fn update(&mut self, delta: Duration, ui: &mut Ui) {
         self.time.update(delta);
 
-        self.time.set_paused(self.user_paused || self.scripts.can_resume());
+        self.update_pending_save(ui);
+
+        self.time.set_paused(self.user_paused || self.scripts.can_resume()
+            || self.inventory.is_some() || self.loot.is_some() || self.called_shot.is_some()
+            || self.skilldex.is_some() || self.perk_select.is_some()
+            || self.character.is_some() || self.save_load.is_some()
+            || self.elevator.is_some() || self.world_map.is_some());
 
         if self.time.is_running() {
             {
@@ -808,12 +5125,24 @@ impl AppState for GameState {
 
             self.handle_seq_events(ui);
 
-            self.fidget.update(self.time.time(), &mut self.world.borrow_mut(), &mut self.sequencer);
+            self.update_combat(ui);
+
+            self.handle_map_transition(ui);
+
+            self.update_game_time(delta, ui);
+
+            if !self.is_in_combat() {
+                self.fidget.update(self.time.time(), &mut self.world.borrow_mut(), &mut self.sequencer);
+            }
         } else {
             self.sequencer.cleanup(&mut sequence::Cleanup {
                 world: &mut self.world.borrow_mut(),
             });
         }
+
+        if self.world_map.is_some() {
+            self.update_world_map(delta, ui);
+        }
     }
 }
 
@@ -860,4 +5189,38 @@ impl PausableTime {
 struct ObjectActionMenu {
     menu: ui::Handle,
     obj: object::Handle,
-}
\ No newline at end of file
+}
+
+struct CharacterEditor {
+    window: CharacterWindow,
+    /// Map to start the new game on once the character is created.
+    new_game_map: Option<String>,
+    name: BString,
+    /// Points left to distribute between primary stats.
+    char_points: i32,
+    editing_name: bool,
+    /// Index of the last loaded premade in `PREMADES`.
+    premade: usize,
+}
+
+struct WorldMapScreen {
+    window: WorldMapWindow,
+    /// Real time elapsed since the last travel step.
+    step_time: Duration,
+    /// Real time elapsed since the last random encounter check.
+    encounter_time: Duration,
+    /// Detected random encounter awaiting the player's decision.
+    encounter: Option<(RandomEncounter, EncounterWindow)>,
+}
+
+struct PendingSave {
+    slot: u32,
+    description: BString,
+}
+
+/// Map the game is played on.
+struct CurrentMap {
+    name: String,
+    entrance: EPoint,
+    entrance_direction: Direction,
+}
@@ -1,26 +1,59 @@
 mod def;
 
+use bstring::bstr;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use enum_map::EnumMap;
-use num_traits::clamp;
+use num_traits::{clamp, FromPrimitive};
 use std::cell::Ref;
+use std::cmp;
 use std::collections::HashMap;
 use std::rc::Rc;
-use std::io;
+use std::io::{self, Error, ErrorKind, prelude::*};
 
-use crate::asset::{Stat, Trait, Perk};
+use crate::asset::{Flag, PcStat, Perk, Skill, Stat, Trait, WeaponKind};
+use crate::asset::gcd::Gcd;
+use crate::asset::save;
 use crate::asset::message::Messages;
-use crate::asset::proto::{self, ProtoDb};
-use crate::game::object::{DamageFlag, Object, ObjectProtoId};
+use crate::asset::proto::{self, ProtoDb, ProtoId};
+use crate::game::object::{self, DamageFlag, Object, ObjectProtoId};
+use crate::game::roll::{self, Roll};
+use crate::game::world::World;
 use crate::fs::FileSystem;
 
-use def::StatDef;
+use def::{PcStatDef, PerkDef, SkillDef, StatDef};
 
 const STAT_NAME_MSG_BASE: u32 = 100;
 const STAT_DESCR_MSG_BASE: u32 = 200;
 const STAT_LEVEL_DESCR_BASE: u32 = 300;
 const PC_STAT_NAME_MSG_BASE: u32 = 400;
 const PC_STAT_DESCR_MSG_BASE: u32 = 500;
-const LEVEL_UP_MSG: u32 = 600;
+const LEVEL_UP_MSG: i32 = 600;
+
+const SKILL_NAME_MSG_BASE: i32 = 100;
+const SKILL_DESCR_MSG_BASE: i32 = 200;
+
+const TRAIT_NAME_MSG_BASE: i32 = 100;
+const TRAIT_DESCR_MSG_BASE: i32 = 200;
+const MAX_TRAITS: usize = 2;
+
+const MAX_TAGGED_SKILLS: usize = 3;
+
+const PERK_NAME_MSG_BASE: i32 = 101;
+const PERK_DESCR_MSG_BASE: i32 = 1101;
+
+const SKILL_MAX: i32 = 300;
+
+const GAME_TIME_DECIS_PER_YEAR: u32 = 365 * 24 * 60 * 60 * 10;
+
+const PRIMARY_STATS: [Stat; 7] = [
+    Stat::Strength,
+    Stat::Perception,
+    Stat::Endurance,
+    Stat::Charisma,
+    Stat::Intelligence,
+    Stat::Agility,
+    Stat::Luck,
+];
 
 impl StatDef {
     const fn new(
@@ -38,134 +71,755 @@ impl StatDef {
     }
 }
 
+impl PcStatDef {
+    const fn new(min: i32, max: i32, default: i32) -> Self {
+        Self {
+            min,
+            max,
+            default,
+        }
+    }
+}
+
+impl PerkDef {
+    const fn new(
+        max_rank: u32,
+        min_level: i32,
+        stat_bonus: Option<(Stat, i32)>,
+        skills: &'static [(Skill, i32)],
+        any_skill: bool,
+        stats: [i32; 7],
+    ) -> Self {
+        Self {
+            max_rank,
+            min_level,
+            stat_bonus,
+            skills,
+            any_skill,
+            stats,
+        }
+    }
+}
+
+impl SkillDef {
+    const fn new(base: i32, stat_multiplier: i32, stat1: Stat, stat2: Option<Stat>) -> Self {
+        Self {
+            base,
+            stat_multiplier,
+            stat1,
+            stat2,
+        }
+    }
+}
+
 pub struct Stats {
     proto_db: Rc<ProtoDb>,
     stat_msgs: Messages,
+    skill_msgs: Messages,
+    perk_msgs: Messages,
+    trait_msgs: Messages,
     stat_defs: EnumMap<Stat, StatDef>,
+    pc_stat_defs: EnumMap<PcStat, PcStatDef>,
+    skill_defs: EnumMap<Skill, SkillDef>,
+    perk_defs: EnumMap<Perk, PerkDef>,
     traits: Vec<Trait>,
-    perks: HashMap<ObjectProtoId, EnumMap<Perk, bool>>,
+    /// Perk ranks of the dude and party members.
+    perks: HashMap<ObjectProtoId, EnumMap<Perk, u32>>,
+    /// Number of perks the dude has earned on level up but hasn't picked yet.
+    free_perks: u32,
+    dude_base_stats: EnumMap<Stat, i32>,
+    /// Bonus stats changed by drugs. For critters other than the dude these are added on top of
+    /// the bonus stats from the proto.
+    bonus_stats: HashMap<ObjectProtoId, EnumMap<Stat, i32>>,
+    pc_stats: EnumMap<PcStat, i32>,
+    dude_skills: EnumMap<Skill, i32>,
+    tagged_skills: Vec<Skill>,
 }
 
 impl Stats {
     pub fn new(fs: &FileSystem, proto_db: Rc<ProtoDb>, language: &str) -> io::Result<Self> {
         let stat_msgs = Messages::read_file(fs, language, "game/stat.msg")?;
+        let skill_msgs = Messages::read_file(fs, language, "game/skill.msg")?;
+        let perk_msgs = Messages::read_file(fs, language, "game/perk.msg")?;
+        let trait_msgs = Messages::read_file(fs, language, "game/trait.msg")?;
         let stat_defs = StatDef::defaults();
+        let pc_stat_defs = PcStatDef::defaults();
 
-        let mut perks = HashMap::new();
-        perks.insert(ObjectProtoId::Dude, Default::default());
-        Ok(Self {
+        let dude_base_stats = EnumMap::from(|stat| stat_defs[stat].default);
+        let pc_stats = EnumMap::from(|stat| pc_stat_defs[stat].default);
+        let mut r = Self {
             proto_db,
             stat_msgs,
+            skill_msgs,
+            perk_msgs,
+            trait_msgs,
             stat_defs,
+            pc_stat_defs,
+            skill_defs: SkillDef::defaults(),
+            perk_defs: PerkDef::defaults(),
             traits: Vec::new(),
-            perks,
-        })
+            perks: HashMap::new(),
+            free_perks: 0,
+            dude_base_stats,
+            bonus_stats: HashMap::new(),
+            pc_stats,
+            dude_skills: EnumMap::new(),
+            tagged_skills: Vec::new(),
+        };
+        r.recalc_dude_derived_stats();
+        Ok(r)
+    }
+
+    /// Replaces the dude's stats, skills, traits and tagged skills with the ones from the
+    /// character file.
+    // pc_load_data()
+    pub fn load_gcd(&mut self, gcd: &Gcd) {
+        self.dude_base_stats = gcd.base_stats;
+        self.bonus_stats.insert(ObjectProtoId::Dude, gcd.bonus_stats);
+        self.dude_skills = gcd.skills;
+        self.traits = gcd.traits.iter().cloned().take(MAX_TRAITS).collect();
+        self.tagged_skills = gcd.tagged_skills.clone();
+        self.recalc_dude_derived_stats();
+    }
+
+    /// Replaces the state with the one from the original savegame. The `party_member_pids` are
+    /// the PIDs listed in `data/party.txt`, in the order the perks are stored.
+    // critter_load(), perk_load(), skill_load(), stat_load(), trait_load()
+    pub fn load_save_dat(&mut self, dude: &save::Dude, party_member_pids: &[ObjectProtoId]) {
+        self.dude_base_stats = dude.critter.base_stats;
+        self.bonus_stats.insert(ObjectProtoId::Dude, dude.critter.bonus_stats);
+        self.dude_skills = dude.critter.skills;
+        self.pc_stats = dude.pc_stats;
+        self.traits = dude.traits.iter().cloned().take(MAX_TRAITS).collect();
+        self.tagged_skills = dude.tagged_skills.clone();
+
+        self.perks.clear();
+        for (&pid, perks) in party_member_pids.iter().zip(&dude.perks) {
+            if perks.values().any(|&rank| rank > 0) {
+                self.perks.insert(pid, *perks);
+            }
+        }
+        self.free_perks = 0;
+    }
+
+    /// Writes the state of the dude and the perks and bonus stats of other critters.
+    // stat_save(), perk_save(), skill_save(), trait_save()
+    pub fn write(&self, wr: &mut impl Write) -> io::Result<()> {
+        for &v in self.dude_base_stats.values() {
+            wr.write_i32::<BigEndian>(v)?;
+        }
+        for &v in self.pc_stats.values() {
+            wr.write_i32::<BigEndian>(v)?;
+        }
+        for &v in self.dude_skills.values() {
+            wr.write_i32::<BigEndian>(v)?;
+        }
+
+        wr.write_u32::<BigEndian>(self.tagged_skills.len() as u32)?;
+        for &skill in &self.tagged_skills {
+            wr.write_i32::<BigEndian>(skill as i32)?;
+        }
+        wr.write_u32::<BigEndian>(self.traits.len() as u32)?;
+        for &tr in &self.traits {
+            wr.write_i32::<BigEndian>(tr as i32)?;
+        }
+
+        wr.write_u32::<BigEndian>(self.perks.len() as u32)?;
+        for (pid, perks) in &self.perks {
+            wr.write_u32::<BigEndian>(pid.pack())?;
+            for &rank in perks.values() {
+                wr.write_u32::<BigEndian>(rank)?;
+            }
+        }
+        wr.write_u32::<BigEndian>(self.free_perks)?;
+
+        wr.write_u32::<BigEndian>(self.bonus_stats.len() as u32)?;
+        for (pid, bonus_stats) in &self.bonus_stats {
+            wr.write_u32::<BigEndian>(pid.pack())?;
+            for &v in bonus_stats.values() {
+                wr.write_i32::<BigEndian>(v)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the state with the one written with `write()`.
+    // stat_load(), perk_load(), skill_load(), trait_load()
+    pub fn read(&mut self, rd: &mut impl Read) -> io::Result<()> {
+        fn read_enum<T: FromPrimitive>(rd: &mut impl Read, what: &str) -> io::Result<T> {
+            T::from_i32(rd.read_i32::<BigEndian>()?)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("invalid {}", what)))
+        }
+
+        for v in self.dude_base_stats.values_mut() {
+            *v = rd.read_i32::<BigEndian>()?;
+        }
+        for v in self.pc_stats.values_mut() {
+            *v = rd.read_i32::<BigEndian>()?;
+        }
+        for v in self.dude_skills.values_mut() {
+            *v = rd.read_i32::<BigEndian>()?;
+        }
+
+        self.tagged_skills.clear();
+        for _ in 0..rd.read_u32::<BigEndian>()? {
+            self.tagged_skills.push(read_enum(rd, "tagged skill")?);
+        }
+        self.traits.clear();
+        for _ in 0..rd.read_u32::<BigEndian>()? {
+            self.traits.push(read_enum(rd, "trait")?);
+        }
+
+        self.perks.clear();
+        for _ in 0..rd.read_u32::<BigEndian>()? {
+            let pid = ObjectProtoId::read(rd)?;
+            let mut perks = EnumMap::new();
+            for rank in perks.values_mut() {
+                *rank = rd.read_u32::<BigEndian>()?;
+            }
+            self.perks.insert(pid, perks);
+        }
+        self.free_perks = rd.read_u32::<BigEndian>()?;
+
+        self.bonus_stats.clear();
+        for _ in 0..rd.read_u32::<BigEndian>()? {
+            let pid = ObjectProtoId::read(rd)?;
+            let mut bonus_stats = EnumMap::new();
+            for v in bonus_stats.values_mut() {
+                *v = rd.read_i32::<BigEndian>()?;
+            }
+            self.bonus_stats.insert(pid, bonus_stats);
+        }
+
+        Ok(())
+    }
+
+    /// Recomputes the derived base stats of the dude from the primary stats.
+    // stat_recalc_derived()
+    pub fn recalc_dude_derived_stats(&mut self) {
+        use Stat::*;
+
+        let st = |stat| {
+            let r = self.dude_base_stats[stat] + self.extra_bonus_stat(stat, ObjectProtoId::Dude);
+            let def = &self.stat_defs[stat];
+            clamp(r, def.min, def.max)
+        };
+        let strength = st(Strength);
+        let perception = st(Perception);
+        let endurance = st(Endurance);
+        let agility = st(Agility);
+        let luck = st(Luck);
+
+        let base = &mut self.dude_base_stats;
+        base[HitPoints] = 15 + strength + 2 * endurance;
+        base[ActionPoints] = agility / 2 + 5;
+        base[ArmorClass] = agility;
+        base[MeleeDmg] = cmp::max(strength - 5, 1);
+        base[CarryWeight] = 25 + 25 * strength;
+        base[Sequence] = 2 * perception;
+        base[HealRate] = cmp::max(endurance / 3, 1);
+        base[CritChance] = luck;
+        base[BetterCrit] = 0;
+        base[RadResist] = 2 * endurance;
+        base[PoisonResist] = 5 * endurance;
+    }
+
+    // perk_level()
+    pub fn perk_rank(&self, perk: Perk, pid: ObjectProtoId) -> u32 {
+        self.perks.get(&pid).map(|m| m[perk]).unwrap_or(0)
     }
 
     pub fn has_perk(&self, perk: Perk, pid: ObjectProtoId) -> bool {
-        self.perks.get(&pid).map(|m| m[perk]).unwrap_or(false)
+        self.perk_rank(perk, pid) > 0
+    }
+
+    /// Adds a rank of the `perk` to the critter with `pid`.
+    // perk_add()
+    pub fn add_perk(&mut self, perk: Perk, pid: ObjectProtoId) {
+        self.perks.entry(pid).or_insert_with(EnumMap::new)[perk] += 1;
+        if let Some((stat, bonus)) = self.perk_defs[perk].stat_bonus {
+            self.mod_bonus_stat(stat, bonus, pid);
+        }
+    }
+
+    /// Removes a rank of the `perk` from the critter with `pid`. Returns `false` if the critter
+    /// doesn't have the perk.
+    // perk_sub()
+    pub fn remove_perk(&mut self, perk: Perk, pid: ObjectProtoId) -> bool {
+        match self.perks.get_mut(&pid).map(|m| &mut m[perk]) {
+            Some(rank) if *rank > 0 => *rank -= 1,
+            _ => return false,
+        }
+        if let Some((stat, bonus)) = self.perk_defs[perk].stat_bonus {
+            self.mod_bonus_stat(stat, -bonus, pid);
+        }
+        true
+    }
+
+    // perk_name()
+    pub fn perk_name(&self, perk: Perk) -> &bstr {
+        &self.perk_msgs.get(PERK_NAME_MSG_BASE + perk as i32).unwrap().text
+    }
+
+    // perk_description()
+    pub fn perk_description(&self, perk: Perk) -> &bstr {
+        &self.perk_msgs.get(PERK_DESCR_MSG_BASE + perk as i32).unwrap().text
+    }
+
+    pub fn free_perks(&self) -> u32 {
+        self.free_perks
+    }
+
+    /// Whether the dude meets the requirements of the `perk` and hasn't reached its max rank.
+    // perk_can_add()
+    pub fn can_pick_perk(&self, perk: Perk, dude_obj: &Object, world: &World) -> bool {
+        let def = &self.perk_defs[perk];
+        if self.perk_rank(perk, dude_obj.pid) >= def.max_rank
+            || self.pc_stat(PcStat::Level) < def.min_level
+        {
+            return false;
+        }
+
+        let skill_ok = |&(skill, min): &(Skill, i32)| self.skill(skill, dude_obj, world) >= min;
+        let skills_ok = if def.any_skill {
+            def.skills.iter().any(skill_ok)
+        } else {
+            def.skills.iter().all(skill_ok)
+        };
+        if !skills_ok {
+            return false;
+        }
+
+        PRIMARY_STATS.iter().zip(def.stats.iter()).all(|(&stat, &req)| {
+            let value = self.stat(stat, dude_obj, world);
+            if req < 0 {
+                value <= -req
+            } else {
+                value >= req
+            }
+        })
+    }
+
+    /// Perks the dude can pick now.
+    // perk_make_list()
+    pub fn eligible_perks(&self, dude_obj: &Object, world: &World) -> Vec<Perk> {
+        self.perk_defs.iter()
+            .map(|(perk, _)| perk)
+            .filter(|&perk| self.can_pick_perk(perk, dude_obj, world))
+            .collect()
+    }
+
+    /// Adds a rank of the `perk` the dude has earned on level up.
+    pub fn pick_perk(&mut self, perk: Perk, dude_obj: &mut Object, world: &World) {
+        assert!(self.free_perks > 0);
+        assert!(self.can_pick_perk(perk, dude_obj, world));
+        self.free_perks -= 1;
+        self.add_perk(perk, dude_obj.pid);
+        if perk == Perk::HereAndNow {
+            let next_level = self.pc_stat(PcStat::Level) + 1;
+            let amount = level_experience(next_level) - self.pc_stat(PcStat::Experience);
+            self.add_experience0(amount, dude_obj, world);
+        }
     }
 
     pub fn has_trait(&self, tr: Trait) -> bool {
         self.traits.contains(&tr)
     }
 
-    // stat_level()
-    pub fn stat(&self, stat: Stat, obj: &Object) -> i32 {
-        use Perk::*;
-        use Stat::*;
-
-        let pei = |p| self.has_perk(p, obj.pid) as i32;
+    pub fn traits(&self) -> &[Trait] {
+        &self.traits
+    }
 
-        if stat == Age {
-            // TODO
-            return 35;
+    /// Returns `false` if the dude already has the trait or the maximum number of traits.
+    pub fn add_trait(&mut self, tr: Trait) -> bool {
+        if self.has_trait(tr) || self.traits.len() >= MAX_TRAITS {
+            return false;
         }
+        self.traits.push(tr);
+        true
+    }
 
-        let mut r = self.base_stat(stat, obj) + self.bonus_stat(stat, obj);
-        if stat == ArmorClass /* in_combat && whose_turn != obj */ {
-            // TODO
+    /// Returns `false` if the dude doesn't have the trait `tr`.
+    pub fn remove_trait(&mut self, tr: Trait) -> bool {
+        let len = self.traits.len();
+        self.traits.retain(|&t| t != tr);
+        self.traits.len() != len
+    }
+
+    /// Adds `amount` (possibly negative) of experience points to the dude, levelling up if it's
+    /// enough for the next level. Returns the number of levels gained.
+    // stat_pc_add_experience()
+    pub fn add_experience(&mut self, amount: i32, dude_obj: &mut Object, world: &World) -> u32 {
+        let amount = amount
+            + amount * 5 * self.perk_rank(Perk::SwiftLearner, ObjectProtoId::Dude) as i32 / 100;
+        self.add_experience0(amount, dude_obj, world)
+    }
+
+    fn add_experience0(&mut self, amount: i32, dude_obj: &mut Object, world: &World) -> u32 {
+        use PcStat::*;
+
+        assert_eq!(dude_obj.pid, ObjectProtoId::Dude);
+        self.set_pc_stat(Experience, self.pc_stat(Experience).saturating_add(amount));
+        let mut levels = 0;
+        loop {
+            let level = self.pc_stat(Level) + 1;
+            if level > self.pc_stat_defs[Level].max
+                || self.pc_stat(Experience) < level_experience(level)
+            {
+                break;
+            }
+            self.set_pc_stat(Level, level);
+            levels += 1;
+
+            let mut skill_points = 5 + 2 * self.stat(Stat::Intelligence, dude_obj, world)
+                + 2 * self.perk_rank(Perk::Educated, dude_obj.pid) as i32;
+            if self.has_trait(Trait::Skilled) {
+                skill_points += 5;
+            }
+            if self.has_trait(Trait::Gifted) {
+                skill_points = cmp::max(skill_points - 5, 0);
+            }
+            self.set_pc_stat(UnspentSkillPoints, self.pc_stat(UnspentSkillPoints) + skill_points);
+
+            let hp = 2 + self.stat(Stat::Endurance, dude_obj, world) / 2
+                + 4 * self.perk_rank(Perk::Lifegiver, dude_obj.pid) as i32;
+            self.mod_bonus_stat(Stat::HitPoints, hp, dude_obj.pid);
+            dude_obj.sub.critter_mut().unwrap().health += hp;
+
+            let perk_rate = if self.has_trait(Trait::Skilled) { 4 } else { 3 };
+            if level % perk_rate == 0 {
+                self.free_perks += 1;
+            }
         }
-        if stat == Perception && obj.sub.critter().unwrap().combat.damage_flags.contains(DamageFlag::Blind) {
-            r -= 5;
+        levels
+    }
+
+    pub fn level_up_message(&self) -> &bstr {
+        &self.stat_msgs.get(LEVEL_UP_MSG).unwrap().text
+    }
+
+    // stat_pc_get()
+    pub fn pc_stat(&self, stat: PcStat) -> i32 {
+        self.pc_stats[stat]
+    }
+
+    // stat_pc_set()
+    pub fn set_pc_stat(&mut self, stat: PcStat, value: i32) {
+        let def = &self.pc_stat_defs[stat];
+        self.pc_stats[stat] = clamp(value, def.min, def.max);
+    }
+
+    pub fn dude_base_stat(&self, stat: Stat) -> i32 {
+        self.dude_base_stats[stat]
+    }
+
+    /// Sets the base `stat` of the dude clamping the `value` to the valid range. Derived stats
+    /// are recomputed if `stat` is a primary stat.
+    pub fn set_dude_base_stat(&mut self, stat: Stat, value: i32) {
+        let def = &self.stat_defs[stat];
+        self.dude_base_stats[stat] = clamp(value, def.min, def.max);
+        if is_primary(stat) {
+            self.recalc_dude_derived_stats();
         }
-        if stat == ActionPoints {
-            let carry_weight = self.stat(CarryWeight, obj);
-            let left = carry_weight - /* TODO item_total_weight_(obj) */ 0;
-            if left < 0 {
-                r -= -left / 40 + 1;
+    }
+
+    /// Adds `amount` (possibly negative) to the base `stat` of the dude. Derived stats are
+    /// recomputed if `stat` is a primary stat.
+    // stat_set_base()
+    pub fn mod_dude_base_stat(&mut self, stat: Stat, amount: i32, dude_obj: &mut Object) {
+        assert_eq!(dude_obj.pid, ObjectProtoId::Dude);
+        let def = &self.stat_defs[stat];
+        let value = clamp(self.base_stat(stat, dude_obj) + amount, def.min, def.max);
+        let critter = dude_obj.sub.critter_mut().unwrap();
+        match stat {
+            Stat::CurrentHitPoints => critter.health = value,
+            Stat::CurrentPoison => critter.poison = value,
+            Stat::CurrentRad => critter.radiation = value,
+            _ => {
+                self.dude_base_stats[stat] = value;
+                if is_primary(stat) {
+                    self.recalc_dude_derived_stats();
+                }
             }
         }
+    }
+
+    /// Applies `amount` (possibly negative) of a drug effect on the `stat` of critter `obj`.
+    /// Current hit points, poison and radiation levels are changed directly, other stats get
+    /// the bonus.
+    // item_d_take_drug()
+    pub fn apply_drug_effect(&mut self, stat: Stat, amount: i32, obj: &mut Object,
+        world: &World)
+    {
+        let max_health = self.stat(Stat::HitPoints, obj, world);
+        let critter = obj.sub.critter_mut().unwrap();
+        match stat {
+            Stat::CurrentHitPoints => critter.health = clamp(critter.health + amount, 0,
+                max_health),
+            Stat::CurrentPoison => critter.poison = cmp::max(critter.poison + amount, 0),
+            Stat::CurrentRad => critter.radiation = cmp::max(critter.radiation + amount, 0),
+            _ => self.mod_bonus_stat(stat, amount, obj.pid),
+        }
+    }
+
+    // stat_level()
+    pub fn stat(&self, stat: Stat, obj: &Object, world: &World) -> i32 {
+        use Perk::*;
+        use Stat::*;
+
+        let pei = |p| self.has_perk(p, obj.pid) as i32;
+
+        let mut r = self.base_stat(stat, obj) + self.bonus_stat(stat, obj);
         if obj.pid == ObjectProtoId::Dude {
             r += self.trait_modifier(stat, obj);
-
-            r += match stat {
-                Strength => {
-                    pei(GainStrength) +
-                        if self.has_perk(AdrenalineRush, obj.pid) &&
-                            self.stat(CurrentHitPoints, obj) < self.stat(HitPoints, obj) / 2
-                        {
-                            1
-                        } else {
-                            0
-                        }
+        }
+        match stat {
+            Perception => {
+                if obj.sub.critter().unwrap().combat.damage_flags.contains(DamageFlag::Blind) {
+                    r -= 5;
                 }
-                Perception => pei(GainPerception),
-                Endurance => pei(GainEndurance),
-                Charisma => {
-                    pei(GainCharisma)
-
-                    // TODO
-//                    bonus_for_shades = 0;
-//                  obj_in_right_hand = inven_right_hand_(obj);
-//                  if ( obj_in_right_hand && obj_in_right_hand->_.pid == PID_MIRRORED_SHADES )
-//                    bonus_for_shades = 1;
-//                  obj_in_left_hand = inven_left_hand_(obj);
-//                  if ( obj_in_left_hand && obj_in_left_hand->_.pid == PID_MIRRORED_SHADES )
-//                    bonus_for_shades = 1;
-//                  if ( bonus_for_shades )
-//                    ++v7;
+            }
+            ActionPoints => {
+                let carry_weight = self.stat(CarryWeight, obj, world);
+                let left = carry_weight - world.objects().items_weight(&obj.inventory) as i32;
+                if left < 0 {
+                    r -= -left / 40 + 1;
                 }
-                Intelligence => pei(GainIntelligence),
-                Agility => pei(GainAgility),
-                Luck => pei(GainLuck),
-                PoisonResist => 10 * pei(VaultCityInoculations),
-                RadResist => 10 * pei(VaultCityInoculations),
-                HitPoints =>
-                    2 * pei(AlcoholHpBonus1) +
-                    4 * pei(AlcoholHpBonus2) +
-                    -2 * pei(AlcoholHpNeg1) +
-                    -4 * pei(AlcoholHpNeg2) +
-                    2 * pei(AutodocHpBonus1) +
-                    4 * pei(AutodocHpBonus2) +
-                    -2 * pei(AutodocHpNeg1) +
-                    -4 * pei(AutodocHpNeg2),
-                DmgResistLaser | DmgResistFire | DmgResistPlasma =>
-                    if self.has_perk(PhoenixArmor, obj.pid) {
-                        5
-                    } else if self.has_perk(PhoenixEnhancement, obj.pid) {
-                        10
-                    } else {
-                        0
-                    }
-                DmgResist | DmgResistExplosion =>
-                    if self.has_perk(DermalArmor, obj.pid) {
-                        5
-                    } else if self.has_perk(DermalEnhancement, obj.pid) {
-                        10
+            }
+            Charisma => {
+                if self.hand_items(obj, world)
+                    .any(|item| item.pid.proto_id() == Some(ProtoId::MIRRORED_SHADES))
+                {
+                    r += 1;
+                }
+            }
+            Age => r += (world.game_time.as_decis() / GAME_TIME_DECIS_PER_YEAR) as i32,
+            _ => {}
+        }
+
+        r += match stat {
+            Strength => {
+                pei(GainStrength) +
+                    if self.has_perk(AdrenalineRush, obj.pid) &&
+                        self.stat(CurrentHitPoints, obj, world) <
+                            self.stat(HitPoints, obj, world) / 2
+                    {
+                        1
                     } else {
                         0
                     }
-                _ => 0,
             }
-        }
+            Perception => pei(GainPerception),
+            Endurance => pei(GainEndurance),
+            Charisma => pei(GainCharisma),
+            Intelligence => pei(GainIntelligence),
+            Agility => pei(GainAgility),
+            Luck => pei(GainLuck),
+            PoisonResist => 10 * pei(VaultCityInoculations),
+            RadResist => 10 * pei(VaultCityInoculations),
+            HitPoints =>
+                2 * pei(AlcoholHpBonus1) +
+                4 * pei(AlcoholHpBonus2) +
+                -2 * pei(AlcoholHpNeg1) +
+                -4 * pei(AlcoholHpNeg2) +
+                2 * pei(AutodocHpBonus1) +
+                4 * pei(AutodocHpBonus2) +
+                -2 * pei(AutodocHpNeg1) +
+                -4 * pei(AutodocHpNeg2),
+            DmgResistLaser | DmgResistFire | DmgResistPlasma =>
+                if self.has_perk(PhoenixArmor, obj.pid) {
+                    5
+                } else if self.has_perk(PhoenixEnhancement, obj.pid) {
+                    10
+                } else {
+                    0
+                }
+            DmgResist | DmgResistExplosion =>
+                if self.has_perk(DermalArmor, obj.pid) {
+                    5
+                } else if self.has_perk(DermalEnhancement, obj.pid) {
+                    10
+                } else {
+                    0
+                }
+            _ => 0,
+        };
 
         let stat_def = &self.stat_defs[stat];
-        let r = clamp(r, stat_def.min, stat_def.max);
+        clamp(r, stat_def.min, stat_def.max)
+    }
+
+    pub fn is_skill_tagged(&self, skill: Skill) -> bool {
+        self.tagged_skills.contains(&skill)
+    }
+
+    pub fn tagged_skills(&self) -> &[Skill] {
+        &self.tagged_skills
+    }
+
+    /// Returns `false` if the `skill` is already tagged or there are no more tags left.
+    /// The Tag! perk gives an extra tag.
+    pub fn tag_skill(&mut self, skill: Skill) -> bool {
+        let max = MAX_TAGGED_SKILLS + self.has_perk(Perk::Tag, ObjectProtoId::Dude) as usize;
+        if self.is_skill_tagged(skill) || self.tagged_skills.len() >= max {
+            return false;
+        }
+        self.tagged_skills.push(skill);
+        true
+    }
+
+    /// Returns `false` if the `skill` isn't tagged.
+    pub fn untag_skill(&mut self, skill: Skill) -> bool {
+        let len = self.tagged_skills.len();
+        self.tagged_skills.retain(|&s| s != skill);
+        self.tagged_skills.len() != len
+    }
+
+    // skill_level()
+    pub fn skill(&self, skill: Skill, obj: &Object, world: &World) -> i32 {
+        let def = &self.skill_defs[skill];
+        let stats = self.stat(def.stat1, obj, world)
+            + def.stat2.map(|s| self.stat(s, obj, world)).unwrap_or(0);
+        let mut r = def.base + def.stat_multiplier * stats;
+        if obj.pid == ObjectProtoId::Dude {
+            let points = self.dude_skills[skill];
+            r += points;
+            if self.is_skill_tagged(skill) {
+                r += points;
+                // The skill tagged with the Tag! perk doesn't get the bonus.
+                if !self.has_perk(Perk::Tag, obj.pid) || self.tagged_skills.get(3) != Some(&skill) {
+                    r += 20;
+                }
+            }
+            r += self.trait_skill_modifier(skill);
+            r += self.perk_skill_modifier(skill, obj);
+        } else {
+            r += self.critter_proto(obj).skills[skill];
+        }
+        cmp::min(r, SKILL_MAX)
+    }
+
+    /// Highest level of the `skill` among the dude and the living party members.
+    // partyGetBestSkillValue()
+    pub fn party_best_skill(&self, skill: Skill, world: &World) -> i32 {
+        world.dude_obj().into_iter()
+            .chain(world.party().members().iter().map(|m| m.obj))
+            .filter_map(|h| {
+                let obj = world.objects().get(h).borrow();
+                if obj.sub.critter().map(|c| c.is_dead()).unwrap_or(true) {
+                    None
+                } else {
+                    Some(self.skill(skill, &obj, world))
+                }
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    // skill_name()
+    pub fn skill_name(&self, skill: Skill) -> &bstr {
+        &self.skill_msgs.get(SKILL_NAME_MSG_BASE + skill as i32).unwrap().text
+    }
+
+    pub fn skill_description(&self, skill: Skill) -> &bstr {
+        &self.skill_msgs.get(SKILL_DESCR_MSG_BASE + skill as i32).unwrap().text
+    }
+
+    // stat_name()
+    pub fn stat_name(&self, stat: Stat) -> &bstr {
+        &self.stat_msgs.get(STAT_NAME_MSG_BASE as i32 + stat as i32).unwrap().text
+    }
 
+    // stat_description()
+    pub fn stat_description(&self, stat: Stat) -> &bstr {
+        &self.stat_msgs.get(STAT_DESCR_MSG_BASE as i32 + stat as i32).unwrap().text
+    }
+
+    /// Description of the primary stat `value` like "Good" or "Heroic".
+    // stat_level_description()
+    pub fn stat_level_description(&self, value: i32) -> &bstr {
+        let value = clamp(value, 1, 10);
+        &self.stat_msgs.get(STAT_LEVEL_DESCR_BASE as i32 + value).unwrap().text
+    }
+
+    // stat_pc_name()
+    pub fn pc_stat_name(&self, stat: PcStat) -> &bstr {
+        &self.stat_msgs.get(PC_STAT_NAME_MSG_BASE as i32 + stat as i32).unwrap().text
+    }
+
+    // trait_name()
+    pub fn trait_name(&self, tr: Trait) -> &bstr {
+        &self.trait_msgs.get(TRAIT_NAME_MSG_BASE + tr as i32).unwrap().text
+    }
+
+    // trait_description()
+    pub fn trait_description(&self, tr: Trait) -> &bstr {
+        &self.trait_msgs.get(TRAIT_DESCR_MSG_BASE + tr as i32).unwrap().text
+    }
+
+    /// Adds `amount` (possibly negative) of skill points to the dude's `skill`.
+    /// Tagged skills grow twice as fast so the amount is halved for them.
+    pub fn mod_dude_skill(&mut self, skill: Skill, amount: i32) {
+        let amount = if self.is_skill_tagged(skill) {
+            amount / 2
+        } else {
+            amount
+        };
+        let points = &mut self.dude_skills[skill];
+        *points = cmp::max(*points + amount, 0);
+    }
+
+    /// Rolls `obj`'s `skill` adjusted by `modifier` against the critical chance of `obj`.
+    /// Returns the outcome and how much the roll succeeded or failed by.
+    // skill_result()
+    pub fn roll_skill(&self, skill: Skill, obj: &Object, world: &World, modifier: i32,
+        rng: &mut impl FnMut(i32, i32) -> i32) -> (Roll, i32)
+    {
+        let crit_chance = self.stat(Stat::CritChance, obj, world);
+        roll::check(self.skill(skill, obj, world) + modifier, crit_chance, rng)
+    }
+
+    // trait_adjust_skill()
+    fn trait_skill_modifier(&self, skill: Skill) -> i32 {
+        use Skill::*;
+
+        let mut r = 0;
+        if self.has_trait(Trait::Gifted) {
+            r -= 10;
+        }
+        if self.has_trait(Trait::GoodNatured) {
+            r += match skill {
+                SmallGuns | BigGuns | EnergyWeapons | UnarmedCombat | Melee | Throwing => -10,
+                FirstAid | Doctor | Conversant | Barter => 15,
+                _ => 0,
+            };
+        }
         r
     }
 
+    // perk_adjust_skill()
+    fn perk_skill_modifier(&self, skill: Skill, obj: &Object) -> i32 {
+        use Perk::*;
+        use Skill::*;
+
+        let pe = |p, v| if self.has_perk(p, obj.pid) { v } else { 0 };
+        match skill {
+            FirstAid | Doctor => pe(Medic, 10) + pe(VaultCityTraining, 5),
+            // TODO Ghost should only apply in the dark.
+            Sneak => pe(Ghost, 20) + pe(Thief, 10),
+            Lockpick => pe(Thief, 10) + pe(MasterThief, 15),
+            Steal => pe(Thief, 10) + pe(MasterThief, 15) + pe(Harmless, 20),
+            Traps => pe(Thief, 10),
+            Science | Repair => pe(MrFixit, 10),
+            Conversant => pe(Speaker, 20) + pe(Negotiator, 10),
+            Barter => pe(Negotiator, 10) + pe(Salesman, 20),
+            Gambling => pe(Gambler, 20),
+            Outdoorsman => pe(Ranger, 15) + pe(Survivalist, 25),
+            _ => 0,
+        }
+    }
+
     // trait_adjust_stat()
     fn trait_modifier(&self, stat: Stat, obj: &Object) -> i32 {
         let tr = |tr| {
@@ -205,12 +859,77 @@ impl Stats {
             Stat::CurrentHitPoints => critter().health,
             Stat::CurrentPoison => critter().poison,
             Stat::CurrentRad => critter().radiation,
+            _ if obj.pid == ObjectProtoId::Dude => self.dude_base_stats[stat],
             _ => self.critter_proto(obj).base_stats[stat],
         }
     }
 
     fn bonus_stat(&self, stat: Stat, obj: &Object) -> i32 {
-        self.critter_proto(obj).bonus_stats[stat]
+        let r = self.extra_bonus_stat(stat, obj.pid);
+        if obj.pid == ObjectProtoId::Dude {
+            r
+        } else {
+            r + self.critter_proto(obj).bonus_stats[stat]
+        }
+    }
+
+    fn mod_bonus_stat(&mut self, stat: Stat, amount: i32, pid: ObjectProtoId) {
+        self.bonus_stats.entry(pid).or_insert_with(EnumMap::new)[stat] += amount;
+        if pid == ObjectProtoId::Dude && is_primary(stat) {
+            self.recalc_dude_derived_stats();
+        }
+    }
+
+    fn extra_bonus_stat(&self, stat: Stat, pid: ObjectProtoId) -> i32 {
+        self.bonus_stats.get(&pid).map(|m| m[stat]).unwrap_or(0)
+    }
+
+    /// Armor class of `obj` including the combat bonus from `combat_armor_class()`.
+    pub fn armor_class(&self, obj: object::Handle, world: &World) -> i32 {
+        let objr = world.objects().get(obj).borrow();
+        self.stat(Stat::ArmorClass, &objr, world) + self.combat_armor_class(obj, &objr, world)
+    }
+
+    /// Critters waiting for their turn in combat get the armor class bonus from the unspent
+    /// action points.
+    fn combat_armor_class(&self, handle: object::Handle, obj: &Object, world: &World) -> i32 {
+        let combat = if let Some(c) = &world.combat {
+            c
+        } else {
+            return 0;
+        };
+        if combat.current() == handle {
+            return 0;
+        }
+        let ap = obj.sub.critter().unwrap().combat.ap;
+        if obj.pid == ObjectProtoId::Dude
+            && self.has_perk(Perk::HthEvade, obj.pid)
+            && !self.has_weapon_in_hands(obj, world)
+        {
+            2 * ap + self.skill(Skill::UnarmedCombat, obj, world) / 12
+        } else {
+            ap
+        }
+    }
+
+    fn has_weapon_in_hands(&self, obj: &Object, world: &World) -> bool {
+        self.hand_items(obj, world).any(|item| {
+            item.pid.proto_id()
+                .map(|pid| self.proto_db.proto(pid).unwrap().sub.item()
+                    .and_then(|i| i.sub.weapon())
+                    .map(|w| w.animation_code != WeaponKind::Unarmed)
+                    .unwrap_or(false))
+                .unwrap_or(false)
+        })
+    }
+
+    // inven_left_hand(), inven_right_hand()
+    fn hand_items<'a>(&self, obj: &'a Object, world: &'a World)
+        -> impl Iterator<Item=Ref<'a, Object>>
+    {
+        obj.inventory.items.iter()
+            .map(move |i| world.objects().get(i.object).borrow())
+            .filter(|item| item.flags.intersects(Flag::LeftHand | Flag::RightHand))
     }
 
     fn critter_proto(&self, obj: &Object) -> Ref<proto::Critter> {
@@ -218,3 +937,96 @@ impl Stats {
         Ref::map(proto, |p| p.sub.critter().unwrap())
     }
 }
+
+fn is_primary(stat: Stat) -> bool {
+    stat as u32 <= Stat::Luck as u32
+}
+
+/// Experience points needed to reach the `level`.
+// stat_pc_min_exp()
+pub fn level_experience(level: i32) -> i32 {
+    500 * level * (level - 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+    use crate::asset::{CritterAnim, EntityKind};
+    use crate::asset::frame::FrameId;
+    use crate::asset::proto::CritterKillKind;
+    use crate::util::test::TestAssets;
+
+    #[test]
+    fn write_read() {
+        let assets = TestAssets::new();
+        let proto_db = assets.proto_db();
+        let new_stats = || Stats::new(&assets.fs(), proto_db.clone(), "english").unwrap();
+        let pid = ObjectProtoId::ProtoId(ProtoId::new(EntityKind::Critter, 3).unwrap());
+
+        let mut stats = new_stats();
+        stats.dude_base_stats[Stat::Strength] = 8;
+        stats.pc_stats[PcStat::Experience] = 1500;
+        stats.dude_skills[Skill::Lockpick] = 20;
+        stats.tagged_skills = vec![Skill::Lockpick, Skill::Conversant];
+        stats.traits = vec![Trait::Kamikaze];
+        stats.add_perk(Perk::BonusAwareness, ObjectProtoId::Dude);
+        stats.add_perk(Perk::Toughness, pid);
+        stats.free_perks = 1;
+        stats.mod_bonus_stat(Stat::Agility, 2, pid);
+
+        let mut data = Vec::new();
+        stats.write(&mut data).unwrap();
+        let mut actual = new_stats();
+        actual.read(&mut Cursor::new(data)).unwrap();
+
+        assert_eq!(actual.dude_base_stats, stats.dude_base_stats);
+        assert_eq!(actual.pc_stats, stats.pc_stats);
+        assert_eq!(actual.dude_skills, stats.dude_skills);
+        assert_eq!(actual.tagged_skills, stats.tagged_skills);
+        assert_eq!(actual.traits, stats.traits);
+        assert_eq!(actual.perks, stats.perks);
+        assert_eq!(actual.free_perks, stats.free_perks);
+        assert_eq!(actual.bonus_stats, stats.bonus_stats);
+    }
+
+    #[test]
+    fn party_best_skill() {
+        let mut assets = TestAssets::new();
+        let pid = ObjectProtoId::ProtoId(assets.add_critter(0, CritterKillKind::Man));
+        let mut world = assets.world();
+        let mut stats = Stats::new(&assets.fs(), assets.proto_db(), "english").unwrap();
+
+        let fid = FrameId::new_critter(None, CritterAnim::Stand, WeaponKind::Unarmed, 0).unwrap();
+        let mut new_critter = |pid| {
+            let mut obj = Object::new(fid, pid, None);
+            obj.sub.critter_mut().unwrap().health = 1;
+            world.insert_object(obj)
+        };
+        let dude = new_critter(ObjectProtoId::Dude);
+        let member = new_critter(pid);
+        world.set_dude_obj(dude);
+        world.party_mut().add(member);
+
+        let dude_skill = stats.skill(Skill::Barter, &world.objects().get(dude).borrow(), &world);
+        assert_eq!(stats.party_best_skill(Skill::Barter, &world), dude_skill);
+
+        stats.mod_bonus_stat(Stat::Charisma, 10, pid);
+        let member_skill = stats.skill(Skill::Barter, &world.objects().get(member).borrow(),
+            &world);
+        assert!(member_skill > dude_skill);
+        assert_eq!(stats.party_best_skill(Skill::Barter, &world), member_skill);
+
+        world.objects().get(member).borrow_mut().sub.critter_mut().unwrap().health = 0;
+        assert_eq!(stats.party_best_skill(Skill::Barter, &world), dude_skill);
+    }
+
+    #[test]
+    fn level_experience_() {
+        assert_eq!(level_experience(1), 0);
+        assert_eq!(level_experience(2), 1000);
+        assert_eq!(level_experience(3), 3000);
+        assert_eq!(level_experience(4), 6000);
+        assert_eq!(level_experience(21), 210000);
+    }
+}
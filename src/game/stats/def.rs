@@ -53,4 +53,226 @@ const DEFS: &[StatDef] = &[
     StatDef::new(10, 0, 2000, 0), // CurrentHitPoints
     StatDef::new(11, 0, 2000, 0), // CurrentPoison
     StatDef::new(12, 0, 2000, 0), // CurrentRad
-];
\ No newline at end of file
+];
+
+#[derive(Clone)]
+pub struct PcStatDef {
+    pub min: i32,
+    pub max: i32,
+    pub default: i32,
+}
+
+impl PcStatDef {
+    pub fn defaults() -> EnumMap<PcStat, PcStatDef> {
+        EnumMap::from(|stat| PC_STAT_DEFS[stat as usize].clone())
+    }
+}
+
+const PC_STAT_DEFS: &[PcStatDef] = &[
+    PcStatDef::new(0, i32::max_value(), 0), // UnspentSkillPoints
+    PcStatDef::new(1, 99, 1), // Level
+    PcStatDef::new(0, i32::max_value(), 0), // Experience
+    PcStatDef::new(-20, 20, 0), // Reputation
+    PcStatDef::new(i32::min_value(), i32::max_value(), 0), // Karma
+];
+
+#[derive(Clone)]
+pub struct PerkDef {
+    /// Zero for perks that can't be picked on level up.
+    pub max_rank: u32,
+    pub min_level: i32,
+    /// Bonus to the stat granted by each rank of the perk.
+    pub stat_bonus: Option<(Stat, i32)>,
+    pub skills: &'static [(Skill, i32)],
+    /// If `true` any of the `skills` requirements is enough, otherwise all of them must be met.
+    pub any_skill: bool,
+    /// Minimum levels of the primary stats. Negative value is the maximum level instead.
+    pub stats: [i32; 7],
+}
+
+impl PerkDef {
+    pub fn defaults() -> EnumMap<Perk, PerkDef> {
+        EnumMap::from(|perk| PERK_DEFS[perk as usize].clone())
+    }
+}
+
+const PERK_DEFS: &[PerkDef] = &[
+    PerkDef::new(1, 3, None, &[], false, [0, 5, 0, 0, 0, 0, 0]), // BonusAwareness
+    PerkDef::new(1, 15, None, &[], false, [0, 0, 0, 0, 0, 6, 0]), // BonusHthAttacks
+    // BonusHthDamage
+    PerkDef::new(3, 3, Some((Stat::MeleeDmg, 2)), &[], false, [6, 0, 0, 0, 0, 6, 0]),
+    PerkDef::new(2, 6, None, &[], false, [0, 0, 0, 0, 0, 5, 0]), // BonusMove
+    PerkDef::new(2, 6, None, &[], false, [0, 0, 0, 0, 0, 6, 6]), // BonusRangedDamage
+    PerkDef::new(1, 15, None, &[], false, [0, 6, 0, 0, 6, 7, 0]), // BonusRateOfFire
+    // EarlierSequence
+    PerkDef::new(3, 3, Some((Stat::Sequence, 2)), &[], false, [0, 6, 0, 0, 0, 0, 0]),
+    // FasterHealing
+    PerkDef::new(3, 3, Some((Stat::HealRate, 2)), &[], false, [0, 0, 6, 0, 0, 0, 0]),
+    // MoreCriticals
+    PerkDef::new(3, 6, Some((Stat::CritChance, 5)), &[], false, [0, 0, 0, 0, 0, 0, 6]),
+    PerkDef::new(1, 3, None, &[], false, [0, 6, 0, 0, 0, 0, 0]), // NightVision
+    PerkDef::new(3, 3, None, &[], false, [0, 0, 0, 6, 0, 0, 0]), // Presence
+    // RadResistance
+    PerkDef::new(2, 6, Some((Stat::RadResist, 15)), &[], false, [0, 0, 6, 0, 4, 0, 0]),
+    PerkDef::new(3, 3, Some((Stat::DmgResist, 10)), &[], false, [0, 0, 6, 0, 0, 0, 6]), // Toughness
+    // StrongBack
+    PerkDef::new(3, 3, Some((Stat::CarryWeight, 50)), &[], false, [6, 0, 6, 0, 0, 0, 0]),
+    PerkDef::new(1, 9, None, &[], false, [0, 7, 0, 0, 6, 0, 0]), // Sharpshooter
+    PerkDef::new(1, 6, None, &[(Skill::Sneak, 50)], false, [0, 0, 0, 0, 0, 6, 0]), // SilentRunning
+    // Survivalist
+    PerkDef::new(1, 3, None, &[(Skill::Outdoorsman, 40)], false, [0, 0, 6, 0, 6, 0, 0]),
+    PerkDef::new(1, 12, None, &[(Skill::Barter, 75)], false, [0, 0, 0, 7, 0, 0, 0]), // MasterTrader
+    PerkDef::new(3, 6, None, &[], false, [0, 0, 0, 0, 6, 0, 0]), // Educated
+    PerkDef::new(2, 3, None, &[(Skill::FirstAid, 40)], false, [0, 7, 0, 0, 5, 6, 0]), // Healer
+    PerkDef::new(1, 6, None, &[], false, [0, 0, 0, 0, 0, 0, 8]), // FortuneFinder
+    PerkDef::new(1, 9, None, &[], false, [0, 6, 0, 0, 0, 4, 6]), // BetterCriticals
+    PerkDef::new(1, 6, None, &[], false, [0, 7, 0, 0, 5, 0, 0]), // Empathy
+    // Slayer
+    PerkDef::new(1, 24, None, &[(Skill::UnarmedCombat, 80)], false, [8, 0, 0, 0, 0, 8, 0]),
+    PerkDef::new(1, 24, None, &[(Skill::SmallGuns, 80)], false, [0, 8, 0, 0, 0, 8, 0]), // Sniper
+    // SilentDeath
+    PerkDef::new(1, 18, None, &[(Skill::Sneak, 80), (Skill::UnarmedCombat, 80)], false,
+        [0, 0, 0, 0, 0, 10, 0]),
+    // ActionBoy
+    PerkDef::new(2, 12, Some((Stat::ActionPoints, 1)), &[], false, [0, 0, 0, 0, 0, 5, 0]),
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // MentalBlock
+    PerkDef::new(2, 12, None, &[], false, [0, 0, 4, 0, 0, 0, 0]), // Lifegiver
+    PerkDef::new(1, 9, Some((Stat::ArmorClass, 5)), &[], false, [0, 0, 0, 0, 0, 6, 0]), // Dodger
+    // Snakeater
+    PerkDef::new(2, 6, Some((Stat::PoisonResist, 25)), &[], false, [0, 0, 3, 0, 0, 0, 0]),
+    // MrFixit
+    PerkDef::new(1, 12, None, &[(Skill::Repair, 40), (Skill::Science, 40)], true,
+        [0, 0, 0, 0, 0, 0, 0]),
+    // Medic
+    PerkDef::new(1, 12, None, &[(Skill::FirstAid, 40), (Skill::Doctor, 40)], true,
+        [0, 0, 0, 0, 0, 0, 0]),
+    // MasterThief
+    PerkDef::new(1, 12, None, &[(Skill::Steal, 50), (Skill::Lockpick, 50)], false,
+        [0, 0, 0, 0, 0, 0, 0]),
+    PerkDef::new(1, 9, None, &[(Skill::Conversant, 50)], false, [0, 0, 0, 0, 0, 0, 0]), // Speaker
+    PerkDef::new(1, 6, None, &[], false, [-8, 0, 0, 0, 0, 0, 0]), // HeaveHo
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // FriendlyFoe
+    PerkDef::new(1, 15, None, &[(Skill::Steal, 80)], false, [0, 0, 0, 0, 0, 8, 0]), // Pickpocket
+    PerkDef::new(1, 6, None, &[(Skill::Sneak, 60)], false, [0, 0, 0, 0, 0, 0, 0]), // Ghost
+    PerkDef::new(1, 12, None, &[], false, [0, 0, 0, 10, 0, 0, 0]), // CultOfPersonality
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // Scrounger
+    PerkDef::new(1, 9, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // Explorer
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // FlowerChild
+    // Pathfinder
+    PerkDef::new(2, 6, None, &[(Skill::Outdoorsman, 40)], false, [0, 0, 6, 0, 0, 0, 0]),
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // AnimalFriend
+    PerkDef::new(1, 3, None, &[], false, [0, 7, 0, 0, 0, 0, 0]), // Scout
+    PerkDef::new(1, 9, None, &[], false, [0, 0, 0, 0, 0, 0, 4]), // MysteriousStranger
+    PerkDef::new(1, 6, None, &[], false, [0, 6, 0, 0, 0, 0, 0]), // Ranger
+    PerkDef::new(1, 3, None, &[], false, [0, 0, 0, 0, 0, 5, 0]), // QuickPockets
+    PerkDef::new(3, 3, None, &[], false, [0, 0, 0, 0, 4, 0, 0]), // SmoothTalker
+    PerkDef::new(3, 3, None, &[], false, [0, 0, 0, 0, 4, 0, 0]), // SwiftLearner
+    PerkDef::new(1, 12, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // Tag
+    PerkDef::new(1, 9, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // Mutate
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // AddNuka
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // AddBuffout
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // AddMentats
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // AddPsycho
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // AddRadaway
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // WeaponLongRange
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // WeaponAccurate
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // WeaponPenetrate
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // WeaponKnockback
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // ArmorPowered
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // ArmorCombat
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // WeaponScopeRange
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // WeaponFastReload
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // WeaponNightSight
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // WeaponFlameboy
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // ArmorAdvanced1
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // ArmorAdvanced2
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // AddJet
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // AddTragic
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // ArmorCharisma
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // GeckoSkinning
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // DermalArmor
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // DermalEnhancement
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // PhoenixArmor
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // PhoenixEnhancement
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // VaultCityInoculations
+    PerkDef::new(1, 6, None, &[], false, [-9, 0, 0, 0, 0, 0, 0]), // AdrenalineRush
+    PerkDef::new(1, 3, None, &[], false, [0, 6, 0, 0, 0, 0, 0]), // CautiousNature
+    PerkDef::new(1, 3, None, &[], false, [0, 0, 0, 0, 6, 0, 0]), // Comprehension
+    // DemolitionExpert
+    PerkDef::new(1, 9, None, &[(Skill::Traps, 75)], false, [0, 0, 0, 0, 0, 4, 0]),
+    PerkDef::new(1, 6, None, &[(Skill::Gambling, 50)], false, [0, 0, 0, 0, 0, 0, 0]), // Gambler
+    PerkDef::new(1, 12, None, &[], false, [-9, 0, 0, 0, 0, 0, 0]), // GainStrength
+    PerkDef::new(1, 12, None, &[], false, [0, -9, 0, 0, 0, 0, 0]), // GainPerception
+    PerkDef::new(1, 12, None, &[], false, [0, 0, -9, 0, 0, 0, 0]), // GainEndurance
+    PerkDef::new(1, 12, None, &[], false, [0, 0, 0, -9, 0, 0, 0]), // GainCharisma
+    PerkDef::new(1, 12, None, &[], false, [0, 0, 0, 0, -9, 0, 0]), // GainIntelligence
+    PerkDef::new(1, 12, None, &[], false, [0, 0, 0, 0, 0, -9, 0]), // GainAgility
+    PerkDef::new(1, 12, None, &[], false, [0, 0, 0, 0, 0, 0, -9]), // GainLuck
+    PerkDef::new(1, 6, None, &[(Skill::Steal, 50)], false, [0, 0, 0, 0, 0, 0, 0]), // Harmless
+    PerkDef::new(1, 3, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // HereAndNow
+    // HthEvade
+    PerkDef::new(1, 12, None, &[(Skill::UnarmedCombat, 75)], false, [0, 0, 0, 0, 0, 0, 0]),
+    PerkDef::new(1, 3, None, &[], false, [0, 0, 5, 0, 0, 5, 0]), // KamaSutra
+    PerkDef::new(1, 9, None, &[], false, [0, 0, 0, 6, 0, 0, 0]), // KarmaBeacon
+    PerkDef::new(1, 9, None, &[], false, [0, 0, 0, 0, 0, 5, 5]), // LightStep
+    // LivingAnatomy
+    PerkDef::new(1, 12, None, &[(Skill::Doctor, 60)], false, [0, 0, 0, 0, 0, 0, 0]),
+    PerkDef::new(1, 3, None, &[], false, [0, 0, 0, -9, 0, 0, 0]), // MagneticPersonality
+    // Negotiator
+    PerkDef::new(1, 6, None, &[(Skill::Barter, 50), (Skill::Conversant, 50)], false,
+        [0, 0, 0, 0, 0, 0, 0]),
+    PerkDef::new(1, 6, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // PackRat
+    PerkDef::new(1, 9, None, &[(Skill::BigGuns, 75)], false, [0, 0, 0, 0, 0, 0, 0]), // Pyromaniac
+    PerkDef::new(1, 3, None, &[], false, [0, 0, 0, 0, 0, 5, 0]), // QuickRecovery
+    PerkDef::new(1, 6, None, &[(Skill::Barter, 50)], false, [0, 0, 0, 0, 0, 0, 0]), // Salesman
+    PerkDef::new(1, 3, None, &[], false, [6, 0, 0, 0, 0, 0, 0]), // Stonewall
+    PerkDef::new(1, 3, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // Thief
+    PerkDef::new(1, 12, None, &[], false, [-6, 0, 0, 0, 0, 5, 0]), // WeaponHandling
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // VaultCityTraining
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // AlcoholHpBonus1
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // AlcoholHpBonus2
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // AlcoholHpNeg1
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // AlcoholHpNeg2
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // AutodocHpBonus1
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // AutodocHpBonus2
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // AutodocHpNeg1
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // AutodocHpNeg2
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // ExpertExcrementExpediter
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // WeaponKnockout
+    PerkDef::new(0, 0, None, &[], false, [0, 0, 0, 0, 0, 0, 0]), // Jinxed
+];
+
+#[derive(Clone)]
+pub struct SkillDef {
+    pub base: i32,
+    pub stat_multiplier: i32,
+    pub stat1: Stat,
+    pub stat2: Option<Stat>,
+}
+
+impl SkillDef {
+    pub fn defaults() -> EnumMap<Skill, SkillDef> {
+        EnumMap::from(|skill| SKILL_DEFS[skill as usize].clone())
+    }
+}
+
+const SKILL_DEFS: &[SkillDef] = &[
+    SkillDef::new(5, 4, Stat::Agility, None), // SmallGuns
+    SkillDef::new(0, 2, Stat::Agility, None), // BigGuns
+    SkillDef::new(0, 2, Stat::Agility, None), // EnergyWeapons
+    SkillDef::new(30, 2, Stat::Agility, Some(Stat::Strength)), // UnarmedCombat
+    SkillDef::new(20, 2, Stat::Agility, Some(Stat::Strength)), // Melee
+    SkillDef::new(0, 4, Stat::Agility, None), // Throwing
+    SkillDef::new(0, 2, Stat::Perception, Some(Stat::Intelligence)), // FirstAid
+    SkillDef::new(5, 1, Stat::Perception, Some(Stat::Intelligence)), // Doctor
+    SkillDef::new(5, 3, Stat::Agility, None), // Sneak
+    SkillDef::new(10, 1, Stat::Perception, Some(Stat::Agility)), // Lockpick
+    SkillDef::new(0, 3, Stat::Agility, None), // Steal
+    SkillDef::new(10, 1, Stat::Perception, Some(Stat::Agility)), // Traps
+    SkillDef::new(0, 4, Stat::Intelligence, None), // Science
+    SkillDef::new(0, 3, Stat::Intelligence, None), // Repair
+    SkillDef::new(0, 5, Stat::Charisma, None), // Conversant
+    SkillDef::new(0, 4, Stat::Charisma, None), // Barter
+    SkillDef::new(0, 5, Stat::Luck, None), // Gambling
+    SkillDef::new(0, 2, Stat::Endurance, Some(Stat::Intelligence)), // Outdoorsman
+];
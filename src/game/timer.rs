@@ -0,0 +1,214 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use num_traits::FromPrimitive;
+use std::io::{self, Error, ErrorKind, prelude::*};
+
+use crate::asset::Stat;
+use crate::game::GameTime;
+use crate::game::object;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EventKind {
+    /// Runs `timed_event_p_proc` of the object's script.
+    /// The `fixed_param` is made available to the script via `fixed_param()` instruction.
+    Script {
+        fixed_param: i32,
+    },
+    /// Applies delayed effect of a drug taken by the object.
+    DrugEffect {
+        stat: Stat,
+        amount: i32,
+    },
+}
+
+impl EventKind {
+    fn tag(self) -> u32 {
+        match self {
+            EventKind::Script { .. } => 0,
+            EventKind::DrugEffect { .. } => 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Event {
+    pub time: GameTime,
+    pub obj: object::Handle,
+    pub kind: EventKind,
+}
+
+/// Queue of events ordered by game time. Events scheduled for the same time fire in the order
+/// they were pushed.
+// queue_*()
+#[derive(Default)]
+pub struct Timer {
+    events: Vec<Event>,
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Self {
+            events: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=&Event> {
+        self.events.iter()
+    }
+
+    // queue_add()
+    pub fn push(&mut self, event: Event) {
+        let i = self.events.iter()
+            .position(|e| e.time > event.time)
+            .unwrap_or(self.events.len());
+        self.events.insert(i, event);
+    }
+
+    /// Time of the earliest event.
+    pub fn next_time(&self) -> Option<GameTime> {
+        self.events.first().map(|e| e.time)
+    }
+
+    /// Removes and returns the earliest event if it's due at `now`.
+    #[must_use]
+    pub fn pop_due(&mut self, now: GameTime) -> Option<Event> {
+        if self.next_time()? <= now {
+            Some(self.events.remove(0))
+        } else {
+            None
+        }
+    }
+
+    pub fn retain(&mut self, f: impl FnMut(&Event) -> bool) {
+        self.events.retain(f);
+    }
+
+    // queue_remove()
+    pub fn remove_object(&mut self, obj: object::Handle) {
+        self.retain(|e| e.obj != obj);
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// Writes the queue. `obj_id` maps object handle to its persistent ID. Events of objects that
+    /// don't map to an ID are skipped.
+    // queue_save()
+    pub fn write(&self, wr: &mut impl Write, obj_id: impl Fn(object::Handle) -> Option<u32>)
+        -> io::Result<()>
+    {
+        let events: Vec<_> = self.events.iter()
+            .filter_map(|e| obj_id(e.obj).map(|id| (e, id)))
+            .collect();
+        wr.write_u32::<BigEndian>(events.len() as u32)?;
+        for (event, obj_id) in events {
+            wr.write_u32::<BigEndian>(event.time.as_decis())?;
+            wr.write_u32::<BigEndian>(obj_id)?;
+            wr.write_u32::<BigEndian>(event.kind.tag())?;
+            match event.kind {
+                EventKind::Script { fixed_param } => wr.write_i32::<BigEndian>(fixed_param)?,
+                EventKind::DrugEffect { stat, amount } => {
+                    wr.write_i32::<BigEndian>(stat as i32)?;
+                    wr.write_i32::<BigEndian>(amount)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the queue written with `write()`. `obj` maps persistent object ID back to handle.
+    /// Events of objects that can't be resolved are dropped.
+    // queue_load()
+    pub fn read(rd: &mut impl Read, obj: impl Fn(u32) -> Option<object::Handle>)
+        -> io::Result<Self>
+    {
+        let mut r = Self::new();
+        let len = rd.read_u32::<BigEndian>()?;
+        for _ in 0..len {
+            let time = GameTime::from_decis(rd.read_u32::<BigEndian>()?);
+            let obj_id = rd.read_u32::<BigEndian>()?;
+            let kind = match rd.read_u32::<BigEndian>()? {
+                0 => EventKind::Script {
+                    fixed_param: rd.read_i32::<BigEndian>()?,
+                },
+                1 => EventKind::DrugEffect {
+                    stat: Stat::from_i32(rd.read_i32::<BigEndian>()?)
+                        .ok_or_else(|| Error::new(ErrorKind::InvalidData,
+                            "invalid drug effect stat"))?,
+                    amount: rd.read_i32::<BigEndian>()?,
+                },
+                v => return Err(Error::new(ErrorKind::InvalidData,
+                    format!("unknown timer event kind: {}", v))),
+            };
+            if let Some(obj) = obj(obj_id) {
+                r.push(Event {
+                    time,
+                    obj,
+                    kind,
+                });
+            }
+        }
+        Ok(r)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    fn script(time: u32, fixed_param: i32) -> Event {
+        Event {
+            time: GameTime::from_decis(time),
+            obj: object::Handle::null(),
+            kind: EventKind::Script { fixed_param },
+        }
+    }
+
+    #[test]
+    fn order() {
+        let mut t = Timer::new();
+        t.push(script(20, 1));
+        t.push(script(10, 2));
+        t.push(script(20, 3));
+        t.push(script(15, 4));
+
+        assert_eq!(t.pop_due(GameTime::from_decis(9)), None);
+        let mut fired = Vec::new();
+        while let Some(e) = t.pop_due(GameTime::from_decis(20)) {
+            if let EventKind::Script { fixed_param } = e.kind {
+                fired.push(fixed_param);
+            }
+        }
+        assert_eq!(fired, &[2, 4, 1, 3]);
+        assert!(t.is_empty());
+    }
+
+    #[test]
+    fn write_read() {
+        let mut t = Timer::new();
+        t.push(script(123, -1));
+        t.push(script(45, 7));
+        t.push(Event {
+            time: GameTime::from_decis(600),
+            obj: object::Handle::null(),
+            kind: EventKind::DrugEffect { stat: Stat::Strength, amount: -2 },
+        });
+
+        let mut buf = Vec::new();
+        t.write(&mut buf, |_| Some(42)).unwrap();
+        let t2 = Timer::read(&mut Cursor::new(buf), |id| {
+            assert_eq!(id, 42);
+            Some(object::Handle::null())
+        }).unwrap();
+        assert_eq!(t.events, t2.events);
+    }
+}
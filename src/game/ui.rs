@@ -1,4 +1,17 @@
 pub mod action_menu;
+pub mod barter;
+pub mod called_shot;
+pub mod character;
+pub mod combat_control;
+pub mod elevator;
+pub mod encounter;
 pub mod hud;
+pub mod inventory;
+pub mod inventory_list;
+pub mod loot;
+pub mod perk_select;
+pub mod save_load;
 pub mod scroll_area;
+pub mod skilldex;
 pub mod world;
+pub mod worldmap;
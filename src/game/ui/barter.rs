@@ -0,0 +1,205 @@
+use bstring::BString;
+use enum_map::{enum_map, Enum, EnumMap};
+
+use crate::asset::frame::FrameId;
+use crate::game::object;
+use crate::game::world::World;
+use crate::graphics::{Point, Rect};
+use crate::graphics::color::GREEN;
+use crate::graphics::font::FontKey;
+use crate::graphics::sprite::Sprite;
+use crate::ui::*;
+use crate::ui::button::Button;
+use crate::ui::command::UiCommandData;
+use crate::ui::message_panel::MessagePanel;
+
+use super::inventory_list::{self, InventoryList};
+
+#[derive(Clone, Copy, Debug, Enum, Eq, PartialEq)]
+pub enum Side {
+    Player,
+    Trader,
+}
+
+/// Item table of the barter screen.
+#[derive(Clone, Copy, Debug, Enum, Eq, PartialEq)]
+pub enum Table {
+    PlayerInventory,
+    PlayerOffer,
+    TraderOffer,
+    TraderInventory,
+}
+
+impl Table {
+    pub fn inventory(side: Side) -> Self {
+        match side {
+            Side::Player => Table::PlayerInventory,
+            Side::Trader => Table::TraderInventory,
+        }
+    }
+
+    pub fn offer(side: Side) -> Self {
+        match side {
+            Side::Player => Table::PlayerOffer,
+            Side::Trader => Table::TraderOffer,
+        }
+    }
+
+    pub fn side(self) -> Side {
+        match self {
+            Table::PlayerInventory | Table::PlayerOffer => Side::Player,
+            Table::TraderOffer | Table::TraderInventory => Side::Trader,
+        }
+    }
+
+    pub fn is_offer(self) -> bool {
+        self == Table::PlayerOffer || self == Table::TraderOffer
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Command {
+    ScrollUp(Table),
+    ScrollDown(Table),
+    Offer,
+    Talk,
+}
+
+/// Barter screen shown in place of the dialog options. Offered items stay in the owners'
+/// inventories until the deal is made.
+// barter_inventory()
+pub struct Barter {
+    window: Handle,
+    tables: EnumMap<Table, Handle>,
+    totals: EnumMap<Side, Handle>,
+    objects: EnumMap<Side, object::Handle>,
+    offers: EnumMap<Side, Vec<object::Handle>>,
+    /// Price modifier in percents.
+    pub barter_mod: i32,
+}
+
+impl Barter {
+    pub fn show(ui: &mut Ui, player: object::Handle, trader: object::Handle, barter_mod: i32)
+        -> Self
+    {
+        let window = ui.new_window(Rect::with_size(0, 480 - 190, 640, 190),
+            Some(Sprite::new(FrameId::BARTER)));
+
+        let mut table = |x, y| ui.new_widget(window, Rect::with_size(x, y, 64, 48 * 3),
+            None, None, InventoryList::new(48, 3));
+        let tables = enum_map! {
+            Table::PlayerInventory => table(109, 35),
+            Table::PlayerOffer => table(245, 20),
+            Table::TraderOffer => table(330, 20),
+            Table::TraderInventory => table(475, 35),
+        };
+
+        let mut total = |x| {
+            let total = MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(1), GREEN);
+            ui.new_widget(window, Rect::with_size(x, 168, 64, 16), None, None, total)
+        };
+        let totals = enum_map! {
+            Side::Player => total(245),
+            Side::Trader => total(330),
+        };
+
+        for &(table, x) in &[(Table::PlayerInventory, 80), (Table::TraderInventory, 541)] {
+            ui.new_widget(window, Rect::with_size(x, 35, 22, 23), None, None,
+                Button::new(FrameId::INVUPOUT, FrameId::INVUPIN,
+                    Some(UiCommandData::Barter(Command::ScrollUp(table)))));
+            ui.new_widget(window, Rect::with_size(x, 58, 22, 23), None, None,
+                Button::new(FrameId::INVDNOUT, FrameId::INVDNIN,
+                    Some(UiCommandData::Barter(Command::ScrollDown(table)))));
+        }
+        ui.new_widget(window, Rect::with_size(40, 162, 15, 16), None, None,
+            Button::new(FrameId::DI_RDBT1, FrameId::DI_RDBT2,
+                Some(UiCommandData::Barter(Command::Offer))));
+        ui.new_widget(window, Rect::with_size(583, 162, 15, 16), None, None,
+            Button::new(FrameId::DI_RDBT1, FrameId::DI_RDBT2,
+                Some(UiCommandData::Barter(Command::Talk))));
+
+        Self {
+            window,
+            tables,
+            totals,
+            objects: enum_map! {
+                Side::Player => player,
+                Side::Trader => trader,
+            },
+            offers: Default::default(),
+            barter_mod,
+        }
+    }
+
+    pub fn hide(self, ui: &mut Ui) {
+        ui.remove(self.window);
+    }
+
+    pub fn object(&self, side: Side) -> object::Handle {
+        self.objects[side]
+    }
+
+    pub fn offer(&self, side: Side) -> &[object::Handle] {
+        &self.offers[side]
+    }
+
+    pub fn table_of(&self, widget: Handle) -> Option<Table> {
+        self.tables.iter()
+            .find(|(_, &h)| h == widget)
+            .map(|(table, _)| table)
+    }
+
+    pub fn table_at(&self, ui: &Ui, pos: Point) -> Option<Table> {
+        self.tables.iter()
+            .find(|(_, &h)| ui.widget_base(h).borrow().rect().contains(pos))
+            .map(|(table, _)| table)
+    }
+
+    pub fn scroll(&self, ui: &mut Ui, table: Table, up: bool) {
+        let mut list = ui.widget_mut::<InventoryList>(self.tables[table]);
+        if up {
+            list.scroll_up();
+        } else {
+            list.scroll_down();
+        }
+    }
+
+    /// Moves `item` of the `side` to the offer table if `offered` is `true` or back to the
+    /// inventory table otherwise.
+    pub fn set_offered(&mut self, side: Side, item: object::Handle, offered: bool) {
+        let offer = &mut self.offers[side];
+        if offered {
+            if !offer.contains(&item) {
+                offer.push(item);
+            }
+        } else {
+            offer.retain(|&i| i != item);
+        }
+    }
+
+    pub fn clear_offers(&mut self) {
+        for offer in self.offers.values_mut() {
+            offer.clear();
+        }
+    }
+
+    /// Updates the tables from the inventories. `totals` are the values displayed under
+    /// the offer tables.
+    pub fn sync(&mut self, ui: &mut Ui, world: &World, totals: EnumMap<Side, u32>) {
+        for side in &[Side::Player, Side::Trader] {
+            let side = *side;
+            let mut items = inventory_list::items(world, self.objects[side], true);
+            self.offers[side].retain(|o| items.iter().any(|i| i.object == *o));
+            let (offer, inventory) = items.drain(..)
+                .partition(|i| self.offers[side].contains(&i.object));
+            ui.widget_mut::<InventoryList>(self.tables[Table::inventory(side)])
+                .set_items(inventory);
+            ui.widget_mut::<InventoryList>(self.tables[Table::offer(side)])
+                .set_items(offer);
+
+            let mut total = ui.widget_mut::<MessagePanel>(self.totals[side]);
+            total.clear_messages();
+            total.push_message(BString::from(format!("${}", totals[side])));
+        }
+    }
+}
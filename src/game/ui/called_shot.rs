@@ -0,0 +1,104 @@
+use bstring::BString;
+use enum_map::EnumMap;
+
+use crate::asset::{CritterAnim, WeaponKind};
+use crate::asset::frame::FrameId;
+use crate::game::combat::attack::HitLocation;
+use crate::game::object;
+use crate::graphics::Rect;
+use crate::graphics::color::GREEN;
+use crate::graphics::font::FontKey;
+use crate::graphics::geometry::hex::Direction;
+use crate::graphics::sprite::Sprite;
+use crate::ui::*;
+use crate::ui::button::Button;
+use crate::ui::command::UiCommandData;
+use crate::ui::message_panel::MessagePanel;
+use crate::ui::panel::Panel;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Command {
+    Pick(HitLocation),
+    Cancel,
+}
+
+/// Locations listed at the left side of the window, top to bottom.
+// hit_loc_left
+const LEFT: [HitLocation; 4] = [
+    HitLocation::Head,
+    HitLocation::Eyes,
+    HitLocation::RightArm,
+    HitLocation::RightLeg,
+];
+
+/// Locations listed at the right side of the window, top to bottom.
+// hit_loc_right
+const RIGHT: [HitLocation; 4] = [
+    HitLocation::Torso,
+    HitLocation::Groin,
+    HitLocation::LeftArm,
+    HitLocation::LeftLeg,
+];
+
+/// Window for choosing the body part to aim at. Shows the target's portrait surrounded by
+/// the hit locations along with the chance to hit each of them.
+// called_shot_display()
+pub struct CalledShotWindow {
+    window: Handle,
+    target: object::Handle,
+}
+
+impl CalledShotWindow {
+    /// `fid` is the current frame of the `target` critter. `hit_chances` are in percents.
+    /// `location_names` are the names of the target's body parts.
+    pub fn show(ui: &mut Ui, target: object::Handle, fid: FrameId,
+        hit_chances: &EnumMap<HitLocation, i32>, location_names: &EnumMap<HitLocation, BString>)
+        -> Self
+    {
+        let window = ui.new_window(Rect::with_size(68, 20, 504, 309),
+            Some(Sprite::new(FrameId::CALLED)));
+
+        if let FrameId::Critter(fid) = fid {
+            let fid = fid
+                .with_anim(CritterAnim::CalledShotPic)
+                .with_weapon(WeaponKind::Unarmed)
+                .with_direction(Some(Direction::NE));
+            ui.new_widget(window, Rect::with_size(170, 31, 164, 228), None,
+                Some(Sprite::new(fid.into())), Panel::new());
+        }
+
+        for &(locations, button_x, label_x) in &[(&LEFT, 33, 53), (&RIGHT, 456, 366)] {
+            for (i, &location) in locations.iter().enumerate() {
+                let y = 42 + i as i32 * 60;
+                ui.new_widget(window, Rect::with_size(button_x, y, 15, 16), None, None,
+                    Button::new(FrameId::DI_RDBT1, FrameId::DI_RDBT2,
+                        Some(UiCommandData::CalledShot(Command::Pick(location)))));
+
+                let label = ui.new_widget(window, Rect::with_size(label_x, y, 85, 30), None, None,
+                    MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(1), GREEN));
+                let mut label = ui.widget_mut::<MessagePanel>(label);
+                label.push_message(&location_names[location]);
+                label.push_message(BString::from(
+                    format!("{}%", hit_chances[location].max(0))));
+            }
+        }
+
+        // The Cancel label is part of the window art.
+        ui.new_widget(window, Rect::with_size(210, 268, 15, 16), None, None,
+            Button::new(FrameId::MENUUP, FrameId::MENUDOWN,
+                Some(UiCommandData::CalledShot(Command::Cancel))));
+
+        Self {
+            window,
+            target,
+        }
+    }
+
+    pub fn hide(self, ui: &mut Ui) {
+        ui.remove(self.window);
+    }
+
+    pub fn target(&self) -> object::Handle {
+        self.target
+    }
+}
@@ -0,0 +1,258 @@
+use bstring::{bstr, BString};
+
+use crate::asset::Stat;
+use crate::asset::frame::FrameId;
+use crate::graphics::Rect;
+use crate::graphics::color::{GREEN, WHITE};
+use crate::graphics::font::FontKey;
+use crate::graphics::sprite::Sprite;
+use crate::ui::*;
+use crate::ui::button::{self, Button};
+use crate::ui::command::UiCommandData;
+use crate::ui::message_panel::{MessagePanel, MouseControl};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Command {
+    StatUp(Stat),
+    StatDown(Stat),
+    EditName,
+    NextAge,
+    ToggleGender,
+    NextPremade,
+    Perks,
+    Done,
+    Cancel,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mode {
+    /// Creating a new character. Primary stats, traits, tagged skills, name, age and gender
+    /// can be changed.
+    Create,
+
+    /// Character sheet of the dude in game.
+    View,
+}
+
+/// Primary stats listed in the window, top to bottom.
+pub const PRIMARY_STATS: [Stat; 7] = [
+    Stat::Strength,
+    Stat::Perception,
+    Stat::Endurance,
+    Stat::Charisma,
+    Stat::Intelligence,
+    Stat::Agility,
+    Stat::Luck,
+];
+
+/// Number of lines in each of the two trait columns.
+pub const TRAIT_COLUMN_LEN: u32 = 8;
+
+/// Contents of the character window.
+pub struct Sheet<'a> {
+    pub name: &'a bstr,
+    pub age: i32,
+    pub female: bool,
+    /// Values and level descriptions of the `PRIMARY_STATS`.
+    pub primary_stats: Vec<(i32, &'a bstr)>,
+    /// Level, experience and unspent points.
+    pub info: Vec<BString>,
+    pub derived_stats: Vec<BString>,
+    /// Trait names and whether they're selected. In `Mode::View` these are the traits and perks
+    /// the dude has.
+    pub traits: Vec<(&'a bstr, bool)>,
+    /// Skill names, levels and whether they're tagged.
+    pub skills: Vec<(&'a bstr, i32, bool)>,
+}
+
+/// Character editor used for creating the character at the start of a new game and as the
+/// character sheet in game.
+// editor_design()
+pub struct CharacterWindow {
+    window: Handle,
+    mode: Mode,
+    name: Handle,
+    age: Handle,
+    gender: Handle,
+    primary_stats: Vec<Handle>,
+    info: Handle,
+    derived_stats: Handle,
+    traits: [Handle; 2],
+    skill_names: Handle,
+    skill_levels: Handle,
+    description: Handle,
+}
+
+impl CharacterWindow {
+    pub fn show(ui: &mut Ui, mode: Mode) -> Self {
+        let background = match mode {
+            Mode::Create => FrameId::EDTRCRTE,
+            Mode::View => FrameId::EDTREDT,
+        };
+        let window = ui.new_window(Rect::with_size(0, 0, 640, 480),
+            Some(Sprite::new(background)));
+
+        let cmd = |cmd| if mode == Mode::Create {
+            Some(UiCommandData::Character(cmd))
+        } else {
+            None
+        };
+
+        let name = ui.new_widget(window, Rect::with_size(9, 0, 125, 26), None, None,
+            Button::new(FrameId::NAMEOFF, FrameId::NAMEON, cmd(Command::EditName)));
+        let age = ui.new_widget(window, Rect::with_size(154, 0, 80, 26), None, None,
+            Button::new(FrameId::AGEOFF, FrameId::AGEON, cmd(Command::NextAge)));
+        let gender = ui.new_widget(window, Rect::with_size(235, 0, 80, 26), None, None,
+            Button::new(FrameId::SEXOFF, FrameId::SEXON, cmd(Command::ToggleGender)));
+
+        let mut primary_stats = Vec::with_capacity(PRIMARY_STATS.len());
+        for (i, &stat) in PRIMARY_STATS.iter().enumerate() {
+            let y = 37 + i as i32 * 33;
+            primary_stats.push(ui.new_widget(window, Rect::with_size(58, y + 7, 85, 20), None,
+                None, MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(1), GREEN)));
+            if mode == Mode::Create {
+                ui.new_widget(window, Rect::with_size(149, y, 22, 11), None, None,
+                    Button::new(FrameId::SPLSOFF, FrameId::SPLSON,
+                        Some(UiCommandData::Character(Command::StatUp(stat)))));
+                ui.new_widget(window, Rect::with_size(149, y + 11, 22, 11), None, None,
+                    Button::new(FrameId::SNEGOFF, FrameId::SNEGON,
+                        Some(UiCommandData::Character(Command::StatDown(stat)))));
+            }
+        }
+
+        let info = ui.new_widget(window, Rect::with_size(32, 280, 150, 60), None, None,
+            MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(1), GREEN));
+        let derived_stats = ui.new_widget(window, Rect::with_size(194, 46, 120, 180), None,
+            None, MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(1), GREEN));
+
+        let new_list = |ui: &mut Ui, rect| {
+            let mut list = MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(1), GREEN);
+            if mode == Mode::Create {
+                list.set_mouse_control(MouseControl::Pick);
+                list.set_highlight_color(WHITE);
+            }
+            ui.new_widget(window, rect, None, None, list)
+        };
+        let traits = [
+            new_list(ui, Rect::with_size(23, 353, 150, 90)),
+            new_list(ui, Rect::with_size(175, 353, 150, 90)),
+        ];
+        let skill_names = new_list(ui, Rect::with_size(380, 27, 180, 200));
+        let skill_levels = ui.new_widget(window, Rect::with_size(573, 27, 40, 200), None, None,
+            MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(1), GREEN));
+
+        let description = ui.new_widget(window, Rect::with_size(348, 272, 280, 170), None, None,
+            MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(1), GREEN));
+
+        let mut buttons = vec![(455, Command::Done, "Done")];
+        match mode {
+            Mode::Create => buttons.push((345, Command::NextPremade, "Premade")),
+            Mode::View => {
+                buttons.push((345, Command::Perks, "Perks"));
+                buttons.push((552, Command::Cancel, "Cancel"));
+            }
+        }
+        for &(x, cmd, text) in &buttons {
+            ui.new_widget(window, Rect::with_size(x, 454, 15, 16), None, None,
+                Button::new(FrameId::LILREDUP, FrameId::LILREDDN,
+                    Some(UiCommandData::Character(cmd))));
+            let label = ui.new_widget(window, Rect::with_size(x + 21, 453, 70, 20), None, None,
+                MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(3), GREEN));
+            // TODO take the text from editor.msg
+            ui.widget_mut::<MessagePanel>(label).push_message(text);
+        }
+
+        Self {
+            window,
+            mode,
+            name,
+            age,
+            gender,
+            primary_stats,
+            info,
+            derived_stats,
+            traits,
+            skill_names,
+            skill_levels,
+            description,
+        }
+    }
+
+    pub fn hide(self, ui: &mut Ui) {
+        ui.remove(self.window);
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// If the `widget` is one of the trait lists returns index of the first trait in it.
+    pub fn trait_list_offset(&self, widget: Handle) -> Option<u32> {
+        self.traits.iter().position(|&h| h == widget).map(|i| i as u32 * TRAIT_COLUMN_LEN)
+    }
+
+    pub fn is_skill_list(&self, widget: Handle) -> bool {
+        widget == self.skill_names
+    }
+
+    pub fn sync(&self, ui: &mut Ui, sheet: &Sheet) {
+        fn set_text(ui: &mut Ui, button: Handle, text: BString) {
+            ui.widget_mut::<Button>(button).set_text(Some(button::Text {
+                text,
+                font: FontKey::antialiased(3),
+                color: WHITE,
+            }));
+        }
+        fn set_lines<T: AsRef<bstr>>(ui: &mut Ui, panel: Handle,
+            lines: impl IntoIterator<Item=T>)
+        {
+            let mut panel = ui.widget_mut::<MessagePanel>(panel);
+            panel.clear_messages();
+            for line in lines {
+                panel.push_message(line);
+            }
+        }
+
+        set_text(ui, self.name, sheet.name.into());
+        // TODO take the text from editor.msg
+        set_text(ui, self.age, format!("Age {}", sheet.age).into());
+        set_text(ui, self.gender, if sheet.female { "Female" } else { "Male" }.into());
+
+        for (&panel, &(value, level)) in self.primary_stats.iter().zip(&sheet.primary_stats) {
+            let mut line = BString::from(format!("{:02}  ", value));
+            line.push_str(level);
+            set_lines(ui, panel, Some(line));
+        }
+
+        set_lines(ui, self.info, &sheet.info);
+        set_lines(ui, self.derived_stats, &sheet.derived_stats);
+
+        let mut traits = sheet.traits.iter().map(|&(name, selected)| marked(name, selected));
+        for &list in &self.traits {
+            set_lines(ui, list, traits.by_ref().take(TRAIT_COLUMN_LEN as usize));
+        }
+
+        set_lines(ui, self.skill_names, sheet.skills.iter()
+            .map(|&(name, _, tagged)| marked(name, tagged)));
+        set_lines(ui, self.skill_levels, sheet.skills.iter()
+            .map(|&(_, level, _)| BString::from(format!("{}%", level))));
+    }
+
+    pub fn set_description(&self, ui: &mut Ui, title: &bstr, description: &bstr) {
+        let mut panel = ui.widget_mut::<MessagePanel>(self.description);
+        panel.clear_messages();
+        panel.push_message(title);
+        panel.push_message("");
+        panel.push_message(description);
+    }
+}
+
+/// Prefixes selected trait and tagged skill names with a mark.
+fn marked(name: &bstr, mark: bool) -> BString {
+    let mut r = BString::new();
+    if mark {
+        r.push_str("* ");
+    }
+    r.push_str(name);
+    r
+}
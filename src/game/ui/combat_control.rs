@@ -0,0 +1,77 @@
+use bstring::bstr;
+use enum_map::{enum_map, EnumMap};
+
+use crate::asset::frame::FrameId;
+use crate::game::party::Order;
+use crate::graphics::Rect;
+use crate::graphics::color::GREEN;
+use crate::graphics::font::FontKey;
+use crate::graphics::sprite::Sprite;
+use crate::ui::*;
+use crate::ui::button::Button;
+use crate::ui::command::UiCommandData;
+use crate::ui::message_panel::MessagePanel;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Command {
+    /// Show the panel in place of the dialog options.
+    Show,
+    /// Switch the order to the next allowed value.
+    Change(Order),
+    Done,
+}
+
+/// Panel for giving combat orders to a party member. Shown in place of the dialog options.
+// gdControl()
+pub struct CombatControl {
+    window: Handle,
+    values: EnumMap<Order, Handle>,
+}
+
+impl CombatControl {
+    pub fn show(ui: &mut Ui) -> Self {
+        let window = ui.new_window(Rect::with_size(0, 480 - 190, 640, 190),
+            Some(Sprite::new(FrameId::CONTROL)));
+
+        fn label(ui: &mut Ui, window: Handle, rect: Rect, text: &str) -> Handle {
+            let w = ui.new_widget(window, rect, None, None,
+                MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(1), GREEN));
+            ui.widget_mut::<MessagePanel>(w).push_message(text);
+            w
+        }
+        // TODO take the texts from custom.msg
+        let mut row = |i, order, text| {
+            let y = 40 + i * 30;
+            ui.new_widget(window, Rect::with_size(130, y, 15, 16), None, None,
+                Button::new(FrameId::DI_RDBT1, FrameId::DI_RDBT2,
+                    Some(UiCommandData::CombatControl(Command::Change(order)))));
+            label(ui, window, Rect::with_size(155, y, 120, 16), text);
+            label(ui, window, Rect::with_size(280, y, 240, 16), "")
+        };
+        let values = enum_map! {
+            Order::Distance => row(0, Order::Distance, "Distance:"),
+            Order::BestWeapon => row(1, Order::BestWeapon, "Weapon:"),
+            Order::AreaAttackMode => row(2, Order::AreaAttackMode, "Burst:"),
+        };
+
+        ui.new_widget(window, Rect::with_size(583, 162, 15, 16), None, None,
+            Button::new(FrameId::DI_RDBT1, FrameId::DI_RDBT2,
+                Some(UiCommandData::CombatControl(Command::Done))));
+
+        Self {
+            window,
+            values,
+        }
+    }
+
+    pub fn hide(self, ui: &mut Ui) {
+        ui.remove(self.window);
+    }
+
+    /// Shows the `value` of the `order`.
+    pub fn set_value(&self, ui: &mut Ui, order: Order, value: &bstr) {
+        let mut w = ui.widget_mut::<MessagePanel>(self.values[order]);
+        w.clear_messages();
+        w.push_message(value);
+    }
+}
@@ -0,0 +1,145 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::asset::elevator::ElevatorDef;
+use crate::asset::frame::{FrameDb, FrameId};
+use crate::graphics::Rect;
+use crate::graphics::geometry::hex::Direction;
+use crate::graphics::sprite::Sprite;
+use crate::ui::*;
+use crate::ui::button::Button;
+use crate::ui::command::{UiCommand, UiCommandData};
+use crate::ui::panel::Panel;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Command {
+    /// Button of the level was clicked.
+    Pick(u32),
+    /// The level indicator reached the picked level.
+    Arrived(u32),
+}
+
+const INDICATOR_STEP: Duration = Duration::from_millis(50);
+
+/// Elevator panel with a button for each level. Picking a level moves the level indicator
+/// towards it.
+// elevator_select()
+pub struct ElevatorWindow {
+    window: Handle,
+    indicator: Handle,
+    current_level: u32,
+}
+
+impl ElevatorWindow {
+    /// Shows the panel of the elevator `def` with the indicator at `current_level`.
+    pub fn show(ui: &mut Ui, frm_db: &FrameDb, def: &ElevatorDef, current_level: u32)
+        -> io::Result<Self>
+    {
+        assert!((current_level as usize) < def.levels.len());
+
+        let background = frm_db.get(def.fid)?;
+        let size = background.first().size();
+        let frame_count = background.frame_lists[Direction::NE].frames.len();
+
+        let window = ui.new_window(Rect::with_size((640 - size.x) / 2, (379 - size.y) / 2,
+            size.x, size.y), None);
+
+        let indicator = Indicator {
+            frame_count,
+            level_count: def.levels.len() as u32,
+            target: None,
+            last_step: None,
+        };
+        let mut sprite = Sprite::new(def.fid);
+        sprite.frame_idx = indicator.frame(current_level);
+        let indicator = ui.new_widget(window, Rect::with_size(0, 0, size.x, size.y), None,
+            Some(sprite), indicator);
+
+        if let Some(panel_fid) = def.panel_fid {
+            let panel_size = frm_db.get(panel_fid)?.first().size();
+            ui.new_widget(window,
+                Rect::with_size(0, size.y - panel_size.y, panel_size.x, panel_size.y), None,
+                Some(Sprite::new(panel_fid)), Panel::new());
+        }
+
+        let button_size = frm_db.get(FrameId::EBUT_OUT)?.first().size();
+        for level in 0..def.levels.len() as u32 {
+            ui.new_widget(window,
+                Rect::with_size(13, 40 + level as i32 * 60, button_size.x, button_size.y),
+                None, None,
+                Button::new(FrameId::EBUT_OUT, FrameId::EBUT_IN,
+                    Some(UiCommandData::Elevator(Command::Pick(level)))));
+        }
+
+        Ok(Self {
+            window,
+            indicator,
+            current_level,
+        })
+    }
+
+    pub fn hide(self, ui: &mut Ui) {
+        ui.remove(self.window);
+    }
+
+    pub fn current_level(&self) -> u32 {
+        self.current_level
+    }
+
+    /// Starts moving the level indicator to the `level`. `Command::Arrived` is sent once it's
+    /// there.
+    pub fn move_to(&mut self, ui: &mut Ui, level: u32) {
+        let mut indicator = ui.widget_mut::<Indicator>(self.indicator);
+        if indicator.target.is_none() {
+            indicator.target = Some(level);
+        }
+    }
+}
+
+/// Level indicator drawn by the frames of the elevator background.
+struct Indicator {
+    frame_count: usize,
+    level_count: u32,
+    target: Option<u32>,
+    last_step: Option<Instant>,
+}
+
+impl Indicator {
+    fn frame(&self, level: u32) -> usize {
+        if self.level_count > 1 {
+            level as usize * (self.frame_count - 1) / (self.level_count - 1) as usize
+        } else {
+            0
+        }
+    }
+}
+
+impl Widget for Indicator {
+    fn handle_event(&mut self, ctx: HandleEvent) {
+        let target = match (&ctx.event, self.target) {
+            (Event::Tick, Some(target)) => target,
+            _ => return,
+        };
+        if self.last_step.map(|t| ctx.now - t < INDICATOR_STEP).unwrap_or(false) {
+            return;
+        }
+        self.last_step = Some(ctx.now);
+
+        let target_frame = self.frame(target);
+        let sprite = ctx.base.background_mut().unwrap();
+        if sprite.frame_idx < target_frame {
+            sprite.frame_idx += 1;
+        } else if sprite.frame_idx > target_frame {
+            sprite.frame_idx -= 1;
+        } else {
+            self.target = None;
+            self.last_step = None;
+            ctx.out.push(UiCommand {
+                source: ctx.this,
+                data: UiCommandData::Elevator(Command::Arrived(target)),
+            });
+        }
+    }
+
+    fn render(&mut self, _ctx: Render) {}
+}
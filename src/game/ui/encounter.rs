@@ -0,0 +1,60 @@
+use bstring::bstr;
+use std::io;
+
+use crate::asset::frame::{FrameDb, FrameId};
+use crate::graphics::Rect;
+use crate::graphics::color::GREEN;
+use crate::graphics::font::FontKey;
+use crate::graphics::sprite::Sprite;
+use crate::ui::*;
+use crate::ui::button::Button;
+use crate::ui::command::UiCommandData;
+use crate::ui::message_panel::MessagePanel;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Command {
+    /// The player chose to face the encounter.
+    Accept,
+    /// The player chose to avoid the encounter.
+    Avoid,
+}
+
+/// Yes/No prompt shown over the world map when the party detects a random encounter.
+// wmRndEncounterOccurred()
+pub struct EncounterWindow {
+    window: Handle,
+}
+
+impl EncounterWindow {
+    pub fn show(ui: &mut Ui, frm_db: &FrameDb, text: &bstr) -> io::Result<Self> {
+        let size = frm_db.get(FrameId::MEDIALOG)?.first().size();
+        let window = ui.new_window(Rect::with_size((640 - size.x) / 2, (480 - size.y) / 2,
+            size.x, size.y), Some(Sprite::new(FrameId::MEDIALOG)));
+
+        let message = ui.new_widget(window, Rect::with_size(20, 25, size.x - 40, size.y - 70),
+            None, None, MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(1), GREEN));
+        ui.widget_mut::<MessagePanel>(message).push_message(text);
+
+        // TODO take the text from dbox.msg
+        for &(x, label, cmd) in &[
+            (size.x / 2 - 90, "YES", Command::Accept),
+            (size.x / 2 + 10, "NO", Command::Avoid),
+        ] {
+            ui.new_widget(window, Rect::with_size(x, size.y - 35, 15, 16), None, None,
+                Button::new(FrameId::LILREDUP, FrameId::LILREDDN,
+                    Some(UiCommandData::Encounter(cmd))));
+            let label_widget = ui.new_widget(window,
+                Rect::with_size(x + 20, size.y - 36, 60, 20), None, None,
+                MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(3), GREEN));
+            ui.widget_mut::<MessagePanel>(label_widget).push_message(label);
+        }
+
+        Ok(Self {
+            window,
+        })
+    }
+
+    pub fn hide(self, ui: &mut Ui) {
+        ui.remove(self.window);
+    }
+}
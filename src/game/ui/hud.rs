@@ -5,8 +5,19 @@ use crate::graphics::font::FontKey;
 use crate::graphics::sprite::Sprite;
 use crate::ui::*;
 use crate::ui::button::Button;
+use crate::ui::command::UiCommandData;
 use crate::ui::message_panel::{MessagePanel, Anchor};
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Command {
+    Inventory,
+    EndTurn,
+    EndCombat,
+    ToggleAimed,
+    Skilldex,
+    Character,
+}
+
 pub fn create(ui: &mut Ui) -> Handle {
     let main_hud = ui.new_window(Rect::with_size(0, 379, 640, 100), Some(Sprite::new(FrameId::IFACE)));
 
@@ -20,36 +31,50 @@ pub fn create(ui: &mut Ui) -> Handle {
     // Inventory button.
     // Original location is a bit off, at y=41.
     ui.new_widget(main_hud, Rect::with_size(211, 40, 32, 21), None, None,
-        Button::new(FrameId::INVENTORY_BUTTON_UP, FrameId::INVENTORY_BUTTON_DOWN));
+        Button::new(FrameId::INVENTORY_BUTTON_UP, FrameId::INVENTORY_BUTTON_DOWN,
+            Some(UiCommandData::Hud(Command::Inventory))));
 
     // Options button.
     ui.new_widget(main_hud, Rect::with_size(210, 62, 34, 34), None, None,
-        Button::new(FrameId::OPTIONS_BUTTON_UP, FrameId::OPTIONS_BUTTON_DOWN));
+        Button::new(FrameId::OPTIONS_BUTTON_UP, FrameId::OPTIONS_BUTTON_DOWN, None));
 
-    // Single/burst switch button.
+    // Attack mode switch button.
     ui.new_widget(main_hud, Rect::with_size(218, 6, 22, 21), None, None,
-        Button::new(FrameId::BIG_RED_BUTTON_UP, FrameId::BIG_RED_BUTTON_DOWN));
+        Button::new(FrameId::BIG_RED_BUTTON_UP, FrameId::BIG_RED_BUTTON_DOWN,
+            Some(UiCommandData::Hud(Command::ToggleAimed))));
 
     // Skilldex button.
     ui.new_widget(main_hud, Rect::with_size(523, 6, 22, 21), None, None,
-        Button::new(FrameId::BIG_RED_BUTTON_UP, FrameId::BIG_RED_BUTTON_DOWN));
+        Button::new(FrameId::BIG_RED_BUTTON_UP, FrameId::BIG_RED_BUTTON_DOWN,
+            Some(UiCommandData::Hud(Command::Skilldex))));
 
     // MAP button.
     ui.new_widget(main_hud, Rect::with_size(526, 40, 41, 19), None, None,
-        Button::new(FrameId::MAP_BUTTON_UP, FrameId::MAP_BUTTON_DOWN));
+        Button::new(FrameId::MAP_BUTTON_UP, FrameId::MAP_BUTTON_DOWN, None));
 
     // CHA button.
     ui.new_widget(main_hud, Rect::with_size(526, 59, 41, 19), None, None,
-        Button::new(FrameId::CHARACTER_BUTTON_UP, FrameId::CHARACTER_BUTTON_DOWN));
+        Button::new(FrameId::CHARACTER_BUTTON_UP, FrameId::CHARACTER_BUTTON_DOWN,
+            Some(UiCommandData::Hud(Command::Character))));
 
     // PIP button.
     ui.new_widget(main_hud, Rect::with_size(526, 78, 41, 19), None, None,
-        Button::new(FrameId::PIP_BUTTON_UP, FrameId::PIP_BUTTON_DOWN));
+        Button::new(FrameId::PIP_BUTTON_UP, FrameId::PIP_BUTTON_DOWN, None));
 
     // Attack button.
     // FIXME this should be a custom button with overlay text images.
     ui.new_widget(main_hud, Rect::with_size(267, 26, 188, 67), None, None,
-        Button::new(FrameId::SINGLE_ATTACK_BUTTON_UP, FrameId::SINGLE_ATTACK_BUTTON_DOWN));
+        Button::new(FrameId::SINGLE_ATTACK_BUTTON_UP, FrameId::SINGLE_ATTACK_BUTTON_DOWN, None));
+
+    // End turn button.
+    ui.new_widget(main_hud, Rect::with_size(590, 43, 38, 22), None, None,
+        Button::new(FrameId::ENDTURNU, FrameId::ENDTURND,
+            Some(UiCommandData::Hud(Command::EndTurn))));
+
+    // End combat button.
+    ui.new_widget(main_hud, Rect::with_size(590, 65, 38, 22), None, None,
+        Button::new(FrameId::ENDCMBTU, FrameId::ENDCMBTD,
+            Some(UiCommandData::Hud(Command::EndCombat))));
 
     message_panel
 }
\ No newline at end of file
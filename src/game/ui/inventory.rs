@@ -0,0 +1,150 @@
+use bstring::bstr;
+use enum_map::{enum_map, EnumMap};
+
+use crate::asset::frame::FrameId;
+use crate::asset::message::Messages;
+use crate::game::object::{self, EquipmentSlot};
+use crate::game::world::World;
+use crate::graphics::{Point, Rect};
+use crate::graphics::color::GREEN;
+use crate::graphics::font::FontKey;
+use crate::graphics::sprite::Sprite;
+use crate::ui::*;
+use crate::ui::button::Button;
+use crate::ui::command::UiCommandData;
+use crate::ui::message_panel::MessagePanel;
+
+use super::inventory_list::{self, InventoryList};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Command {
+    ScrollUp,
+    ScrollDown,
+    Done,
+}
+
+/// Place inside the inventory window where an item can be dropped to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DropTarget {
+    List,
+    Slot(EquipmentSlot),
+}
+
+/// Player inventory window.
+// handle_inventory(), setup_inventory()
+pub struct InventoryWindow {
+    window: Handle,
+    list: Handle,
+    slots: EnumMap<EquipmentSlot, Handle>,
+    info: Handle,
+    owner: object::Handle,
+    /// Item action menu and the item it was shown for.
+    pub item_action_menu: Option<(Handle, object::Handle)>,
+}
+
+impl InventoryWindow {
+    pub fn show(ui: &mut Ui, owner: object::Handle) -> Self {
+        let window = ui.new_window(Rect::with_size(80, 0, 499, 377),
+            Some(Sprite::new(FrameId::INVBOX)));
+
+        let list = ui.new_widget(window, Rect::with_size(44, 35, 64, 48 * 6), None, None,
+            InventoryList::new(48, 6));
+
+        let slot = |ui: &mut Ui, x, y| ui.new_widget(window, Rect::with_size(x, y, 90, 61),
+            None, None, InventoryList::new(61, 1));
+        let slots = enum_map! {
+            EquipmentSlot::Armor => slot(ui, 154, 183),
+            EquipmentSlot::LeftHand => slot(ui, 154, 286),
+            EquipmentSlot::RightHand => slot(ui, 245, 286),
+        };
+
+        let info = MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(1), GREEN);
+        let info = ui.new_widget(window, Rect::with_size(297, 44, 150, 180), None, None, info);
+
+        ui.new_widget(window, Rect::with_size(128, 39, 22, 23), None, None,
+            Button::new(FrameId::INVUPOUT, FrameId::INVUPIN,
+                Some(UiCommandData::Inventory(Command::ScrollUp))));
+        ui.new_widget(window, Rect::with_size(128, 62, 22, 23), None, None,
+            Button::new(FrameId::INVDNOUT, FrameId::INVDNIN,
+                Some(UiCommandData::Inventory(Command::ScrollDown))));
+        ui.new_widget(window, Rect::with_size(437, 329, 15, 16), None, None,
+            Button::new(FrameId::MENUUP, FrameId::MENUDOWN,
+                Some(UiCommandData::Inventory(Command::Done))));
+
+        Self {
+            window,
+            list,
+            slots,
+            info,
+            owner,
+            item_action_menu: None,
+        }
+    }
+
+    pub fn hide(self, ui: &mut Ui) {
+        ui.remove(self.window);
+    }
+
+    pub fn window(&self) -> Handle {
+        self.window
+    }
+
+    pub fn owner(&self) -> object::Handle {
+        self.owner
+    }
+
+    /// Returns `true` if `widget` is one of the item lists of this window.
+    pub fn is_list(&self, widget: Handle) -> bool {
+        widget == self.list || self.slots.values().any(|&h| h == widget)
+    }
+
+    pub fn drop_target_at(&self, ui: &Ui, pos: Point) -> Option<DropTarget> {
+        if ui.widget_base(self.list).borrow().rect().contains(pos) {
+            return Some(DropTarget::List);
+        }
+        self.slots.iter()
+            .find(|(_, &h)| ui.widget_base(h).borrow().rect().contains(pos))
+            .map(|(slot, _)| DropTarget::Slot(slot))
+    }
+
+    pub fn scroll(&self, ui: &mut Ui, up: bool) {
+        let mut list = ui.widget_mut::<InventoryList>(self.list);
+        if up {
+            list.scroll_up();
+        } else {
+            list.scroll_down();
+        }
+    }
+
+    /// Updates the item lists and the weight readout from the owner's inventory.
+    pub fn sync(&self, ui: &mut Ui, world: &World, carry_weight: i32, msgs: &Messages) {
+        let objects = world.objects();
+
+        let items = inventory_list::items(world, self.owner, true);
+        ui.widget_mut::<InventoryList>(self.list).set_items(items);
+
+        for (slot, &h) in &self.slots {
+            let items = objects.equipped(self.owner, slot)
+                .map(|object| vec![inventory_list::Item {
+                    object,
+                    fid: inventory_list::item_fid(world, object),
+                    count: 1,
+                }])
+                .unwrap_or_default();
+            ui.widget_mut::<InventoryList>(h).set_items(items);
+        }
+
+        let weight = objects.inventory_weight(self.owner);
+        let mut info = msgs.get(20).map(|m| m.text.clone()).unwrap_or_default();
+        info.push_str(format!(" {}/{}", weight, carry_weight));
+        self.set_info(ui, &[&info]);
+    }
+
+    pub fn set_info(&self, ui: &mut Ui, lines: &[&bstr]) {
+        let mut info = ui.widget_mut::<MessagePanel>(self.info);
+        info.clear_messages();
+        for line in lines {
+            info.push_message(line);
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use bstring::BString;
+use std::cmp;
+
+use crate::asset::Flag;
+use crate::asset::frame::FrameId;
+use crate::game::object;
+use crate::game::world::World;
+use crate::graphics::{Point, Rect};
+use crate::graphics::color::WHITE;
+use crate::graphics::font::{self, FontKey};
+use crate::graphics::geometry::hex::Direction;
+use crate::graphics::sprite::Sprite;
+use crate::ui::*;
+use crate::ui::command::{UiCommand, UiCommandData};
+
+/// Distance the cursor must travel with mouse button pressed to start dragging.
+const DRAG_THRESHOLD: i32 = 4;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Command {
+    /// Item was clicked.
+    Click {
+        object: object::Handle,
+    },
+    /// Item was dragged and dropped at `pos`.
+    Drop {
+        pos: Point,
+        object: object::Handle,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Item {
+    pub object: object::Handle,
+    pub fid: FrameId,
+    pub count: usize,
+}
+
+/// Returns FID used to display the `object` in inventory.
+pub fn item_fid(world: &World, object: object::Handle) -> FrameId {
+    let obj = world.objects().get(object).borrow();
+    obj.pid.proto_id()
+        .and_then(|pid| world.proto_db().proto(pid).unwrap()
+            .sub.item()
+            .and_then(|i| i.inventory_fid))
+        .unwrap_or(obj.fid)
+}
+
+/// Builds list items from inventory of `owner`. If `skip_equipped` is `true` the items occupying
+/// equipment slots are not included.
+pub fn items(world: &World, owner: object::Handle, skip_equipped: bool) -> Vec<Item> {
+    let objects = world.objects();
+    objects.get(owner).borrow().inventory.items.iter()
+        .filter(|i| !skip_equipped || !objects.get(i.object).borrow().flags
+            .intersects(Flag::Worn | Flag::LeftHand | Flag::RightHand))
+        .map(|i| Item {
+            object: i.object,
+            fid: item_fid(world, i.object),
+            count: i.count,
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Drag {
+    idx: usize,
+    start: Point,
+    active: bool,
+}
+
+/// Vertical list of inventory items. A list with single visible item is used for the equipment
+/// slots.
+// inven_display(), display_inventory()
+pub struct InventoryList {
+    item_height: i32,
+    items: Vec<Item>,
+    scroll_idx: usize,
+    visible_items: usize,
+    drag: Option<Drag>,
+}
+
+impl InventoryList {
+    pub fn new(item_height: i32, visible_items: usize) -> Self {
+        Self {
+            item_height,
+            items: Vec::new(),
+            scroll_idx: 0,
+            visible_items,
+            drag: None,
+        }
+    }
+
+    pub fn items(&self) -> &[Item] {
+        &self.items
+    }
+
+    pub fn set_items(&mut self, items: Vec<Item>) {
+        self.items = items;
+        self.drag = None;
+        self.scroll_idx = cmp::min(self.scroll_idx, self.max_scroll_idx());
+    }
+
+    pub fn can_scroll_up(&self) -> bool {
+        self.scroll_idx > 0
+    }
+
+    pub fn can_scroll_down(&self) -> bool {
+        self.scroll_idx < self.max_scroll_idx()
+    }
+
+    pub fn scroll_up(&mut self) {
+        if self.can_scroll_up() {
+            self.scroll_idx -= 1;
+        }
+    }
+
+    pub fn scroll_down(&mut self) {
+        if self.can_scroll_down() {
+            self.scroll_idx += 1;
+        }
+    }
+
+    fn max_scroll_idx(&self) -> usize {
+        self.items.len().saturating_sub(self.visible_items)
+    }
+
+    fn item_index_at(&self, rect: Rect, pos: Point) -> Option<usize> {
+        if !rect.contains(pos) {
+            return None;
+        }
+        let i = self.scroll_idx + ((pos.y - rect.top) / self.item_height) as usize;
+        if i < self.items.len() {
+            Some(i)
+        } else {
+            None
+        }
+    }
+
+    fn render_item(item: &Item, rect: Rect, ctx: &mut Render) {
+        let frms = ctx.frm_db.get(item.fid).unwrap();
+        let frm = &frms.frame_lists[Direction::NE].frames[0];
+        // FIXME scale down images that don't fit into the rect.
+        let pos = rect.center() - Point::new(frm.width, frm.height) / 2;
+        Sprite {
+            pos,
+            centered: false,
+            fid: item.fid,
+            frame_idx: 0,
+            direction: Direction::NE,
+            light: 0x10000,
+            effect: None,
+        }.render(ctx.canvas, ctx.frm_db);
+
+        if item.count > 1 {
+            let text = BString::from(format!("x{}", item.count));
+            ctx.canvas.draw_text(text.as_ref(), rect.top_left() + Point::new(3, 3),
+                FontKey::antialiased(1), WHITE, &font::DrawOptions::default());
+        }
+    }
+}
+
+impl Widget for InventoryList {
+    fn handle_event(&mut self, mut ctx: HandleEvent) {
+        match ctx.event {
+            Event::MouseDown { pos, button } if button == MouseButton::Left => {
+                if let Some(idx) = self.item_index_at(ctx.base.rect(), pos) {
+                    self.drag = Some(Drag {
+                        idx,
+                        start: pos,
+                        active: false,
+                    });
+                    ctx.capture();
+                }
+            }
+            Event::MouseMove { pos } => {
+                if let Some(drag) = self.drag.as_mut() {
+                    let d = pos - drag.start;
+                    if d.x.abs() >= DRAG_THRESHOLD || d.y.abs() >= DRAG_THRESHOLD {
+                        drag.active = true;
+                    }
+                }
+            }
+            Event::MouseUp { pos, button } if button == MouseButton::Left => {
+                if let Some(drag) = self.drag.take() {
+                    let object = self.items[drag.idx].object;
+                    let data = if drag.active {
+                        Command::Drop { pos, object }
+                    } else {
+                        Command::Click { object }
+                    };
+                    ctx.out.push(UiCommand {
+                        source: ctx.this,
+                        data: UiCommandData::InventoryList(data),
+                    });
+                    ctx.release();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn render(&mut self, mut ctx: Render) {
+        let rect = ctx.base.unwrap().rect();
+        let dragged = self.drag.filter(|d| d.active).map(|d| d.idx);
+        let end = cmp::min(self.scroll_idx + self.visible_items, self.items.len());
+        for (i, item) in self.items[self.scroll_idx..end].iter().enumerate() {
+            let idx = self.scroll_idx + i;
+            if Some(idx) == dragged {
+                continue;
+            }
+            let item_rect = Rect::with_size(rect.left, rect.top + i as i32 * self.item_height,
+                rect.width(), self.item_height);
+            Self::render_item(item, item_rect, &mut ctx);
+        }
+
+        if let Some(idx) = dragged {
+            let size = Point::new(rect.width(), self.item_height);
+            let pos = ctx.cursor_pos - size / 2;
+            let item_rect = Rect::with_size(pos.x, pos.y, size.x, size.y);
+            Self::render_item(&self.items[idx], item_rect, &mut ctx);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use enum_map::{enum_map, Enum, EnumMap};
+
+use crate::asset::frame::FrameId;
+use crate::game::object;
+use crate::game::world::World;
+use crate::graphics::{Point, Rect};
+use crate::graphics::sprite::Sprite;
+use crate::ui::*;
+use crate::ui::button::Button;
+use crate::ui::command::UiCommandData;
+
+use super::inventory_list::{self, InventoryList};
+
+#[derive(Clone, Copy, Debug, Enum, Eq, PartialEq)]
+pub enum Side {
+    Looter,
+    Target,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Command {
+    ScrollUp(Side),
+    ScrollDown(Side),
+    TakeAll,
+    Done,
+}
+
+/// Loot window showing inventories of the looter and the looted object side by side.
+// loot_container(), setup_inventory(INVENTORY_WINDOW_TYPE_LOOT)
+pub struct LootWindow {
+    window: Handle,
+    lists: EnumMap<Side, Handle>,
+    objects: EnumMap<Side, object::Handle>,
+}
+
+impl LootWindow {
+    pub fn show(ui: &mut Ui, looter: object::Handle, target: object::Handle) -> Self {
+        let window = ui.new_window(Rect::with_size(80, 0, 537, 376),
+            Some(Sprite::new(FrameId::LOOT)));
+
+        let mut list = |x| ui.new_widget(window, Rect::with_size(x, 37, 64, 48 * 6), None, None,
+            InventoryList::new(48, 6));
+        let lists = enum_map! {
+            Side::Looter => list(176),
+            Side::Target => list(297),
+        };
+
+        for &(side, x) in &[(Side::Looter, 109), (Side::Target, 379)] {
+            ui.new_widget(window, Rect::with_size(x, 56, 22, 23), None, None,
+                Button::new(FrameId::INVUPOUT, FrameId::INVUPIN,
+                    Some(UiCommandData::Loot(Command::ScrollUp(side)))));
+            ui.new_widget(window, Rect::with_size(x, 82, 22, 23), None, None,
+                Button::new(FrameId::INVDNOUT, FrameId::INVDNIN,
+                    Some(UiCommandData::Loot(Command::ScrollDown(side)))));
+        }
+        ui.new_widget(window, Rect::with_size(432, 204, 39, 41), None, None,
+            Button::new(FrameId::INVMAUP, FrameId::INVMADN,
+                Some(UiCommandData::Loot(Command::TakeAll))));
+        ui.new_widget(window, Rect::with_size(476, 331, 15, 16), None, None,
+            Button::new(FrameId::MENUUP, FrameId::MENUDOWN,
+                Some(UiCommandData::Loot(Command::Done))));
+
+        Self {
+            window,
+            lists,
+            objects: enum_map! {
+                Side::Looter => looter,
+                Side::Target => target,
+            },
+        }
+    }
+
+    pub fn hide(self, ui: &mut Ui) {
+        ui.remove(self.window);
+    }
+
+    pub fn window(&self) -> Handle {
+        self.window
+    }
+
+    pub fn object(&self, side: Side) -> object::Handle {
+        self.objects[side]
+    }
+
+    /// Returns side of the list `widget`.
+    pub fn side_of(&self, widget: Handle) -> Option<Side> {
+        self.lists.iter()
+            .find(|(_, &h)| h == widget)
+            .map(|(side, _)| side)
+    }
+
+    pub fn side_at(&self, ui: &Ui, pos: Point) -> Option<Side> {
+        self.lists.iter()
+            .find(|(_, &h)| ui.widget_base(h).borrow().rect().contains(pos))
+            .map(|(side, _)| side)
+    }
+
+    pub fn scroll(&self, ui: &mut Ui, side: Side, up: bool) {
+        let mut list = ui.widget_mut::<InventoryList>(self.lists[side]);
+        if up {
+            list.scroll_up();
+        } else {
+            list.scroll_down();
+        }
+    }
+
+    pub fn sync(&self, ui: &mut Ui, world: &World) {
+        for (side, &list) in &self.lists {
+            let items = inventory_list::items(world, self.objects[side], false);
+            ui.widget_mut::<InventoryList>(list).set_items(items);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use bstring::{bstr, BString};
+
+use crate::asset::Perk;
+use crate::asset::frame::FrameId;
+use crate::graphics::Rect;
+use crate::graphics::color::{GREEN, WHITE};
+use crate::graphics::font::FontKey;
+use crate::graphics::sprite::Sprite;
+use crate::ui::*;
+use crate::ui::button::Button;
+use crate::ui::command::UiCommandData;
+use crate::ui::message_panel::{MessagePanel, MouseControl};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Command {
+    ScrollUp,
+    ScrollDown,
+    Done,
+    Cancel,
+}
+
+const PAGE_LEN: usize = 11;
+
+/// Window for picking a perk the dude has earned on level up. Clicking a perk in the list
+/// selects it and shows its description, the Done button confirms the choice.
+// perk_dialog()
+pub struct PerkSelectWindow {
+    window: Handle,
+    list: Handle,
+    description: Handle,
+    perks: Vec<Perk>,
+    names: Vec<BString>,
+    scroll_pos: usize,
+    selected: Option<Perk>,
+}
+
+impl PerkSelectWindow {
+    /// `perks` are the eligible perks along with their names.
+    pub fn show(ui: &mut Ui, perks: &[(Perk, &bstr)]) -> Self {
+        let window = ui.new_window(Rect::with_size(33, 91, 573, 230),
+            Some(Sprite::new(FrameId::PERKWIN)));
+
+        let title = ui.new_widget(window, Rect::with_size(48, 14, 200, 20), None, None,
+            MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(3), GREEN));
+        // TODO take the text from editor.msg
+        ui.widget_mut::<MessagePanel>(title).push_message("Select a Perk");
+
+        let mut list = MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(1), GREEN);
+        list.set_mouse_control(MouseControl::Pick);
+        list.set_highlight_color(WHITE);
+        let list = ui.new_widget(window, Rect::with_size(45, 43, 190, 130), None, None, list);
+
+        let description = ui.new_widget(window, Rect::with_size(280, 43, 270, 140), None, None,
+            MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(1), GREEN));
+
+        ui.new_widget(window, Rect::with_size(242, 43, 11, 21), None, None,
+            Button::new(FrameId::UPARWOFF, FrameId::UPARWON,
+                Some(UiCommandData::PerkSelect(Command::ScrollUp))));
+        ui.new_widget(window, Rect::with_size(242, 152, 11, 21), None, None,
+            Button::new(FrameId::DNARWOFF, FrameId::DNARWON,
+                Some(UiCommandData::PerkSelect(Command::ScrollDown))));
+
+        ui.new_widget(window, Rect::with_size(48, 186, 15, 16), None, None,
+            Button::new(FrameId::LILREDUP, FrameId::LILREDDN,
+                Some(UiCommandData::PerkSelect(Command::Done))));
+        let done = ui.new_widget(window, Rect::with_size(69, 185, 70, 20), None, None,
+            MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(3), GREEN));
+        // TODO take the text from editor.msg
+        ui.widget_mut::<MessagePanel>(done).push_message("Done");
+
+        ui.new_widget(window, Rect::with_size(153, 186, 15, 16), None, None,
+            Button::new(FrameId::LILREDUP, FrameId::LILREDDN,
+                Some(UiCommandData::PerkSelect(Command::Cancel))));
+        let cancel = ui.new_widget(window, Rect::with_size(174, 185, 80, 20), None, None,
+            MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(3), GREEN));
+        // TODO take the text from editor.msg
+        ui.widget_mut::<MessagePanel>(cancel).push_message("Cancel");
+
+        let r = Self {
+            window,
+            list,
+            description,
+            perks: perks.iter().map(|&(perk, _)| perk).collect(),
+            names: perks.iter().map(|&(_, name)| name.to_owned()).collect(),
+            scroll_pos: 0,
+            selected: None,
+        };
+        r.sync_list(ui);
+        r
+    }
+
+    pub fn hide(self, ui: &mut Ui) {
+        ui.remove(self.window);
+    }
+
+    /// Whether the `widget` is the perk list of this window.
+    pub fn is_list(&self, widget: Handle) -> bool {
+        widget == self.list
+    }
+
+    /// Perk at the `index` of the visible part of the list.
+    pub fn perk_at(&self, index: u32) -> Perk {
+        self.perks[self.scroll_pos + index as usize]
+    }
+
+    pub fn selected(&self) -> Option<Perk> {
+        self.selected
+    }
+
+    pub fn select(&mut self, ui: &mut Ui, perk: Perk, name: &bstr, description: &bstr) {
+        self.selected = Some(perk);
+        let mut panel = ui.widget_mut::<MessagePanel>(self.description);
+        panel.clear_messages();
+        panel.push_message(name);
+        panel.push_message("");
+        panel.push_message(description);
+    }
+
+    pub fn scroll(&mut self, ui: &mut Ui, up: bool) {
+        let max = self.perks.len().saturating_sub(PAGE_LEN);
+        self.scroll_pos = if up {
+            self.scroll_pos.saturating_sub(1)
+        } else {
+            (self.scroll_pos + 1).min(max)
+        };
+        self.sync_list(ui);
+    }
+
+    fn sync_list(&self, ui: &mut Ui) {
+        let mut list = ui.widget_mut::<MessagePanel>(self.list);
+        list.clear_messages();
+        for name in self.names.iter().skip(self.scroll_pos).take(PAGE_LEN) {
+            list.push_message(name);
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use bstring::{bstr, BString};
+
+use crate::asset::frame::FrameId;
+use crate::asset::message::Messages;
+use crate::game::save::{self, Header, SLOT_COUNT, THUMBNAIL_HEIGHT, THUMBNAIL_WIDTH};
+use crate::graphics::Rect;
+use crate::graphics::color::{GREEN, WHITE};
+use crate::graphics::font::FontKey;
+use crate::graphics::render::TextureHandle;
+use crate::graphics::sprite::Sprite;
+use crate::ui::*;
+use crate::ui::button::Button;
+use crate::ui::command::UiCommandData;
+use crate::ui::message_panel::{MessagePanel, MouseControl};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mode {
+    Save,
+    Load,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Command {
+    Done,
+    Cancel,
+}
+
+/// Window listing the save slots. Clicking a slot selects it and shows its thumbnail, date and
+/// map name, the Done button saves to or loads from the selected slot. When saving the
+/// description of the selected slot is edited. The quicksave and autosave slots are marked as such
+/// and can only be loaded from.
+// LoadGame(), SaveGame()
+pub struct SaveLoadWindow {
+    window: Handle,
+    mode: Mode,
+    list: Handle,
+    thumbnail: Handle,
+    info: Handle,
+    slots: Vec<Option<Header>>,
+    selected: u32,
+    /// Description of the save to make in the selected slot.
+    description: BString,
+    /// Description of the save to make in an empty slot.
+    default_description: BString,
+    empty_text: BString,
+}
+
+impl SaveLoadWindow {
+    /// `slots` are the headers of the saves in each slot. `msgs` is `lsgame.msg`.
+    pub fn show(ui: &mut Ui, mode: Mode, slots: Vec<Option<Header>>,
+        default_description: BString, msgs: &Messages) -> Self
+    {
+        assert_eq!(slots.len(), SLOT_COUNT as usize);
+
+        let msg = |id| msgs.get(id).map(|m| m.text.clone()).unwrap_or_default();
+
+        let window = ui.new_window(Rect::with_size(0, 0, 640, 480),
+            Some(Sprite::new(FrameId::LSGAME)));
+
+        let title = ui.new_widget(window, Rect::with_size(48, 27, 250, 20), None, None,
+            MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(3), GREEN));
+        ui.widget_mut::<MessagePanel>(title).push_message(msg(match mode {
+            Mode::Save => 102,
+            Mode::Load => 100,
+        }));
+
+        let mut list = MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(1), GREEN);
+        list.set_mouse_control(MouseControl::Pick);
+        list.set_highlight_color(WHITE);
+        list.set_message_spacing(4);
+        let list = ui.new_widget(window, Rect::with_size(55, 87, 230, 300), None, None, list);
+
+        let thumbnail = ui.new_widget(window,
+            Rect::with_size(366, 58, THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT), None, None,
+            Thumbnail::new());
+
+        let info = ui.new_widget(window, Rect::with_size(396, 254, 180, 80), None, None,
+            MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(1), GREEN));
+
+        ui.new_widget(window, Rect::with_size(391, 349, 15, 16), None, None,
+            Button::new(FrameId::LILREDUP, FrameId::LILREDDN,
+                Some(UiCommandData::SaveLoad(Command::Done))));
+        let done = ui.new_widget(window, Rect::with_size(412, 348, 70, 20), None, None,
+            MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(3), GREEN));
+        ui.widget_mut::<MessagePanel>(done).push_message(msg(104));
+
+        ui.new_widget(window, Rect::with_size(495, 349, 15, 16), None, None,
+            Button::new(FrameId::LILREDUP, FrameId::LILREDDN,
+                Some(UiCommandData::SaveLoad(Command::Cancel))));
+        let cancel = ui.new_widget(window, Rect::with_size(516, 348, 80, 20), None, None,
+            MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(3), GREEN));
+        ui.widget_mut::<MessagePanel>(cancel).push_message(msg(105));
+
+        let mut r = Self {
+            window,
+            mode,
+            list,
+            thumbnail,
+            info,
+            slots,
+            selected: 0,
+            description: BString::new(),
+            default_description,
+            empty_text: msg(111),
+        };
+        let first = match mode {
+            Mode::Save => (0..SLOT_COUNT).find(|&slot| !save::is_reserved_slot(slot)).unwrap(),
+            Mode::Load => 0,
+        };
+        r.select(ui, first);
+        r
+    }
+
+    pub fn hide(self, ui: &mut Ui) {
+        ui.remove(self.window);
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Whether the `widget` is the slot list of this window.
+    pub fn is_list(&self, widget: Handle) -> bool {
+        widget == self.list
+    }
+
+    pub fn selected(&self) -> u32 {
+        self.selected
+    }
+
+    /// Whether the description of the selected slot is being edited, i.e. the window is saving
+    /// and the slot isn't reserved.
+    pub fn is_editing(&self) -> bool {
+        self.mode == Mode::Save && !save::is_reserved_slot(self.selected)
+    }
+
+    /// Header of the save in the selected slot or `None` if the slot is empty.
+    pub fn selected_header(&self) -> Option<&Header> {
+        self.slots[self.selected as usize].as_ref()
+    }
+
+    pub fn select(&mut self, ui: &mut Ui, slot: u32) {
+        assert!(slot < SLOT_COUNT);
+        self.selected = slot;
+        self.description = self.selected_header()
+            .map(|h| h.description.clone())
+            .unwrap_or_else(|| self.default_description.clone());
+        self.sync(ui);
+    }
+
+    pub fn description(&self) -> &bstr {
+        &self.description
+    }
+
+    pub fn set_description(&mut self, ui: &mut Ui, description: BString) {
+        self.description = description;
+        self.sync(ui);
+    }
+
+    fn sync(&self, ui: &mut Ui) {
+        {
+            let mut list = ui.widget_mut::<MessagePanel>(self.list);
+            list.clear_messages();
+            for (i, header) in self.slots.iter().enumerate() {
+                let selected = i == self.selected as usize;
+                let slot = i as u32;
+                let name = if slot == save::QUICKSAVE_SLOT {
+                    "QUICKSAVE".into()
+                } else if save::AUTOSAVE_SLOTS.contains(&slot) {
+                    format!("AUTOSAVE {}", slot - save::AUTOSAVE_SLOTS.start + 1)
+                } else {
+                    format!("SLOT {:02}", i + 1)
+                };
+                let mut s = BString::from(format!("{} {}: ",
+                    if selected { ">" } else { " " }, name));
+                if selected && self.is_editing() {
+                    s.push_str(&self.description);
+                    s.push(b'_');
+                } else if let Some(header) = header {
+                    s.push_str(&header.description);
+                } else {
+                    s.push_str(&self.empty_text);
+                }
+                list.push_message(s);
+            }
+        }
+
+        let header = self.selected_header();
+        ui.widget_mut::<Thumbnail>(self.thumbnail)
+            .set_pixels(header.map(|h| h.thumbnail.clone()));
+
+        let mut info = ui.widget_mut::<MessagePanel>(self.info);
+        info.clear_messages();
+        if let Some(header) = header {
+            let (year, month, day, hour, minute) = save::saved_at_date(header.saved_at);
+            let t = header.game_time;
+            info.push_message(&header.dude_name);
+            info.push_message(format!("{:04}-{:02}-{:02} {:02}:{:02}",
+                year, month, day, hour, minute));
+            info.push_message(format!("Game date: {:04}-{:02}-{:02}", t.year(), t.month(),
+                t.day()));
+            info.push_message(header.map_name.to_uppercase());
+        }
+    }
+}
+
+/// Image of `THUMBNAIL_WIDTH` x `THUMBNAIL_HEIGHT` palette color indices.
+struct Thumbnail {
+    pixels: Option<Box<[u8]>>,
+    texture: Option<TextureHandle>,
+}
+
+impl Thumbnail {
+    fn new() -> Self {
+        Self {
+            pixels: None,
+            texture: None,
+        }
+    }
+
+    fn set_pixels(&mut self, pixels: Option<Box<[u8]>>) {
+        self.pixels = pixels;
+        self.texture = None;
+    }
+}
+
+impl Widget for Thumbnail {
+    fn handle_event(&mut self, _ctx: HandleEvent) {}
+
+    fn render(&mut self, ctx: Render) {
+        if let Some(pixels) = &self.pixels {
+            let texture = self.texture.get_or_insert_with(||
+                ctx.frm_db.texture_factory().new_texture(THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT,
+                    pixels.clone()));
+            ctx.canvas.draw(texture, ctx.base.unwrap().rect().top_left(), 0x10000);
+        }
+    }
+}
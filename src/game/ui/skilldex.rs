@@ -0,0 +1,93 @@
+use bstring::{bstr, BString};
+
+use crate::asset::Skill;
+use crate::asset::frame::FrameId;
+use crate::game::object;
+use crate::graphics::Rect;
+use crate::graphics::color::{GREEN, WHITE};
+use crate::graphics::font::FontKey;
+use crate::graphics::sprite::Sprite;
+use crate::ui::*;
+use crate::ui::button::{self, Button};
+use crate::ui::command::UiCommandData;
+use crate::ui::message_panel::MessagePanel;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Command {
+    Pick(Skill),
+    Cancel,
+}
+
+/// Skills listed in the skilldex, top to bottom.
+pub const SKILLS: [Skill; 8] = [
+    Skill::Sneak,
+    Skill::Lockpick,
+    Skill::Steal,
+    Skill::Traps,
+    Skill::FirstAid,
+    Skill::Doctor,
+    Skill::Science,
+    Skill::Repair,
+];
+
+/// Window for choosing the skill to use. When opened from the action menu the skill is used on
+/// the `target`, otherwise the target is picked in the world afterwards.
+// skilldex_select()
+pub struct SkilldexWindow {
+    window: Handle,
+    target: Option<object::Handle>,
+}
+
+impl SkilldexWindow {
+    /// `skills` are names and levels of the `SKILLS`.
+    pub fn show(ui: &mut Ui, target: Option<object::Handle>, skills: &[(&bstr, i32)]) -> Self {
+        assert_eq!(skills.len(), SKILLS.len());
+
+        let window = ui.new_window(Rect::with_size(450, 6, 185, 368),
+            Some(Sprite::new(FrameId::SKLDXBOX)));
+
+        let title = ui.new_widget(window, Rect::with_size(55, 14, 100, 20), None, None,
+            MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(3), GREEN));
+        // TODO take the text from skilldex.msg
+        ui.widget_mut::<MessagePanel>(title).push_message("Skilldex");
+
+        for (i, (&skill, &(name, level))) in SKILLS.iter().zip(skills).enumerate() {
+            let y = 45 + i as i32 * 36;
+
+            let mut button = Button::new(FrameId::SKLDXOFF, FrameId::SKLDXON,
+                Some(UiCommandData::Skilldex(Command::Pick(skill))));
+            button.set_text(Some(button::Text {
+                text: name.as_bytes().to_ascii_uppercase().into(),
+                font: FontKey::antialiased(3),
+                color: WHITE,
+            }));
+            ui.new_widget(window, Rect::with_size(15, y, 86, 34), None, None, button);
+
+            let level_label = ui.new_widget(window, Rect::with_size(111, y + 7, 50, 20), None,
+                None, MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(3), GREEN));
+            ui.widget_mut::<MessagePanel>(level_label)
+                .push_message(BString::from(format!("{:03}", level.max(0))));
+        }
+
+        ui.new_widget(window, Rect::with_size(48, 338, 15, 16), None, None,
+            Button::new(FrameId::LILREDUP, FrameId::LILREDDN,
+                Some(UiCommandData::Skilldex(Command::Cancel))));
+        let cancel = ui.new_widget(window, Rect::with_size(72, 337, 80, 20), None, None,
+            MessagePanel::new(ui.fonts().clone(), FontKey::antialiased(3), GREEN));
+        // TODO take the text from skilldex.msg
+        ui.widget_mut::<MessagePanel>(cancel).push_message("Cancel");
+
+        Self {
+            window,
+            target,
+        }
+    }
+
+    pub fn hide(self, ui: &mut Ui) {
+        ui.remove(self.window);
+    }
+
+    pub fn target(&self) -> Option<object::Handle> {
+        self.target
+    }
+}
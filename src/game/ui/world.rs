@@ -6,6 +6,7 @@ use crate::asset::Flag;
 use crate::asset::frame::FrameId;
 use crate::game::world::World;
 use crate::game::object::{self, Object, ObjectProtoId};
+use crate::game::save;
 use crate::graphics::{EPoint, Point};
 use crate::graphics::color;
 use crate::graphics::font::*;
@@ -22,6 +23,8 @@ use super::action_menu::{Action, Placement};
 enum PickMode {
     Hex,
     Object,
+    /// Picking target for the skill chosen in the skilldex.
+    Skill,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -50,6 +53,11 @@ pub struct WorldView {
 
     /// Icon displayed near the cursor in object pick mode.
     pub default_action_icon: Option<Action>,
+
+    /// Downscaled screenshot of the world taken on the first render after
+    /// `request_thumbnail()`. Used as the savegame thumbnail.
+    thumbnail: Option<Box<[u8]>>,
+    thumbnail_requested: bool,
 }
 
 impl WorldView {
@@ -65,9 +73,24 @@ impl WorldView {
             pick_state: PickState::Idle,
             action_menu_state: None,
             default_action_icon: None,
+            thumbnail: None,
+            thumbnail_requested: false,
         }
     }
 
+    /// Requests the screenshot of the world as described in `save::Header` to be taken on the
+    /// next render.
+    pub fn request_thumbnail(&mut self) {
+        self.thumbnail = None;
+        self.thumbnail_requested = true;
+    }
+
+    /// Takes the screenshot requested with `request_thumbnail()`. `None` if the world hasn't been
+    /// rendered since the request.
+    pub fn take_thumbnail(&mut self) -> Option<Box<[u8]>> {
+        self.thumbnail.take()
+    }
+
     pub fn hex_cursor_pos(&self) -> Option<EPoint> {
         if self.pick_mode == PickMode::Hex {
             let world = self.world.borrow();
@@ -85,6 +108,15 @@ impl WorldView {
         }
     }
 
+    /// Switches to picking of the skill target. The mode ends when the target is picked or on
+    /// right click.
+    pub fn enter_skill_pick_mode(&mut self) {
+        self.pick_mode = PickMode::Skill;
+        self.default_action_icon = None;
+        self.pick_state = PickState::Idle;
+        self.update_hex_cursor_visibility(None);
+    }
+
     fn insert_hex_cursor(world: &mut World) -> object::Handle {
         let mut hex_cursor = Object::new(FrameId::MOUSE_HEX_OUTLINE, ObjectProtoId::None,
             Some(Default::default()));
@@ -146,6 +178,7 @@ impl Widget for WorldView {
                         self.pick_state = PickState::Pending { start: ctx.now, pos };
                         self.default_action_icon = None;
                     }
+                    PickMode::Skill => {}
                 }
                 self.update_hex_cursor_visibility(None);
             }
@@ -181,6 +214,20 @@ impl Widget for WorldView {
                                     });
                                 }
                             }
+                            PickMode::Skill => {
+                                if let Some(obj) = self.world.borrow().pick_object(pos, true) {
+                                    ctx.out.push(UiCommand {
+                                        source: ctx.this,
+                                        data: UiCommandData::ObjectPick {
+                                            kind: ObjectPickKind::Skill,
+                                            obj,
+                                        },
+                                    });
+                                }
+                                ctx.base.set_cursor(Some(Cursor::Hidden));
+                                self.pick_mode = PickMode::Hex;
+                                self.update_hex_cursor_visibility(None);
+                            }
                         }
                     }
                     MouseButton::Right => {
@@ -189,7 +236,7 @@ impl Widget for WorldView {
                                 ctx.base.set_cursor(Some(Cursor::ActionArrow));
                                 PickMode::Object
                             }
-                            PickMode::Object => {
+                            PickMode::Object | PickMode::Skill => {
                                 ctx.base.set_cursor(Some(Cursor::Hidden));
                                 let (pos, changed) = self.update_hex_cursor_pos(pos);
                                 if changed {
@@ -251,7 +298,9 @@ impl Widget for WorldView {
     }
 
     fn sync(&mut self, ctx: Sync) {
-        if ctx.base.cursor() != Some(Cursor::Hidden) {
+        if self.pick_mode == PickMode::Skill {
+            ctx.base.set_cursor(Some(Cursor::UseCrosshair));
+        } else if ctx.base.cursor() != Some(Cursor::Hidden) {
             ctx.base.set_cursor(Some(
                 if self.default_action_icon.is_some() {
                     Placement::new(1, ctx.cursor_pos, ctx.base.rect()).cursor
@@ -265,6 +314,11 @@ impl Widget for WorldView {
         let world = self.world.borrow();
 
         world.render(ctx.canvas, self.roof_visible);
+        if self.thumbnail_requested {
+            self.thumbnail = Some(ctx.canvas.capture(ctx.base.unwrap().rect(),
+                save::THUMBNAIL_WIDTH, save::THUMBNAIL_HEIGHT));
+            self.thumbnail_requested = false;
+        }
 
         match self.pick_mode {
             PickMode::Hex => if self.hex_cursor_style == HexCursorStyle::Blocked {
@@ -298,6 +352,7 @@ impl Widget for WorldView {
                     effect: None,
                 }.render(ctx.canvas, ctx.frm_db);
             }
+            PickMode::Skill => {}
         }
     }
 }
\ No newline at end of file
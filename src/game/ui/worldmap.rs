@@ -0,0 +1,235 @@
+use std::cell::RefCell;
+use std::cmp;
+use std::io;
+use std::rc::Rc;
+
+use crate::asset::frame::{FrameDb, FrameId};
+use crate::asset::worldmap::{AreaDef, SUBTILE_SIZE, TILE_HEIGHT, TILE_WIDTH};
+use crate::game::world::World;
+use crate::game::worldmap::VisitState;
+use crate::graphics::{Point, Rect};
+use crate::graphics::color::{BLACK, GREEN};
+use crate::graphics::font::{DrawOptions, FontKey, HorzAlign};
+use crate::graphics::render::TextureHandle;
+use crate::graphics::sprite::Sprite;
+use crate::ui::*;
+use crate::ui::button::Button;
+use crate::ui::command::{UiCommand, UiCommandData};
+use crate::ui::panel::Panel;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Command {
+    /// The world map was clicked at the position given in world map pixels.
+    Pick(Point),
+    /// Entrance of the area was picked on the town map.
+    Enter {
+        area: usize,
+        entrance: usize,
+    },
+}
+
+/// Part of the screen where the world map or the town map is shown.
+const VIEW_RECT: Rect = Rect {
+    left: 22,
+    top: 21,
+    right: 22 + 450,
+    bottom: 21 + 443,
+};
+
+/// Fog mask values for the subtile states as used by `Canvas::draw_masked_color()`.
+const FOG_UNKNOWN: u8 = 7;
+const FOG_KNOWN: u8 = 4;
+
+/// Full screen world map with the party marker, town areas and the fog of war. Town maps with
+/// the area entrances are shown over the world map.
+// wmWorldMapFunc(), wmTownMapFunc()
+pub struct WorldMapWindow {
+    window: Handle,
+    view: Handle,
+    town: Option<(Handle, usize)>,
+}
+
+impl WorldMapWindow {
+    pub fn show(ui: &mut Ui, world: Rc<RefCell<World>>) -> Self {
+        let window = ui.new_window(Rect::with_size(0, 0, 640, 480), None);
+
+        let center = world.borrow().world_map().pos();
+        let mut view = View {
+            world,
+            origin: Point::new(0, 0),
+            fog: None,
+        };
+        view.look_at(center);
+        let view = ui.new_widget(window, VIEW_RECT, None, None, view);
+
+        // The frame is drawn over the view to clip the parts of the map outside of it.
+        ui.new_widget(window, Rect::with_size(0, 0, 640, 480), None,
+            Some(Sprite::new(FrameId::WMAPBOX)), Panel::new());
+
+        Self {
+            window,
+            view,
+            town: None,
+        }
+    }
+
+    pub fn hide(mut self, ui: &mut Ui) {
+        self.hide_town(ui);
+        ui.remove(self.window);
+    }
+
+    /// Scrolls the world map by `d` pixels.
+    pub fn scroll(&self, ui: &mut Ui, d: Point) {
+        let mut view = ui.widget_mut::<View>(self.view);
+        let origin = view.origin + d;
+        view.set_origin(origin);
+    }
+
+    /// Scrolls the world map if needed so the party is visible.
+    pub fn follow_party(&self, ui: &mut Ui) {
+        let mut view = ui.widget_mut::<View>(self.view);
+        let pos = view.world.borrow().world_map().pos() - view.origin;
+        if !Rect::with_size(0, 0, VIEW_RECT.width(), VIEW_RECT.height()).contains(pos) {
+            let pos = pos + view.origin;
+            view.look_at(pos);
+        }
+    }
+
+    /// The area whose town map is shown.
+    pub fn town(&self) -> Option<usize> {
+        self.town.map(|(_, area)| area)
+    }
+
+    /// Shows the town map of the `area` with hotspots for the `entrances` given as indices into
+    /// `AreaDef::entrances`.
+    pub fn show_town(&mut self, ui: &mut Ui, frm_db: &FrameDb, area: usize, def: &AreaDef,
+        entrances: &[usize]) -> io::Result<()>
+    {
+        let fid = def.townmap_fid.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+            format!("area {} has no town map", area)))?;
+        frm_db.get(fid)?;
+        let hotspot_size = frm_db.get(FrameId::HOTSPOT1)?.first().size();
+
+        self.hide_town(ui);
+
+        let window = ui.new_window(VIEW_RECT, Some(Sprite::new(fid)));
+        for &i in entrances {
+            let pos = def.entrances[i].pos;
+            ui.new_widget(window,
+                Rect::with_size(pos.x, pos.y, hotspot_size.x, hotspot_size.y), None, None,
+                Button::new(FrameId::HOTSPOT1, FrameId::HOTSPOT2,
+                    Some(UiCommandData::WorldMap(Command::Enter { area, entrance: i }))));
+        }
+        self.town = Some((window, area));
+
+        Ok(())
+    }
+
+    pub fn hide_town(&mut self, ui: &mut Ui) {
+        if let Some((window, _)) = self.town.take() {
+            ui.remove(window);
+        }
+    }
+}
+
+struct View {
+    world: Rc<RefCell<World>>,
+    /// World map pixel shown at the top left corner of the view.
+    origin: Point,
+    /// Masks for the unknown and known subtiles.
+    fog: Option<(TextureHandle, TextureHandle)>,
+}
+
+impl View {
+    fn look_at(&mut self, pos: Point) {
+        self.set_origin(pos - Point::new(VIEW_RECT.width() / 2, VIEW_RECT.height() / 2));
+    }
+
+    fn set_origin(&mut self, origin: Point) {
+        let size = self.world.borrow().world_map().def().size();
+        let max = Point::new(
+            cmp::max(size.x - VIEW_RECT.width(), 0),
+            cmp::max(size.y - VIEW_RECT.height(), 0));
+        self.origin = Point::new(
+            cmp::min(cmp::max(origin.x, 0), max.x),
+            cmp::min(cmp::max(origin.y, 0), max.y));
+    }
+}
+
+impl Widget for View {
+    fn handle_event(&mut self, ctx: HandleEvent) {
+        if let Event::MouseUp { pos, button: MouseButton::Left } = ctx.event {
+            let rect = ctx.base.rect();
+            if rect.contains(pos) {
+                ctx.out.push(UiCommand {
+                    source: ctx.this,
+                    data: UiCommandData::WorldMap(Command::Pick(
+                        pos - rect.top_left() + self.origin)),
+                });
+            }
+        }
+    }
+
+    fn render(&mut self, ctx: Render) {
+        let top_left = ctx.base.unwrap().rect().top_left() - self.origin;
+        let world = self.world.borrow();
+        let wm = world.world_map();
+        let def = wm.def();
+
+        for (i, tile) in def.tiles.iter().enumerate() {
+            let i = i as i32;
+            let pos = top_left + Point::new(i % def.tile_cols * TILE_WIDTH,
+                i / def.tile_cols * TILE_HEIGHT);
+            let mut sprite = Sprite::new(tile.fid);
+            sprite.pos = pos;
+            sprite.render(ctx.canvas, ctx.frm_db);
+        }
+
+        let (unknown, known) = self.fog.get_or_insert_with(|| {
+            let fog = |v| ctx.frm_db.texture_factory().new_texture(SUBTILE_SIZE, SUBTILE_SIZE,
+                vec![v; (SUBTILE_SIZE * SUBTILE_SIZE) as usize].into());
+            (fog(FOG_UNKNOWN), fog(FOG_KNOWN))
+        });
+        let size = def.size();
+        for y in (0..size.y).step_by(SUBTILE_SIZE as usize) {
+            for x in (0..size.x).step_by(SUBTILE_SIZE as usize) {
+                let mask = match wm.subtile_state(Point::new(x, y)) {
+                    Some(VisitState::Unknown) | None => &*unknown,
+                    Some(VisitState::Known) => &*known,
+                    Some(VisitState::Visited) => continue,
+                };
+                ctx.canvas.draw_masked_color(BLACK, None, top_left + Point::new(x, y), mask);
+            }
+        }
+
+        for (i, area) in wm.areas().iter().enumerate() {
+            if wm.area_state(i) == Some(VisitState::Unknown) {
+                continue;
+            }
+            let pos = top_left + wm.area_pos(i).unwrap();
+            let fid = area.size.fid();
+            let circle_size = ctx.frm_db.get(fid).unwrap().first().size();
+            let mut sprite = Sprite::new(fid);
+            sprite.pos = pos - Point::new(circle_size.x / 2, circle_size.y / 2);
+            sprite.render(ctx.canvas, ctx.frm_db);
+
+            ctx.canvas.draw_text(area.name.as_bytes().into(),
+                pos + Point::new(0, area.size.radius() + 2), FontKey::antialiased(1), GREEN,
+                &DrawOptions {
+                    horz_align: HorzAlign::Center,
+                    ..Default::default()
+                });
+        }
+
+        let mut marker = |fid, pos| {
+            let size = ctx.frm_db.get(fid).unwrap().first().size();
+            let mut sprite = Sprite::new(fid);
+            sprite.pos = top_left + pos - Point::new(size.x / 2, size.y / 2);
+            sprite.render(ctx.canvas, ctx.frm_db);
+        };
+        if let Some(destination) = wm.destination() {
+            marker(FrameId::WMAPTARG, destination);
+        }
+        marker(FrameId::WMAPLOC, wm.pos());
+    }
+}
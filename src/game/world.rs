@@ -9,13 +9,18 @@ use std::cmp;
 use std::rc::Rc;
 use std::time::{Duration, Instant};
 
-use crate::asset::EntityKind;
+use crate::asset::{EntityKind, Flag};
 use crate::asset::frame::{FrameId, FrameDb};
+use crate::asset::ai::{AiPacket, AiPackets};
 use crate::asset::map::ELEVATION_COUNT;
 use crate::asset::message::Messages;
 use crate::asset::proto::{ProtoDb, ProtoId};
 use crate::game::GameTime;
+use crate::game::combat::{self, Combat};
 use crate::game::object::{self, DamageFlag, Egg, Object, Objects, SubObject};
+use crate::game::party::Party;
+use crate::game::timer::Timer;
+use crate::game::worldmap::WorldMap;
 use crate::graphics::{EPoint, Point, Rect};
 use crate::graphics::font::Fonts;
 use crate::graphics::geometry::TileGridView;
@@ -24,7 +29,7 @@ use crate::graphics::geometry::hex::{self, Direction};
 use crate::graphics::lighting::light_grid::LightGrid;
 use crate::graphics::map::*;
 use crate::graphics::render::Canvas;
-use crate::util::VecExt;
+use crate::util::{EnumExt, VecExt};
 use crate::util::array2d::Array2d;
 
 use floating_text::FloatingText;
@@ -46,6 +51,27 @@ pub enum ScrollDirection {
     NW,
 }
 
+/// Destination map of `MapTransition`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MapRef {
+    Id(i32),
+    /// Map file name with optional `.map` extension.
+    Name(String),
+}
+
+/// Request to move the dude to another map or elevation.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MapTransition {
+    Map {
+        /// Destination map or `None` for the current map.
+        map: Option<MapRef>,
+        /// Destination position or `None` for the map entrance.
+        pos: Option<EPoint>,
+        direction: Option<Direction>,
+    },
+    WorldMap,
+}
+
 impl ScrollDirection {
     fn go(self, p: Point) -> Point {
         use ScrollDirection::*;
@@ -67,6 +93,7 @@ pub struct World {
     proto_db: Rc<ProtoDb>,
     frm_db: Rc<FrameDb>,
     critter_names: Messages,
+    ai_packets: AiPackets,
     hex_grid: hex::TileGrid,
     camera: Camera,
     sqr_tiles: Vec<Option<Array2d<(u16, u16)>>>,
@@ -76,10 +103,24 @@ pub struct World {
     dude_obj: Option<object::Handle>,
     update_time: Instant,
     fonts: Rc<Fonts>,
+    world_map: WorldMap,
+    party: Party,
 
     pub dude_name: BString,
     pub game_time: GameTime,
+    pub timer: Timer,
+    pub combat: Option<Combat>,
+    /// Combat requests issued by scripts.
+    pub combat_requests: Vec<combat::Request>,
+    /// Whether the dude is in sneak mode.
+    pub dude_sneaking: bool,
     pub ambient_light: u32,
+    /// Whether the map is being entered as a result of loading a saved game.
+    pub loading_game: bool,
+    /// Map transition requested by scripts or by the dude using an exit.
+    pub map_transition: Option<MapTransition>,
+    /// Kind of the elevator the dude requested to use.
+    pub elevator_request: Option<u32>,
 }
 
 impl World {
@@ -87,10 +128,13 @@ impl World {
         proto_db: Rc<ProtoDb>,
         frm_db: Rc<FrameDb>,
         critter_names: Messages,
+        ai_packets: AiPackets,
         hex_grid: hex::TileGrid,
         viewport: Rect,
         update_time: Instant,
         fonts: Rc<Fonts>,
+        world_map: WorldMap,
+        party: Party,
     ) -> Self {
         let light_grid = LightGrid::new(
             hex_grid.width(),
@@ -101,6 +145,7 @@ impl World {
             proto_db,
             frm_db,
             critter_names,
+            ai_packets,
             hex_grid,
             camera: Camera {
                 origin: Point::new(0, 0),
@@ -113,10 +158,33 @@ impl World {
             dude_obj: None,
             update_time,
             fonts,
+            world_map,
+            party,
             dude_name: BString::new(),
             game_time: START_GAME_TIME,
+            timer: Timer::new(),
+            combat: None,
+            combat_requests: Vec::new(),
+            dude_sneaking: false,
             ambient_light: 0x10000,
+            loading_game: false,
+            map_transition: None,
+            elevator_request: None,
+        }
+    }
+
+    pub fn ai_packets(&self) -> &AiPackets {
+        &self.ai_packets
+    }
+
+    /// AI packet of the critter `obj` with the orders given to it as a party member applied.
+    pub fn ai_packet(&self, obj: object::Handle) -> Option<AiPacket> {
+        let packet_num = self.objects.get(obj).borrow().sub.critter()?.combat.ai_packet;
+        let mut r = self.ai_packets.get(packet_num)?.clone();
+        if let Some(member) = self.party.member(obj) {
+            member.orders.apply(&mut r);
         }
+        Some(r)
     }
 
     pub fn proto_db(&self) -> &ProtoDb {
@@ -147,18 +215,51 @@ impl World {
         &mut self.objects
     }
 
+    pub fn world_map(&self) -> &WorldMap {
+        &self.world_map
+    }
+
+    pub fn world_map_mut(&mut self) -> &mut WorldMap {
+        &mut self.world_map
+    }
+
+    pub fn party(&self) -> &Party {
+        &self.party
+    }
+
+    pub fn party_mut(&mut self) -> &mut Party {
+        &mut self.party
+    }
+
     pub fn light_grid(&self) -> &LightGrid {
         &self.light_grid
     }
 
     pub fn clear(&mut self) {
+        self.clear_except(&[]);
+    }
+
+    /// Same as `clear()` but keeps the `kept` objects along with their timer events. Used for
+    /// the objects carried between maps.
+    // queue_leaving_map()
+    pub fn clear_except(&mut self, kept: &[object::Handle]) {
         for v in &mut self.sqr_tiles {
             *v = None;
         }
-        self.objects.clear();
+        self.objects.retain(|h| kept.contains(&h));
+        self.timer.retain(|e| kept.contains(&e.obj));
+        self.party.retain(|h| kept.contains(&h));
+        self.combat = None;
+        self.combat_requests.clear();
         self.floating_texts.clear();
-        self.dude_obj = None;
+        self.dude_obj = self.dude_obj.filter(|h| kept.contains(h));
         self.light_grid.clear();
+        self.map_transition = None;
+        self.elevator_request = None;
+    }
+
+    pub fn sqr_tiles(&self) -> &[Option<Array2d<(u16, u16)>>] {
+        &self.sqr_tiles
     }
 
     pub fn set_sqr_tiles(&mut self, sqr_tiles: Vec<Option<Array2d<(u16, u16)>>>) {
@@ -174,6 +275,48 @@ impl World {
         h
     }
 
+    pub fn remove_object(&mut self, h: object::Handle) -> Object {
+        Self::update_light_grid(&self.objects, &mut self.light_grid, h, -1);
+        self.hide_floating_text(Some(h));
+        self.party.remove(h);
+        self.objects.remove(h)
+    }
+
+    /// Removes the object along with its inventory and timer events. The object is taken out of
+    /// its owner's inventory first.
+    // obj_destroy()
+    pub fn destroy_object(&mut self, h: object::Handle) {
+        if let Some(owner) = self.objects.inventory_owner(h) {
+            let count = self.objects.get(owner).borrow().inventory.count(h);
+            self.objects.remove_from_inventory(owner, h, count);
+        }
+        for obj in self.objects.with_inventory(h) {
+            self.timer.remove_object(obj);
+            self.remove_object(obj);
+        }
+    }
+
+    /// Puts `count` units of `item` into the inventory of `owner`. Returns the stack the units
+    /// went to. If that's an existing stack the `item` is destroyed.
+    // item_add_force()
+    pub fn add_to_inventory(&mut self, owner: object::Handle, item: object::Handle, count: usize)
+        -> object::Handle
+    {
+        let stack = self.objects.add_to_inventory(owner, item, count);
+        if stack != item {
+            self.destroy_object(item);
+        }
+        stack
+    }
+
+    /// Moves all items from inventory of `src` to inventory of `dst`.
+    // item_move_all()
+    pub fn move_inventory(&mut self, src: object::Handle, dst: object::Handle) {
+        for (item, count) in self.objects.take_inventory(src) {
+            self.add_to_inventory(dst, item, count);
+        }
+    }
+
     pub fn dude_obj(&self) -> Option<object::Handle> {
         self.dude_obj
     }
@@ -206,6 +349,19 @@ impl World {
         Self::update_light_grid(&self.objects, &mut self.light_grid, h, 1);
     }
 
+    /// Finds the tile nearest to `pos` within `max_distance` where the `obj` wouldn't be blocked.
+    pub fn free_pos_near(&self, obj: object::Handle, pos: EPoint, max_distance: u32)
+        -> Option<EPoint>
+    {
+        (0..=max_distance)
+            .flat_map(|distance| Direction::iter()
+                .take(if distance == 0 { 1 } else { Direction::len() })
+                .map(move |direction| (direction, distance)))
+            .filter_map(|(direction, distance)| self.hex_grid.go(pos.point, direction, distance))
+            .map(|p| p.elevated(pos.elevation))
+            .find(|&p| !self.objects.is_blocked_at(obj, p))
+    }
+
     pub fn make_object_standing(&mut self, h: object::Handle) {
         self.objects.make_standing(h, &self.frm_db);
     }
@@ -219,6 +375,21 @@ impl World {
         self.objects.path(obj, to, smooth, allow_neighbor_tile, &self.proto_db)
     }
 
+    /// Makes the door `obj` let through or block the movement, light and projectiles.
+    // set_door_state_open(), set_door_state_closed()
+    pub fn set_door_passable(&mut self, obj: object::Handle, passable: bool) {
+        {
+            let mut objr = self.objects.get(obj).borrow_mut();
+            let flags = Flag::NoBlock | Flag::LightThru | Flag::ShootThru;
+            if passable {
+                objr.flags.insert(flags);
+            } else {
+                objr.flags.remove(flags);
+            }
+        }
+        self.rebuild_light_grid();
+    }
+
     pub fn rebuild_light_grid(&mut self) {
         self.light_grid.clear();
         for h in self.objects.iter() {
@@ -226,6 +397,11 @@ impl World {
         }
     }
 
+    /// Returns light level at `pos` in range `0..=0x10000`.
+    pub fn light_at(&self, pos: EPoint) -> u32 {
+        cmp::max(self.light_grid().get_clipped(pos), self.ambient_light)
+    }
+
     pub fn object_bounds(&self, obj: object::Handle) -> Rect {
         self.objects.bounds(obj, &self.camera.hex())
     }
@@ -320,6 +496,10 @@ impl World {
 
     pub fn render(&self, canvas: &mut Canvas, draw_roof: bool) {
         let elevation = self.elevation();
+        // Nothing to render until a map is loaded.
+        if !self.has_elevation(elevation) {
+            return;
+        }
         render_floor(canvas, &self.camera.sqr(), self.camera.viewport,
             |p| {
                 let fid = FrameId::new_generic(EntityKind::SqrTile,
@@ -336,7 +516,7 @@ impl World {
         self.objects().render(canvas, elevation, self.camera.viewport, &self.camera.hex(),
             self.egg().as_ref(),
             |pos| if let Some(pos) = pos {
-                cmp::max(self.light_grid().get_clipped(pos), self.ambient_light)
+                self.light_at(pos)
             } else {
                 self.ambient_light
             });
@@ -452,3 +632,35 @@ impl World {
 
 
 
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::object::ObjectProtoId;
+    use crate::game::timer::{self, EventKind};
+    use crate::util::test::TestAssets;
+
+    #[test]
+    fn add_to_inventory_merge() {
+        let mut assets = TestAssets::new();
+        let fid = assets.add_frm(EntityKind::Item);
+        let pid = assets.add_key(fid, 1);
+        let mut world = assets.world();
+
+        let owner = world.insert_object(Object::new(FrameId::BLANK, ObjectProtoId::None,
+            Some((0, (10, 10)).into())));
+        let item1 = world.insert_object(Object::new(fid, pid.into(), None));
+        let item2 = world.insert_object(Object::new(fid, pid.into(), Some((0, (11, 10)).into())));
+        world.timer.push(timer::Event {
+            time: GameTime::from_decis(10),
+            obj: item2,
+            kind: EventKind::Script { fixed_param: 0 },
+        });
+
+        assert_eq!(world.add_to_inventory(owner, item1, 2), item1);
+        assert_eq!(world.add_to_inventory(owner, item2, 3), item1);
+        assert_eq!(world.objects().get(owner).borrow().inventory.count(item1), 5);
+        assert!(!world.objects().contains(item2));
+        assert!(world.timer.is_empty());
+    }
+}
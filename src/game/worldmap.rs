@@ -0,0 +1,910 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use enum_primitive_derive::Primitive;
+use log::*;
+use num_traits::FromPrimitive;
+use std::cmp;
+use std::io::{self, Error, ErrorKind, prelude::*};
+use std::rc::Rc;
+
+use crate::asset::worldmap::{AreaDef, DayTime, Formation, WorldMapDef, SUBTILE_SIZE};
+use crate::graphics::Point;
+use crate::graphics::geometry::hex::{self, Direction};
+use crate::util::EnumExt;
+
+/// Game time passing with each travel step, in deciseconds.
+pub const TRAVEL_STEP_TIME: u32 = 20 * 60 * 10;
+
+/// Capacity of the car fuel tank.
+pub const CAR_FUEL_MAX: u32 = 80000;
+
+/// Fuel used by the car with each travel step.
+pub const CAR_FUEL_PER_STEP: u32 = 100;
+
+/// Part of the day at the `hour` of the game time.
+// wmRndEncounterOccurred()
+pub fn day_time(hour: u8) -> DayTime {
+    match hour {
+        6..=11 => DayTime::Morning,
+        12..=17 => DayTime::Afternoon,
+        _ => DayTime::Night,
+    }
+}
+
+/// Position of the `i`-th member of a critter group in the `formation`. The group is centered
+/// at `center` and faces the dude at `dude_pos`. Members are `spacing` hexes apart.
+// wmSetupCritterObjs()
+pub fn formation_pos(formation: Formation, dude_pos: Point, center: Point, spacing: u32, i: u32)
+    -> Point
+{
+    fn rotate(direction: Direction, n: u32) -> Direction {
+        Direction::from_ordinal((direction.ordinal() + n as usize) % Direction::len())
+    }
+
+    let spacing = cmp::max(spacing, 1);
+    let facing = hex::direction(center, dude_pos);
+    // Opposite directions along the front of the group.
+    let side = |i: u32| if i % 2 == 1 { rotate(facing, 4) } else { rotate(facing, 1) };
+    let rank = (i + 1) / 2;
+    match formation {
+        Formation::Surrounding => {
+            let distance = cmp::max(hex::distance(dude_pos, center), 1) + i / 6 * spacing;
+            hex::go(dude_pos, rotate(hex::direction(dude_pos, center), i), distance)
+        }
+        Formation::StraightLine => hex::go(center, side(i), rank * spacing),
+        Formation::DoubleLine => {
+            let row_start = if i % 2 == 1 {
+                hex::go(center, rotate(facing, 3), spacing)
+            } else {
+                center
+            };
+            let i = i / 2;
+            hex::go(row_start, side(i), (i + 1) / 2 * spacing)
+        }
+        // Wings going back from the center.
+        Formation::Wedge => hex::go(center,
+            rotate(facing, if i % 2 == 1 { 2 } else { 4 }), rank * spacing),
+        // Wings going forward from the center.
+        Formation::Cone => hex::go(center,
+            rotate(facing, if i % 2 == 1 { 1 } else { 5 }), rank * spacing),
+        Formation::Huddle => if i == 0 {
+            center
+        } else {
+            hex::go(center, rotate(facing, i - 1), ((i - 1) / 6 + 1) * spacing)
+        }
+    }
+}
+
+/// Random encounter picked from an encounter table.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RandomEncounter {
+    /// Index into `WorldMapDef::encounter_tables`.
+    pub table: usize,
+    /// Index into `EncounterTable::encounters`.
+    pub encounter: usize,
+    pub map_id: i32,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Primitive)]
+pub enum VisitState {
+    Unknown = 0,
+    Known = 1,
+    Visited = 2,
+}
+
+struct Travel {
+    from: Point,
+    to: Point,
+    /// Number of pixels travelled so far.
+    done: i32,
+    /// Steps made on the current pixel. The pixel is left after the terrain difficulty number
+    /// of steps.
+    steps: u32,
+}
+
+/// Car of the party.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Car {
+    /// Map where the car is parked. `None` while the party drives it.
+    pub map_id: Option<i32>,
+    pub fuel: u32,
+}
+
+/// State of the world map: the party position, known town areas and the fog of war.
+// wmWorldMap
+pub struct WorldMap {
+    def: Rc<WorldMapDef>,
+    areas: Rc<[AreaDef]>,
+    area_states: Vec<VisitState>,
+    area_positions: Vec<Point>,
+    /// Whether each entrance of each area is known.
+    entrance_states: Vec<Vec<bool>>,
+    /// Subtile states row by row.
+    subtile_states: Vec<VisitState>,
+    subtile_cols: i32,
+    pos: Point,
+    travel: Option<Travel>,
+    /// Remaining number of times each encounter of each encounter table can happen.
+    encounter_counters: Vec<Vec<Option<u32>>>,
+    car: Option<Car>,
+}
+
+impl WorldMap {
+    pub fn new(def: Rc<WorldMapDef>, areas: Rc<[AreaDef]>) -> Self {
+        let size = def.size();
+        let subtile_cols = size.x / SUBTILE_SIZE;
+        let mut r = Self {
+            def,
+            areas,
+            area_states: Vec::new(),
+            area_positions: Vec::new(),
+            entrance_states: Vec::new(),
+            subtile_states: vec![VisitState::Unknown;
+                (subtile_cols * (size.y / SUBTILE_SIZE)) as usize],
+            subtile_cols,
+            pos: Point::new(0, 0),
+            travel: None,
+            encounter_counters: Vec::new(),
+            car: None,
+        };
+        r.reset();
+        r
+    }
+
+    /// Resets the state to the one of a new game.
+    pub fn reset(&mut self) {
+        self.area_states = self.areas.iter()
+            .map(|a| if a.known { VisitState::Known } else { VisitState::Unknown })
+            .collect();
+        self.area_positions = self.areas.iter().map(|a| a.pos).collect();
+        self.entrance_states = self.areas.iter()
+            .map(|a| a.entrances.iter().map(|e| e.known).collect())
+            .collect();
+        for s in &mut self.subtile_states {
+            *s = VisitState::Unknown;
+        }
+        self.pos = self.areas.first().map(|a| a.pos).unwrap_or(Point::new(0, 0));
+        self.travel = None;
+        self.encounter_counters = self.def.encounter_tables.iter()
+            .map(|t| t.encounters.iter().map(|e| e.counter).collect())
+            .collect();
+        self.car = None;
+        for i in 0..self.areas.len() {
+            if self.area_states[i] != VisitState::Unknown {
+                self.mark_subtiles(self.area_positions[i], 0, VisitState::Known);
+            }
+        }
+    }
+
+    pub fn def(&self) -> &WorldMapDef {
+        &self.def
+    }
+
+    pub fn areas(&self) -> &[AreaDef] {
+        &self.areas
+    }
+
+    pub fn area_state(&self, area: usize) -> Option<VisitState> {
+        self.area_states.get(area).cloned()
+    }
+
+    // wmAreaSetVisibleState()
+    pub fn set_area_state(&mut self, area: usize, state: VisitState) {
+        if let Some(s) = self.area_states.get_mut(area) {
+            *s = state;
+            if state != VisitState::Unknown {
+                let pos = self.area_positions[area];
+                self.mark_subtiles(pos, 0, VisitState::Known);
+            }
+        }
+    }
+
+    pub fn area_pos(&self, area: usize) -> Option<Point> {
+        self.area_positions.get(area).cloned()
+    }
+
+    pub fn set_area_pos(&mut self, area: usize, pos: Point) {
+        if let Some(p) = self.area_positions.get_mut(area) {
+            *p = pos;
+        }
+    }
+
+    /// Finds the known area whose circle contains `pos`.
+    pub fn area_at(&self, pos: Point) -> Option<usize> {
+        (0..self.areas.len())
+            .find(|&i| self.area_states[i] != VisitState::Unknown && self.area_contains(i, pos))
+    }
+
+    fn area_contains(&self, area: usize, pos: Point) -> bool {
+        let r = self.areas[area].size.radius();
+        let d = pos - self.area_positions[area];
+        d.x * d.x + d.y * d.y <= r * r
+    }
+
+    /// Marks the `area` visited and moves the party there unless it's already within the area
+    /// circle.
+    // wmMapMarkVisited()
+    pub fn enter_area(&mut self, area: usize) {
+        if area >= self.areas.len() {
+            return;
+        }
+        self.set_area_state(area, VisitState::Visited);
+        if !self.area_contains(area, self.pos) {
+            let pos = self.area_positions[area];
+            self.set_pos(pos);
+        }
+    }
+
+    pub fn is_entrance_known(&self, area: usize, entrance: usize) -> bool {
+        self.entrance_states.get(area)
+            .and_then(|e| e.get(entrance))
+            .cloned()
+            .unwrap_or(false)
+    }
+
+    /// Marks the entrances to the `map_id` known and the area of the map visited.
+    // wmMapMarkVisited()
+    pub fn mark_map_known(&mut self, map_id: i32) {
+        let area = if let Some(v) = self.area_of_map(map_id) {
+            v
+        } else {
+            return;
+        };
+        for (state, entrance) in self.entrance_states[area].iter_mut()
+            .zip(&self.areas[area].entrances)
+        {
+            if entrance.map_id == Some(map_id) {
+                *state = true;
+            }
+        }
+        self.set_area_state(area, VisitState::Visited);
+    }
+
+    /// Whether the area of the `map_id` is known and has a known entrance to the map.
+    // wmMapIsKnown()
+    pub fn is_map_known(&self, map_id: i32) -> bool {
+        let area = if let Some(v) = self.area_of_map(map_id) {
+            v
+        } else {
+            return false;
+        };
+        self.area_states[area] != VisitState::Unknown
+            && self.areas[area].entrances.iter().enumerate()
+                .any(|(i, e)| e.map_id == Some(map_id) && self.entrance_states[area][i])
+    }
+
+    /// Finds the area which has an entrance to the `map_id`.
+    // wmMatchAreaContainingMapIdx()
+    pub fn area_of_map(&self, map_id: i32) -> Option<usize> {
+        self.areas.iter().position(|a| a.entrances.iter().any(|e| e.map_id == Some(map_id)))
+    }
+
+    /// Party position in world map pixels.
+    pub fn pos(&self) -> Point {
+        self.pos
+    }
+
+    pub fn set_pos(&mut self, pos: Point) {
+        self.travel = None;
+        self.move_to(pos);
+    }
+
+    /// Moves the party revealing the surrounding subtiles and the unknown areas the party
+    /// stepped into.
+    fn move_to(&mut self, pos: Point) {
+        self.pos = pos;
+        self.mark_subtiles(pos, 1, VisitState::Known);
+        self.mark_subtiles(pos, 0, VisitState::Visited);
+        for i in 0..self.areas.len() {
+            if self.area_states[i] == VisitState::Unknown && self.area_contains(i, pos) {
+                self.area_states[i] = VisitState::Known;
+            }
+        }
+    }
+
+    /// Destination of the current travel.
+    pub fn destination(&self) -> Option<Point> {
+        self.travel.as_ref().map(|t| t.to)
+    }
+
+    pub fn is_travelling(&self) -> bool {
+        self.travel.is_some()
+    }
+
+    pub fn travel_to(&mut self, to: Point) {
+        let size = self.def.size();
+        let to = Point::new(
+            cmp::min(cmp::max(to.x, 0), size.x - 1),
+            cmp::min(cmp::max(to.y, 0), size.y - 1));
+        self.travel = if to != self.pos {
+            Some(Travel {
+                from: self.pos,
+                to,
+                done: 0,
+                steps: 0,
+            })
+        } else {
+            None
+        };
+    }
+
+    pub fn stop(&mut self) {
+        self.travel = None;
+    }
+
+    /// Advances the travel by one step. The party moves one pixel per the terrain difficulty
+    /// number of steps. In the car the party moves twice as fast while there's fuel, each step
+    /// uses `CAR_FUEL_PER_STEP`. Each step takes `TRAVEL_STEP_TIME`. Returns `false` if there's no
+    /// travel in progress.
+    // wmPartyWalkingStep(), wmCarUseGas()
+    pub fn travel_step(&mut self) -> bool {
+        if !self.walk_step() {
+            return false;
+        }
+        if let Some(car) = self.car.as_mut().filter(|c| c.map_id.is_none() && c.fuel > 0) {
+            car.fuel = car.fuel.saturating_sub(CAR_FUEL_PER_STEP);
+            self.walk_step();
+        }
+        true
+    }
+
+    fn walk_step(&mut self) -> bool {
+        let difficulty = self.def.subtile_at(self.pos)
+            .map(|s| self.def.terrains[s.terrain].difficulty)
+            .unwrap_or(1)
+            .max(1);
+        let pos = {
+            let travel = if let Some(v) = self.travel.as_mut() {
+                v
+            } else {
+                return false;
+            };
+            travel.steps += 1;
+            if travel.steps < difficulty {
+                return true;
+            }
+            travel.steps = 0;
+            travel.done += 1;
+
+            let d = travel.to - travel.from;
+            let len = cmp::max(d.x.abs(), d.y.abs());
+            let done = cmp::min(travel.done, len);
+            travel.from + Point::new(d.x * done / len, d.y * done / len)
+        };
+        if Some(pos) == self.destination() {
+            self.travel = None;
+        }
+        self.move_to(pos);
+        true
+    }
+
+    pub fn car(&self) -> Option<&Car> {
+        self.car.as_ref()
+    }
+
+    /// Gives the car with the full tank to the party. The car is parked at the `map_id`.
+    // wmCarGiveToParty()
+    pub fn give_car(&mut self, map_id: i32) {
+        let car = self.car.get_or_insert(Car {
+            map_id: None,
+            fuel: CAR_FUEL_MAX,
+        });
+        car.map_id = Some(map_id);
+    }
+
+    /// Area where the car is parked.
+    // wmCarCurrentArea()
+    pub fn car_area(&self) -> Option<usize> {
+        self.car.and_then(|c| c.map_id).and_then(|id| self.area_of_map(id))
+    }
+
+    /// Adds `amount` of fuel to the car up to `CAR_FUEL_MAX`. Returns the amount actually added.
+    // wmCarRefuel()
+    pub fn refuel_car(&mut self, amount: u32) -> u32 {
+        if let Some(car) = &mut self.car {
+            let fuel = cmp::min(car.fuel.saturating_add(amount), CAR_FUEL_MAX);
+            let r = fuel - car.fuel;
+            car.fuel = fuel;
+            r
+        } else {
+            0
+        }
+    }
+
+    // wmCarIsOutOfGas()
+    pub fn is_car_out_of_fuel(&self) -> bool {
+        self.car.map(|c| c.fuel == 0).unwrap_or(false)
+    }
+
+    pub fn is_driving(&self) -> bool {
+        self.car.map(|c| c.map_id.is_none()).unwrap_or(false)
+    }
+
+    /// Puts the party in the car if it's parked at the `map_id`. Returns `true` if the party is
+    /// driving.
+    pub fn enter_car(&mut self, map_id: i32) -> bool {
+        if let Some(car) = self.car.as_mut().filter(|c| c.map_id == Some(map_id)) {
+            car.map_id = None;
+        }
+        self.is_driving()
+    }
+
+    /// Parks the car the party drives at the `map_id`.
+    pub fn park_car(&mut self, map_id: i32) {
+        if let Some(car) = self.car.as_mut().filter(|c| c.map_id.is_none()) {
+            car.map_id = Some(map_id);
+        }
+    }
+
+    /// Rolls for a random encounter at the party position using the encounter chance of the
+    /// subtile for the `day_time`. If the roll succeeds picks an encounter from the subtile's
+    /// encounter table by the encounter chances. Encounters don't happen within the town areas.
+    /// `rng(from_inclusive, to_inclusive)` is the source of random numbers.
+    // wmRndEncounterOccurred(), wmRndEncounterPick()
+    pub fn roll_encounter(&mut self, day_time: DayTime, rng: &mut impl FnMut(i32, i32) -> i32)
+        -> Option<RandomEncounter>
+    {
+        if self.area_at(self.pos).is_some() {
+            return None;
+        }
+        let def = self.def.clone();
+        let subtile = def.subtile_at(self.pos)?;
+        let chance = subtile.encounter_chance[day_time] as i32;
+        if chance == 0 || rng(1, 100) > chance {
+            return None;
+        }
+
+        let table_name = subtile.encounter_table.as_ref()?;
+        let table_idx = if let Some(v) = def.find_encounter_table(table_name) {
+            v
+        } else {
+            warn!("unknown encounter table: {}", table_name);
+            return None;
+        };
+        let table = &def.encounter_tables[table_idx];
+        let counters = &mut self.encounter_counters[table_idx];
+        let total: u32 = table.encounters.iter().zip(counters.iter())
+            .filter(|&(_, &c)| c != Some(0))
+            .map(|(e, _)| e.chance)
+            .sum();
+        if total == 0 {
+            return None;
+        }
+        let mut roll = rng(1, total as i32) as u32;
+        let encounter = table.encounters.iter().zip(counters.iter())
+            .position(|(e, &c)| {
+                if c == Some(0) {
+                    return false;
+                }
+                if roll <= e.chance {
+                    true
+                } else {
+                    roll -= e.chance;
+                    false
+                }
+            })?;
+
+        let map_id = if let Some(v) = table.encounters[encounter].map_id {
+            v
+        } else if !table.maps.is_empty() {
+            table.maps[rng(0, table.maps.len() as i32 - 1) as usize]
+        } else {
+            warn!("no maps in encounter table {}", table.name);
+            return None;
+        };
+
+        if let Some(c) = &mut counters[encounter] {
+            *c -= 1;
+        }
+
+        Some(RandomEncounter {
+            table: table_idx,
+            encounter,
+            map_id,
+        })
+    }
+
+    pub fn subtile_state(&self, pos: Point) -> Option<VisitState> {
+        self.subtile_idx(pos).map(|i| self.subtile_states[i])
+    }
+
+    /// Raises the state of subtiles within `radius` subtiles around the one at `pos` to
+    /// `state`. States are never lowered.
+    // wmSubTileMarkRadiusVisited()
+    pub fn mark_subtiles(&mut self, pos: Point, radius: i32, state: VisitState) {
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let p = pos + Point::new(dx * SUBTILE_SIZE, dy * SUBTILE_SIZE);
+                if let Some(i) = self.subtile_idx(p) {
+                    let s = &mut self.subtile_states[i];
+                    *s = cmp::max(*s, state);
+                }
+            }
+        }
+    }
+
+    fn subtile_idx(&self, pos: Point) -> Option<usize> {
+        if pos.x < 0 || pos.y < 0 {
+            return None;
+        }
+        let (x, y) = (pos.x / SUBTILE_SIZE, pos.y / SUBTILE_SIZE);
+        if x >= self.subtile_cols {
+            return None;
+        }
+        let i = (y * self.subtile_cols + x) as usize;
+        if i < self.subtile_states.len() {
+            Some(i)
+        } else {
+            None
+        }
+    }
+
+    pub fn write(&self, wr: &mut impl Write) -> io::Result<()> {
+        wr.write_i32::<BigEndian>(self.pos.x)?;
+        wr.write_i32::<BigEndian>(self.pos.y)?;
+        wr.write_u32::<BigEndian>(self.area_states.len() as u32)?;
+        for ((&state, &pos), entrances) in self.area_states.iter()
+            .zip(&self.area_positions)
+            .zip(&self.entrance_states)
+        {
+            wr.write_u8(state as u8)?;
+            wr.write_i32::<BigEndian>(pos.x)?;
+            wr.write_i32::<BigEndian>(pos.y)?;
+            wr.write_u32::<BigEndian>(entrances.len() as u32)?;
+            for &known in entrances {
+                wr.write_u8(known as u8)?;
+            }
+        }
+        wr.write_u32::<BigEndian>(self.subtile_states.len() as u32)?;
+        for &state in &self.subtile_states {
+            wr.write_u8(state as u8)?;
+        }
+        wr.write_u32::<BigEndian>(self.encounter_counters.len() as u32)?;
+        for counters in &self.encounter_counters {
+            wr.write_u32::<BigEndian>(counters.len() as u32)?;
+            for &c in counters {
+                wr.write_i32::<BigEndian>(c.map(|c| c as i32).unwrap_or(-1))?;
+            }
+        }
+        wr.write_u8(self.car.is_some() as u8)?;
+        if let Some(car) = &self.car {
+            wr.write_i32::<BigEndian>(car.map_id.unwrap_or(-1))?;
+            wr.write_u32::<BigEndian>(car.fuel)?;
+        }
+        Ok(())
+    }
+
+    pub fn read(&mut self, rd: &mut impl Read) -> io::Result<()> {
+        fn read_state(rd: &mut impl Read) -> io::Result<VisitState> {
+            VisitState::from_u8(rd.read_u8()?)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "invalid visit state"))
+        }
+
+        self.reset();
+
+        let pos = Point::new(rd.read_i32::<BigEndian>()?, rd.read_i32::<BigEndian>()?);
+        let area_count = rd.read_u32::<BigEndian>()? as usize;
+        for i in 0..area_count {
+            let state = read_state(rd)?;
+            let pos = Point::new(rd.read_i32::<BigEndian>()?, rd.read_i32::<BigEndian>()?);
+            if i < self.area_states.len() {
+                self.area_states[i] = state;
+                self.area_positions[i] = pos;
+            }
+            let entrance_count = rd.read_u32::<BigEndian>()? as usize;
+            for j in 0..entrance_count {
+                let known = rd.read_u8()? != 0;
+                if let Some(s) = self.entrance_states.get_mut(i).and_then(|e| e.get_mut(j)) {
+                    *s = known;
+                }
+            }
+        }
+        let subtile_count = rd.read_u32::<BigEndian>()? as usize;
+        if subtile_count != self.subtile_states.len() {
+            return Err(Error::new(ErrorKind::InvalidData, "world map size mismatch"));
+        }
+        for s in &mut self.subtile_states {
+            *s = read_state(rd)?;
+        }
+        let table_count = rd.read_u32::<BigEndian>()? as usize;
+        for table in 0..table_count {
+            let count = rd.read_u32::<BigEndian>()? as usize;
+            for i in 0..count {
+                let v = rd.read_i32::<BigEndian>()?;
+                if let Some(c) = self.encounter_counters.get_mut(table).and_then(|c| c.get_mut(i)) {
+                    *c = if v >= 0 { Some(v as u32) } else { None };
+                }
+            }
+        }
+        if rd.read_u8()? != 0 {
+            let map_id = rd.read_i32::<BigEndian>()?;
+            self.car = Some(Car {
+                map_id: if map_id >= 0 { Some(map_id) } else { None },
+                fuel: rd.read_u32::<BigEndian>()?,
+            });
+        }
+        self.pos = pos;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::asset::map::db::MapDb;
+    use crate::asset::worldmap;
+
+    fn new() -> WorldMap {
+        let map_db = MapDb::read(&mut &b"
+[Map 005]
+lookup_name=Town
+map_name=town
+[Map 007]
+lookup_name=Desert 1
+map_name=desert1
+[Map 008]
+lookup_name=Desert 2
+map_name=desert2
+"[..]).unwrap();
+
+        let mut s = String::from("
+[Data]
+terrain_types=Desert:1, Mountain:2
+encounter_frequency=None:0%, Always:100%
+
+[Tile Data]
+num_horizontal_tiles=1
+
+[Tile 0]
+art_idx=339
+");
+        for y in 0..6 {
+            for x in 0..7 {
+                let terrain = if x < 2 { "Desert" } else { "Mountain" };
+                s += &if y < 5 {
+                    format!("{}_{}={},No_Fill,None,None,None\n", x, y, terrain)
+                } else {
+                    format!("{}_{}={},No_Fill,None,None,Always,Desert\n", x, y, terrain)
+                };
+            }
+        }
+        s += "
+[Encounter Table 0]
+lookup_name=Desert
+maps=Desert 1,Desert 2
+enc_00=chance:10%,counter:1,enc:(1) Geckos
+enc_01=chance:30%,map:Town,enc:(2-3) Geckos AMBUSH Player
+
+[Encounter: Geckos]
+type_00=pid:16777231
+";
+        let def = WorldMapDef::read(&mut s.as_bytes(), &map_db).unwrap();
+
+        let areas = worldmap::read_areas(&mut &b"
+[Area 00]
+area_name=Start
+world_pos=10,10
+start_state=On
+size=Small
+
+[Area 01]
+area_name=Town
+world_pos=200,200
+start_state=Off
+size=Large
+entrance_0=Off,0,0,Town,-1,-1,0
+"[..], &map_db).unwrap();
+
+        WorldMap::new(Rc::new(def), areas.into())
+    }
+
+    #[test]
+    fn areas() {
+        let mut wm = new();
+        assert_eq!(wm.pos(), Point::new(10, 10));
+        assert_eq!(wm.area_state(0), Some(VisitState::Known));
+        assert_eq!(wm.area_state(1), Some(VisitState::Unknown));
+        assert_eq!(wm.area_state(2), None);
+        assert_eq!(wm.area_of_map(5), Some(1));
+        assert_eq!(wm.area_of_map(0), None);
+
+        assert_eq!(wm.area_at(Point::new(15, 15)), Some(0));
+        assert_eq!(wm.area_at(Point::new(200, 200)), None);
+        wm.set_area_state(1, VisitState::Known);
+        assert_eq!(wm.area_at(Point::new(220, 210)), Some(1));
+        assert_eq!(wm.subtile_state(Point::new(200, 200)), Some(VisitState::Known));
+
+        wm.set_area_pos(1, Point::new(100, 100));
+        assert_eq!(wm.area_at(Point::new(100, 120)), Some(1));
+
+        wm.enter_area(1);
+        assert_eq!(wm.area_state(1), Some(VisitState::Visited));
+        assert_eq!(wm.pos(), Point::new(100, 100));
+        wm.set_pos(Point::new(110, 110));
+        wm.enter_area(1);
+        assert_eq!(wm.pos(), Point::new(110, 110));
+    }
+
+    #[test]
+    fn travel() {
+        let mut wm = new();
+        assert_eq!(wm.subtile_state(Point::new(10, 10)), Some(VisitState::Known));
+        assert_eq!(wm.subtile_state(Point::new(60, 60)), Some(VisitState::Unknown));
+
+        wm.travel_to(Point::new(12, 11));
+        assert!(wm.travel_step());
+        assert_eq!(wm.pos(), Point::new(11, 10));
+        assert_eq!(wm.subtile_state(Point::new(10, 10)), Some(VisitState::Visited));
+        assert_eq!(wm.subtile_state(Point::new(60, 60)), Some(VisitState::Known));
+        assert!(wm.travel_step());
+        assert_eq!(wm.pos(), Point::new(12, 11));
+        assert!(!wm.is_travelling());
+        assert!(!wm.travel_step());
+
+        // Mountains take two steps per pixel.
+        wm.set_pos(Point::new(100, 0));
+        wm.travel_to(Point::new(100, 2));
+        assert!(wm.travel_step());
+        assert_eq!(wm.pos(), Point::new(100, 0));
+        assert!(wm.travel_step());
+        assert_eq!(wm.pos(), Point::new(100, 1));
+
+        // Stepping into an unknown area reveals it.
+        wm.set_pos(Point::new(200, 160));
+        wm.travel_to(Point::new(200, 175));
+        assert_eq!(wm.area_state(1), Some(VisitState::Unknown));
+        while wm.travel_step() {}
+        assert_eq!(wm.area_state(1), Some(VisitState::Known));
+    }
+
+    #[test]
+    fn car() {
+        let mut wm = new();
+        assert_eq!(wm.car(), None);
+        assert!(!wm.enter_car(5));
+        assert_eq!(wm.refuel_car(100), 0);
+
+        wm.give_car(5);
+        assert_eq!(wm.car_area(), Some(1));
+        assert!(!wm.enter_car(7));
+        assert!(wm.enter_car(5));
+        assert_eq!(wm.car_area(), None);
+
+        // The car moves two pixels per step.
+        wm.travel_to(Point::new(20, 10));
+        assert!(wm.travel_step());
+        assert_eq!(wm.pos(), Point::new(12, 10));
+        assert_eq!(wm.car().unwrap().fuel, CAR_FUEL_MAX - CAR_FUEL_PER_STEP);
+
+        assert_eq!(wm.refuel_car(1000), CAR_FUEL_PER_STEP);
+        assert_eq!(wm.car().unwrap().fuel, CAR_FUEL_MAX);
+
+        // Without fuel the party moves at the walking speed.
+        wm.car.as_mut().unwrap().fuel = CAR_FUEL_PER_STEP;
+        assert!(wm.travel_step());
+        assert_eq!(wm.pos(), Point::new(14, 10));
+        assert!(wm.is_car_out_of_fuel());
+        assert!(wm.travel_step());
+        assert_eq!(wm.pos(), Point::new(15, 10));
+
+        wm.park_car(7);
+        assert!(!wm.is_driving());
+        assert_eq!(wm.car().unwrap().map_id, Some(7));
+        assert_eq!(wm.car_area(), None);
+    }
+
+    fn rng(rolls: &[i32]) -> impl FnMut(i32, i32) -> i32 + '_ {
+        let mut rolls = rolls.iter();
+        move |from, to| {
+            let r = *rolls.next().unwrap();
+            assert!(r >= from && r <= to);
+            r
+        }
+    }
+
+    #[test]
+    fn day_time_() {
+        assert_eq!(day_time(0), DayTime::Night);
+        assert_eq!(day_time(6), DayTime::Morning);
+        assert_eq!(day_time(12), DayTime::Afternoon);
+        assert_eq!(day_time(17), DayTime::Afternoon);
+        assert_eq!(day_time(18), DayTime::Night);
+    }
+
+    #[test]
+    fn roll_encounter() {
+        let mut wm = new();
+
+        // No encounters in the morning.
+        wm.set_pos(Point::new(100, 260));
+        assert_eq!(wm.roll_encounter(DayTime::Morning, &mut rng(&[])), None);
+
+        // No encounters within areas.
+        wm.set_area_pos(1, Point::new(20, 260));
+        wm.set_area_state(1, VisitState::Known);
+        wm.set_pos(Point::new(20, 260));
+        assert_eq!(wm.roll_encounter(DayTime::Night, &mut rng(&[])), None);
+        wm.set_pos(Point::new(100, 260));
+
+        assert_eq!(wm.roll_encounter(DayTime::Night, &mut rng(&[100, 10, 1])),
+            Some(RandomEncounter { table: 0, encounter: 0, map_id: 8 }));
+        assert_eq!(wm.roll_encounter(DayTime::Night, &mut rng(&[1, 11])),
+            Some(RandomEncounter { table: 0, encounter: 1, map_id: 5 }));
+
+        // The first encounter has run out.
+        assert_eq!(wm.roll_encounter(DayTime::Night, &mut rng(&[1, 1])),
+            Some(RandomEncounter { table: 0, encounter: 1, map_id: 5 }));
+    }
+
+    #[test]
+    fn formation_pos_() {
+        let dude = Point::new(50, 50);
+        let center = Point::new(50, 44);
+        let distance = |p| hex::distance(dude, p);
+        let d = distance(center);
+
+        for i in 0..6 {
+            assert_eq!(distance(formation_pos(Formation::Surrounding, dude, center, 2, i)), d);
+        }
+        assert_eq!(distance(formation_pos(Formation::Surrounding, dude, center, 2, 6)), d + 2);
+
+        assert_eq!(formation_pos(Formation::Huddle, dude, center, 2, 0), center);
+        assert_eq!(formation_pos(Formation::StraightLine, dude, center, 2, 0), center);
+        for &formation in &[Formation::StraightLine, Formation::Huddle, Formation::Wedge,
+            Formation::Cone]
+        {
+            let p1 = formation_pos(formation, dude, center, 2, 1);
+            let p2 = formation_pos(formation, dude, center, 2, 2);
+            assert_eq!(hex::distance(center, p1), hex::distance(center, p2));
+            assert_ne!(p1, p2);
+        }
+        let p1 = formation_pos(Formation::StraightLine, dude, center, 2, 1);
+        let p2 = formation_pos(Formation::StraightLine, dude, center, 2, 2);
+        assert_eq!(hex::distance(p1, p2), 4);
+    }
+
+    #[test]
+    fn mark_map_known() {
+        let mut wm = new();
+        assert!(!wm.is_map_known(5));
+        assert!(!wm.is_entrance_known(1, 0));
+
+        wm.set_area_state(1, VisitState::Known);
+        assert!(!wm.is_map_known(5));
+
+        wm.mark_map_known(5);
+        assert!(wm.is_map_known(5));
+        assert!(wm.is_entrance_known(1, 0));
+        assert_eq!(wm.area_state(1), Some(VisitState::Visited));
+
+        wm.mark_map_known(7);
+        assert!(!wm.is_map_known(7));
+    }
+
+    #[test]
+    fn write_read() {
+        let mut wm = new();
+        wm.set_area_state(1, VisitState::Visited);
+        wm.set_area_pos(1, Point::new(50, 60));
+        wm.mark_map_known(5);
+        wm.set_pos(Point::new(300, 250));
+        wm.roll_encounter(DayTime::Night, &mut rng(&[1, 1, 0]));
+        assert_eq!(wm.encounter_counters, vec![vec![Some(0), None]]);
+
+        wm.give_car(5);
+        wm.enter_car(5);
+
+        let mut buf = Vec::new();
+        wm.write(&mut buf).unwrap();
+
+        let mut wm2 = new();
+        wm2.read(&mut &buf[..]).unwrap();
+        assert_eq!(wm2.pos(), Point::new(300, 250));
+        assert_eq!(wm2.area_state(1), Some(VisitState::Visited));
+        assert_eq!(wm2.area_pos(1), Some(Point::new(50, 60)));
+        assert_eq!(wm2.entrance_states, wm.entrance_states);
+        assert_eq!(wm2.subtile_states, wm.subtile_states);
+        assert_eq!(wm2.encounter_counters, wm.encounter_counters);
+        assert_eq!(wm2.car(), wm.car());
+    }
+}
@@ -6,7 +6,7 @@ use std::fmt;
 use std::rc::Rc;
 use std::time::Instant;
 
-use crate::graphics::Point;
+use crate::graphics::{Point, Rect};
 use crate::graphics::color::Rgb15;
 use crate::graphics::font::{self, FontKey, Fonts};
 use crate::util::SmKey;
@@ -41,6 +41,12 @@ enum TextureFactoryInner {
 }
 
 impl TextureFactory {
+    /// Creates factory whose textures aren't bound to any canvas.
+    #[cfg(test)]
+    pub fn new_detached() -> Self {
+        TextureFactory(TextureFactoryInner::Software(software::Textures::new()))
+    }
+
     pub fn new_texture(&self, width: i32, height: i32, data: Box<[u8]>) -> TextureHandle {
         match self.0 {
             TextureFactoryInner::Software(ref i) => i.new_texture(width, height, data),
@@ -93,4 +99,8 @@ pub trait Canvas {
     fn draw_outline(&mut self, tex: &TextureHandle, pos: Point, outline: Outline);
     fn draw_text(&mut self, text: &bstr, pos: Point, font: FontKey, color: Rgb15,
         options: &font::DrawOptions);
+
+    /// Returns color indices of the back buffer pixels in `rect` scaled to `width` x `height`.
+    /// Pixels outside of the back buffer are 0.
+    fn capture(&self, rect: Rect, width: i32, height: i32) -> Box<[u8]>;
 }
\ No newline at end of file
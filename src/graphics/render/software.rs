@@ -107,7 +107,7 @@ impl TexturesInner {
 pub(in super) struct Textures(Rc<RefCell<TexturesInner>>);
 
 impl Textures {
-    fn new() -> Self {
+    pub(in super) fn new() -> Self {
         Textures(Rc::new(RefCell::new(TexturesInner::new())))
     }
 
@@ -493,4 +493,21 @@ impl Canvas for CanvasImpl {
         let fonts = self.fonts.clone();
         fonts.get(font).draw(self, text.into(), pos, color, options);
     }
+
+    fn capture(&self, rect: Rect, width: i32, height: i32) -> Box<[u8]> {
+        let src = &self.back_buf;
+        let mut r = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            let src_y = rect.top + y * rect.height() / height;
+            for x in 0..width {
+                let src_x = rect.left + x * rect.width() / width;
+                r.push(if src_x >= 0 && src_x < src.width && src_y >= 0 && src_y < src.height {
+                    src.data[(src_y * src.width + src_x) as usize]
+                } else {
+                    0
+                });
+            }
+        }
+        r.into()
+    }
 }
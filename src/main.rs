@@ -130,10 +130,13 @@ fn main() {
     let mut fs = fs::FileSystem::new();
 
     let map_name: String;
+    let save_dir: PathBuf;
     {
         let args = &args().get_matches();
         setup_file_system(&mut fs, args);
 
+        save_dir = [args.value_of("RESOURCE_DIR").unwrap(), "data", "savegame"].iter().collect();
+
         let s = args.value_of("MAP").unwrap().to_lowercase();
         map_name = if s.ends_with(".map") {
             s[..s.len() - 4].into()
@@ -201,6 +204,7 @@ fn main() {
     let mut state = GameState::new(
         fs.clone(),
         language,
+        save_dir,
         proto_db.clone(),
         frm_db.clone(),
         fonts.clone(),
@@ -209,7 +213,7 @@ fn main() {
         ui,
     );
 
-    state.new_game(&map_name, "Narg".into(), ui);
+    state.start_new_game(&map_name, ui);
 
     let mut draw_debug = true;
 
@@ -269,7 +273,9 @@ fn main() {
             } else {
                 (Point::new(-1, -1), Point::new(-1, -1))
             };
-            let dude_pos = world.objects().get(world.dude_obj().unwrap()).borrow().pos.unwrap().point;
+            let dude_pos = world.dude_obj()
+                .map(|dude_obj| world.objects().get(dude_obj).borrow().pos.unwrap().point)
+                .unwrap_or_else(|| Point::new(-1, -1));
             let ref msg = format!(
                 "mouse: {}, {}\n\
                  mouse hex: {}, {} ({})\n\
@@ -1,3 +1,5 @@
+use crate::asset::Skill;
+use crate::game::combat::attack::HitLocation;
 use crate::game::object;
 use crate::graphics::EPoint;
 
@@ -13,10 +15,32 @@ pub enum Event {
         old_pos: EPoint,
         new_pos: EPoint,
     },
+    Attack {
+        attacker: object::Handle,
+        target: object::Handle,
+        location: HitLocation,
+    },
+    Loot {
+        looter: object::Handle,
+        target: object::Handle,
+    },
     Talk {
         talker: object::Handle,
         talked: object::Handle,
     },
+    UseObj {
+        user: object::Handle,
+        target: object::Handle,
+    },
+    UseSkill {
+        user: object::Handle,
+        target: object::Handle,
+        skill: Skill,
+    },
+    /// The `door` on the way of a walking critter should be opened.
+    OpenDoor {
+        door: object::Handle,
+    },
 }
 
 pub struct PushEvent {
@@ -66,6 +66,8 @@ pub enum Cursor {
     ScrollWestX,
     ScrollNorthWestX,
 
+    UseCrosshair,
+
     Hidden,
 }
 
@@ -96,6 +98,8 @@ impl Cursor {
             ScrollWestX => FrameId::SCRWX,
             ScrollNorthWestX => FrameId::SCRNWX,
 
+            UseCrosshair => FrameId::CROSSUSE,
+
             Hidden => FrameId::BLANK,
         }
     }
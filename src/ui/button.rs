@@ -1,7 +1,11 @@
+use bstring::BString;
 use enum_map::{enum_map, EnumMap};
 
 use super::*;
+use crate::graphics::color::Rgb15;
+use crate::graphics::font::{DrawOptions, FontKey, HorzAlign, VertAlign};
 use crate::graphics::sprite::Sprite;
+use crate::ui::command::{UiCommand, UiCommandData};
 
 #[derive(Clone, Copy, Debug, Enum, Eq, PartialEq, Ord, PartialOrd)]
 enum State {
@@ -9,21 +13,37 @@ enum State {
     Down,
 }
 
+/// Text drawn centered over the button.
+pub struct Text {
+    pub text: BString,
+    pub font: FontKey,
+    pub color: Rgb15,
+}
+
 pub struct Button {
     sprites: EnumMap<State, Sprite>,
     state: State,
+    command: Option<UiCommandData>,
+    text: Option<Text>,
 }
 
 impl Button {
-    pub fn new(up: FrameId, down: FrameId) -> Self {
+    /// `command` is sent when the button is clicked.
+    pub fn new(up: FrameId, down: FrameId, command: Option<UiCommandData>) -> Self {
         Self {
             sprites: enum_map! {
                 State::Up => Sprite::new(up),
                 State::Down => Sprite::new(down),
             },
             state: State::Up,
+            command,
+            text: None,
         }
     }
+
+    pub fn set_text(&mut self, text: Option<Text>) {
+        self.text = text;
+    }
 }
 
 impl Widget for Button {
@@ -45,7 +65,12 @@ impl Widget for Button {
                 self.state = State::Up;
                 // FIXME should optionally hit test the frame as in original.
                 if ctx.base.rect.contains(pos) {
-                    dbg!("clicked");
+                    if let Some(data) = self.command.clone() {
+                        ctx.out.push(UiCommand {
+                            source: ctx.this,
+                            data,
+                        });
+                    }
                 }
                 ctx.release();
             }
@@ -54,8 +79,18 @@ impl Widget for Button {
     }
 
     fn render(&mut self, ctx: Render) {
+        let rect = ctx.base.unwrap().rect;
         let sprite = &mut self.sprites[self.state];
-        sprite.pos = ctx.base.unwrap().rect.top_left();
+        sprite.pos = rect.top_left();
         sprite.render(ctx.canvas, ctx.frm_db);
+
+        if let Some(text) = &self.text {
+            let center = rect.top_left() + Point::new(rect.width() / 2, rect.height() / 2);
+            ctx.canvas.draw_text(&text.text, center, text.font, text.color, &DrawOptions {
+                horz_align: HorzAlign::Center,
+                vert_align: VertAlign::Middle,
+                ..Default::default()
+            });
+        }
     }
 }
\ No newline at end of file
@@ -27,6 +27,20 @@ pub enum UiCommandData {
         id: u32,
     },
     Scroll,
+    Barter(crate::game::ui::barter::Command),
+    CalledShot(crate::game::ui::called_shot::Command),
+    Character(crate::game::ui::character::Command),
+    CombatControl(crate::game::ui::combat_control::Command),
+    Elevator(crate::game::ui::elevator::Command),
+    Encounter(crate::game::ui::encounter::Command),
+    Hud(crate::game::ui::hud::Command),
+    Inventory(crate::game::ui::inventory::Command),
+    InventoryList(crate::game::ui::inventory_list::Command),
+    Loot(crate::game::ui::loot::Command),
+    PerkSelect(crate::game::ui::perk_select::Command),
+    SaveLoad(crate::game::ui::save_load::Command),
+    Skilldex(crate::game::ui::skilldex::Command),
+    WorldMap(crate::game::ui::worldmap::Command),
 
     #[doc(hidden)]
     __NonExhaustive,
@@ -37,4 +51,6 @@ pub enum ObjectPickKind {
     Hover,
     DefaultAction,
     ActionMenu,
+    /// Target of the skill chosen in the skilldex.
+    Skill,
 }
\ No newline at end of file
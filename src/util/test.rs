@@ -1,8 +1,213 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use enum_map::EnumMap;
 use flate2::bufread::GzDecoder;
+use std::collections::HashMap;
 use std::io::Read;
+use std::rc::Rc;
+use std::time::Instant;
+
+use crate::asset::{CritterAnim, EntityKind, ItemKind, SceneryKind, WeaponKind};
+use crate::asset::ai::AiPackets;
+use crate::asset::frame::{FrameDb, FrameId};
+use crate::asset::message::Messages;
+use crate::asset::proto::{CritterKillKind, ProtoDb, ProtoId, proto_entity_kinds};
+use crate::asset::script::ProgramId;
+use crate::asset::script::db::ScriptDb;
+use crate::asset::worldmap::WorldMapDef;
+use crate::fs::{self, FileSystem};
+use crate::game::party::Party;
+use crate::game::script::Scripts;
+use crate::game::world::World;
+use crate::game::worldmap::WorldMap;
+use crate::graphics::Rect;
+use crate::graphics::font::Fonts;
+use crate::graphics::geometry::hex::TileGrid;
+use crate::graphics::render::TextureFactory;
+use crate::util::EnumExt;
+use crate::vm::Vm;
 
 pub fn ungz(buf: &[u8]) -> Vec<u8> {
     let mut r = Vec::new();
     GzDecoder::new(buf).read_to_end(&mut r).unwrap();
     r
-}
\ No newline at end of file
+}
+
+/// In-memory game data to build the asset databases from. Every list and message file
+/// the databases read exists and is empty unless filled with the `add_*()` methods.
+pub struct TestAssets {
+    files: HashMap<String, Vec<u8>>,
+    protos: EnumMap<EntityKind, Vec<String>>,
+    frms: EnumMap<EntityKind, Vec<String>>,
+    programs: Vec<String>,
+}
+
+impl TestAssets {
+    pub fn new() -> Self {
+        Self {
+            files: HashMap::new(),
+            protos: EnumMap::new(),
+            frms: EnumMap::new(),
+            programs: Vec::new(),
+        }
+    }
+
+    /// Adds single frame 1x1 image that is the same in all directions.
+    pub fn add_frm(&mut self, kind: EntityKind) -> FrameId {
+        self.add_anim_frm(kind, 1)
+    }
+
+    /// Same as `add_frm()` but the image has `frame_count` frames.
+    pub fn add_anim_frm(&mut self, kind: EntityKind, frame_count: u16) -> FrameId {
+        let id = self.frms[kind].len();
+        let name = format!("test{}.frm", id);
+
+        let mut frm = Vec::new();
+        frm.write_u32::<BigEndian>(4).unwrap();
+        frm.write_u16::<BigEndian>(10).unwrap();
+        frm.write_u16::<BigEndian>(0).unwrap();
+        frm.write_u16::<BigEndian>(frame_count).unwrap();
+        // Centers and frame offsets for all 6 directions.
+        frm.extend_from_slice(&[0; 6 * 2 * 2 + 6 * 4]);
+        frm.write_u32::<BigEndian>(13 * frame_count as u32).unwrap();
+        for _ in 0..frame_count {
+            frm.write_u16::<BigEndian>(1).unwrap();
+            frm.write_u16::<BigEndian>(1).unwrap();
+            frm.write_u32::<BigEndian>(1).unwrap();
+            frm.write_u32::<BigEndian>(0).unwrap();
+            frm.push(1);
+        }
+
+        self.files.insert(format!("art/{}/{}", kind.dir(), name), frm);
+        self.frms[kind].push(name);
+        FrameId::new_generic(kind, id as u16).unwrap()
+    }
+
+    pub fn add_key(&mut self, fid: FrameId, key_code: i32) -> ProtoId {
+        self.add_proto(EntityKind::Item, fid, |w| {
+            w.write_u32::<BigEndian>(ItemKind::Key as u32).unwrap();
+            // Material, size, weight, price.
+            w.extend_from_slice(&[0; 4 * 4]);
+            w.write_i32::<BigEndian>(-1).unwrap();
+            w.push(0);
+            w.write_i32::<BigEndian>(key_code).unwrap();
+        })
+    }
+
+    /// Adds door scenery proto. `door_flags` is the door specific flags field.
+    pub fn add_door(&mut self, fid: FrameId, door_flags: u32) -> ProtoId {
+        self.add_proto(EntityKind::Scenery, fid, |w| {
+            w.write_u32::<BigEndian>(SceneryKind::Door as u32).unwrap();
+            w.write_u32::<BigEndian>(0).unwrap();
+            w.push(0);
+            w.write_u32::<BigEndian>(door_flags).unwrap();
+            w.write_u32::<BigEndian>(0).unwrap();
+        })
+    }
+
+    pub fn add_critter(&mut self, body_kind: u32, kill_kind: CritterKillKind) -> ProtoId {
+        let fid = FrameId::new_critter(None, CritterAnim::Stand, WeaponKind::Unarmed, 0).unwrap();
+        self.add_proto(EntityKind::Critter, fid, |w| {
+            w.write_i32::<BigEndian>(-1).unwrap();
+            // AI packet, team, flags.
+            w.extend_from_slice(&[0; 3 * 4]);
+            // Base and bonus stats, skills.
+            w.extend_from_slice(&[0; (35 + 35 + 18) * 4]);
+            w.write_u32::<BigEndian>(body_kind).unwrap();
+            w.write_i32::<BigEndian>(0).unwrap();
+            w.write_u32::<BigEndian>(kill_kind as u32).unwrap();
+            w.write_u32::<BigEndian>(0).unwrap();
+        })
+    }
+
+    /// Adds program that has no procedures.
+    pub fn add_program(&mut self, local_var_count: usize) -> ProgramId {
+        let name = format!("test{}", self.programs.len());
+
+        let mut code = vec![0; 42];
+        code.write_u32::<BigEndian>(0).unwrap();
+        // Empty name and string tables.
+        code.write_u32::<BigEndian>(0xffff_ffff).unwrap();
+        code.write_u32::<BigEndian>(0xffff_ffff).unwrap();
+
+        self.files.insert(format!("scripts/{}.int", name), code);
+        self.programs.push(format!("{}.int ; # local_vars={}", name, local_var_count));
+        ProgramId::new(self.programs.len() as u32).unwrap()
+    }
+
+    pub fn fs(&self) -> Rc<FileSystem> {
+        let mut files = self.files.clone();
+        for kind in EntityKind::iter() {
+            files.insert(format!("art/{0}/{0}.lst", kind.dir()), lst(&self.frms[kind]));
+        }
+        for kind in proto_entity_kinds() {
+            files.insert(format!("proto/{0}/{0}.lst", kind.dir()), lst(&self.protos[kind]));
+            files.insert(format!("text/english/game/pro_{}.msg", &kind.dir()[..4]), Vec::new());
+        }
+        for name in &["perk", "proto", "skill", "stat", "trait"] {
+            files.insert(format!("text/english/game/{}.msg", name), Vec::new());
+        }
+        files.insert("scripts/scripts.lst".into(), lst(&self.programs));
+
+        let mut fs = FileSystem::new();
+        fs.register_provider(fs::mem::new_provider(files));
+        Rc::new(fs)
+    }
+
+    pub fn proto_db(&self) -> Rc<ProtoDb> {
+        Rc::new(ProtoDb::new(self.fs(), "english").unwrap())
+    }
+
+    pub fn frm_db(&self) -> Rc<FrameDb> {
+        Rc::new(FrameDb::new(self.fs(), "english", TextureFactory::new_detached()).unwrap())
+    }
+
+    pub fn scripts(&self, proto_db: Rc<ProtoDb>) -> Scripts {
+        Scripts::new(proto_db, ScriptDb::new(self.fs(), "english").unwrap(), Vm::default())
+    }
+
+    /// World with empty map, world map and party.
+    pub fn world(&self) -> World {
+        let world_map_def = WorldMapDef {
+            terrains: Vec::new(),
+            tile_cols: 1,
+            tiles: Vec::new(),
+            encounter_tables: Vec::new(),
+            critter_groups: Vec::new(),
+        };
+        World::new(
+            self.proto_db(),
+            self.frm_db(),
+            Messages::default(),
+            AiPackets::default(),
+            TileGrid::default(),
+            Rect::with_size(0, 0, 640, 380),
+            Instant::now(),
+            Rc::new(Fonts::new()),
+            WorldMap::new(Rc::new(world_map_def), Vec::new().into()),
+            Party::new(Vec::new()))
+    }
+
+    fn add_proto(&mut self, kind: EntityKind, fid: FrameId, write_sub: impl FnOnce(&mut Vec<u8>))
+        -> ProtoId
+    {
+        let pid = ProtoId::new(kind, self.protos[kind].len() as u32).unwrap();
+        let name = format!("{:08}.pro", pid.id() + 1);
+
+        let mut w = Vec::new();
+        w.write_u32::<BigEndian>(pid.pack()).unwrap();
+        w.write_i32::<BigEndian>(0).unwrap();
+        w.write_u32::<BigEndian>(fid.packed()).unwrap();
+        // Light radius and intensity, flags and extended flags.
+        w.extend_from_slice(&[0; 4 * 4]);
+        w.write_i32::<BigEndian>(-1).unwrap();
+        write_sub(&mut w);
+
+        self.files.insert(format!("proto/{}/{}", kind.dir(), name), w);
+        self.protos[kind].push(name);
+        pid
+    }
+}
+
+fn lst(lines: &[String]) -> Vec<u8> {
+    lines.join("\n").into_bytes()
+}
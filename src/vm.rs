@@ -143,9 +143,9 @@ impl PredefinedProc {
     pub fn name(&self) -> &'static str {
         use PredefinedProc::*;
         match self {
-            Combat => "combat_is_over_p_proc",
-            CombatIsOver => "combat_is_starting_p_proc",
-            CombatIsStarting => "combat_p_proc",
+            Combat => "combat_p_proc",
+            CombatIsOver => "combat_is_over_p_proc",
+            CombatIsStarting => "combat_is_starting_p_proc",
             Create => "create_p_proc",
             Critter => "critter_p_proc",
             Damage => "damage_p_proc",
@@ -163,8 +163,8 @@ impl PredefinedProc {
             Start => "start",
             Talk => "talk_p_proc",
             TimedEvent => "timed_event_p_proc",
-            Use => "use_obj_on_p_proc",
-            UseObjOn => "use_p_proc",
+            Use => "use_p_proc",
+            UseObjOn => "use_obj_on_p_proc",
             UseSkillOn => "use_skill_on_p_proc",
         }
     }
@@ -216,9 +216,13 @@ pub struct Context<'a> {
     pub external_vars: &'a mut HashMap<Rc<BString>, Option<Value>>,
 
     pub self_obj: Option<object::Handle>,
+    pub fixed_param: i32,
+    pub source_obj: Option<object::Handle>,
+    pub action_being_used: i32,
     pub ui: &'a mut crate::ui::Ui,
     pub world: &'a mut crate::game::world::World,
     pub sequencer: &'a mut crate::sequence::Sequencer,
+    pub stats: &'a mut crate::game::stats::Stats,
     pub dialog: &'a mut Option<crate::game::dialog::Dialog>,
     pub message_panel: crate::ui::Handle,
     pub script_db: &'a mut crate::asset::script::db::ScriptDb,
@@ -710,6 +714,11 @@ impl Vm {
         Handle(k)
     }
 
+    pub fn remove(&mut self, program: Handle) {
+        self.program_handles.remove(program.0);
+        self.program_states.remove(program.0);
+    }
+
     pub fn run(&mut self, program: Handle, ctx: &mut Context) -> Result<InvocationResult> {
         self.program_state_mut(program).run(ctx)
     }
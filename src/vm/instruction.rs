@@ -403,7 +403,7 @@ pub(in super) mod instructions {
     use self::impls::*;
 
     pub static INSTRUCTIONS: [Instruction; enum_len!(Opcode)] = [
-        i!(ActionBeingUsed,             unimplemented),
+        i!(ActionBeingUsed,             action_being_used),
         i!(Activateregion,              unimplemented),
         i!(Add,                         add),
         i!(Addbutton,                   unimplemented),
@@ -416,7 +416,7 @@ pub(in super) mod instructions {
         i!(AddMultObjsToInven,          add_mult_objs_to_inven),
         i!(Addnamedevent,               unimplemented),
         i!(Addnamedhandler,             unimplemented),
-        i!(AddObjToInven,               unimplemented),
+        i!(AddObjToInven,               add_obj_to_inven),
         i!(Addregion,                   unimplemented),
         i!(Addregionflag,               unimplemented),
         i!(Addregionproc,               unimplemented),
@@ -431,9 +431,9 @@ pub(in super) mod instructions {
         i!(AnimBusy,                    unimplemented),
         i!(ArtAnim,                     unimplemented),
         i!(AToD,                        atod),
-        i!(Attack,                      unimplemented),
+        i!(Attack,                      attack),
         i!(Attack80dd,                  unimplemented),
-        i!(AttackSetup,                 unimplemented),
+        i!(AttackSetup,                 attack_setup),
         i!(Bwand,                       bwand),
         i!(Bwnot,                       bwnot),
         i!(Bwor,                        bwor),
@@ -448,7 +448,7 @@ pub(in super) mod instructions {
         i!(Checkregion,                 unimplemented),
         i!(Clearnamed,                  unimplemented),
         i!(CombatDifficulty,            unimplemented),
-        i!(CombatIsInitialized,         unimplemented),
+        i!(CombatIsInitialized,         combat_is_initialized),
         i!(ConstFloat,                  const_float),
         i!(ConstLong,                   const_int),
         i!(ConstShort,                  const_int),
@@ -461,16 +461,16 @@ pub(in super) mod instructions {
         i!(CriticalStart804a,           noop),
         i!(CritterAddTrait,             critter_add_trait),
         i!(CritterAttemptPlacement,     unimplemented),
-        i!(CritterDamage,               unimplemented),
-        i!(CritterHeal,                 unimplemented),
-        i!(CritterInjure,               unimplemented),
+        i!(CritterDamage,               critter_damage),
+        i!(CritterHeal,                 critter_heal),
+        i!(CritterInjure,               critter_injure),
         i!(CritterInvenObj,             critter_inven_obj),
-        i!(CritterIsFleeing,            unimplemented),
-        i!(CritterModSkill,             unimplemented),
-        i!(CritterRmTrait,              unimplemented),
-        i!(CritterSetFleeState,         unimplemented),
+        i!(CritterIsFleeing,            critter_is_fleeing),
+        i!(CritterModSkill,             critter_mod_skill),
+        i!(CritterRmTrait,              critter_rm_trait),
+        i!(CritterSetFleeState,         critter_set_flee_state),
         i!(CritterState,                unimplemented),
-        i!(CritterStopAttacking,        unimplemented),
+        i!(CritterStopAttacking,        critter_stop_attacking),
         i!(CurMapIndex,                 cur_map_index),
         i!(DaysSinceVisited,            unimplemented),
         i!(DebugMsg,                    debug_msg),
@@ -490,7 +490,7 @@ pub(in super) mod instructions {
         i!(Displayraw,                  unimplemented),
         i!(Div,                         div),
         i!(DoCheck,                     unimplemented),
-        i!(DropObj,                     unimplemented),
+        i!(DropObj,                     drop_obj),
         i!(DToA,                        dtoa),
         i!(DudeObj,                     dude_obj),
         i!(Dump,                        unimplemented),
@@ -503,7 +503,7 @@ pub(in super) mod instructions {
         i!(Exec,                        unimplemented),
         i!(Exit,                        unimplemented),
         i!(ExitProg,                    exit_prog),
-        i!(Explosion,                   unimplemented),
+        i!(Explosion,                   explosion),
         i!(ExportProc,                  unimplemented),
         i!(ExportVar,                   export_var),
         i!(Fadein,                      unimplemented),
@@ -515,7 +515,7 @@ pub(in super) mod instructions {
         i!(Fillrect,                    unimplemented),
         i!(Fillwin,                     unimplemented),
         i!(Fillwin3X3,                  unimplemented),
-        i!(FixedParam,                  unimplemented),
+        i!(FixedParam,                  fixed_param),
         i!(FloatMsg,                    float_msg),
         i!(Floor,                       unimplemented),
         i!(Fork,                        unimplemented),
@@ -528,17 +528,17 @@ pub(in super) mod instructions {
         i!(GameUiDisable,               unimplemented),
         i!(GameUiEnable,                unimplemented),
         i!(GameUiIsDisabled,            unimplemented),
-        i!(GdialogBarter,               unimplemented),
-        i!(GdialogSetBarterMod,         unimplemented),
+        i!(GdialogBarter,               gdialog_barter),
+        i!(GdialogSetBarterMod,         gdialog_set_barter_mod),
         i!(GetCritterStat,              get_critter_stat),
         i!(GetDay,                      get_day),
         i!(GetMonth,                    get_month),
-        i!(GetPcStat,                   unimplemented),
+        i!(GetPcStat,                   get_pc_stat),
         i!(GetPoison,                   unimplemented),
         i!(GfadeIn,                     unimplemented),
         i!(GfadeOut,                    unimplemented),
         i!(GiqOption,                   giq_option),
-        i!(GiveExpPoints,               unimplemented),
+        i!(GiveExpPoints,               give_exp_points),
         i!(GlobalVar,                   global_var),
         i!(Gotoxy,                      unimplemented),
         i!(Greater,                     greater),
@@ -548,29 +548,29 @@ pub(in super) mod instructions {
         i!(GsayOption,                  unimplemented),
         i!(GsayReply,                   gsay_reply),
         i!(GsayStart,                   gsay_start),
-        i!(HasSkill,                    unimplemented),
+        i!(HasSkill,                    has_skill),
         i!(HasTrait,                    has_trait),
         i!(Hidemouse,                   unimplemented),
         i!(HowMuch,                     unimplemented),
         i!(If,                          if_),
-        i!(InvenCmds,                   unimplemented),
-        i!(InvenUnwield,                unimplemented),
-        i!(IsCritical,                  unimplemented),
-        i!(IsSuccess,                   unimplemented),
-        i!(ItemCapsAdjust,              unimplemented),
-        i!(ItemCapsTotal,               unimplemented),
-        i!(JamLock,                     unimplemented),
+        i!(InvenCmds,                   inven_cmds),
+        i!(InvenUnwield,                inven_unwield),
+        i!(IsCritical,                  is_critical),
+        i!(IsSuccess,                   is_success),
+        i!(ItemCapsAdjust,              item_caps_adjust),
+        i!(ItemCapsTotal,               item_caps_total),
+        i!(JamLock,                     jam_lock),
         i!(Jmp,                         jmp),
-        i!(KillCritter,                 unimplemented),
-        i!(KillCritterType,             unimplemented),
+        i!(KillCritter,                 kill_critter),
+        i!(KillCritterType,             kill_critter_type),
         i!(Less,                        less),
         i!(LessEqual,                   less_equal),
-        i!(LoadMap,                     unimplemented),
+        i!(LoadMap,                     load_map),
         i!(Loadpalettetable,            unimplemented),
         i!(LocalVar,                    local_var),
         i!(LookupStringProc,            unimplemented),
         i!(MapVar,                      map_var),
-        i!(MarkAreaKnown,               unimplemented),
+        i!(MarkAreaKnown,               mark_area_known),
         i!(MessageStr,                  message_str),
         i!(Metarule,                    metarule),
         i!(Metarule3,                   metarule3),
@@ -589,26 +589,26 @@ pub(in super) mod instructions {
         i!(ObjBeingUsedWith,            unimplemented),
         i!(ObjCanHearObj,               unimplemented),
         i!(ObjCanSeeObj,                obj_can_see_obj),
-        i!(ObjCarryingPidObj,           unimplemented),
-        i!(ObjClose,                    unimplemented),
+        i!(ObjCarryingPidObj,           obj_carrying_pid_obj),
+        i!(ObjClose,                    obj_close),
         i!(ObjIsCarryingObjPid,         obj_is_carrying_obj_pid),
-        i!(ObjIsLocked,                 unimplemented),
-        i!(ObjIsOpen,                   unimplemented),
+        i!(ObjIsLocked,                 obj_is_locked),
+        i!(ObjIsOpen,                   obj_is_open),
         i!(ObjItemSubtype,              unimplemented),
         i!(ObjLock,                     obj_lock),
         i!(ObjName,                     obj_name),
         i!(ObjOnScreen,                 obj_on_screen),
-        i!(ObjOpen,                     unimplemented),
+        i!(ObjOpen,                     obj_open),
         i!(ObjPid,                      obj_pid),
         i!(ObjSetLightLevel,            unimplemented),
         i!(ObjType,                     unimplemented),
         i!(ObjUnlock,                   obj_unlock),
         i!(Or,                          or),
         i!(OverrideMapStart,            override_map_start),
-        i!(PartyAdd,                    unimplemented),
+        i!(PartyAdd,                    party_add),
         i!(PartyMemberObj,              party_member_obj),
-        i!(PartyRemove,                 unimplemented),
-        i!(PickupObj,                   unimplemented),
+        i!(PartyRemove,                 party_remove),
+        i!(PickupObj,                   pickup_obj),
         i!(PlayGmovie,                  unimplemented),
         i!(Playmovie,                   unimplemented),
         i!(Playmovierect,               unimplemented),
@@ -647,11 +647,11 @@ pub(in super) mod instructions {
         i!(RegAnimObjRunToTile,         unimplemented),
         i!(RegAnimPlaySfx,              unimplemented),
         i!(Resizewin,                   unimplemented),
-        i!(RmMultObjsFromInven,         unimplemented),
-        i!(RmObjFromInven,              unimplemented),
-        i!(RmTimerEvent,                unimplemented),
+        i!(RmMultObjsFromInven,         rm_mult_objs_from_inven),
+        i!(RmObjFromInven,              rm_obj_from_inven),
+        i!(RmTimerEvent,                rm_timer_event),
         i!(RollDice,                    unimplemented),
-        i!(RollVsSkill,                 unimplemented),
+        i!(RollVsSkill,                 roll_vs_skill),
         i!(RotationToTile,              unimplemented),
         i!(RunningBurningGuy,           unimplemented),
         i!(Sayborder,                   unimplemented),
@@ -683,8 +683,8 @@ pub(in super) mod instructions {
         i!(Selectfilelist,              unimplemented),
         i!(Selectwin,                   unimplemented),
         i!(SelfObj,                     self_obj),
-        i!(SetCritterStat,              unimplemented),
-        i!(SetExitGrids,                unimplemented),
+        i!(SetCritterStat,              set_critter_stat),
+        i!(SetExitGrids,                set_exit_grids),
         i!(Setfont,                     unimplemented),
         i!(SetGlobal,                   set_global),
         i!(Setglobalmousefunc,          unimplemented),
@@ -709,14 +709,14 @@ pub(in super) mod instructions {
         i!(Showmouse,                   unimplemented),
         i!(Showwin,                     unimplemented),
         i!(Signalnamed,                 unimplemented),
-        i!(SkillContest,                unimplemented),
+        i!(SkillContest,                skill_contest),
         i!(Sounddelete,                 unimplemented),
         i!(Soundpause,                  unimplemented),
         i!(Soundplay,                   unimplemented),
         i!(Soundresume,                 unimplemented),
         i!(Soundrewind,                 unimplemented),
         i!(Soundstop,                   unimplemented),
-        i!(SourceObj,                   unimplemented),
+        i!(SourceObj,                   source_obj),
         i!(Spawn,                       unimplemented),
         i!(StartGdialog,                start_gdialog),
         i!(Stopmovie,                   unimplemented),
@@ -728,7 +728,7 @@ pub(in super) mod instructions {
         i!(Swap,                        swap),
         i!(Swapa,                       swapa),
         i!(TargetObj,                   unimplemented),
-        i!(TerminateCombat,             unimplemented),
+        i!(TerminateCombat,             terminate_combat),
         i!(TileContainsObjPid,          tile_contains_pid_obj),
         i!(TileContainsPidObj,          tile_contains_pid_obj),
         i!(TileDistance,                unimplemented),
@@ -740,12 +740,12 @@ pub(in super) mod instructions {
         i!(Tokenize,                    unimplemented),
         i!(UseObj,                      unimplemented),
         i!(UseObjOnObj,                 unimplemented),
-        i!(UsingSkill,                  unimplemented),
+        i!(UsingSkill,                  using_skill),
         i!(Wait,                        unimplemented),
         i!(While,                       while_),
-        i!(WieldObjCritter,             unimplemented),
-        i!(WmAreaSetPos,                unimplemented),
-        i!(WorldMap,                    unimplemented),
+        i!(WieldObjCritter,             wield_obj_critter),
+        i!(WmAreaSetPos,                wm_area_set_pos),
+        i!(WorldMap,                    world_map),
     ];
 }
 
@@ -392,6 +392,13 @@ pub fn script_overrides(ctx: Context) -> Result<()> {
     Ok(())
 }
 
+pub fn source_obj(ctx: Context) -> Result<()> {
+    ctx.prg.data_stack.push(Value::Object(ctx.ext.source_obj))?;
+    log_r1!(ctx.prg, ctx.prg.data_stack.top().unwrap());
+
+    Ok(())
+}
+
 pub fn store(ctx: Context) -> Result<()> {
     let id = ctx.prg.data_stack.pop()?.into_int()? as usize;
     let value = ctx.prg.data_stack.pop()?;
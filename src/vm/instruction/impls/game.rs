@@ -1,4 +1,5 @@
 use enum_map_derive::Enum;
+use enumflags2::BitFlags;
 use enum_primitive_derive::Primitive;
 use log::*;
 use num_traits::FromPrimitive;
@@ -6,13 +7,20 @@ use std::cmp;
 use std::convert::TryInto;
 
 use super::*;
-use crate::asset::{Perk, Stat, Trait};
+use crate::asset::{CritterAnim, DamageKind, EntityKind, Flag, PcStat, Perk, Skill, Stat, Trait};
+use crate::asset::map::ELEVATION_COUNT;
 use crate::asset::proto::ProtoId;
 use crate::asset::script::ProgramId;
 use crate::game::dialog::Dialog;
-use crate::game::object::{Object, ObjectProtoId};
+use crate::game::object::{self, DamageFlag, EquipmentSlot, Exit, LockFlag, Maneuver, Object,
+    ObjectProtoId};
 use crate::game::script::Sid;
-use crate::game::world::floating_text;
+use crate::game::sequence::door;
+use crate::game::combat;
+use crate::game::roll::{self, Roll};
+use crate::game::timer;
+use crate::game::world::{floating_text, MapRef, MapTransition};
+use crate::game::worldmap::VisitState;
 use crate::graphics::{EPoint, Point};
 use crate::graphics::color::*;
 use crate::graphics::font::FontKey;
@@ -92,6 +100,12 @@ enum RegAnimFuncOp {
     End = 3,
 }
 
+pub fn action_being_used(ctx: Context) -> Result<()> {
+    ctx.prg.data_stack.push(Value::Int(ctx.ext.action_being_used))?;
+    log_r1!(ctx.prg, ctx.ext.action_being_used);
+    Ok(())
+}
+
 pub fn add_mult_objs_to_inven(ctx: Context) -> Result<()> {
     let count = ctx.prg.data_stack.pop()?.into_int()?;
     let count = if count > 99999 {
@@ -101,24 +115,95 @@ pub fn add_mult_objs_to_inven(ctx: Context) -> Result<()> {
     } else {
         count
     };
-    let item = ctx.prg.data_stack.pop()?.coerce_into_object()?;
-    let target = ctx.prg.data_stack.pop()?.coerce_into_object()?;
+    let item = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+    let target = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
     log_a3!(ctx.prg, target, item, count);
-    log_stub!(ctx.prg);
+
+    if count > 0 {
+        ctx.ext.world.add_to_inventory(target, item, count as usize);
+    }
+
+    Ok(())
+}
+
+pub fn add_obj_to_inven(ctx: Context) -> Result<()> {
+    let item = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+    let target = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a2!(ctx.prg, target, item);
+
+    ctx.ext.world.add_to_inventory(target, item, 1);
+
     Ok(())
 }
 
 pub fn add_timer_event(ctx: Context) -> Result<()> {
-    let flags = ctx.prg.data_stack.pop()?.into_int()?;
+    let fixed_param = ctx.prg.data_stack.pop()?.into_int()?;
     let ticks = ctx.prg.data_stack.pop()?.into_int()?;
     let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?;
 
-    log_a3!(ctx.prg, obj, ticks, flags);
-    log_stub!(ctx.prg);
+    log_a3!(ctx.prg, obj, ticks, fixed_param);
+
+    if let Some(obj) = obj {
+        let world = &mut ctx.ext.world;
+        let time = world.game_time.add_decis(cmp::max(ticks, 0) as u32);
+        world.timer.push(timer::Event {
+            time,
+            obj,
+            kind: timer::EventKind::Script { fixed_param },
+        });
+    } else {
+        log_error!(ctx.prg, "object is null");
+    }
+
+    Ok(())
+}
+
+pub fn attack(ctx: Context) -> Result<()> {
+    let _target_results = ctx.prg.data_stack.pop()?.into_int()?;
+    let _attacker_results = ctx.prg.data_stack.pop()?.into_int()?;
+    let _max_damage = ctx.prg.data_stack.pop()?.into_int()?;
+    let _min_damage = ctx.prg.data_stack.pop()?.into_int()?;
+    let _bonus = ctx.prg.data_stack.pop()?.into_int()?;
+    let _num_attacks = ctx.prg.data_stack.pop()?.into_int()?;
+    let called_shot = ctx.prg.data_stack.pop()?.into_int()?;
+    let target = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a2!(ctx.prg, target, called_shot);
+
+    // TODO handle the rest of parameters
+    let attacker = ctx.ext.self_obj.ok_or(Error::BadValue(BadValue::Content))?;
+    ctx.ext.world.combat_requests.push(combat::Request::Attack { attacker, target });
 
     Ok(())
 }
 
+pub fn attack_setup(ctx: Context) -> Result<()> {
+    let target = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+    let attacker = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a2!(ctx.prg, attacker, target);
+
+    ctx.ext.world.combat_requests.push(combat::Request::Attack { attacker, target });
+
+    Ok(())
+}
+
+pub fn combat_is_initialized(ctx: Context) -> Result<()> {
+    let r = ctx.ext.world.combat.is_some() as i32;
+    ctx.prg.data_stack.push(r.into())?;
+    log_r1!(ctx.prg, r);
+    Ok(())
+}
+
 pub fn create_object_sid(ctx: Context) -> Result<()> {
     let sid = ctx.prg.data_stack.pop()?.into_int()?;
     let sid = if sid >= 0 {
@@ -136,10 +221,18 @@ pub fn create_object_sid(ctx: Context) -> Result<()> {
         .ok_or(Error::BadValue(BadValue::Content))?;
 
     // FIXME add proper impl
-    let fid = ctx.ext.world.proto_db().proto(pid).unwrap().fid;
+    let (fid, health) = {
+        let proto = ctx.ext.world.proto_db().proto(pid).unwrap();
+        let health = proto.sub.critter()
+            .map(|c| c.base_stats[Stat::HitPoints] + c.bonus_stats[Stat::HitPoints]);
+        (proto.fid, health)
+    };
     let pos = ctx.ext.world.hex_grid().from_linear_inv(tile_num);
     let pos = pos.elevated(elevation);
-    let obj = Object::new(fid, pid.into(), Some(pos));
+    let mut obj = Object::new(fid, pid.into(), Some(pos));
+    if let (Some(critter), Some(health)) = (obj.sub.critter_mut(), health) {
+        critter.health = health;
+    }
     let objh = ctx.ext.world.insert_object(obj);
 
     ctx.prg.data_stack.push(Value::Object(Some(objh)))?;
@@ -152,27 +245,236 @@ pub fn create_object_sid(ctx: Context) -> Result<()> {
 
 pub fn critter_add_trait(ctx: Context) -> Result<()> {
     let value = ctx.prg.data_stack.pop()?.into_int()?;
-    let sub_kind = ctx.prg.data_stack.pop()?.into_int()?;
     let kind = ctx.prg.data_stack.pop()?.into_int()?;
-    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?;
+    let family = TraitFamily::new(ctx.prg.data_stack.pop()?.into_int()?, kind);
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
 
-    let r = -1;
+    let r = {
+        let mut objr = ctx.ext.world.objects().get(obj).borrow_mut();
+        let pid = objr.pid;
+        match (family, objr.sub.critter_mut()) {
+            (TraitFamily::Perk(perk), Some(_)) => {
+                if value != 0 {
+                    ctx.ext.stats.add_perk(perk, pid);
+                } else if !ctx.ext.stats.remove_perk(perk, pid) {
+                    log_error!(ctx.prg, "critter doesn't have the perk");
+                }
+                0
+            }
+            (TraitFamily::Object(ObjectTrait::AiPacket), Some(critter)) => {
+                critter.combat.ai_packet = value as u32;
+                0
+            }
+            (TraitFamily::Object(ObjectTrait::TeamNum), Some(critter)) => {
+                critter.combat.team_id = value as u32;
+                0
+            }
+            _ => {
+                log_error!(ctx.prg, "unsupported trait");
+                -1
+            }
+        }
+    };
     ctx.prg.data_stack.push(r.into())?;
 
-    log_a4r1!(ctx.prg, obj, kind, sub_kind, value, r);
-    log_stub!(ctx.prg);
+    log_a3r1!(ctx.prg, obj, family, value, r);
+
+    Ok(())
+}
+
+pub fn critter_damage(ctx: Context) -> Result<()> {
+    const BYPASS_ARMOR: i32 = 0x100;
+    const NO_ANIMATE: i32 = 0x200;
+
+    let kind_and_flags = ctx.prg.data_stack.pop()?.into_int()?;
+    let damage = ctx.prg.data_stack.pop()?.into_int()?;
+    let target = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a3!(ctx.prg, target, damage, kind_and_flags);
+
+    let kind = DamageKind::from_i32(kind_and_flags & !(BYPASS_ARMOR | NO_ANIMATE))
+        .ok_or(Error::BadValue(BadValue::Content))?;
+    if ctx.ext.world.objects().get(target).borrow().sub.critter().is_some() {
+        ctx.ext.world.combat_requests.push(combat::Request::Damage {
+            target,
+            damage,
+            kind,
+            bypass: kind_and_flags & BYPASS_ARMOR != 0,
+            animate: kind_and_flags & NO_ANIMATE == 0,
+        });
+    } else {
+        log_error!(ctx.prg, "object is not a critter");
+    }
+
+    Ok(())
+}
+
+pub fn critter_heal(ctx: Context) -> Result<()> {
+    let amount = ctx.prg.data_stack.pop()?.into_int()?;
+    let target = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a2!(ctx.prg, target, amount);
+
+    let world = &*ctx.ext.world;
+    let mut obj = world.objects().get(target).borrow_mut();
+    let max_health = ctx.ext.stats.stat(Stat::HitPoints, &obj, world);
+    if let Some(critter) = obj.sub.critter_mut() {
+        if !critter.is_dead() {
+            critter.health = cmp::min(critter.health + amount, max_health);
+        }
+    } else {
+        log_error!(ctx.prg, "object is not a critter");
+    }
+
+    Ok(())
+}
+
+pub fn critter_injure(ctx: Context) -> Result<()> {
+    let flags = ctx.prg.data_stack.pop()?.into_int()?;
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a2!(ctx.prg, obj, flags);
+
+    let flags = BitFlags::<DamageFlag>::from_bits_truncate(flags as u32);
+    let injuries = flags & (DamageFlag::CripLegLeft
+        | DamageFlag::CripLegRight
+        | DamageFlag::CripArmLeft
+        | DamageFlag::CripArmRight
+        | DamageFlag::Blind);
+    if let Some(critter) = ctx.ext.world.objects().get(obj).borrow_mut().sub.critter_mut() {
+        if flags.contains(DamageFlag::PerformReverse) {
+            critter.combat.damage_flags.remove(injuries);
+        } else {
+            critter.combat.damage_flags.insert(injuries);
+        }
+    } else {
+        log_error!(ctx.prg, "object is not a critter");
+    }
+
     Ok(())
 }
 
 pub fn critter_inven_obj(ctx: Context) -> Result<()> {
     let query = ctx.prg.data_stack.pop()?.into_int()?;
-    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?;
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
 
-    let r = 0;
+    let objects = ctx.ext.world.objects();
+    let slot = |slot| Value::Object(objects.equipped(obj, slot));
+    let r = match query {
+        0 => slot(EquipmentSlot::Armor),
+        1 => slot(EquipmentSlot::RightHand),
+        2 => slot(EquipmentSlot::LeftHand),
+        -2 => Value::Int(objects.get(obj).borrow().inventory.items.len() as i32),
+        _ => {
+            log_error!(ctx.prg, "invalid query");
+            Value::Int(0)
+        }
+    };
+    ctx.prg.data_stack.push(r)?;
+
+    log_a2r1!(ctx.prg, obj, query, ctx.prg.data_stack.top().unwrap());
+    Ok(())
+}
+
+pub fn critter_is_fleeing(ctx: Context) -> Result<()> {
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    let r = ctx.ext.world.objects().get(obj).borrow().sub.critter()
+        .map(|c| c.combat.maneuver.contains(Maneuver::Fleeing))
+        .unwrap_or(false) as i32;
     ctx.prg.data_stack.push(r.into())?;
 
-    log_a2r1!(ctx.prg, obj, query, r);
-    log_stub!(ctx.prg);
+    log_a1r1!(ctx.prg, obj, r);
+    Ok(())
+}
+
+pub fn critter_mod_skill(ctx: Context) -> Result<()> {
+    let amount = ctx.prg.data_stack.pop()?.into_int()?;
+    let skill = Skill::from_i32(ctx.prg.data_stack.pop()?.into_int()?)
+        .ok_or(Error::BadValue(BadValue::Content))?;
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a3!(ctx.prg, obj, skill, amount);
+
+    if Some(obj) == ctx.ext.world.dude_obj() {
+        ctx.ext.stats.mod_dude_skill(skill, amount);
+    } else {
+        log_error!(ctx.prg, "can't modify skills of anyone except dude");
+    }
+    ctx.prg.data_stack.push(0.into())?;
+
+    Ok(())
+}
+
+pub fn critter_rm_trait(ctx: Context) -> Result<()> {
+    let value = ctx.prg.data_stack.pop()?.into_int()?;
+    let kind = ctx.prg.data_stack.pop()?.into_int()?;
+    let family = TraitFamily::new(ctx.prg.data_stack.pop()?.into_int()?, kind);
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    let pid = ctx.ext.world.objects().get(obj).borrow().pid;
+    let r = match family {
+        TraitFamily::Perk(perk) => {
+            while ctx.ext.stats.remove_perk(perk, pid) {}
+            0
+        }
+        TraitFamily::Trait(tr) if Some(obj) == ctx.ext.world.dude_obj() => {
+            ctx.ext.stats.remove_trait(tr);
+            0
+        }
+        _ => {
+            log_error!(ctx.prg, "unsupported trait");
+            -1
+        }
+    };
+    ctx.prg.data_stack.push(r.into())?;
+
+    log_a3r1!(ctx.prg, obj, family, value, r);
+
+    Ok(())
+}
+
+pub fn critter_set_flee_state(ctx: Context) -> Result<()> {
+    let fleeing = ctx.prg.data_stack.pop()?.into_int()? != 0;
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a2!(ctx.prg, obj, fleeing);
+
+    if let Some(critter) = ctx.ext.world.objects().get(obj).borrow_mut().sub.critter_mut() {
+        if fleeing {
+            critter.combat.maneuver.insert(Maneuver::Fleeing);
+        } else {
+            critter.combat.maneuver.remove(Maneuver::Fleeing);
+        }
+    } else {
+        log_error!(ctx.prg, "object is not a critter");
+    }
+
+    Ok(())
+}
+
+pub fn critter_stop_attacking(ctx: Context) -> Result<()> {
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a1!(ctx.prg, obj);
+
+    if let Some(critter) = ctx.ext.world.objects().get(obj).borrow_mut().sub.critter_mut() {
+        critter.combat.maneuver.insert(Maneuver::Disengaging);
+        critter.combat.who_hit_me = None;
+    } else {
+        log_error!(ctx.prg, "object is not a critter");
+    }
+
     Ok(())
 }
 
@@ -186,7 +488,17 @@ pub fn cur_map_index(ctx: Context) -> Result<()> {
 pub fn destroy_object(ctx: Context) -> Result<()> {
     let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?;
     log_a1!(ctx.prg, obj);
-    log_stub!(ctx.prg);
+
+    if let Some(obj) = obj {
+        if Some(obj) == ctx.ext.world.dude_obj() {
+            log_error!(ctx.prg, "can't destroy dude");
+        } else {
+            ctx.ext.world.destroy_object(obj);
+        }
+    } else {
+        log_error!(ctx.prg, "object is null");
+    }
+
     Ok(())
 }
 
@@ -210,6 +522,27 @@ pub fn dude_obj(ctx: Context) -> Result<()> {
     Ok(())
 }
 
+pub fn drop_obj(ctx: Context) -> Result<()> {
+    let item = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a1!(ctx.prg, item);
+
+    let owner = ctx.ext.self_obj.ok_or(Error::BadValue(BadValue::Content))?;
+    let objects = ctx.ext.world.objects_mut();
+    let count = objects.get(owner).borrow().inventory.count(item);
+    if let Some((item, _)) = objects.remove_from_inventory(owner, item, count) {
+        let pos = objects.get(owner).borrow().pos;
+        if let Some(pos) = pos {
+            objects.set_pos(item, pos);
+        }
+    } else {
+        log_error!(ctx.prg, "item is not in inventory of self_obj");
+    }
+
+    Ok(())
+}
+
 pub fn elevation(ctx: Context) -> Result<()> {
     let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
         .ok_or(Error::BadValue(BadValue::Content))?;
@@ -251,6 +584,26 @@ impl FloatingTextStyle {
     const SEQ_MAX: u32 = 12;
 }
 
+pub fn explosion(ctx: Context) -> Result<()> {
+    let damage = ctx.prg.data_stack.pop()?.into_int()?;
+    let elevation = ctx.prg.data_stack.pop()?.into_int()? as u32;
+    let tile_num = ctx.prg.data_stack.pop()?.into_int()?;
+
+    log_a3!(ctx.prg, tile_num, elevation, damage);
+
+    let pos = ctx.ext.world.hex_grid().from_linear_inv(tile_num as u32)
+        .elevated(elevation);
+    ctx.ext.world.combat_requests.push(combat::Request::Explode { pos, damage });
+
+    Ok(())
+}
+
+pub fn fixed_param(ctx: Context) -> Result<()> {
+    ctx.prg.data_stack.push(Value::Int(ctx.ext.fixed_param))?;
+    log_r1!(ctx.prg, ctx.ext.fixed_param);
+    Ok(())
+}
+
 pub fn float_msg(ctx: Context) -> Result<()> {
     let style = FloatingTextStyle::from_i32(ctx.prg.data_stack.pop()?.into_int()?);
     let msg = ctx.prg.data_stack.pop()?.into_string(ctx.prg.strings())?;
@@ -342,14 +695,58 @@ pub fn game_time_in_seconds(ctx: Context) -> Result<()> {
     Ok(())
 }
 
+pub fn gdialog_barter(ctx: Context) -> Result<()> {
+    let barter_mod = ctx.prg.data_stack.pop()?.into_int()?;
+
+    log_a1!(ctx.prg, barter_mod);
+
+    if let Some(dialog) = ctx.ext.dialog.as_mut() {
+        dialog.barter_request = Some(barter_mod);
+    } else {
+        log_error!(ctx.prg, "not in dialog");
+    }
+
+    Ok(())
+}
+
+pub fn gdialog_set_barter_mod(ctx: Context) -> Result<()> {
+    let barter_mod = ctx.prg.data_stack.pop()?.into_int()?;
+
+    log_a1!(ctx.prg, barter_mod);
+
+    if let Some(dialog) = ctx.ext.dialog.as_mut() {
+        dialog.barter_mod = barter_mod;
+    } else {
+        log_error!(ctx.prg, "not in dialog");
+    }
+
+    Ok(())
+}
+
 pub fn get_critter_stat(ctx: Context) -> Result<()> {
     let stat = Stat::from_i32(ctx.prg.data_stack.pop()?.coerce_into_int()?)
         .ok_or(Error::BadValue(BadValue::Content))?;
     let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?;
-    let r = 5;
+
+    let r = if let Some(obj) = obj {
+        let world = &*ctx.ext.world;
+        let objr = world.objects().get(obj).borrow();
+        if objr.sub.critter().is_none() {
+            log_error!(ctx.prg, "object is not a critter");
+            -1
+        } else if stat == Stat::ArmorClass {
+            ctx.ext.stats.armor_class(obj, world)
+        } else {
+            ctx.ext.stats.stat(stat, &objr, world)
+        }
+    } else {
+        log_error!(ctx.prg, "object is null");
+        -1
+    };
     ctx.prg.data_stack.push(Value::Int(r))?;
+
     log_a2r1!(ctx.prg, obj, stat, r);
-    log_stub!(ctx.prg);
+
     Ok(())
 }
 
@@ -367,6 +764,42 @@ pub fn get_month(ctx: Context) -> Result<()> {
     Ok(())
 }
 
+pub fn get_pc_stat(ctx: Context) -> Result<()> {
+    let stat = PcStat::from_i32(ctx.prg.data_stack.pop()?.into_int()?)
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    let r = ctx.ext.stats.pc_stat(stat);
+    ctx.prg.data_stack.push(Value::Int(r))?;
+
+    log_a1r1!(ctx.prg, stat, r);
+
+    Ok(())
+}
+
+pub fn give_exp_points(ctx: Context) -> Result<()> {
+    use crate::ui::message_panel::MessagePanel;
+
+    let amount = ctx.prg.data_stack.pop()?.into_int()?;
+
+    log_a1!(ctx.prg, amount);
+
+    let world = &*ctx.ext.world;
+    let levels = if let Some(dude_obj) = world.dude_obj() {
+        let mut dude_obj = world.objects().get(dude_obj).borrow_mut();
+        ctx.ext.stats.add_experience(amount, &mut dude_obj, world)
+    } else {
+        log_error!(ctx.prg, "no dude");
+        0
+    };
+    if levels > 0 {
+        ctx.ext.ui.widget_mut::<MessagePanel>(ctx.ext.message_panel)
+            .push_message(BString::concat(&[crate::asset::message::BULLET_STR,
+                ctx.ext.stats.level_up_message().as_bytes()]));
+    }
+
+    Ok(())
+}
+
 pub fn giq_option(mut ctx: Context) -> Result<()> {
     // FIXME display reaction with Empathy perk.
     let reaction = ctx.prg.data_stack.pop()?.into_int()?;
@@ -455,6 +888,27 @@ pub fn gsay_reply(mut ctx: Context) -> Result<()> {
     Ok(())
 }
 
+pub fn has_skill(ctx: Context) -> Result<()> {
+    let skill = Skill::from_i32(ctx.prg.data_stack.pop()?.into_int()?)
+        .ok_or(Error::BadValue(BadValue::Content))?;
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    let r = {
+        let obj = ctx.ext.world.objects().get(obj).borrow();
+        if obj.sub.critter().is_some() {
+            ctx.ext.stats.skill(skill, &obj, ctx.ext.world)
+        } else {
+            0
+        }
+    };
+    ctx.prg.data_stack.push(r.into())?;
+
+    log_a2r1!(ctx.prg, obj, skill, r);
+
+    Ok(())
+}
+
 #[derive(Clone, Copy, Debug, Enum, Eq, PartialEq, Primitive)]
 enum TraitFamilyKind {
     Perk = 0,
@@ -473,35 +927,262 @@ enum TraitFamily {
     Unknown(i32),
 }
 
-#[derive(Clone, Copy, Debug, Enum, Eq, PartialEq, Primitive)]
-enum ObjectTrait {
-    AiPacket = 5,
-    TeamNum = 6,
-    Direction = 10,
-    IsTurnedOff = 666,
-    ItemTotalWeight = 669,
-}
+impl TraitFamily {
+    fn new(family_kind: i32, kind: i32) -> Self {
+        match TraitFamilyKind::from_i32(family_kind) {
+            Some(TraitFamilyKind::Perk) => Perk::from_i32(kind)
+                .map(TraitFamily::Perk)
+                .unwrap_or(TraitFamily::PerkUnknown(kind)),
+            Some(TraitFamilyKind::Object) => ObjectTrait::from_i32(kind)
+                .map(TraitFamily::Object)
+                .unwrap_or(TraitFamily::ObjectUnknown(kind)),
+            Some(TraitFamilyKind::Trait) => Trait::from_i32(kind)
+                .map(TraitFamily::Trait)
+                .unwrap_or(TraitFamily::TraitUnknown(kind)),
+            None => TraitFamily::Unknown(family_kind),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Enum, Eq, PartialEq, Primitive)]
+enum ObjectTrait {
+    AiPacket = 5,
+    TeamNum = 6,
+    Direction = 10,
+    IsTurnedOff = 666,
+    ItemTotalWeight = 669,
+}
+
+pub fn has_trait(ctx: Context) -> Result<()> {
+    let kind = ctx.prg.data_stack.pop()?.into_int()?;
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?;
+    let family = TraitFamily::new(ctx.prg.data_stack.pop()?.coerce_into_int()?, kind);
+
+    let r = if let Some(obj) = obj {
+        let world = &*ctx.ext.world;
+        let objr = world.objects().get(obj).borrow();
+        let critter = objr.sub.critter();
+        match family {
+            TraitFamily::Perk(perk) => ctx.ext.stats.perk_rank(perk, objr.pid) as i32,
+            TraitFamily::Object(ObjectTrait::AiPacket) =>
+                critter.map(|c| c.combat.ai_packet as i32).unwrap_or(0),
+            TraitFamily::Object(ObjectTrait::TeamNum) =>
+                critter.map(|c| c.combat.team_id as i32).unwrap_or(0),
+            TraitFamily::Object(ObjectTrait::Direction) => objr.direction as i32,
+            TraitFamily::Object(ObjectTrait::IsTurnedOff) =>
+                !objr.flags.contains(Flag::TurnedOff) as i32,
+            TraitFamily::Object(ObjectTrait::ItemTotalWeight) =>
+                world.objects().items_weight(&objr.inventory) as i32,
+            TraitFamily::Trait(tr) =>
+                (Some(obj) == world.dude_obj() && ctx.ext.stats.has_trait(tr)) as i32,
+            _ => {
+                log_error!(ctx.prg, "unsupported trait");
+                0
+            }
+        }
+    } else {
+        log_error!(ctx.prg, "object is null");
+        0
+    };
+    ctx.prg.data_stack.push(Value::Int(r))?;
+
+    log_a2r1!(ctx.prg, family, obj, r);
+
+    Ok(())
+}
+
+pub fn inven_cmds(ctx: Context) -> Result<()> {
+    let index = ctx.prg.data_stack.pop()?.into_int()?;
+    let cmd = ctx.prg.data_stack.pop()?.into_int()?;
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    // Only INVEN_CMD_INDEX_PTR is supported by the original.
+    let r = if cmd == 13 {
+        let obj = ctx.ext.world.objects().get(obj).borrow();
+        let items = &obj.inventory.items;
+        if index >= 0 && (index as usize) < items.len() {
+            Some(items[index as usize].object)
+        } else {
+            None
+        }
+    } else {
+        log_error!(ctx.prg, "unsupported command");
+        None
+    };
+    ctx.prg.data_stack.push(Value::Object(r))?;
+
+    log_a3r1!(ctx.prg, obj, cmd, index, r);
+    Ok(())
+}
+
+pub fn inven_unwield(ctx: Context) -> Result<()> {
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a1!(ctx.prg, obj);
+
+    ctx.ext.world.objects_mut().unequip(obj, EquipmentSlot::RightHand);
+
+    Ok(())
+}
+
+pub fn is_critical(ctx: Context) -> Result<()> {
+    let roll = Roll::from_i32(ctx.prg.data_stack.pop()?.into_int()?)
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    let r = roll.is_critical();
+    ctx.prg.data_stack.push(Value::Int(r as i32))?;
+
+    log_a1r1!(ctx.prg, roll, r);
+
+    Ok(())
+}
+
+pub fn jam_lock(ctx: Context) -> Result<()> {
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a1!(ctx.prg, obj);
+
+    ctx.ext.world.objects().get(obj).borrow_mut().lock_flags.insert(LockFlag::Jammed);
+
+    Ok(())
+}
+
+pub fn is_success(ctx: Context) -> Result<()> {
+    let roll = Roll::from_i32(ctx.prg.data_stack.pop()?.into_int()?)
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    let r = roll.is_success();
+    ctx.prg.data_stack.push(Value::Int(r as i32))?;
+
+    log_a1r1!(ctx.prg, roll, r);
+
+    Ok(())
+}
+
+pub fn item_caps_adjust(ctx: Context) -> Result<()> {
+    let amount = ctx.prg.data_stack.pop()?.into_int()?;
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    let r = if ctx.ext.world.objects_mut().adjust_caps(obj, amount) {
+        0
+    } else {
+        -1
+    };
+    ctx.prg.data_stack.push(r.into())?;
+
+    log_a2r1!(ctx.prg, obj, amount, r);
+
+    Ok(())
+}
+
+pub fn item_caps_total(ctx: Context) -> Result<()> {
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    let r = ctx.ext.world.objects().caps_total(obj) as i32;
+    ctx.prg.data_stack.push(r.into())?;
+
+    log_a1r1!(ctx.prg, obj, r);
+
+    Ok(())
+}
+
+pub fn kill_critter(ctx: Context) -> Result<()> {
+    let anim = ctx.prg.data_stack.pop()?.into_int()?;
+    let target = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a2!(ctx.prg, target, anim);
+
+    let anim = CritterAnim::from_i32(anim);
+    ctx.ext.world.combat_requests.push(combat::Request::Kill { target, anim });
+
+    Ok(())
+}
+
+pub fn kill_critter_type(ctx: Context) -> Result<()> {
+    let anim = ctx.prg.data_stack.pop()?.into_int()?;
+    let pid = ctx.prg.data_stack.pop()?.into_int()?;
+    let pid = ProtoId::from_packed(pid as u32)
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a2!(ctx.prg, pid, anim);
+
+    let anim = CritterAnim::from_i32(anim);
+    let targets: Vec<_> = ctx.ext.world.objects().iter()
+        .filter(|&h| {
+            let obj = ctx.ext.world.objects().get(h).borrow();
+            obj.pid == ObjectProtoId::ProtoId(pid) && obj.pos.is_some()
+                && obj.sub.critter().map(|c| !c.is_dead()).unwrap_or(false)
+        })
+        .collect();
+    for target in targets {
+        ctx.ext.world.combat_requests.push(combat::Request::Kill { target, anim });
+    }
+
+    Ok(())
+}
+
+pub fn load_map(ctx: Context) -> Result<()> {
+    /// Global var the entrance is passed through to the map script.
+    const GVAR_LOAD_MAP_INDEX: usize = 15;
+
+    let entrance = ctx.prg.data_stack.pop()?.into_int()?;
+    let map = match ctx.prg.data_stack.pop()? {
+        Value::Int(id) => MapRef::Id(id),
+        Value::String(name) => {
+            let name = name.resolve(ctx.prg.strings())?;
+            MapRef::Name(String::from_utf8_lossy(name.as_bytes()).into_owned())
+        }
+        _ => return Err(Error::BadValue(BadValue::Type)),
+    };
+
+    log_a2!(ctx.prg, map, entrance);
+
+    ctx.ext.world.map_transition = Some(MapTransition::Map {
+        map: Some(map),
+        pos: None,
+        direction: None,
+    });
+    if let Some(v) = ctx.ext.global_vars.get_mut(GVAR_LOAD_MAP_INDEX) {
+        *v = entrance;
+    } else {
+        warn!("load_map: no global var {} to store the entrance", GVAR_LOAD_MAP_INDEX);
+    }
+
+    Ok(())
+}
+
+pub fn mark_area_known(ctx: Context) -> Result<()> {
+    const KIND_AREA: i32 = 0;
+    const KIND_MAP: i32 = 1;
+    const STATE_INVISIBLE: i32 = -66;
 
-pub fn has_trait(ctx: Context) -> Result<()> {
+    let state = ctx.prg.data_stack.pop()?.into_int()?;
+    let area_or_map = ctx.prg.data_stack.pop()?.into_int()?;
     let kind = ctx.prg.data_stack.pop()?.into_int()?;
-    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?;
-    let family_kind = ctx.prg.data_stack.pop()?.coerce_into_int()?;
-    let family = match TraitFamilyKind::from_i32(family_kind) {
-        Some(TraitFamilyKind::Perk) => Perk::from_i32(kind)
-            .map(TraitFamily::Perk)
-            .unwrap_or(TraitFamily::PerkUnknown(kind)),
-        Some(TraitFamilyKind::Object) => ObjectTrait::from_i32(kind)
-            .map(TraitFamily::Object)
-            .unwrap_or(TraitFamily::ObjectUnknown(kind)),
-        Some(TraitFamilyKind::Trait) => Trait::from_i32(kind)
-            .map(TraitFamily::Trait)
-            .unwrap_or(TraitFamily::TraitUnknown(kind)),
-        None => TraitFamily::Unknown(family_kind),
-    };
-    let r = 0;
-    ctx.prg.data_stack.push(Value::Int(r))?;
-    log_a2r1!(ctx.prg, family, obj, r);
-    log_stub!(ctx.prg);
+
+    log_a3!(ctx.prg, kind, area_or_map, state);
+
+    let wm = ctx.ext.world.world_map_mut();
+    match kind {
+        KIND_AREA => if area_or_map >= 0 {
+            let state = match state {
+                STATE_INVISIBLE => VisitState::Unknown,
+                _ => VisitState::from_i32(state)
+                    .filter(|&s| s != VisitState::Unknown)
+                    .unwrap_or(VisitState::Known),
+            };
+            wm.set_area_state(area_or_map as usize, state);
+        }
+        KIND_MAP => wm.mark_map_known(area_or_map),
+        _ => { log_error!(ctx.prg, "unknown kind"); }
+    }
+
     Ok(())
 }
 
@@ -528,28 +1209,61 @@ pub fn metarule(ctx: Context) -> Result<()> {
         match mr {
             SignalEndGame   => 0,
             TestFirstrun    => 1,
-            Elevator        => 0,
-            PartyCount      => 0,
-            AreaKnown       => 1,
+            Elevator        => {
+                ctx.ext.world.elevator_request = Some(arg.clone().into_int()? as u32);
+                0
+            }
+            PartyCount      => ctx.ext.world.party().members().len() as i32 + 1,
+            AreaKnown       => {
+                let area = arg.clone().into_int()?;
+                let state = if area >= 0 {
+                    ctx.ext.world.world_map().area_state(area as usize)
+                } else {
+                    None
+                };
+                state.map(|s| s != VisitState::Unknown).unwrap_or(false) as i32
+            }
             WhoOnDrugs      => 0,
-            MapKnown        => 1,
-            IsLoadgame      => 0,
-            CarCurrentTown  => 0,
-            GiveCarToParty  => 0,
-            GiveCarGas      => 0,
+            MapKnown        => {
+                let map_id = arg.clone().into_int()?;
+                ctx.ext.world.world_map().is_map_known(map_id) as i32
+            }
+            IsLoadgame      => ctx.ext.world.loading_game as i32,
+            CarCurrentTown  => ctx.ext.world.world_map().car_area()
+                .map(|a| a as i32)
+                .unwrap_or(-1),
+            GiveCarToParty  => {
+                ctx.ext.world.world_map_mut().give_car(ctx.ext.map_id);
+                0
+            }
+            GiveCarGas      => {
+                let amount = cmp::max(arg.clone().into_int()?, 0) as u32;
+                ctx.ext.world.world_map_mut().refuel_car(amount);
+                0
+            }
             SkillCheckTag   => 0,
             DropAllInven    => 0,
             InvenUnwieldWho => 0,
-            GetWorldmapXpos => 0,
-            GetWorldmapYpos => 0,
-            CurrentTown     => 0,
+            GetWorldmapXpos => ctx.ext.world.world_map().pos().x,
+            GetWorldmapYpos => ctx.ext.world.world_map().pos().y,
+            CurrentTown     => ctx.ext.world.world_map().area_of_map(ctx.ext.map_id)
+                .map(|a| a as i32)
+                .unwrap_or(-1),
             LanguageFilter  => 0,
             ViolenceFilter  => 0,
             WDamageType     => 0,
             CritterBarters  => 0,
             CritterKillType => 0,
-            CarTrunkSetAnim => 0,
-            CarTrunkGetAnim => 0,
+            CarTrunkSetAnim => {
+                let frame_idx = cmp::max(arg.clone().into_int()?, 0) as usize;
+                for trunk in ctx.ext.world.objects().find_on_map(ProtoId::CAR_TRUNK) {
+                    ctx.ext.world.objects().get(trunk).borrow_mut().frame_idx = frame_idx;
+                }
+                0
+            }
+            CarTrunkGetAnim => ctx.ext.world.objects().find_on_map(ProtoId::CAR_TRUNK).first()
+                .map(|&h| ctx.ext.world.objects().get(h).borrow().frame_idx as i32)
+                .unwrap_or(0),
         }
     } else {
         error!("unknown Metarule ID {}", id);
@@ -578,17 +1292,43 @@ pub fn metarule3(ctx: Context) -> Result<()> {
     let mr = Metarule3::from_i32(id);
     let r = if let Some(mr) = mr {
         match mr {
-            ClrFixedTimedEvents => 0,
-            MarkSubtile         => 0,
+            ClrFixedTimedEvents => {
+                let obj = v1.clone().coerce_into_object()?;
+                let param = v2.clone().into_int()?;
+                ctx.ext.world.timer.retain(|e| match e.kind {
+                    timer::EventKind::Script { fixed_param } =>
+                        Some(e.obj) != obj || fixed_param != param,
+                    timer::EventKind::DrugEffect { .. } => true,
+                });
+                0
+            }
+            MarkSubtile         => {
+                let pos = Point::new(v1.clone().into_int()?, v2.clone().into_int()?);
+                let radius = v3.clone().into_int()?;
+                ctx.ext.world.world_map_mut().mark_subtiles(pos, radius, VisitState::Visited);
+                0
+            }
             SetWmMusic          => 0,
             GetKillCount        => 0,
             MarkMapEntrance     => 0,
-            WmSubtileState      => 0,
+            WmSubtileState      => {
+                let pos = Point::new(v1.clone().into_int()?, v2.clone().into_int()?);
+                ctx.ext.world.world_map().subtile_state(pos).map(|s| s as i32).unwrap_or(0)
+            }
             TileGetNextCritter  => 0,
             ArtSetBaseFidNum    => 0,
             TileSetCenter       => 0,
-            AiGetChemUseValue   => 0,
-            WmCarIsOutOfGas     => 0,
+            AiGetChemUseValue   => {
+                let obj = v1.clone().coerce_into_object()?
+                    .ok_or(Error::BadValue(BadValue::Content))?;
+                let ai_packet = ctx.ext.world.objects().get(obj).borrow().sub.critter()
+                    .map(|c| c.combat.ai_packet);
+                ai_packet
+                    .and_then(|p| ctx.ext.world.ai_packets().get(p))
+                    .map(|p| p.chem_use as i32)
+                    .unwrap_or(0)
+            }
+            WmCarIsOutOfGas     => ctx.ext.world.world_map().is_car_out_of_fuel() as i32,
             MapTargetLoadArea   => 0,
         }
     } else {
@@ -620,7 +1360,10 @@ pub fn move_obj_inven_to_obj(ctx: Context) -> Result<()> {
     }
 
     log_a2!(ctx.prg, src, dst);
-    log_stub!(ctx.prg);
+
+    if let (Some(src), Some(dst)) = (src, dst) {
+        ctx.ext.world.move_inventory(src, dst);
+    }
 
     Ok(())
 }
@@ -653,16 +1396,66 @@ pub fn obj_can_see_obj(ctx: Context) -> Result<()> {
     Ok(())
 }
 
+pub fn obj_carrying_pid_obj(ctx: Context) -> Result<()> {
+    let pid = ctx.prg.data_stack.pop()?.into_int()?;
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    let r = ProtoId::from_packed(pid as u32)
+        .and_then(|pid| ctx.ext.world.objects().find_in_inventory(obj, pid));
+    ctx.prg.data_stack.push(Value::Object(r))?;
+
+    log_a2r1!(ctx.prg, obj, pid, r);
+
+    Ok(())
+}
+
 pub fn obj_is_carrying_obj_pid(ctx: Context) -> Result<()> {
     let pid = ctx.prg.data_stack.pop()?.into_int()?;
     let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
         .ok_or(Error::BadValue(BadValue::Content))?;
 
-    let r = 0;
+    let r = ProtoId::from_packed(pid as u32)
+        .map(|pid| ctx.ext.world.objects().inventory_pid_count(obj, pid))
+        .unwrap_or(0) as i32;
     ctx.prg.data_stack.push(r.into())?;
 
     log_a2r1!(ctx.prg, obj, pid, r);
-    log_stub!(ctx.prg);
+
+    Ok(())
+}
+
+pub fn obj_close(ctx: Context) -> Result<()> {
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a1!(ctx.prg, obj);
+
+    set_door_open(ctx, obj, false);
+
+    Ok(())
+}
+
+pub fn obj_is_locked(ctx: Context) -> Result<()> {
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    let r = ctx.ext.world.objects().get(obj).borrow().is_locked() as i32;
+    ctx.prg.data_stack.push(r.into())?;
+
+    log_a1r1!(ctx.prg, obj, r);
+
+    Ok(())
+}
+
+pub fn obj_is_open(ctx: Context) -> Result<()> {
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    let r = ctx.ext.world.objects().get(obj).borrow().is_open() as i32;
+    ctx.prg.data_stack.push(r.into())?;
+
+    log_a1r1!(ctx.prg, obj, r);
 
     Ok(())
 }
@@ -672,7 +1465,8 @@ pub fn obj_lock(ctx: Context) -> Result<()> {
         .ok_or(Error::BadValue(BadValue::Content))?;
 
     log_a1!(ctx.prg, obj);
-    log_stub!(ctx.prg);
+
+    ctx.ext.world.objects().get(obj).borrow_mut().lock_flags.insert(LockFlag::Locked);
 
     Ok(())
 }
@@ -701,6 +1495,17 @@ pub fn obj_on_screen(ctx: Context) -> Result<()> {
     Ok(())
 }
 
+pub fn obj_open(ctx: Context) -> Result<()> {
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a1!(ctx.prg, obj);
+
+    set_door_open(ctx, obj, true);
+
+    Ok(())
+}
+
 pub fn obj_pid(ctx: Context) -> Result<()> {
     let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?;
 
@@ -718,12 +1523,22 @@ pub fn obj_pid(ctx: Context) -> Result<()> {
     Ok(())
 }
 
+// obj_use_door()
+fn set_door_open(ctx: Context, obj: object::Handle, open: bool) {
+    if !ctx.ext.world.objects().is_door(obj) {
+        log_error!(ctx.prg, "object is not a door");
+        return;
+    }
+    door::start(obj, open, ctx.ext.world, ctx.ext.sequencer);
+}
+
 pub fn obj_unlock(ctx: Context) -> Result<()> {
     let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
         .ok_or(Error::BadValue(BadValue::Content))?;
 
     log_a1!(ctx.prg, obj);
-    log_stub!(ctx.prg);
+
+    ctx.ext.world.objects().get(obj).borrow_mut().lock_flags.remove(LockFlag::Locked);
 
     Ok(())
 }
@@ -753,12 +1568,71 @@ pub fn override_map_start(ctx: Context) -> Result<()> {
     Ok(())
 }
 
+pub fn party_add(ctx: Context) -> Result<()> {
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a1!(ctx.prg, obj);
+
+    let world = &mut ctx.ext.world;
+    let pid = world.objects().get(obj).borrow().pid;
+    if world.party().def(pid).is_none() || pid == ObjectProtoId::Dude {
+        log_error!(ctx.prg, "object can't be a party member");
+        return Ok(());
+    }
+    let team_id = world.dude_obj()
+        .and_then(|h| world.objects().get(h).borrow().sub.critter().map(|c| c.combat.team_id))
+        .unwrap_or(0);
+    if world.party_mut().add(obj) {
+        if let Some(critter) = world.objects().get(obj).borrow_mut().sub.critter_mut() {
+            critter.combat.team_id = team_id;
+            critter.combat.who_hit_me = None;
+        }
+    }
+
+    Ok(())
+}
+
 pub fn party_member_obj(ctx: Context) -> Result<()> {
     let pid = ctx.prg.data_stack.pop()?.into_int()?;
-    let r = Value::Object(None);
-    ctx.prg.data_stack.push(r)?;
-    log_a1r1!(ctx.prg, pid, ctx.prg.data_stack.top().unwrap());
-    log_stub!(ctx.prg);
+
+    let world = &ctx.ext.world;
+    let r = ObjectProtoId::from_packed(pid as u32).and_then(|pid| if pid == ObjectProtoId::Dude {
+        world.dude_obj()
+    } else {
+        world.party().members().iter()
+            .map(|m| m.obj)
+            .find(|&h| world.objects().get(h).borrow().pid == pid)
+    });
+    ctx.prg.data_stack.push(Value::Object(r))?;
+
+    log_a1r1!(ctx.prg, pid, r);
+
+    Ok(())
+}
+
+pub fn party_remove(ctx: Context) -> Result<()> {
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a1!(ctx.prg, obj);
+
+    if !ctx.ext.world.party_mut().remove(obj) {
+        log_error!(ctx.prg, "object is not a party member");
+    }
+
+    Ok(())
+}
+
+pub fn pickup_obj(ctx: Context) -> Result<()> {
+    let item = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a1!(ctx.prg, item);
+
+    let owner = ctx.ext.self_obj.ok_or(Error::BadValue(BadValue::Content))?;
+    ctx.ext.world.add_to_inventory(owner, item, 1);
+
     Ok(())
 }
 
@@ -777,7 +1651,6 @@ pub fn random(ctx: Context) -> Result<()> {
 }
 
 pub fn reg_anim_animate_forever(ctx: Context) -> Result<()> {
-    use crate::asset::CritterAnim;
     use crate::game::sequence::frame_anim::*;
 
     let critter_anim = CritterAnim::from_i32(ctx.prg.data_stack.pop()?.into_int()?)
@@ -834,6 +1707,140 @@ pub fn reg_anim_func(ctx: Context) -> Result<()> {
     Ok(())
 }
 
+pub fn roll_vs_skill(ctx: Context) -> Result<()> {
+    let modifier = ctx.prg.data_stack.pop()?.into_int()?;
+    let skill = Skill::from_i32(ctx.prg.data_stack.pop()?.into_int()?)
+        .ok_or(Error::BadValue(BadValue::Content))?;
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    let r = {
+        let obj = ctx.ext.world.objects().get(obj).borrow();
+        if obj.sub.critter().is_some() {
+            ctx.ext.stats.roll_skill(skill, &obj, ctx.ext.world, modifier, &mut rand).0
+        } else {
+            Roll::Failure
+        }
+    };
+    ctx.prg.data_stack.push(Value::Int(r as i32))?;
+
+    log_a3r1!(ctx.prg, obj, skill, modifier, r);
+
+    Ok(())
+}
+
+pub fn rm_mult_objs_from_inven(ctx: Context) -> Result<()> {
+    let count = ctx.prg.data_stack.pop()?.into_int()?;
+    let item = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+    let owner = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    let r = if let Some((removed, count)) = ctx.ext.world.objects_mut()
+        .remove_from_inventory(owner, item, cmp::max(count, 0) as usize)
+    {
+        // The part split off the stack is unknown to the script.
+        if removed != item {
+            ctx.ext.world.destroy_object(removed);
+        }
+        count as i32
+    } else {
+        0
+    };
+    ctx.prg.data_stack.push(r.into())?;
+
+    log_a3r1!(ctx.prg, owner, item, count, r);
+
+    Ok(())
+}
+
+pub fn rm_obj_from_inven(ctx: Context) -> Result<()> {
+    let item = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+    let owner = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a2!(ctx.prg, owner, item);
+
+    match ctx.ext.world.objects_mut().remove_from_inventory(owner, item, 1) {
+        // The part split off the stack is unknown to the script.
+        Some((removed, _)) => if removed != item {
+            ctx.ext.world.destroy_object(removed);
+        }
+        None => { log_error!(ctx.prg, "item is not in inventory"); }
+    }
+
+    Ok(())
+}
+
+pub fn rm_timer_event(ctx: Context) -> Result<()> {
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?;
+
+    log_a1!(ctx.prg, obj);
+
+    if let Some(obj) = obj {
+        ctx.ext.world.timer.remove_object(obj);
+    } else {
+        log_error!(ctx.prg, "object is null");
+    }
+
+    Ok(())
+}
+
+pub fn set_exit_grids(ctx: Context) -> Result<()> {
+    let direction = ctx.prg.data_stack.pop()?.into_int()?;
+    let direction = Direction::from_i32(direction)
+        .ok_or(Error::BadValue(BadValue::Content))?;
+    let tile_num = ctx.prg.data_stack.pop()?.into_int()?;
+    let elevation = ctx.prg.data_stack.pop()?.into_int()?;
+    let map_id = ctx.prg.data_stack.pop()?.into_int()?;
+    let on_elevation = ctx.prg.data_stack.pop()?.into_int()?;
+
+    log_a5!(ctx.prg, on_elevation, map_id, elevation, tile_num, direction);
+
+    let pos = if tile_num >= 0 && elevation >= 0 && elevation < ELEVATION_COUNT as i32 {
+        Some(ctx.ext.world.hex_grid().from_linear_inv(tile_num as u32).elevated(elevation as u32))
+    } else {
+        None
+    };
+    let objects = ctx.ext.world.objects();
+    for h in objects.iter() {
+        let mut obj = objects.get(h).borrow_mut();
+        if obj.pos.map(|p| p.elevation as i32) != Some(on_elevation)
+            || !obj.pid.proto_id().map(|pid| pid.is_exit_area()).unwrap_or(false)
+        {
+            continue;
+        }
+        if let Some(exit) = obj.sub.exit_mut() {
+            *exit = Exit { map_id, pos, direction };
+        }
+    }
+
+    Ok(())
+}
+
+pub fn set_critter_stat(ctx: Context) -> Result<()> {
+    let amount = ctx.prg.data_stack.pop()?.into_int()?;
+    let stat = Stat::from_i32(ctx.prg.data_stack.pop()?.into_int()?)
+        .ok_or(Error::BadValue(BadValue::Content))?;
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a3!(ctx.prg, obj, stat, amount);
+
+    let r = if Some(obj) == ctx.ext.world.dude_obj() {
+        let mut objr = ctx.ext.world.objects().get(obj).borrow_mut();
+        ctx.ext.stats.mod_dude_base_stat(stat, amount, &mut objr);
+        0
+    } else {
+        log_error!(ctx.prg, "can't modify stats of anyone except dude");
+        -1
+    };
+    ctx.prg.data_stack.push(r.into())?;
+
+    Ok(())
+}
+
 pub fn set_light_level(ctx: Context) -> Result<()> {
     let v = cmp::min(cmp::max(ctx.prg.data_stack.pop()?.into_int()?, 0), 100) as u32;
 
@@ -869,6 +1876,34 @@ pub fn set_obj_visibility(ctx: Context) -> Result<()> {
     Ok(())
 }
 
+pub fn skill_contest(ctx: Context) -> Result<()> {
+    let skill = Skill::from_i32(ctx.prg.data_stack.pop()?.into_int()?)
+        .ok_or(Error::BadValue(BadValue::Content))?;
+    let defender = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+    let attacker = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    let r = {
+        let world = &*ctx.ext.world;
+        let objs = world.objects();
+        let stats = &ctx.ext.stats;
+        let attackero = objs.get(attacker).borrow();
+        let defendero = objs.get(defender).borrow();
+        if attackero.sub.critter().is_some() && defendero.sub.critter().is_some() {
+            roll::contest(stats.roll_skill(skill, &attackero, world, 0, &mut rand),
+                || stats.roll_skill(skill, &defendero, world, 0, &mut rand))
+        } else {
+            Roll::Failure
+        }
+    };
+    ctx.prg.data_stack.push(Value::Int(r as i32))?;
+
+    log_a3r1!(ctx.prg, attacker, defender, skill, r);
+
+    Ok(())
+}
+
 pub fn start_gdialog(mut ctx: Context) -> Result<()> {
     let background = ctx.prg.data_stack.pop()?.into_int()?;
     let head_id = ctx.prg.data_stack.pop()?.into_int()?;
@@ -888,6 +1923,12 @@ pub fn start_gdialog(mut ctx: Context) -> Result<()> {
     Ok(())
 }
 
+pub fn terminate_combat(ctx: Context) -> Result<()> {
+    log_!(ctx.prg);
+    ctx.ext.world.combat_requests.push(combat::Request::Terminate);
+    Ok(())
+}
+
 pub fn tile_contains_pid_obj(ctx: Context) -> Result<()> {
     let pid = ctx.prg.data_stack.pop()?.into_int()?;
     let pid = ProtoId::from_packed(pid as u32)
@@ -957,3 +1998,64 @@ pub fn tile_num_in_direction(ctx: Context) -> Result<()> {
 
     Ok(())
 }
+
+pub fn using_skill(ctx: Context) -> Result<()> {
+    let skill = Skill::from_i32(ctx.prg.data_stack.pop()?.into_int()?)
+        .ok_or(Error::BadValue(BadValue::Content))?;
+    let obj = ctx.prg.data_stack.pop()?.coerce_into_object()?;
+
+    // Only sneaking of the dude is tracked.
+    let r = skill == Skill::Sneak
+        && obj.is_some()
+        && obj == ctx.ext.world.dude_obj()
+        && ctx.ext.world.dude_sneaking;
+    ctx.prg.data_stack.push(Value::Int(r as i32))?;
+
+    log_a2r1!(ctx.prg, obj, skill, r);
+
+    Ok(())
+}
+
+pub fn wield_obj_critter(ctx: Context) -> Result<()> {
+    let item = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+    let critter = ctx.prg.data_stack.pop()?.coerce_into_object()?
+        .ok_or(Error::BadValue(BadValue::Content))?;
+
+    log_a2!(ctx.prg, critter, item);
+
+    if ctx.ext.world.objects().get(critter).borrow().kind() != EntityKind::Critter {
+        log_error!(ctx.prg, "object is not a critter");
+        return Ok(());
+    }
+    let item = if ctx.ext.world.objects().get(critter).borrow().inventory.contains(item) {
+        item
+    } else {
+        ctx.ext.world.add_to_inventory(critter, item, 1)
+    };
+    ctx.ext.world.objects_mut().equip(critter, item, EquipmentSlot::RightHand);
+
+    Ok(())
+}
+
+pub fn wm_area_set_pos(ctx: Context) -> Result<()> {
+    let y = ctx.prg.data_stack.pop()?.into_int()?;
+    let x = ctx.prg.data_stack.pop()?.into_int()?;
+    let area = ctx.prg.data_stack.pop()?.into_int()?;
+
+    log_a3!(ctx.prg, area, x, y);
+
+    if area >= 0 {
+        ctx.ext.world.world_map_mut().set_area_pos(area as usize, Point::new(x, y));
+    }
+
+    Ok(())
+}
+
+pub fn world_map(ctx: Context) -> Result<()> {
+    log_!(ctx.prg);
+
+    ctx.ext.world.map_transition = Some(MapTransition::WorldMap);
+
+    Ok(())
+}